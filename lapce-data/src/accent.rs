@@ -0,0 +1,126 @@
+use std::{
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+use druid::{Color, ExtEventSink, Target};
+
+use crate::command::{LapceUICommand, LAPCE_UI_COMMAND};
+
+/// Whether the config currently uses the system accent color, so that it is
+/// only watched for changes when it matters
+static ACCENT_IN_USE: AtomicBool = AtomicBool::new(false);
+
+/// How often the system accent color is checked for changes
+const ACCENT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+pub fn set_accent_in_use(in_use: bool) {
+    ACCENT_IN_USE.store(in_use, Ordering::Relaxed);
+}
+
+/// Reload the config whenever the system accent color changes while it's in use.
+/// None of the platforms notify about it in a way we can receive, so it is polled.
+pub fn watch_accent_color(event_sink: ExtEventSink) {
+    thread::spawn(move || {
+        let mut last = None;
+        loop {
+            thread::sleep(ACCENT_POLL_INTERVAL);
+            if !ACCENT_IN_USE.load(Ordering::Relaxed) {
+                last = None;
+                continue;
+            }
+            let color = system_accent_color().map(|c| c.as_rgba_u32());
+            if last.is_some() && color != last {
+                let _ = event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::ReloadConfig,
+                    Target::Auto,
+                );
+            }
+            last = color;
+        }
+    });
+}
+
+/// The accent color the user picked in the system settings, if the platform
+/// has one and it can be read
+#[cfg(target_os = "windows")]
+pub fn system_accent_color() -> Option<Color> {
+    let output = Command::new("reg")
+        .args([
+            "query",
+            r"HKCU\Software\Microsoft\Windows\DWM",
+            "/v",
+            "AccentColor",
+        ])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let value = stdout
+        .lines()
+        .find(|line| line.contains("AccentColor"))?
+        .split_whitespace()
+        .last()?;
+    // The value is stored as 0xAABBGGRR
+    let abgr = u32::from_str_radix(value.trim_start_matches("0x"), 16).ok()?;
+    let [_, b, g, r] = abgr.to_be_bytes();
+    Some(Color::rgb8(r, g, b))
+}
+
+/// The accent color the user picked in the system settings, if the platform
+/// has one and it can be read
+#[cfg(target_os = "macos")]
+pub fn system_accent_color() -> Option<Color> {
+    let output = Command::new("defaults")
+        .args(["read", "-g", "AppleAccentColor"])
+        .output()
+        .ok()?;
+    // The key is missing when the accent is the default blue
+    let accent = if output.status.success() {
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    } else {
+        "4".to_string()
+    };
+    let color = match accent.as_str() {
+        "-1" => Color::rgb8(0x98, 0x98, 0x98),
+        "0" => Color::rgb8(0xE0, 0x38, 0x3E),
+        "1" => Color::rgb8(0xF7, 0x82, 0x1B),
+        "2" => Color::rgb8(0xFF, 0xC6, 0x00),
+        "3" => Color::rgb8(0x62, 0xBA, 0x46),
+        "4" => Color::rgb8(0x00, 0x7A, 0xFF),
+        "5" => Color::rgb8(0x95, 0x3D, 0x96),
+        "6" => Color::rgb8(0xF7, 0x4F, 0x9E),
+        _ => return None,
+    };
+    Some(color)
+}
+
+/// The accent color the user picked in the system settings, if the platform
+/// has one and it can be read
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn system_accent_color() -> Option<Color> {
+    // GNOME is the only desktop with an accent setting we can read
+    let output = Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "accent-color"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let color = match stdout.trim().trim_matches('\'') {
+        "blue" => Color::rgb8(0x35, 0x84, 0xE4),
+        "teal" => Color::rgb8(0x21, 0x90, 0xA4),
+        "green" => Color::rgb8(0x3A, 0x94, 0x4A),
+        "yellow" => Color::rgb8(0xC8, 0x88, 0x00),
+        "orange" => Color::rgb8(0xED, 0x5B, 0x00),
+        "red" => Color::rgb8(0xE6, 0x2D, 0x42),
+        "pink" => Color::rgb8(0xD5, 0x61, 0x99),
+        "purple" => Color::rgb8(0x91, 0x41, 0xAC),
+        "slate" => Color::rgb8(0x6F, 0x83, 0x96),
+        _ => return None,
+    };
+    Some(color)
+}
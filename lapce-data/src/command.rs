@@ -1,4 +1,8 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+};
 
 use anyhow::Result;
 use druid::{
@@ -11,13 +15,18 @@ use lapce_core::command::{
 };
 use lapce_core::syntax::Syntax;
 use lapce_rpc::{
-    buffer::BufferId, file::FileNodeItem, plugin::PluginDescription,
-    source_control::DiffInfo, style::Style, terminal::TermId,
+    buffer::{BufferId, LineEnding},
+    core::LanguageServerState,
+    file::{FileErrorKind, FileNodeItem, FileOperation, PathMetadata},
+    plugin::{ExplorerMenuItem, PluginDescription, PluginState},
+    source_control::{ChangedFile, DiffInfo},
+    style::Style,
+    terminal::TermId,
 };
 use lsp_types::{
     CodeActionOrCommand, CodeActionResponse, CompletionItem, CompletionResponse,
-    InlayHint, Location, Position, ProgressParams, PublishDiagnosticsParams,
-    TextEdit,
+    InlayHint, Location, Position, ProgressParams, ProgressToken,
+    PublishDiagnosticsParams, TextEdit, WorkspaceEdit,
 };
 use serde_json::Value;
 use strum::{self, EnumMessage, IntoEnumIterator};
@@ -35,12 +44,17 @@ use crate::{
     editor::EditorLocation,
     keypress::{KeyMap, KeyPress},
     palette::{PaletteItem, PaletteType},
+    plugin::PluginInstallStatus,
     proxy::ProxyStatus,
     search::Match,
     split::{SplitDirection, SplitMoveDirection},
 };
 
 pub const LAPCE_OPEN_FOLDER: Selector<FileInfo> = Selector::new("lapce.open-folder");
+pub const LAPCE_OPEN_FOLDER_IN_NEW_WINDOW: Selector<FileInfo> =
+    Selector::new("lapce.open-folder-in-new-window");
+pub const LAPCE_ADD_FOLDER_TO_WORKSPACE: Selector<FileInfo> =
+    Selector::new("lapce.add-folder-to-workspace");
 pub const LAPCE_OPEN_FILE: Selector<FileInfo> = Selector::new("lapce.open-file");
 pub const LAPCE_SAVE_FILE_AS: Selector<FileInfo> =
     Selector::new("lapce.save-file-as");
@@ -99,6 +113,8 @@ impl LapceCommand {
                 | LapceWorkbenchCommand::PaletteSymbol
                 | LapceWorkbenchCommand::PaletteCommand
                 | LapceWorkbenchCommand::ChangeFileLanguage
+                | LapceWorkbenchCommand::ReopenWithEncoding
+                | LapceWorkbenchCommand::RestoreFromTrash
                 | LapceWorkbenchCommand::ChangeTheme
                 | LapceWorkbenchCommand::ConnectSshHost
                 | LapceWorkbenchCommand::ConnectWsl
@@ -194,10 +210,23 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Open Folder")]
     OpenFolder,
 
+    #[strum(serialize = "open_folder_in_new_window")]
+    #[strum(message = "Open Folder in New Window")]
+    OpenFolderInNewWindow,
+
     #[strum(serialize = "close_folder")]
     #[strum(message = "Close Folder")]
     CloseFolder,
 
+    /// Opens the workspace again at the path passed in parameter, after its
+    /// folder was renamed.
+    #[strum(serialize = "reopen_workspace_at")]
+    ReopenWorkspaceAt,
+
+    #[strum(serialize = "add_folder_to_workspace")]
+    #[strum(message = "Add Folder to Workspace")]
+    AddFolderToWorkspace,
+
     #[strum(serialize = "open_file")]
     #[strum(message = "Open File")]
     OpenFile,
@@ -214,6 +243,12 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Open Settings File")]
     OpenSettingsFile,
 
+    /// Opens the settings file of the workspace, creating it if it's missing,
+    /// and reveals it in the file explorer.
+    #[strum(serialize = "open_workspace_settings_file")]
+    #[strum(message = "Open Workspace Settings File")]
+    OpenWorkspaceSettingsFile,
+
     #[strum(serialize = "open_keyboard_shortcuts")]
     #[strum(message = "Open Keyboard Shortcuts")]
     OpenKeyboardShortcuts,
@@ -392,6 +427,24 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Change current file language")]
     ChangeFileLanguage,
 
+    #[strum(serialize = "reopen_with_encoding")]
+    #[strum(message = "Reopen current file with encoding")]
+    ReopenWithEncoding,
+
+    #[strum(serialize = "convert_line_endings_to_lf")]
+    #[strum(message = "Convert line endings to LF")]
+    ConvertLineEndingsToLf,
+
+    #[strum(serialize = "convert_line_endings_to_crlf")]
+    #[strum(message = "Convert line endings to CRLF")]
+    ConvertLineEndingsToCrlf,
+
+    /// Switch the current file between LF and CRLF, which makes mixed line
+    /// endings LF
+    #[strum(serialize = "toggle_line_ending")]
+    #[strum(message = "Toggle line ending")]
+    ToggleLineEnding,
+
     #[strum(serialize = "next_editor_tab")]
     #[strum(message = "Next editor tab")]
     NextEditorTab,
@@ -403,6 +456,63 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "toggle_inlay_hints")]
     #[strum(message = "Toggle Inlay Hints")]
     ToggleInlayHints,
+
+    #[strum(serialize = "reveal_terminal_cwd_in_explorer")]
+    #[strum(message = "Reveal Terminal Directory in Explorer")]
+    RevealTerminalCwdInExplorer,
+
+    #[strum(serialize = "refresh_explorer")]
+    #[strum(message = "Refresh Explorer")]
+    RefreshExplorer,
+
+    /// Only show the files with matches of the search in the explorer, or
+    /// show all of them again
+    #[strum(serialize = "toggle_explorer_search_filter")]
+    #[strum(message = "Toggle Explorer Search Filter")]
+    ToggleExplorerSearchFilter,
+
+    /// Moves the path passed in parameter to the trash.
+    #[strum(serialize = "explorer_trash_path")]
+    ExplorerTrashPath,
+
+    /// Moves the paths passed in parameter to the trash.
+    #[strum(serialize = "explorer_trash_paths")]
+    ExplorerTrashPaths,
+
+    /// Deletes the paths passed in parameter permanently, without the trash.
+    #[strum(serialize = "explorer_delete_paths")]
+    ExplorerDeletePaths,
+
+    /// Expands all the levels beneath the directory passed in parameter,
+    /// without asking first.
+    #[strum(serialize = "explorer_expand_all_levels")]
+    ExplorerExpandAllLevels,
+
+    /// Lists the paths in the trash folder of the workspace, which paths are
+    /// moved to when there is no OS trash, to move one back.
+    #[strum(serialize = "restore_from_trash")]
+    #[strum(message = "Restore from Trash")]
+    RestoreFromTrash,
+
+    /// Opens the files and expands the directories of the paths passed in
+    /// parameter.
+    #[strum(serialize = "explorer_open_paths")]
+    ExplorerOpenPaths,
+
+    /// Keeps the unsaved changes of the file passed in parameter, ignoring the
+    /// content it changed to on disk.
+    #[strum(serialize = "keep_buffer_changes")]
+    KeepBufferChanges,
+
+    /// Replaces the unsaved changes of the file passed in parameter with the
+    /// content it changed to on disk.
+    #[strum(serialize = "take_disk_changes")]
+    TakeDiskChanges,
+
+    /// Opens a diff between the unsaved changes of the file passed in
+    /// parameter and the content it changed to on disk.
+    #[strum(serialize = "diff_disk_changes")]
+    DiffDiskChanges,
 }
 
 #[derive(Debug, Clone)]
@@ -446,6 +556,13 @@ pub enum LapceUICommand {
         rev: u64,
         content: Rope,
     },
+    /// Apply the edits of an open file that changed on disk to its buffer at
+    /// the revision
+    ReloadBufferDelta {
+        path: PathBuf,
+        rev: u64,
+        changes: Vec<TextEdit>,
+    },
     LoadBufferHead {
         path: PathBuf,
         version: String,
@@ -462,12 +579,57 @@ pub enum LapceUICommand {
     UpdateSearchInput(String),
     UpdateSearch(String),
     GlobalSearchResult(String, Arc<HashMap<PathBuf, Vec<Match>>>),
+    /// Search the contents of the files in the folder from the search panel
+    FindInFolder {
+        path: PathBuf,
+    },
+    /// Search the whole workspace again instead of the folder
+    ClearSearchScope,
+    /// A match streamed back for the search of a folder
+    SearchResult {
+        query_id: u64,
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        end_column: usize,
+        text: String,
+    },
     CancelFilePicker,
     SetWorkspace(LapceWorkspace),
     SetTheme(String, bool),
     UpdateKeymap(KeyMap, Vec<KeyPress>),
     OpenFile(PathBuf),
+    /// Open the file with the cursor at the position. The line and column are
+    /// one based, and the column counts bytes.
+    OpenFileAt {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+    },
     OpenFileDiff(PathBuf, String),
+    /// Put the text of the file on the clipboard without opening it
+    CopyFileContents {
+        path: PathBuf,
+    },
+    /// The text of the file read for `CopyFileContents`
+    CopyFileContentsRead {
+        path: PathBuf,
+        content: String,
+    },
+    /// Remember the file in the file explorer, to compare another file with
+    SelectForCompare {
+        path: PathBuf,
+    },
+    /// Open a diff of the file against the one selected for comparing
+    CompareWithSelected {
+        path: PathBuf,
+    },
+    /// Open a diff of the file against the content of the one it is compared
+    /// with, once that has been read
+    OpenCompareDiff {
+        path: PathBuf,
+        content: Rope,
+    },
     CancelCompletion(usize),
     ResolveCompletion(BufferId, u64, usize, Box<CompletionItem>),
     UpdateCompletion(usize, String, CompletionResponse),
@@ -503,13 +665,140 @@ pub enum LapceUICommand {
     FilterKeymaps(String, Arc<Vec<KeyMap>>, Arc<Vec<LapceCommand>>),
     UpdatePickerPwd(PathBuf),
     UpdatePickerItems(PathBuf, HashMap<PathBuf, FileNodeItem>),
-    UpdateExplorerItems(PathBuf, HashMap<PathBuf, FileNodeItem>, bool),
+    UpdateExplorerItems {
+        path: PathBuf,
+        items: HashMap<PathBuf, FileNodeItem>,
+        /// Whether to open the directory
+        expand: bool,
+        /// Whether more items will follow as `AppendExplorerItems`
+        more: bool,
+        /// When the read was made, to leave out the listings of reads that
+        /// are out of date by the time they arrive
+        generation: u64,
+    },
+    /// A further chunk of the items of a directory that is still loading
+    AppendExplorerItems {
+        path: PathBuf,
+        items: HashMap<PathBuf, FileNodeItem>,
+        /// Whether more items will follow
+        more: bool,
+        /// The generation of the read the items are from
+        generation: u64,
+    },
+    UpdateExplorerFilter(String),
+    /// The total size of the files beneath the directory of the file explorer
+    UpdateExplorerDirSize {
+        path: PathBuf,
+        size: u64,
+    },
+    /// The metadata of the paths of the rows shown in the file explorer
+    UpdateExplorerMetadata(HashMap<PathBuf, PathMetadata>),
+    /// Switch the directory between showing its children as a tree and as a
+    /// flat list of all the files beneath it
+    ToggleExplorerFlatDir {
+        path: PathBuf,
+    },
+    /// Expand the directory of the file explorer and the directories beneath
+    /// it, `levels` levels deep, or all the way down for `None`
+    ExpandExplorerDir {
+        path: PathBuf,
+        levels: Option<usize>,
+        /// Whether this goes on with an expansion once the directory was
+        /// read, which stops if it was collapsed meanwhile
+        continued: bool,
+    },
+    /// Read and expand the children of the directory of the file explorer
+    /// that are in `open_dirs`, once all of its entries were read
+    RestoreExplorerDirs {
+        path: PathBuf,
+        open_dirs: Arc<HashSet<PathBuf>>,
+    },
+    /// Expand all the levels beneath the directory of the file explorer,
+    /// asking first if there are too many entries beneath it
+    ExpandExplorerDirAll {
+        path: PathBuf,
+    },
+    /// Reveal the path pasted into the file explorer, if it is in the
+    /// workspace and exists
+    ExplorerPastePath {
+        text: String,
+    },
+    /// Expand the directories down to the path and select it in the file
+    /// explorer
+    ExplorerRevealPath {
+        path: PathBuf,
+    },
+    /// Select the row of the path in the file explorer, if it is in view
+    ExplorerSelectPath {
+        path: PathBuf,
+    },
+    /// Expand the directory in the file explorer if it is collapsed, and
+    /// collapse it otherwise
+    ExplorerTogglePath {
+        path: PathBuf,
+    },
+    /// Read the directory of the file explorer again, for changes the file
+    /// watcher missed
+    ExplorerRefresh {
+        path: PathBuf,
+    },
+    /// Pin the file or directory to the favorites of the file explorer
+    PinExplorerPath {
+        path: PathBuf,
+    },
+    UnpinExplorerPath {
+        path: PathBuf,
+    },
+    /// Empty the recent section of the file explorer
+    ClearExplorerRecent,
+    /// A filesystem operation of the file explorer failed in the proxy
+    FileOperationError {
+        operation: FileOperation,
+        path: PathBuf,
+        kind: FileErrorKind,
+        message: String,
+    },
+    /// Narrow the file explorer to the directory, or show the whole workspace
+    /// again with `None`
+    SetExplorerDisplayRoot {
+        path: Option<PathBuf>,
+    },
+    /// The files beneath a directory that is shown as a flat list
+    UpdateExplorerFlatFiles {
+        path: PathBuf,
+        files: Vec<PathBuf>,
+    },
+    /// A plugin added an entry to the file explorer's context menu
+    RegisterExplorerMenuItem(ExplorerMenuItem),
     UpdateInstalledPlugins(HashMap<String, PluginDescription>),
     UpdatePluginDescriptions(Vec<PluginDescription>),
     UpdateInstalledPluginDescriptions(PluginLoadingStatus),
     UpdateUninstalledPluginDescriptions(PluginLoadingStatus),
     UpdatePluginInstallationChange(HashMap<String, PluginDescription>),
     UpdateDisabledPlugins(HashMap<String, PluginDescription>),
+    /// The plugin with the name is being installed or failed to be, or isn't
+    /// anymore if the status is `None`
+    UpdatePluginInstall {
+        plugin_id: String,
+        status: Option<PluginInstallStatus>,
+    },
+    /// The plugin with the name couldn't be started
+    PluginActivationFailed {
+        plugin_id: String,
+        error: String,
+    },
+    UpdatePluginState {
+        plugin_id: String,
+        state: PluginState,
+    },
+    UpdateLanguageServerStatus {
+        language: String,
+        state: LanguageServerState,
+    },
+    /// Start the language server of the language again, such as after it crashed
+    RestartLanguageServer {
+        language: String,
+    },
     DisablePlugin(PluginDescription),
     EnablePlugin(PluginDescription),
     RemovePlugin(PluginDescription),
@@ -528,6 +817,11 @@ pub enum LapceUICommand {
     PreviousEditorTab,
     FilterItems,
     NewWindow(WindowId),
+    /// Open a new window next to the window, with the workspace in it
+    OpenInNewWindow {
+        window_id: WindowId,
+        workspace: LapceWorkspace,
+    },
     ReloadWindow,
     CloseBuffers(Vec<BufferId>),
     RequestPaintRect(Rect),
@@ -537,6 +831,13 @@ pub enum LapceUICommand {
     DocumentFormatAndSave(PathBuf, u64, Result<Vec<TextEdit>>, Option<WidgetId>),
     DocumentSave(PathBuf, Option<WidgetId>),
     BufferSave(PathBuf, u64, Option<WidgetId>),
+    /// The proxy wrote the file at the revision to disk, or failed to
+    BufferSaved {
+        path: PathBuf,
+        rev: u64,
+        success: bool,
+        error: Option<String>,
+    },
     UpdateSemanticStyles(BufferId, PathBuf, u64, Arc<Spans<Style>>),
     UpdateTerminalTitle(TermId, String),
     UpdateHistoryStyle {
@@ -559,8 +860,27 @@ pub enum LapceUICommand {
     CenterOfWindow,
     UpdateLineChanges(BufferId),
     PublishDiagnostics(PublishDiagnosticsParams),
+    /// Apply the edits to all of the files they are for, like the ones from
+    /// renaming a symbol
+    ApplyWorkspaceEdit(WorkspaceEdit),
     WorkDoneProgress(ProgressParams),
+    /// Ask for the operation reporting progress with the token to be stopped
+    CancelWorkDoneProgress(ProgressToken),
     UpdateDiffInfo(DiffInfo),
+    UpdateChangedFiles(Vec<ChangedFile>),
+    /// Stage the changes of the files in git
+    GitStage {
+        paths: Vec<PathBuf>,
+    },
+    /// Unstage the changes of the files in git
+    GitUnstage {
+        paths: Vec<PathBuf>,
+    },
+    /// Add the path to the nearest `.gitignore`, or take it out of it
+    SetGitIgnored {
+        path: PathBuf,
+        ignored: bool,
+    },
     EnsureVisible((Rect, (f64, f64), Option<EnsureVisiblePosition>)),
     EnsureRectVisible(Rect),
     EnsureCursorVisible(Option<EnsureVisiblePosition>),
@@ -572,9 +892,28 @@ pub enum LapceUICommand {
     SaveAs(BufferContent, PathBuf, WidgetId, bool),
     SaveAsSuccess(BufferContent, u64, PathBuf, WidgetId, bool),
     HomeDir(PathBuf),
-    WorkspaceFileChange,
+    /// Files in the workspace changed, including the files that were created
+    /// through the proxy
+    WorkspaceFileChange {
+        created: Vec<PathBuf>,
+    },
+    /// The workspace folder was renamed, or moved away or deleted if `new` is
+    /// `None`, so ask whether to reopen it or close it
+    WorkspaceRootChanged {
+        old: PathBuf,
+        new: Option<PathBuf>,
+    },
     ProxyUpdateStatus(ProxyStatus),
     CloseTerminal(TermId),
+    /// The shell of the terminal reported its new working directory
+    TerminalCwdChanged {
+        term_id: TermId,
+        cwd: PathBuf,
+    },
+    TerminalExited {
+        term_id: TermId,
+        code: Option<i32>,
+    },
     SplitTerminal(bool, WidgetId),
     SplitTerminalClose(TermId, WidgetId),
     SplitEditor(bool, WidgetId),
@@ -613,6 +952,15 @@ pub enum LapceUICommand {
     /// Create a file in the given path with the given name and then open it
     CreateFileOpen {
         path: PathBuf,
+        /// The template to fill the file with, if any
+        template: Option<PathBuf>,
+        /// The text to fill the file with, which takes precedence over the
+        /// template
+        content: Option<String>,
+    },
+    /// Save the image on the clipboard as a new PNG file in the directory
+    PasteImageAsNewFile {
+        dir: PathBuf,
     },
     CreateDirectory {
         path: PathBuf,
@@ -621,10 +969,35 @@ pub enum LapceUICommand {
         from: PathBuf,
         to: PathBuf,
     },
+    /// The path was renamed, so the open files at it or beneath it follow it
+    PathRenamed {
+        from: PathBuf,
+        to: PathBuf,
+    },
     /// Move a file/directory to the os-specific trash
     TrashPath {
         path: PathBuf,
     },
+    /// Move the selected files/directories to the trash, reporting the ones
+    /// that couldn't be moved together
+    TrashPaths {
+        paths: Vec<PathBuf>,
+    },
+    /// Delete the files/directories permanently, reporting the ones that
+    /// couldn't be deleted together
+    DeletePaths {
+        paths: Vec<PathBuf>,
+    },
+    /// Expand or collapse the directory in the file explorer, reading it
+    /// first if needed
+    ExplorerSetOpen {
+        path: PathBuf,
+        open: bool,
+    },
+    /// Move the path in the trash folder of the workspace back to where it was
+    RestoreFromTrash {
+        id: String,
+    },
     /// Start renaming a specific file in view at the given index
     ExplorerStartRename {
         /// The index into the explorer's file listing
@@ -634,6 +1007,29 @@ pub enum LapceUICommand {
         /// The text it will start with
         text: String,
     },
+    /// Show the file/directory in the platform's file manager
+    RevealInFileManager {
+        path: PathBuf,
+    },
+    /// Open the platform's terminal emulator in the directory
+    OpenInSystemTerminal {
+        path: PathBuf,
+    },
+    /// Open the settings file of the workspace in an editor and reveal it in
+    /// the file explorer, creating it first if it's missing
+    OpenWorkspaceSettings,
+    /// Open the file with the application the platform associates with it
+    OpenWithDefaultApplication {
+        path: PathBuf,
+    },
+    /// Start renaming the file at the path, if it is in view
+    ExplorerStartRenamePath {
+        path: PathBuf,
+    },
+    /// Copy a file/directory to a sibling and start renaming the copy
+    DuplicatePath {
+        path: PathBuf,
+    },
     /// Start creating a new file/directory
     ExplorerNew {
         /// The index in the explorer's file listing that this should appear *after*
@@ -644,12 +1040,34 @@ pub enum LapceUICommand {
         is_dir: bool,
         /// The folder that it would be created in
         base_path: PathBuf,
+        /// The template the new file should be created from, if any
+        template: Option<PathBuf>,
+        /// The text the new file should be filled with, if any
+        content: Option<String>,
+    },
+    /// Open each of the files, in order, and expand each of the directories
+    ExplorerOpenPaths {
+        paths: Vec<PathBuf>,
+    },
+    /// The directory couldn't be read, so it's no longer waited for
+    ExplorerReadDirFailed {
+        path: PathBuf,
     },
     ExplorerEndNaming {
         /// Whether it should name/rename the file with the input data
         apply_naming: bool,
     },
     SetLanguage(String),
+    /// Read the file of the active editor again in the encoding
+    ReopenWithEncoding(String),
+    /// The encoding and line ending the proxy read the file in
+    UpdateFileFormat {
+        path: PathBuf,
+        encoding: String,
+        line_ending: LineEnding,
+    },
+    /// Convert the lines of the file of the active editor to the line ending
+    ConvertLineEnding(LineEnding),
 }
 
 /// This can't be an `FnOnce` because we only ever get a reference to
@@ -20,6 +20,7 @@ use thiserror::Error;
 use toml_edit::easy as toml;
 
 use crate::{
+    accent::{set_accent_in_use, system_accent_color},
     command::{LapceUICommand, LAPCE_UI_COMMAND},
     data::{LapceWorkspace, LapceWorkspaceType},
 };
@@ -104,6 +105,10 @@ impl LapceTheme {
     pub const PANEL_BACKGROUND: &'static str = "panel.background";
     pub const PANEL_CURRENT: &'static str = "panel.current";
     pub const PANEL_HOVERED: &'static str = "panel.hovered";
+    pub const PANEL_INDENT_GUIDE: &'static str = "panel.indent_guide";
+
+    pub const EXPLORER_ACTIVE_BORDER: &'static str = "explorer.active_border";
+    pub const EXPLORER_HOVER_BORDER: &'static str = "explorer.hover_border";
 
     pub const STATUS_BACKGROUND: &'static str = "status.background";
     pub const STATUS_MODAL_NORMAL: &'static str = "status.modal.normal";
@@ -176,6 +181,10 @@ pub struct EditorConfig {
         desc = "How long (in ms) it should take before the hover information appears"
     )]
     pub hover_delay: u64,
+    #[field_names(
+        desc = "How long (in ms) diagnostics of a file are held back, so that quick successive updates from the language server are only shown once. If 0, they are shown right away"
+    )]
+    pub diagnostics_debounce: u64,
     #[field_names(
         desc = "If modal mode should have relative line numbers (though, not in insert mode)"
     )]
@@ -291,6 +300,96 @@ pub struct UIConfig {
     hover_font_family: String,
     #[field_names(desc = "Set the hover font size. If 0, uses the UI font size")]
     hover_font_size: usize,
+
+    #[field_names(
+        desc = "Set the indentation width of each nesting level in the file explorer"
+    )]
+    explorer_indent_width: usize,
+    #[field_names(
+        desc = "Show vertical guide lines for each nesting level in the file explorer"
+    )]
+    explorer_indent_guides: bool,
+    #[field_names(
+        desc = "Reveal and highlight the file of the active editor in the file explorer whenever it changes"
+    )]
+    explorer_sync_active_editor: bool,
+    #[field_names(
+        desc = "Show chains of directories that each only contain one directory as a single row in the file explorer"
+    )]
+    explorer_compact_folders: bool,
+    #[field_names(
+        desc = "Glob patterns of the files and directories that are never listed in the file explorer, like \"node_modules\" or \"**/*.o\". Excluded directories are not read at all"
+    )]
+    explorer_exclude: Vec<String>,
+    #[field_names(
+        desc = "Set the folder beneath the workspace that import paths copied from the file explorer are relative to"
+    )]
+    explorer_import_source_root: String,
+    #[field_names(
+        desc = "Set the separator of the import paths copied from the file explorer, by file extension"
+    )]
+    explorer_import_separators: HashMap<String, String>,
+    #[field_names(
+        desc = "Set the file extensions that Tab completes to when naming a file in the file explorer, after the names of the other files in its directory"
+    )]
+    explorer_naming_extensions: Vec<String>,
+    #[field_names(
+        desc = "If the current row of the file explorer should be highlighted with the accent color from the system settings, where the platform has one"
+    )]
+    explorer_use_accent_color: bool,
+    #[field_names(
+        desc = "Set the spacing of the rows in the file explorer, around the UI font size. Can be \"compact\", \"normal\" or \"comfortable\""
+    )]
+    explorer_density: String,
+    #[field_names(
+        desc = "Set what clicking the header of the file explorer does. Can be \"reveal-active-file\", \"collapse-all\" or \"none\""
+    )]
+    explorer_header_click_action: String,
+    #[field_names(
+        desc = "Set how files are opened from the file explorer. Can be \"single-click\", \"double-click\", or \"single-click-preview\" to open on a single click while keeping the focus in the explorer and move it to the editor on a double click"
+    )]
+    explorer_file_open_gesture: String,
+    #[field_names(
+        desc = "Set how clicking the name of a directory in the file explorer expands it, besides clicking its chevron. Can be \"single-click\" or \"double-click\""
+    )]
+    explorer_dir_expand_gesture: String,
+    #[field_names(
+        desc = "Show the number of files and directories in each collapsed directory of the file explorer that has been read"
+    )]
+    explorer_show_child_count: bool,
+    #[field_names(
+        desc = "Set how many of the most recently opened files are listed in the recent section at the top of the file explorer. 0 hides the section"
+    )]
+    explorer_recent_files_limit: usize,
+    #[field_names(
+        desc = "Show the size of each file in the file explorer, and the total size of the files beneath each directory"
+    )]
+    explorer_show_sizes: bool,
+    #[field_names(
+        desc = "Keep the directories that the topmost row of the file explorer is in pinned to the top while scrolling"
+    )]
+    explorer_sticky_headers: bool,
+    #[field_names(
+        desc = "Set the file extensions that are opened with the system's default application when opened from the file explorer, instead of as text"
+    )]
+    explorer_open_externally: Vec<String>,
+    #[field_names(
+        desc = "Set what deleting in the file explorer does. Can be \"trash\" to move to the trash, or \"delete\" to delete permanently after asking. Shift+Delete always deletes permanently"
+    )]
+    explorer_delete_action: String,
+    #[field_names(
+        desc = "If the home directory should be abbreviated to ~ in the paths that are shown"
+    )]
+    abbreviate_home_dir: bool,
+    #[field_names(
+        desc = "Set the order of the changed files in the source control panel. Can be \"path\", or \"status\" to group them by their kind of change first"
+    )]
+    source_control_order: String,
+
+    #[field_names(
+        desc = "Set the path of an icon theme directory with SVGs and an icon-theme.json mapping file types to them. If empty, it uses the built-in icons"
+    )]
+    icon_theme: String,
 }
 
 impl UIConfig {
@@ -347,6 +446,116 @@ impl UIConfig {
             self.hover_font_size
         }
     }
+
+    pub fn explorer_indent_width(&self) -> usize {
+        self.explorer_indent_width.max(4).min(40)
+    }
+
+    pub fn explorer_indent_guides(&self) -> bool {
+        self.explorer_indent_guides
+    }
+
+    pub fn explorer_sync_active_editor(&self) -> bool {
+        self.explorer_sync_active_editor
+    }
+
+    pub fn explorer_compact_folders(&self) -> bool {
+        self.explorer_compact_folders
+    }
+
+    pub fn explorer_exclude(&self) -> &[String] {
+        &self.explorer_exclude
+    }
+
+    pub fn explorer_import_source_root(&self) -> &str {
+        &self.explorer_import_source_root
+    }
+
+    pub fn explorer_import_separator(&self, extension: &str) -> Option<&str> {
+        self.explorer_import_separators
+            .get(extension)
+            .map(String::as_str)
+    }
+
+    pub fn explorer_naming_extensions(&self) -> &[String] {
+        &self.explorer_naming_extensions
+    }
+
+    pub fn explorer_use_accent_color(&self) -> bool {
+        self.explorer_use_accent_color
+    }
+
+    /// The height of the rows in the file explorer, which is 25 at the
+    /// default font size and density
+    pub fn explorer_line_height(&self) -> usize {
+        let spacing = match self.explorer_density.as_str() {
+            "compact" => 8,
+            "comfortable" => 18,
+            _ => 12,
+        };
+        self.font_size() + spacing
+    }
+
+    pub fn explorer_header_click_action(&self) -> &str {
+        &self.explorer_header_click_action
+    }
+
+    pub fn explorer_file_open_gesture(&self) -> &str {
+        &self.explorer_file_open_gesture
+    }
+
+    pub fn explorer_dir_expand_gesture(&self) -> &str {
+        &self.explorer_dir_expand_gesture
+    }
+
+    pub fn explorer_show_child_count(&self) -> bool {
+        self.explorer_show_child_count
+    }
+
+    pub fn explorer_recent_files_limit(&self) -> usize {
+        self.explorer_recent_files_limit
+    }
+
+    pub fn explorer_show_sizes(&self) -> bool {
+        self.explorer_show_sizes
+    }
+
+    pub fn explorer_sticky_headers(&self) -> bool {
+        self.explorer_sticky_headers
+    }
+
+    /// Whether deleting in the file explorer deletes permanently instead of
+    /// moving to the trash
+    pub fn explorer_deletes_permanently(&self) -> bool {
+        self.explorer_delete_action == "delete"
+    }
+
+    /// Whether the file is opened with the system's default application
+    /// instead of as text, by its extension
+    pub fn explorer_opens_externally(&self, path: &Path) -> bool {
+        path.extension().map_or(false, |ext| {
+            let ext = ext.to_string_lossy();
+            self.explorer_open_externally
+                .iter()
+                .any(|open| open.eq_ignore_ascii_case(&ext))
+        })
+    }
+
+    pub fn abbreviate_home_dir(&self) -> bool {
+        self.abbreviate_home_dir
+    }
+
+    pub fn source_control_order(&self) -> &str {
+        &self.source_control_order
+    }
+
+    pub fn icon_theme(&self) -> Option<&Path> {
+        if self.icon_theme.is_empty() {
+            None
+        } else {
+            Some(Path::new(&self.icon_theme))
+        }
+    }
 }
 
 #[derive(FieldNames, Debug, Clone, Deserialize, Serialize, Default)]
@@ -366,6 +575,10 @@ pub struct TerminalConfig {
     pub line_height: usize,
     #[field_names(desc = "Set the terminal Shell")]
     pub shell: String,
+    #[field_names(
+        desc = "Set when a terminal closes after its process exits: \"always\", \"on-success\" to keep it open when the process failed or its exit code is unknown, or \"never\""
+    )]
+    pub close_on_exit: String,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -671,6 +884,16 @@ impl Config {
         config.resolve_colors(Some(&default_config));
         config.default_theme = default_config.theme.clone();
 
+        // The theme's color stays when the accent color can't be read
+        let use_accent = config.ui.explorer_use_accent_color();
+        set_accent_in_use(use_accent);
+        if let Some(accent) = use_accent.then(system_accent_color).flatten() {
+            config.color.ui.insert(
+                LapceTheme::PANEL_CURRENT.to_string(),
+                accent.with_alpha(0.4),
+            );
+        }
+
         Ok(config)
     }
 
@@ -879,6 +1102,40 @@ impl Config {
         Some(path)
     }
 
+    /// Get the path to the templates folder
+    /// Each file within is a template that new files can be created from
+    pub fn templates_folder() -> Option<PathBuf> {
+        let path = Self::dir()?.join("templates");
+
+        if let Some(dir) = path.parent() {
+            if !dir.exists() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+        }
+
+        if !path.exists() {
+            let _ = std::fs::create_dir(&path);
+        }
+
+        Some(path)
+    }
+
+    /// List the templates in the templates folder, sorted by name
+    pub fn templates() -> Vec<PathBuf> {
+        let mut templates = Self::templates_folder()
+            .and_then(|folder| std::fs::read_dir(folder).ok())
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_file())
+                    .collect::<Vec<PathBuf>>()
+            })
+            .unwrap_or_default();
+        templates.sort();
+        templates
+    }
+
     fn get_file_table() -> Option<toml_edit::Document> {
         let path = Self::settings_file()?;
         let content = std::fs::read_to_string(path).ok()?;
@@ -977,6 +1234,12 @@ impl Config {
             .unwrap_or_else(|| panic!("Key not found: {name}"))
     }
 
+    /// Get the color by the name from the current theme or the base theme, for
+    /// the optional colors that a theme can leave out
+    pub fn get_color(&self, name: &str) -> Option<&Color> {
+        self.color.ui.get(name)
+    }
+
     /// Retrieve a color value whose key starts with "style."
     pub fn get_style_color(&self, name: &str) -> Option<&Color> {
         self.color.syntax.get(name)
@@ -31,7 +31,10 @@ use lapce_core::{
     selection::Selection,
 };
 use lapce_rpc::{
-    buffer::BufferId, plugin::PluginDescription, source_control::FileDiff,
+    buffer::{BufferId, LineEnding},
+    core::LanguageServerState,
+    plugin::PluginDescription,
+    source_control::FileDiff,
     terminal::TermId,
 };
 
@@ -45,11 +48,13 @@ use toml_edit::easy as toml;
 use xi_rope::{Rope, RopeDelta};
 
 use crate::{
+    accent::watch_accent_color,
     alert::{AlertContentData, AlertData},
     command::{
         CommandKind, EnsureVisiblePosition, InitBufferContentCb, LapceCommand,
-        LapceUICommand, LapceWorkbenchCommand, PluginLoadingStatus, LAPCE_COMMAND,
-        LAPCE_OPEN_FILE, LAPCE_OPEN_FOLDER, LAPCE_UI_COMMAND,
+        LapceUICommand, LapceWorkbenchCommand, PluginLoadingStatus,
+        LAPCE_ADD_FOLDER_TO_WORKSPACE, LAPCE_COMMAND, LAPCE_OPEN_FILE,
+        LAPCE_OPEN_FOLDER, LAPCE_OPEN_FOLDER_IN_NEW_WINDOW, LAPCE_UI_COMMAND,
     },
     completion::CompletionData,
     config::{Config, ConfigWatcher, GetConfig, LapceTheme},
@@ -58,9 +63,13 @@ use crate::{
         SplitInfo, TabsInfo, WindowInfo, WorkspaceInfo,
     },
     document::{BufferContent, Document, LocalBufferKind},
-    editor::{EditorLocation, EditorPosition, LapceEditorBufferData, Line, TabRect},
-    explorer::FileExplorerData,
+    editor::{
+        split_path_position, EditorLocation, EditorPosition, LapceEditorBufferData,
+        Line, TabRect,
+    },
+    explorer::{FileExplorerData, FileExplorerOptions},
     find::Find,
+    history::{COMPARE_HISTORY_VERSION, DISK_HISTORY_VERSION},
     hover::HoverData,
     keypress::KeyPressData,
     palette::{PaletteData, PaletteType, PaletteViewData},
@@ -68,7 +77,7 @@ use crate::{
         PanelContainerPosition, PanelData, PanelKind, PanelOrder, PanelPosition,
     },
     picker::FilePickerData,
-    plugin::PluginData,
+    plugin::{PluginData, PluginInstallStatus},
     problem::ProblemData,
     proxy::{LapceProxy, ProxyStatus, TermEvent},
     search::SearchData,
@@ -109,7 +118,23 @@ impl LapceData {
             .unwrap_or_else(|_| Self::default_panel_orders());
 
         if let Some(path) = path {
+            // `lapce path:line:column` opens the file at the position, unless
+            // there is a file with that very name
+            let (path, position) = if Path::new(&path).exists() {
+                (path.as_str(), None)
+            } else {
+                split_path_position(&path)
+            };
             let path = PathBuf::from(path).canonicalize().unwrap();
+            // A file is opened in the workspace of its folder
+            let (path, file) = if path.is_file() {
+                match path.parent() {
+                    Some(dir) => (dir.to_path_buf(), Some(path.clone())),
+                    None => (path.clone(), None),
+                }
+            } else {
+                (path, None)
+            };
             if path.is_dir() {
                 #[cfg(target_os = "windows")]
                 let workspace_type =
@@ -144,6 +169,18 @@ impl LapceData {
                     &info,
                     db.clone(),
                 );
+                if let Some(file) = file {
+                    let (line, column) = position.unwrap_or((1, 1));
+                    let _ = event_sink.submit_command(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::OpenFileAt {
+                            path: file,
+                            line,
+                            column,
+                        },
+                        Target::Widget(window.active_id),
+                    );
+                }
                 windows.insert(window.window_id, window);
             }
         } else if let Ok(app) = db.get_app() {
@@ -420,6 +457,7 @@ impl LapceWindowData {
             Target::Widget(active_tab_id),
         );
 
+        watch_accent_color(event_sink.clone());
         let mut watcher =
             notify::recommended_watcher(ConfigWatcher::new(event_sink)).unwrap();
         if let Some(path) = Config::settings_file() {
@@ -492,6 +530,8 @@ pub struct WorkProgress {
     pub title: String,
     pub message: Option<String>,
     pub percentage: Option<u32>,
+    /// Whether the operation can be stopped from the UI
+    pub cancellable: bool,
 }
 
 #[derive(Clone, PartialEq, Data)]
@@ -506,6 +546,8 @@ pub enum FocusArea {
 pub enum DragContent {
     EditorTab(WidgetId, usize, EditorTabChild, Box<TabRect>),
     Panel(PanelKind, Rect),
+    /// A file or directory dragged out of the file explorer
+    ExplorerPath(PathBuf),
 }
 
 #[derive(Clone, Lens)]
@@ -530,6 +572,13 @@ pub struct LapceTabData {
     pub uninstalled_plugins_desc: Arc<PluginLoadingStatus>,
     pub installed_plugins: Arc<HashMap<String, PluginDescription>>,
     pub disabled_plugins: Arc<HashMap<String, PluginDescription>>,
+    /// The error of every installed plugin that failed to start, by name
+    pub failed_plugins: Arc<HashMap<String, String>>,
+    /// The plugins being installed, or that failed to be, by name
+    pub plugin_installs: Arc<HashMap<String, PluginInstallStatus>>,
+    /// The home directory of the machine the workspace is on, once the proxy
+    /// has reported it
+    pub home_dir: Option<PathBuf>,
     pub file_explorer: Arc<FileExplorerData>,
     pub proxy: Arc<LapceProxy>,
     pub proxy_status: Arc<ProxyStatus>,
@@ -545,6 +594,8 @@ pub struct LapceTabData {
     pub focus_area: FocusArea,
     pub db: Arc<LapceDb>,
     pub progresses: im::Vector<WorkProgress>,
+    /// The state of the language server of each language that has one
+    pub language_servers: im::OrdMap<String, LanguageServerState>,
     pub drag: Arc<Option<(Vec2, Vec2, DragContent)>>,
 }
 
@@ -565,6 +616,7 @@ impl Data for LapceTabData {
             && self.find.same(&other.find)
             && self.alert.same(&other.alert)
             && self.progresses.ptr_eq(&other.progresses)
+            && self.language_servers.ptr_eq(&other.language_servers)
             && self.file_explorer.same(&other.file_explorer)
             && self.plugin.same(&other.plugin)
             && self.problem.same(&other.problem)
@@ -577,6 +629,9 @@ impl Data for LapceTabData {
                 .same(&other.uninstalled_plugins_desc)
             && self.disabled_plugins.same(&other.disabled_plugins)
             && self.installed_plugins.same(&other.installed_plugins)
+            && self.failed_plugins.same(&other.failed_plugins)
+            && self.plugin_installs.same(&other.plugin_installs)
+            && self.home_dir == other.home_dir
             && self.picker.same(&other.picker)
             && self.drag.same(&other.drag)
             && self.keypress.same(&other.keypress)
@@ -615,6 +670,10 @@ impl LapceTabData {
             term_sender.clone(),
             event_sink.clone(),
         ));
+        // Sent before the explorer reads the workspace, so that the excluded
+        // paths never show up
+        proxy.set_file_exclude(config.ui.explorer_exclude());
+        proxy.set_diagnostics_debounce(config.editor.diagnostics_debounce);
         let palette = Arc::new(PaletteData::new(proxy.clone()));
         let completion = Arc::new(CompletionData::new());
         let hover = Arc::new(HoverData::new());
@@ -627,6 +686,24 @@ impl LapceTabData {
             workspace.clone(),
             proxy.clone(),
             event_sink.clone(),
+            FileExplorerOptions {
+                open_dirs: workspace_info
+                    .as_ref()
+                    .map(|info| info.explorer_open.clone())
+                    .unwrap_or_default(),
+                scroll_offset: workspace_info
+                    .as_ref()
+                    .map_or(0.0, |info| info.explorer_scroll),
+                pinned: workspace_info
+                    .as_ref()
+                    .map(|info| info.explorer_pinned.clone())
+                    .unwrap_or_default(),
+                extra_roots: workspace_info
+                    .as_ref()
+                    .map(|info| info.explorer_roots.clone())
+                    .unwrap_or_default(),
+                compact_folders: config.ui.explorer_compact_folders(),
+            },
         ));
         let search = Arc::new(SearchData::new());
         let file_picker = Arc::new(FilePickerData::new());
@@ -693,6 +770,13 @@ impl LapceTabData {
             &config,
             event_sink.clone(),
         );
+        main_split.add_editor(
+            file_explorer.filter_editor_view_id,
+            None,
+            LocalBufferKind::ExplorerFilter,
+            &config,
+            event_sink.clone(),
+        );
 
         let terminal = Arc::new(TerminalSplitData::new(proxy.clone()));
         let problem = Arc::new(ProblemData::new());
@@ -724,6 +808,9 @@ impl LapceTabData {
             installed_plugins_desc: Arc::new(PluginLoadingStatus::Ok(Vec::new())),
             uninstalled_plugins_desc: Arc::new(PluginLoadingStatus::Ok(Vec::new())),
             installed_plugins: Arc::new(HashMap::new()),
+            failed_plugins: Arc::new(HashMap::new()),
+            plugin_installs: Arc::new(HashMap::new()),
+            home_dir: None,
             find: Arc::new(Find::new(0)),
             picker: file_picker,
             source_control,
@@ -742,6 +829,7 @@ impl LapceTabData {
             focus_area: FocusArea::Editor,
             db,
             progresses: im::Vector::new(),
+            language_servers: im::OrdMap::new(),
             drag: Arc::new(None),
         };
         tab.start_update_process(event_sink);
@@ -757,6 +845,10 @@ impl LapceTabData {
         WorkspaceInfo {
             split: main_split_data.split_info(self),
             panel: (*self.panel).clone(),
+            explorer_open: self.file_explorer.open_dirs(),
+            explorer_scroll: self.file_explorer.scroll_offset,
+            explorer_pinned: self.file_explorer.pinned.clone(),
+            explorer_roots: self.file_explorer.extra_root_paths(),
         }
     }
 
@@ -1013,6 +1105,58 @@ impl LapceTabData {
         }
     }
 
+    /// The home directory, if paths are shown with it abbreviated to `~`
+    pub fn abbreviated_home(&self) -> Option<&Path> {
+        self.home_dir
+            .as_deref()
+            .filter(|_| self.config.ui.abbreviate_home_dir())
+    }
+
+    /// Let the open file take on the content it changed to on disk, or ask
+    /// which to keep when that would lose unsaved changes
+    pub fn handle_file_changed(
+        &mut self,
+        ctx: &mut EventCtx,
+        path: &Path,
+        content: Rope,
+    ) {
+        let doc = match self.main_split.open_docs.get_mut(path) {
+            Some(doc) => doc,
+            None => return,
+        };
+        if !Arc::make_mut(doc).handle_file_changed(content) {
+            return;
+        }
+
+        let button = |text: &str, command: LapceWorkbenchCommand| {
+            (
+                text.to_string(),
+                self.id,
+                LapceCommand {
+                    kind: CommandKind::Workbench(command),
+                    data: serde_json::to_value(path).ok(),
+                },
+            )
+        };
+        ctx.submit_command(Command::new(
+            LAPCE_UI_COMMAND,
+            LapceUICommand::ShowAlert(AlertContentData {
+                title: format!(
+                    "{} changed on disk",
+                    doc.content().file_name()
+                ),
+                msg: "It also has unsaved changes, which will be lost if you take the content on disk."
+                    .to_string(),
+                buttons: vec![
+                    button("Keep Mine", LapceWorkbenchCommand::KeepBufferChanges),
+                    button("Take Disk", LapceWorkbenchCommand::TakeDiskChanges),
+                    button("Open Diff", LapceWorkbenchCommand::DiffDiskChanges),
+                ],
+            }),
+            Target::Widget(self.id),
+        ));
+    }
+
     pub fn run_workbench_command(
         &mut self,
         ctx: &mut EventCtx,
@@ -1033,6 +1177,19 @@ impl LapceTabData {
                     ));
                 }
             }
+            LapceWorkbenchCommand::ReopenWorkspaceAt => {
+                if let Some(data) = data {
+                    if let Ok(path) = serde_json::from_value::<PathBuf>(data) {
+                        let mut workspace = (*self.workspace).clone();
+                        workspace.path = Some(path);
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::SetWorkspace(workspace),
+                            Target::Auto,
+                        ));
+                    }
+                }
+            }
             LapceWorkbenchCommand::OpenFolder => {
                 if !self.workspace.kind.is_remote() {
                     let options = FileDialogOptions::new()
@@ -1058,6 +1215,29 @@ impl LapceTabData {
                     }
                 }
             }
+            LapceWorkbenchCommand::OpenFolderInNewWindow => {
+                // Picking a folder is only supported on the local file system for now
+                if !self.workspace.kind.is_remote() {
+                    let options = FileDialogOptions::new()
+                        .select_directories()
+                        .accept_command(LAPCE_OPEN_FOLDER_IN_NEW_WINDOW);
+                    ctx.submit_command(
+                        druid::commands::SHOW_OPEN_PANEL.with(options),
+                    );
+                }
+            }
+            LapceWorkbenchCommand::AddFolderToWorkspace => {
+                // Picking a folder is only supported on the local file system for now
+                if self.workspace.path.is_some() && !self.workspace.kind.is_remote()
+                {
+                    let options = FileDialogOptions::new()
+                        .select_directories()
+                        .accept_command(LAPCE_ADD_FOLDER_TO_WORKSPACE);
+                    ctx.submit_command(
+                        druid::commands::SHOW_OPEN_PANEL.with(options),
+                    );
+                }
+            }
             LapceWorkbenchCommand::OpenFile => {
                 if !self.workspace.kind.is_remote() {
                     let options =
@@ -1120,6 +1300,13 @@ impl LapceTabData {
             LapceWorkbenchCommand::OpenSettings => {
                 self.main_split.open_settings(ctx, false);
             }
+            LapceWorkbenchCommand::OpenWorkspaceSettingsFile => {
+                ctx.submit_command(Command::new(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::OpenWorkspaceSettings,
+                    Target::Widget(self.id),
+                ));
+            }
             LapceWorkbenchCommand::OpenSettingsFile => {
                 if let Some(path) = Config::settings_file() {
                     self.main_split.jump_to_location(
@@ -1340,6 +1527,126 @@ impl LapceTabData {
                     }
                 }
             }
+            LapceWorkbenchCommand::RevealTerminalCwdInExplorer => {
+                let cwd = self
+                    .terminal
+                    .terminals
+                    .get(&self.terminal.active_term_id)
+                    .and_then(|terminal| terminal.cwd.clone());
+                if let Some(cwd) = cwd {
+                    let file_explorer = Arc::make_mut(&mut self.file_explorer);
+                    file_explorer.active_selected = Some(cwd.clone());
+                    file_explorer.reveal_path(&cwd);
+                    self.show_panel(ctx, PanelKind::FileExplorer);
+                }
+            }
+            LapceWorkbenchCommand::RefreshExplorer => {
+                // The sizes are added up again as the directories are listed
+                Arc::make_mut(&mut self.file_explorer).dir_sizes.clear();
+                self.file_explorer.reload();
+            }
+            LapceWorkbenchCommand::ToggleExplorerSearchFilter => {
+                let file_explorer = Arc::make_mut(&mut self.file_explorer);
+                file_explorer.set_search_filter(!file_explorer.search_filter);
+            }
+            LapceWorkbenchCommand::ExplorerTrashPath => {
+                if let Some(data) = data {
+                    if let Ok(path) = serde_json::from_value::<PathBuf>(data) {
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::TrashPath { path },
+                            Target::Widget(self.id),
+                        ));
+                    }
+                }
+            }
+            LapceWorkbenchCommand::ExplorerTrashPaths => {
+                if let Some(data) = data {
+                    if let Ok(paths) = serde_json::from_value::<Vec<PathBuf>>(data) {
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::TrashPaths { paths },
+                            Target::Widget(self.id),
+                        ));
+                    }
+                }
+            }
+            LapceWorkbenchCommand::ExplorerDeletePaths => {
+                if let Some(data) = data {
+                    if let Ok(paths) = serde_json::from_value::<Vec<PathBuf>>(data) {
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::DeletePaths { paths },
+                            Target::Widget(self.id),
+                        ));
+                    }
+                }
+            }
+            LapceWorkbenchCommand::ExplorerExpandAllLevels => {
+                if let Some(data) = data {
+                    if let Ok(path) = serde_json::from_value::<PathBuf>(data) {
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::ExpandExplorerDir {
+                                path,
+                                levels: None,
+                                continued: false,
+                            },
+                            Target::Widget(self.id),
+                        ));
+                    }
+                }
+            }
+            LapceWorkbenchCommand::RestoreFromTrash => {
+                ctx.submit_command(Command::new(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::RunPalette(Some(PaletteType::Trash)),
+                    Target::Auto,
+                ))
+            }
+            LapceWorkbenchCommand::ExplorerOpenPaths => {
+                if let Some(data) = data {
+                    if let Ok(paths) = serde_json::from_value::<Vec<PathBuf>>(data) {
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::ExplorerOpenPaths { paths },
+                            Target::Widget(self.id),
+                        ));
+                    }
+                }
+            }
+            LapceWorkbenchCommand::KeepBufferChanges
+            | LapceWorkbenchCommand::TakeDiskChanges => {
+                if let Some(data) = data {
+                    if let Ok(path) = serde_json::from_value::<PathBuf>(data) {
+                        if let Some(doc) = self.main_split.open_docs.get_mut(&path) {
+                            Arc::make_mut(doc).resolve_disk_conflict(
+                                *command == LapceWorkbenchCommand::KeepBufferChanges,
+                            );
+                        }
+                    }
+                }
+            }
+            LapceWorkbenchCommand::DiffDiskChanges => {
+                if let Some(data) = data {
+                    if let Ok(path) = serde_json::from_value::<PathBuf>(data) {
+                        if let Some(doc) = self.main_split.open_docs.get_mut(&path) {
+                            if let Some(content) = doc.disk_content().cloned() {
+                                Arc::make_mut(doc)
+                                    .load_history(DISK_HISTORY_VERSION, content);
+                                ctx.submit_command(Command::new(
+                                    LAPCE_UI_COMMAND,
+                                    LapceUICommand::OpenFileDiff(
+                                        path,
+                                        DISK_HISTORY_VERSION.to_string(),
+                                    ),
+                                    Target::Widget(self.id),
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
             LapceWorkbenchCommand::SourceControlInit => {
                 self.proxy.git_init();
             }
@@ -1439,6 +1746,44 @@ impl LapceTabData {
                     Target::Auto,
                 ))
             }
+            LapceWorkbenchCommand::ReopenWithEncoding => {
+                ctx.submit_command(Command::new(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::RunPalette(Some(PaletteType::Encoding)),
+                    Target::Auto,
+                ))
+            }
+            LapceWorkbenchCommand::ConvertLineEndingsToLf => {
+                ctx.submit_command(Command::new(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::ConvertLineEnding(LineEnding::Lf),
+                    Target::Auto,
+                ))
+            }
+            LapceWorkbenchCommand::ConvertLineEndingsToCrlf => {
+                ctx.submit_command(Command::new(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::ConvertLineEnding(LineEnding::CrLf),
+                    Target::Auto,
+                ))
+            }
+            LapceWorkbenchCommand::ToggleLineEnding => {
+                if let Some(editor) = self.main_split.active_editor() {
+                    let line_ending = match self
+                        .main_split
+                        .content_doc(&editor.content)
+                        .line_ending()
+                    {
+                        LineEnding::Lf => LineEnding::CrLf,
+                        LineEnding::CrLf | LineEnding::Mixed => LineEnding::Lf,
+                    };
+                    ctx.submit_command(Command::new(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::ConvertLineEnding(line_ending),
+                        Target::Auto,
+                    ))
+                }
+            }
             LapceWorkbenchCommand::NextEditorTab => {
                 if let Some(active) = *self.main_split.active_tab {
                     ctx.submit_command(Command::new(
@@ -1674,8 +2019,26 @@ impl LapceTabData {
         }
     }
 
-    pub fn handle_workspace_file_change(&self, _ctx: &mut EventCtx) {
-        self.file_explorer.reload();
+    /// Update the file explorer for the changed files. Files that are known to
+    /// be created only have their directories read again, and are revealed if
+    /// the explorer follows the active editor, as they're usually opened too.
+    pub fn handle_workspace_file_change(
+        &mut self,
+        _ctx: &mut EventCtx,
+        created: &[PathBuf],
+    ) {
+        if created.is_empty() {
+            self.file_explorer.reload();
+            return;
+        }
+
+        self.file_explorer.refresh_parents(created);
+        if self.config.ui.explorer_sync_active_editor() {
+            let file_explorer = Arc::make_mut(&mut self.file_explorer);
+            for path in created {
+                file_explorer.reveal_path(path);
+            }
+        }
     }
 }
 
@@ -1870,6 +2233,14 @@ impl LapceMainSplitData {
         Some(self.editors.get(&id)?.as_ref())
     }
 
+    /// The number of open files with changes that haven't been saved
+    pub fn unsaved_count(&self) -> usize {
+        self.open_docs
+            .values()
+            .filter(|doc| !doc.buffer().is_pristine() && doc.content().is_file())
+            .count()
+    }
+
     pub fn content_doc(&self, content: &BufferContent) -> Arc<Document> {
         match content {
             BufferContent::File(path) => self.open_docs.get(path).unwrap().clone(),
@@ -1963,6 +2334,51 @@ impl LapceMainSplitData {
         );
     }
 
+    /// Open the file in a diff against the content of another file that it is
+    /// compared with, once the file is loaded
+    pub fn open_compare_diff(
+        &mut self,
+        ctx: &mut EventCtx,
+        path: &Path,
+        content: Rope,
+        config: &Config,
+    ) {
+        let tab_id = *self.tab_id;
+        let diff_path = path.to_path_buf();
+        let open_diff =
+            move |ctx: &mut EventCtx, main_split: &mut LapceMainSplitData| {
+                if let Some(doc) = main_split.open_docs.get_mut(&diff_path) {
+                    Arc::make_mut(doc)
+                        .load_history(COMPARE_HISTORY_VERSION, content.clone());
+                    ctx.submit_command(Command::new(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::OpenFileDiff(
+                            diff_path.clone(),
+                            COMPARE_HISTORY_VERSION.to_string(),
+                        ),
+                        Target::Widget(tab_id),
+                    ));
+                }
+            };
+        match self.open_docs.get(path) {
+            Some(doc) if doc.loaded() => open_diff(ctx, self),
+            _ => {
+                self.jump_to_location_cb(
+                    ctx,
+                    None,
+                    EditorLocation {
+                        path: path.to_path_buf(),
+                        position: None::<usize>,
+                        scroll_offset: None,
+                        history: None,
+                    },
+                    config,
+                    Some(open_diff),
+                );
+            }
+        }
+    }
+
     pub fn diagnostics_items(
         &self,
         severity: DiagnosticSeverity,
@@ -2890,6 +3306,43 @@ impl LapceMainSplitData {
         }
     }
 
+    /// Point the documents and editors of the renamed path, or of the files
+    /// beneath it when it's a directory, at their new path. The documents are
+    /// kept as they are, so unsaved changes and cursors are preserved.
+    pub fn rename_path(&mut self, from: &Path, to: &Path) {
+        let renamed: Vec<(PathBuf, PathBuf)> = self
+            .open_docs
+            .keys()
+            .filter_map(|path| {
+                let suffix = path.strip_prefix(from).ok()?;
+                let new_path = if suffix.as_os_str().is_empty() {
+                    to.to_path_buf()
+                } else {
+                    to.join(suffix)
+                };
+                Some((path.clone(), new_path))
+            })
+            .collect();
+
+        for (old_path, new_path) in renamed {
+            let old_content = BufferContent::File(old_path.clone());
+            let new_content = BufferContent::File(new_path.clone());
+            for (_, editor) in self.editors.iter_mut() {
+                if editor.content == old_content {
+                    Arc::make_mut(editor).content = new_content.clone();
+                }
+            }
+
+            if let Some(mut doc) = self.open_docs.remove(&old_path) {
+                Arc::make_mut(&mut doc).set_content(new_content);
+                self.open_docs.insert(new_path.clone(), doc);
+            }
+            if let Some(diagnostics) = self.diagnostics.remove(&old_path) {
+                self.diagnostics.insert(new_path, diagnostics);
+            }
+        }
+    }
+
     pub fn save_as(
         &mut self,
         ctx: &mut EventCtx,
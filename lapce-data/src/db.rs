@@ -23,6 +23,7 @@ use crate::{
     },
     document::{BufferContent, Document},
     editor::EditorLocation,
+    explorer::PinnedPath,
     panel::{PanelData, PanelOrder},
     split::SplitDirection,
 };
@@ -218,6 +219,20 @@ impl SplitInfo {
 pub struct WorkspaceInfo {
     pub split: SplitInfo,
     pub panel: PanelData,
+    /// The open directories in the file explorer, relative to the workspace
+    #[serde(default)]
+    pub explorer_open: Vec<PathBuf>,
+    /// The vertical scroll offset of the file explorer's list
+    #[serde(default)]
+    pub explorer_scroll: f64,
+    /// The paths pinned to the favorites of the file explorer. They're kept
+    /// whole, since they can be in the folders added to the workspace.
+    #[serde(default)]
+    pub explorer_pinned: Vec<PinnedPath>,
+    /// The folders added to the workspace, in the order they're shown in the
+    /// file explorer
+    #[serde(default)]
+    pub explorer_roots: Vec<PathBuf>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
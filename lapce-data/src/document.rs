@@ -31,12 +31,12 @@ use lapce_core::{
     word::WordCursor,
 };
 use lapce_rpc::{
-    buffer::BufferId,
+    buffer::{BufferId, LineEnding},
     style::{LineStyle, LineStyles, Style},
 };
 use lsp_types::{
     CodeActionOrCommand, CodeActionResponse, DiagnosticSeverity, InlayHint,
-    InlayHintLabel,
+    InlayHintLabel, TextEdit,
 };
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
@@ -118,6 +118,7 @@ pub enum LocalBufferKind {
     Keymap,
     Settings,
     PathName,
+    ExplorerFilter,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -143,7 +144,8 @@ impl BufferContent {
                 | LocalBufferKind::FilePicker
                 | LocalBufferKind::Settings
                 | LocalBufferKind::Keymap
-                | LocalBufferKind::PathName => true,
+                | LocalBufferKind::PathName
+                | LocalBufferKind::ExplorerFilter => true,
                 LocalBufferKind::Empty => false,
             },
             BufferContent::SettingsValue(..) => true,
@@ -160,7 +162,8 @@ impl BufferContent {
                 | LocalBufferKind::FilePicker
                 | LocalBufferKind::Settings
                 | LocalBufferKind::Keymap
-                | LocalBufferKind::PathName => true,
+                | LocalBufferKind::PathName
+                | LocalBufferKind::ExplorerFilter => true,
                 LocalBufferKind::Empty | LocalBufferKind::SourceControl => false,
             },
             BufferContent::SettingsValue(..) => true,
@@ -395,6 +398,13 @@ pub struct Document {
     load_started: Rc<RefCell<bool>>,
     loaded: bool,
     histories: im::HashMap<String, DocumentHistory>,
+    /// The content of the file on disk, when it changed while the buffer had
+    /// unsaved changes and the user hasn't picked which to keep yet
+    disk_content: Option<Rope>,
+    /// The name of the encoding the proxy reads and saves the file in
+    encoding: String,
+    /// The line ending detected in the file, or the one it was last converted to
+    line_ending: LineEnding,
     pub cursor_offset: usize,
     pub scroll_offset: Vec2,
     pub code_actions: im::HashMap<usize, CodeActionResponse>,
@@ -435,6 +445,9 @@ impl Document {
             semantic_styles: None,
             load_started: Rc::new(RefCell::new(false)),
             histories: im::HashMap::new(),
+            disk_content: None,
+            encoding: "UTF-8".to_string(),
+            line_ending: LineEnding::Lf,
             loaded: false,
             cursor_offset: 0,
             scroll_offset: Vec2::ZERO,
@@ -456,6 +469,53 @@ impl Document {
         self.loaded
     }
 
+    pub fn encoding(&self) -> &str {
+        &self.encoding
+    }
+
+    pub fn set_encoding(&mut self, encoding: String) {
+        self.encoding = encoding;
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.line_ending = line_ending;
+    }
+
+    /// Change the line breaks that aren't of the line ending into it, as a
+    /// single undoable edit, and keep using the line ending from then on.
+    /// `Mixed` leaves the lines as they are.
+    pub fn convert_line_ending(&mut self, line_ending: LineEnding) {
+        self.line_ending = line_ending;
+        let line_break = match line_ending.line_break() {
+            Some(line_break) => line_break,
+            None => return,
+        };
+
+        let text = self.buffer.text().to_string();
+        let mut selection = Selection::new();
+        for (offset, _) in text.match_indices('\n') {
+            let crlf = offset > 0 && text.as_bytes()[offset - 1] == b'\r';
+            match line_ending {
+                LineEnding::Lf if crlf => selection.add_region(SelRegion::new(
+                    offset - 1,
+                    offset + 1,
+                    None,
+                )),
+                LineEnding::CrLf if !crlf => {
+                    selection.add_region(SelRegion::new(offset, offset + 1, None))
+                }
+                _ => {}
+            }
+        }
+        if !selection.is_empty() {
+            self.do_raw_edit(&[(&selection, line_break)], EditType::Other);
+        }
+    }
+
     pub fn set_content(&mut self, content: BufferContent) {
         self.content = content;
         self.syntax = match &self.content {
@@ -553,12 +613,69 @@ impl Document {
         self.apply_deltas(&[delta]);
     }
 
-    pub fn handle_file_changed(&mut self, content: Rope) {
+    /// Apply the edits of the file changing on disk as a single undoable
+    /// change, which leaves the buffer pristine. Returns the delta so that
+    /// cursors can be moved along, or `None` if an edit is out of bounds.
+    pub fn reload_edits(&mut self, edits: &[TextEdit]) -> Option<RopeDelta> {
+        let edits = edits
+            .iter()
+            .map(|edit| {
+                let start = self.buffer.offset_of_position(&edit.range.start)?;
+                let end = self.buffer.offset_of_position(&edit.range.end)?;
+                Some((Selection::region(start, end), edit.new_text.as_str()))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        self.code_actions.clear();
+        self.inlay_hints = None;
+        let (delta, inval_lines) = self.buffer.edit(&edits, EditType::Other);
+        self.buffer.set_pristine();
+        self.apply_deltas(&[(delta.clone(), inval_lines)]);
+        Some(delta)
+    }
+
+    /// Reload the buffer with the content the file changed to on disk, unless
+    /// that would lose unsaved changes. The content is then kept aside until
+    /// the conflict is resolved, and `true` is returned.
+    pub fn handle_file_changed(&mut self, content: Rope) -> bool {
         if self.buffer.is_pristine() {
+            self.disk_content = None;
             self.reload(content, true);
+            false
+        } else {
+            self.disk_content = Some(content);
+            true
         }
     }
 
+    /// The content of the file on disk that conflicts with unsaved changes
+    pub fn disk_content(&self) -> Option<&Rope> {
+        self.disk_content.as_ref()
+    }
+
+    /// Resolve a conflict with the file on disk by keeping the unsaved changes
+    /// if `keep_buffer`, or by replacing them with the content on disk
+    pub fn resolve_disk_conflict(&mut self, keep_buffer: bool) {
+        if let Some(content) = self.disk_content.take() {
+            if !keep_buffer {
+                self.reload(content, true);
+            }
+        }
+    }
+
+    /// The buffer's content with the edits applied, such as the ones of the
+    /// file changing on disk
+    pub fn content_with_edits(&self, edits: &[TextEdit]) -> Option<Rope> {
+        let mut content = self.buffer.text().clone();
+        // The edits are relative to the original content, so applying them
+        // from last to first keeps the offsets of the rest valid
+        for edit in edits.iter().rev() {
+            let start = self.buffer.offset_of_position(&edit.range.start)?;
+            let end = self.buffer.offset_of_position(&edit.range.end)?;
+            content.edit(start..end, edit.new_text.as_str());
+        }
+        Some(content)
+    }
+
     pub fn retrieve_file<P: EditorPosition + Send + 'static>(
         &mut self,
         locations: Vec<(WidgetId, EditorLocation<P>)>,
@@ -579,6 +696,15 @@ impl Document {
             std::thread::spawn(move || {
                 proxy.new_buffer(id, path.clone(), move |result| {
                     if let Ok(resp) = result {
+                        let _ = event_sink.submit_command(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::UpdateFileFormat {
+                                path: path.clone(),
+                                encoding: resp.encoding,
+                                line_ending: resp.line_ending,
+                            },
+                            Target::Widget(tab_id),
+                        );
                         let _ = event_sink.submit_command(
                             LAPCE_UI_COMMAND,
                             P::init_buffer_content_cmd(
@@ -890,6 +1016,13 @@ impl Document {
                     LocalBufferKind::PathName => {
                         // TODO: anything to update with this?
                     }
+                    LocalBufferKind::ExplorerFilter => {
+                        let _ = self.event_sink.submit_command(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::UpdateExplorerFilter(s),
+                            Target::Widget(self.tab_id),
+                        );
+                    }
                 }
             }
             BufferContent::SettingsValue(..) => {}
@@ -1,3 +1,4 @@
+use crate::alert::AlertContentData;
 use crate::command::InitBufferContentCb;
 use crate::command::LapceCommand;
 use crate::command::LAPCE_COMMAND;
@@ -13,11 +14,12 @@ use crate::data::{
 use crate::document::BufferContent;
 use crate::document::Document;
 use crate::document::LocalBufferKind;
+use crate::explorer::dropped_path_text;
 use crate::hover::HoverData;
 use crate::hover::HoverStatus;
 use crate::keypress::KeyMap;
 use crate::keypress::KeyPressFocus;
-use crate::palette::PaletteData;
+use crate::palette::{PaletteData, PaletteType};
 use crate::proxy::path_from_url;
 use crate::proxy::RequestError;
 use crate::{
@@ -55,6 +57,8 @@ use lsp_types::CompletionTextEdit;
 use lsp_types::DocumentChangeOperation;
 use lsp_types::DocumentChanges;
 use lsp_types::OneOf;
+use lsp_types::PrepareRenameResponse;
+use lsp_types::ResourceOp;
 use lsp_types::TextEdit;
 use lsp_types::Url;
 use lsp_types::WorkspaceEdit;
@@ -172,6 +176,26 @@ impl EditorPosition for LineCol {
     }
 }
 
+/// Split a `path:line:column` or `path:line` string, as given by other tools,
+/// into the path and the one based line and column. The column is 1 when it's
+/// missing, and the position is `None` when there is no line.
+pub fn split_path_position(s: &str) -> (&str, Option<(usize, usize)>) {
+    let (rest, last) = match s.rsplit_once(':') {
+        Some(split) => split,
+        None => return (s, None),
+    };
+    let last = match last.parse::<usize>() {
+        Ok(last) => last,
+        Err(_) => return (s, None),
+    };
+    if let Some((path, line)) = rest.rsplit_once(':') {
+        if let Ok(line) = line.parse::<usize>() {
+            return (path, Some((line, last)));
+        }
+    }
+    (rest, Some((last, 1)))
+}
+
 impl EditorPosition for Position {
     fn to_utf8_offset(&self, buffer: &Buffer) -> Option<usize> {
         buffer.offset_of_position(self)
@@ -1117,6 +1141,41 @@ impl LapceEditorBufferData {
         self.doc.buffer().offset_of_line_col(line, col)
     }
 
+    /// Insert the text for a path dropped at the position, with the cursor
+    /// ending up right after it
+    pub fn drop_path(
+        &mut self,
+        ctx: &mut EventCtx,
+        pos: Point,
+        path: &Path,
+        config: &Config,
+    ) {
+        let (offset, _) = self.doc.offset_of_point(
+            ctx.text(),
+            self.get_mode(),
+            pos,
+            &self.editor.view,
+            config,
+        );
+        let doc_path = match self.doc.content() {
+            BufferContent::File(path) => Some(path.as_path()),
+            _ => None,
+        };
+        let text = dropped_path_text(path, doc_path);
+
+        let doc = Arc::make_mut(&mut self.doc);
+        let delta = doc.do_raw_edit(
+            &[(Selection::caret(offset), text.as_str())],
+            EditType::InsertChars,
+        );
+        Arc::make_mut(&mut self.editor).cursor.set_offset(
+            offset + text.len(),
+            false,
+            false,
+        );
+        self.apply_deltas(&[delta]);
+    }
+
     pub fn single_click(
         &mut self,
         ctx: &mut EventCtx,
@@ -1244,6 +1303,36 @@ impl LapceEditorBufferData {
             let tab_id = self.main_split.tab_id.clone();
             let (sender, receiver) = bounded(1);
             thread::spawn(move || {
+                let (writable_sender, writable_receiver) = bounded(1);
+                proxy.is_path_writable(&path, move |result| {
+                    let _ = writable_sender.send(result);
+                });
+                // If we can't tell, we still try to save and let the proxy report errors
+                let writable = writable_receiver
+                    .recv_timeout(Duration::from_secs(1))
+                    .ok()
+                    .and_then(|result| result.ok())
+                    .map(|resp| resp.writable)
+                    .unwrap_or(true);
+                if !writable {
+                    let _ = event_sink.submit_command(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::ShowAlert(AlertContentData {
+                            title: format!(
+                                "{} is read-only",
+                                path.file_name()
+                                    .map(|f| f.to_string_lossy().to_string())
+                                    .unwrap_or_default()
+                            ),
+                            msg: "You don't have permission to write to this file. Your changes have not been saved."
+                                .to_string(),
+                            buttons: Vec::new(),
+                        }),
+                        Target::Widget(*tab_id),
+                    );
+                    return;
+                }
+
                 proxy.get_document_formatting(
                     buffer_id,
                     Box::new(move |result| {
@@ -1810,6 +1899,65 @@ impl LapceEditorBufferData {
                 // we allow empty inputs to allow for cases where the user wants to get the autocompletion beforehand
                 self.update_completion(ctx, true);
             }
+            RenameSymbol => {
+                let offset = self.editor.cursor.offset();
+                let position = if let Some(position) =
+                    self.doc.buffer().offset_to_position(offset)
+                {
+                    position
+                } else {
+                    log::error!("Failed to convert offset {offset} to position in RenameSymbol");
+                    return CommandExecuted::Yes;
+                };
+                // The new name starts out as the word at the cursor, unless
+                // the language server has another one to start from
+                let (start, end) = self.doc.buffer().select_word(offset);
+                let word = self.doc.buffer().slice_to_cow(start..end).to_string();
+                let buffer_id = self.doc.id();
+                let palette_id = self.palette.widget_id;
+                let tab_id = *self.main_split.tab_id;
+                let event_sink = ctx.get_external_handle();
+                self.proxy
+                    .prepare_rename(buffer_id, position, move |result| {
+                        let msg = match result {
+                            Ok(Some(resp)) => {
+                                let placeholder = match resp {
+                                PrepareRenameResponse::RangeWithPlaceholder {
+                                    placeholder,
+                                    ..
+                                } => placeholder,
+                                _ => word,
+                            };
+                                let _ = event_sink.submit_command(
+                                    LAPCE_UI_COMMAND,
+                                    LapceUICommand::RunPalette(Some(
+                                        PaletteType::Rename {
+                                            buffer_id,
+                                            position,
+                                            placeholder,
+                                        },
+                                    )),
+                                    Target::Widget(palette_id),
+                                );
+                                return;
+                            }
+                            Ok(None) => {
+                                "There is no symbol to rename at the cursor."
+                                    .to_string()
+                            }
+                            Err(err) => err.message(),
+                        };
+                        let _ = event_sink.submit_command(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::ShowAlert(AlertContentData {
+                                title: "Couldn't rename the symbol".to_string(),
+                                msg,
+                                buttons: Vec::new(),
+                            }),
+                            Target::Widget(tab_id),
+                        );
+                    });
+            }
             GotoDefinition => {
                 let offset = self.editor.cursor.offset();
                 let start_offset = self.doc.buffer().prev_code_boundary(offset);
@@ -2327,6 +2475,76 @@ fn process_get_references(
     Ok(())
 }
 
+/// Apply the text edits of the workspace edit to each of the files, loading the
+/// ones that aren't open yet. Returns the paths of the files that are edited.
+pub fn apply_workspace_edit(
+    ctx: &mut EventCtx,
+    main_split: &mut LapceMainSplitData,
+    edit: &WorkspaceEdit,
+    config: &Config,
+) -> Vec<PathBuf> {
+    // Files that the edit creates are made first, so that its text edits to
+    // them load them from disk
+    for path in created_files(edit) {
+        let err_path = path.clone();
+        main_split.proxy.create_file(
+            &path,
+            None,
+            Box::new(move |result| {
+                if let Err(err) = result {
+                    log::warn!(
+                        "Failed to create {err_path:?} for a workspace edit: {err:?}"
+                    );
+                }
+            }),
+        );
+    }
+
+    let edits = match workspace_edits(edit) {
+        Some(edits) => edits,
+        None => return Vec::new(),
+    };
+
+    let mut paths = Vec::new();
+    for (url, edits) in edits {
+        let path = match url.to_file_path() {
+            Ok(path) => path,
+            Err(_) => {
+                log::warn!("Text edits failed to apply to URL {url:?} because it is not a file");
+                continue;
+            }
+        };
+        if let Some(doc) = main_split.open_docs.get(&path).cloned() {
+            apply_code_action(&doc, main_split, &path, &edits);
+        } else {
+            let position = edits.get(0).map(|edit| edit.range.start);
+            let url_path = path.clone();
+            main_split.jump_to_location_cb(
+                ctx,
+                None,
+                EditorLocation {
+                    path: path.clone(),
+                    position,
+                    scroll_offset: None,
+                    history: None,
+                },
+                config,
+                Some(
+                    move |_: &mut EventCtx, main_split: &mut LapceMainSplitData| {
+                        if let Some(doc) =
+                            main_split.open_docs.get(&url_path).cloned()
+                        {
+                            apply_code_action(&doc, main_split, &url_path, &edits);
+                        }
+                    },
+                ),
+            );
+        }
+        paths.push(path);
+    }
+    paths
+}
+
 fn workspace_edits(edit: &WorkspaceEdit) -> Option<HashMap<Url, Vec<TextEdit>>> {
     if let Some(changes) = edit.changes.as_ref() {
         return Some(changes.clone());
@@ -2369,6 +2587,22 @@ fn workspace_edits(edit: &WorkspaceEdit) -> Option<HashMap<Url, Vec<TextEdit>>>
     Some(edits)
 }
 
+/// The paths of the files that the workspace edit creates
+fn created_files(edit: &WorkspaceEdit) -> Vec<PathBuf> {
+    let ops = match edit.document_changes.as_ref() {
+        Some(DocumentChanges::Operations(ops)) => ops,
+        _ => return Vec::new(),
+    };
+    ops.iter()
+        .filter_map(|op| match op {
+            DocumentChangeOperation::Op(ResourceOp::Create(create)) => {
+                create.uri.to_file_path().ok()
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 /// Check if a [`Url`] matches the path
 fn url_matches_path(path: &Path, url: &Url) -> bool {
     // TODO: Neither of these methods work for paths
@@ -2449,3 +2683,26 @@ fn show_completion(
 
     show_completion
 }
+
+#[cfg(test)]
+mod test {
+    use super::split_path_position;
+
+    #[test]
+    fn test_split_path_position() {
+        assert_eq!(split_path_position("src/main.rs"), ("src/main.rs", None));
+        assert_eq!(
+            split_path_position("src/main.rs:42"),
+            ("src/main.rs", Some((42, 1)))
+        );
+        assert_eq!(
+            split_path_position("src/main.rs:42:10"),
+            ("src/main.rs", Some((42, 10)))
+        );
+        assert_eq!(
+            split_path_position(r"C:\src\main.rs:7"),
+            (r"C:\src\main.rs", Some((7, 1)))
+        );
+        assert_eq!(split_path_position("main.rs:"), ("main.rs:", None));
+    }
+}
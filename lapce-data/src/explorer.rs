@@ -1,8 +1,11 @@
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::path::{Component, Path};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use anyhow::Result;
 use druid::Command;
 use druid::EventCtx;
 use druid::ExtEventSink;
@@ -10,7 +13,11 @@ use druid::{Target, WidgetId};
 
 use lapce_core::cursor::CursorMode;
 use lapce_core::selection::Selection;
-use lapce_rpc::file::FileNodeItem;
+use lapce_rpc::file::{FileNodeItem, PathMetadata};
+use lapce_rpc::plugin::ExplorerMenuItem;
+use lapce_rpc::Callback;
+use lsp_types::Url;
+use serde::{Deserialize, Serialize};
 use xi_rope::Rope;
 
 use crate::data::LapceMainSplitData;
@@ -20,20 +27,128 @@ use crate::proxy::LapceProxy;
 
 use crate::{command::LapceUICommand, command::LAPCE_UI_COMMAND};
 
+/// The number of entries of a directory that are sent at once, so that large
+/// directories are shown while they are still being read
+const READ_DIR_CHUNK_SIZE: usize = 1000;
+
+/// The clipboard format of PNG images on this platform
+#[cfg(target_os = "macos")]
+pub const CLIPBOARD_PNG_FORMAT: &str = "public.png";
+#[cfg(windows)]
+pub const CLIPBOARD_PNG_FORMAT: &str = "PNG";
+#[cfg(not(any(target_os = "macos", windows)))]
+pub const CLIPBOARD_PNG_FORMAT: &str = "image/png";
+
+/// Make a token for reporting the progress of a filesystem operation started
+/// from the explorer, which is unique for the lifetime of the process
+pub fn new_progress_token() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    format!("lapce-explorer-{}", NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Make a generation for a read of a directory, which is later than those of
+/// all the reads made before it
+fn new_read_generation() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// How long the explorer waits after laying out and repainting its rows before
+/// doing it again, so that the changes coming in meanwhile are shown at once
+pub const REPAINT_COALESCE_WINDOW: Duration = Duration::from_millis(16);
+
+/// What the explorer should do about a change to its rows
+#[derive(Debug, PartialEq, Eq)]
+pub enum RepaintRequest {
+    /// Lay out and repaint right away
+    Now,
+    /// Wait for the duration, then call [`RepaintCoalescer::fire`] and repaint
+    After(Duration),
+    /// A repaint is already waiting, which will show this change as well
+    Waiting,
+}
+
+/// Coalesces the repaints of the explorer for changes that come in quick
+/// succession, such as the chunks of large directories while a workspace is
+/// opened, so that there is at most one for each [`REPAINT_COALESCE_WINDOW`]
+#[derive(Default)]
+pub struct RepaintCoalescer {
+    last: Option<Instant>,
+    waiting: bool,
+    repaints: usize,
+}
+
+impl RepaintCoalescer {
+    /// Ask for a repaint for a change at `now`
+    pub fn request(&mut self, now: Instant) -> RepaintRequest {
+        if self.waiting {
+            return RepaintRequest::Waiting;
+        }
+        match self.last {
+            Some(last) if now.duration_since(last) < REPAINT_COALESCE_WINDOW => {
+                self.waiting = true;
+                RepaintRequest::After(
+                    REPAINT_COALESCE_WINDOW - now.duration_since(last),
+                )
+            }
+            _ => {
+                self.last = Some(now);
+                self.repaints += 1;
+                RepaintRequest::Now
+            }
+        }
+    }
+
+    /// The wait of a [`RepaintRequest::After`] is over, and the explorer
+    /// repaints
+    pub fn fire(&mut self, now: Instant) {
+        self.waiting = false;
+        self.last = Some(now);
+        self.repaints += 1;
+    }
+
+    /// How many repaints it has let through
+    pub fn repaints(&self) -> usize {
+        self.repaints
+    }
+}
+
+/// A file or directory pinned to the favorites at the top of the explorer
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinnedPath {
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+/// What the explorer starts out with, restored from the last time the
+/// workspace was open and taken from the settings
+#[derive(Clone, Debug, Default)]
+pub struct FileExplorerOptions {
+    /// The directories that were expanded, relative to the workspace folder
+    pub open_dirs: Vec<PathBuf>,
+    pub scroll_offset: f64,
+    pub pinned: Vec<PinnedPath>,
+    /// The folders added to the workspace besides its own folder
+    pub extra_roots: Vec<PathBuf>,
+    pub compact_folders: bool,
+}
+
 #[derive(Clone)]
 pub enum Naming {
     /// Renaming an existing file
     Renaming {
-        /// The index into the file list of the file being renamed
-        list_index: usize,
+        /// The file being renamed
+        path: PathBuf,
         /// Indentation level
         indent_level: usize,
     },
     /// Naming a file that has yet to be created
     Naming {
-        /// The index that the file being created should appear at
-        /// Note that when naming, it is not yet actually created.
-        list_index: usize,
+        /// The entry of `base_path` that the input is shown right after, or
+        /// `None` to show it as the first entry of `base_path`. The row is
+        /// found again whenever the tree changes, so that the input stays in
+        /// place when entries above it are added or removed.
+        after: Option<PathBuf>,
         /// Indentation level
         indent_level: usize,
         /// If true, then we are creating a directory
@@ -41,27 +156,102 @@ pub enum Naming {
         is_dir: bool,
         /// The folder that the file/directory is being created within
         base_path: PathBuf,
+        /// The template to fill the new file with, if any
+        template: Option<PathBuf>,
+        /// The text to fill the new file with, like pasted text, if any
+        content: Option<String>,
     },
 }
-impl Naming {
-    pub fn list_index(&self) -> usize {
-        match self {
-            Naming::Renaming { list_index, .. }
-            | Naming::Naming { list_index, .. } => *list_index,
-        }
-    }
-}
-
 #[derive(Clone)]
 pub struct FileExplorerData {
     pub tab_id: WidgetId,
     pub widget_id: WidgetId,
     pub workspace: Option<FileNodeItem>,
+    /// Folders added to the workspace besides its own folder, which are shown
+    /// as top-level sections after it
+    pub extra_roots: Vec<FileNodeItem>,
+    /// The directory in the workspace the tree is narrowed to, which is shown
+    /// in place of the workspace folder. The workspace folder is still the
+    /// root for everything else, like git and search.
+    pub display_root: Option<PathBuf>,
     pub active_selected: Option<PathBuf>,
+    /// The index into the file list of the node that was last clicked,
+    /// which keyboard actions like renaming and deleting apply to
+    pub selected_index: Option<usize>,
+    /// The nodes selected together by ctrl or shift clicking, which opening
+    /// applies to. Empty when only the node at `selected_index` is selected.
+    pub selected_paths: HashSet<PathBuf>,
     /// The status of renaming/naming a file/directory
     pub naming: Option<Naming>,
+    /// The reason the current naming text couldn't be applied, shown below the input
+    pub naming_error: Option<String>,
     /// The id of the editor (in `main_split.editors`) for renaming
     pub renaming_editor_view_id: WidgetId,
+    /// The id of the editor (in `main_split.editors`) for the filter input
+    pub filter_editor_view_id: WidgetId,
+    /// The current filter pattern, empty when not filtering
+    pub filter: String,
+    /// The nodes matching the filter, along with all of their ancestors.
+    /// `None` when there is no active filter.
+    pub matches: Option<HashSet<PathBuf>>,
+    /// The directories that were open before filtering started, so that
+    /// clearing the filter restores the previous expansion state
+    pre_filter_open: Option<HashSet<PathBuf>>,
+    /// The number of matches of the project-wide search in each file, which
+    /// are shown next to their names. Empty when there is no search.
+    pub search_matches: HashMap<PathBuf, usize>,
+    /// Whether the tree only shows the files with search matches
+    pub search_filter: bool,
+    /// The total size of the files beneath each directory, which is added up
+    /// by the proxy when sizes are shown. It is kept until the explorer is
+    /// refreshed.
+    pub dir_sizes: HashMap<PathBuf, u64>,
+    /// The directories whose size the proxy is still adding up
+    sizing_dirs: HashSet<PathBuf>,
+    /// The children listed so far of the directories whose listing is still
+    /// coming in chunks, so that the children that are gone are only removed
+    /// once all of them are in
+    listed_children: HashMap<PathBuf, HashSet<PathBuf>>,
+    /// The naming text that Tab completed, while going through its completions
+    naming_completion_prefix: Option<String>,
+    /// The indentation level and path of every row in the file list, so that
+    /// looking up the node at an index doesn't have to walk the tree.
+    /// `None` for the loading rows of directories that are still being read.
+    index_paths: Vec<Option<(usize, PathBuf)>>,
+    /// The directories shown as a flat list of all the files beneath them,
+    /// instead of as a tree. The files are sorted by their path and are
+    /// empty until they've been listed.
+    pub flat_dirs: HashMap<PathBuf, Vec<FileNodeItem>>,
+    /// Whether chains of directories that each only contain one directory are
+    /// shown as a single row
+    compact_folders: bool,
+    /// The directories that were asked to be read and haven't gotten their
+    /// first entries yet, which show a spinner on their row meanwhile
+    pub reading_dirs: HashSet<PathBuf>,
+    /// The generation that the reads of each directory have to be from for
+    /// their listings to be applied. It is moved up to the read whose listing
+    /// was last applied, so that the chunks of the reads before it are left
+    /// out, and past the reads in flight when the directory is collapsed, so
+    /// that they can't expand it again.
+    read_cutoffs: HashMap<PathBuf, u64>,
+    /// The entries plugins added to the context menu, in the order they
+    /// were registered
+    pub plugin_menu_items: Vec<ExplorerMenuItem>,
+    /// The vertical scroll offset of the file list, which is saved with the
+    /// workspace. The list scrolls back to it once it has been laid out with
+    /// the restored open directories.
+    pub scroll_offset: f64,
+    /// The files and directories pinned to the favorites above the tree, in
+    /// the order they were pinned, which are saved with the workspace
+    pub pinned: Vec<PinnedPath>,
+    /// The files opened most recently, newest first, which are listed in the
+    /// recent section above the tree
+    pub recent: Vec<PathBuf>,
+    /// Whether the recent section is expanded to show the files
+    pub recent_open: bool,
+    /// The file chosen with "Select for Compare", which the next file is
+    /// compared with. It is per workspace, like the rest of the explorer.
+    pub compare_selected: Option<PathBuf>,
     pub proxy: Arc<LapceProxy>,
     pub event_sink: ExtEventSink,
 }
@@ -72,58 +262,302 @@ impl FileExplorerData {
         workspace: LapceWorkspace,
         proxy: Arc<LapceProxy>,
         event_sink: ExtEventSink,
+        options: FileExplorerOptions,
     ) -> Self {
+        let FileExplorerOptions {
+            open_dirs,
+            scroll_offset,
+            pinned,
+            extra_roots,
+            compact_folders,
+        } = options;
         let mut items = Vec::new();
         let widget_id = WidgetId::next();
         if let Some(path) = workspace.path.as_ref() {
-            items.push(FileNodeItem {
-                path_buf: path.clone(),
-                is_dir: true,
-                read: false,
-                open: false,
-                children: HashMap::new(),
-                children_open_count: 0,
-            });
-            let path = path.clone();
-            Self::read_dir(&path, true, tab_id, &proxy, event_sink.clone());
+            items.push(FileNodeItem::new(path.clone(), true));
+            let open_dirs: HashSet<PathBuf> =
+                open_dirs.iter().map(|dir| path.join(dir)).collect();
+            Self::read_dir_expanding(
+                path.clone(),
+                Arc::new(open_dirs),
+                tab_id,
+                proxy.clone(),
+                event_sink.clone(),
+            );
         }
-        Self {
+        let mut data = Self {
             tab_id,
             widget_id,
-            workspace: workspace.path.as_ref().map(|p| FileNodeItem {
-                path_buf: p.clone(),
-                is_dir: true,
-                read: false,
-                open: false,
-                children: HashMap::new(),
-                children_open_count: 0,
-            }),
+            workspace: workspace
+                .path
+                .as_ref()
+                .map(|p| FileNodeItem::new(p.clone(), true)),
+            extra_roots: Vec::new(),
+            display_root: None,
             active_selected: None,
+            selected_index: None,
+            selected_paths: HashSet::new(),
             naming: None,
+            naming_error: None,
             renaming_editor_view_id: WidgetId::next(),
+            filter_editor_view_id: WidgetId::next(),
+            filter: String::new(),
+            matches: None,
+            pre_filter_open: None,
+            search_matches: HashMap::new(),
+            search_filter: false,
+            dir_sizes: HashMap::new(),
+            sizing_dirs: HashSet::new(),
+            listed_children: HashMap::new(),
+            naming_completion_prefix: None,
+            reading_dirs: HashSet::new(),
+            read_cutoffs: HashMap::new(),
+            plugin_menu_items: Vec::new(),
+            scroll_offset,
+            pinned,
+            recent: Vec::new(),
+            recent_open: true,
+            compare_selected: None,
+            index_paths: workspace
+                .path
+                .as_ref()
+                .map(|p| vec![Some((0, p.clone()))])
+                .unwrap_or_default(),
+            flat_dirs: HashMap::new(),
+            compact_folders,
             proxy,
             event_sink,
+        };
+        for path in extra_roots {
+            data.add_root(path);
         }
+        data
     }
 
-    pub fn update_node_count(&mut self, path: &Path) -> Option<()> {
-        let node = self.get_node_mut(path)?;
-        if node.is_dir {
-            if node.open {
-                node.children_open_count = node
-                    .children
-                    .iter()
-                    .map(|(_, item)| item.children_open_count + 1)
-                    .sum::<usize>();
-            } else {
-                node.children_open_count = 0;
+    /// The workspace folder followed by the folders added to the workspace
+    pub fn roots(&self) -> impl Iterator<Item = &FileNodeItem> {
+        self.workspace.iter().chain(self.extra_roots.iter())
+    }
+
+    /// The node whose children are the top-level rows of the file list, which
+    /// is the directory the tree is narrowed to, or else the workspace folder
+    pub fn display_root_node(&self) -> Option<&FileNodeItem> {
+        self.display_root
+            .as_ref()
+            .and_then(|path| self.workspace.as_ref()?.get_file_node(path))
+            .or(self.workspace.as_ref())
+    }
+
+    /// Narrow the tree to the directory, or show the whole workspace again
+    /// with `None`. Directories outside of the workspace folder are ignored.
+    pub fn set_display_root(&mut self, path: Option<PathBuf>) {
+        if let Some(path) = path.as_ref() {
+            let in_workspace = self
+                .workspace
+                .as_ref()
+                .map_or(false, |workspace| path.starts_with(&workspace.path_buf));
+            let is_dir = self.get_file_node(path).map_or(false, |node| node.is_dir);
+            if !in_workspace || !is_dir {
+                return;
             }
         }
+        let path = path.filter(|path| !self.is_root(path));
+        if path == self.display_root {
+            return;
+        }
+
+        // The rows all move, so whatever referred to them by index is dropped
+        self.cancel_naming();
+        self.selected_index = None;
+        self.selected_paths.clear();
+        self.display_root = path.clone();
+        if let Some(path) = path {
+            // The children of the root are always shown as a tree
+            self.flat_dirs.remove(&path);
+            self.expand_dir(&path);
+        }
+        self.rebuild_index();
+    }
+
+    /// Whether the path is the workspace folder or one of the added folders
+    pub fn is_root(&self, path: &Path) -> bool {
+        self.roots().any(|root| root.path_buf == path)
+    }
+
+    /// Whether the directory the path is in is read-only, which keeps the
+    /// path from being renamed or trashed
+    pub fn is_parent_readonly(&self, path: &Path) -> bool {
+        path.parent()
+            .and_then(|parent| self.get_file_node(parent))
+            .map_or(false, |parent| parent.readonly)
+    }
+
+    /// Get the node at the path, in whichever root contains it
+    pub fn get_file_node(&self, path: &Path) -> Option<&FileNodeItem> {
+        self.roots()
+            .find(|root| path.starts_with(&root.path_buf))?
+            .get_file_node(path)
+    }
+
+    /// Get the node at the path, in whichever root contains it
+    pub fn get_file_node_mut(&mut self, path: &Path) -> Option<&mut FileNodeItem> {
+        find_file_node_mut(
+            self.workspace.iter_mut().chain(self.extra_roots.iter_mut()),
+            path,
+        )
+    }
+
+    pub fn is_pinned(&self, path: &Path) -> bool {
+        self.pinned.iter().any(|pinned| pinned.path == path)
+    }
+
+    /// Pin the file or directory at the path to the favorites
+    pub fn pin(&mut self, path: &Path) {
+        if self.is_pinned(path) {
+            return;
+        }
+        let is_dir = match self
+            .get_flat_file_node(path)
+            .or_else(|| self.get_file_node(path))
+        {
+            Some(node) => node.is_dir,
+            None => return,
+        };
+        self.pinned.push(PinnedPath {
+            path: path.to_path_buf(),
+            is_dir,
+        });
+    }
+
+    pub fn unpin(&mut self, path: &Path) {
+        self.pinned.retain(|pinned| pinned.path != path);
+    }
+
+    /// Put the file at the top of the recent files, keeping at most `limit`
+    pub fn add_recent(&mut self, path: &Path, limit: usize) {
+        push_recent(&mut self.recent, path, limit);
+    }
+
+    /// Add a folder to the workspace as a top-level section and read it.
+    /// Folders that overlap with an existing root are ignored.
+    pub fn add_root(&mut self, path: PathBuf) {
+        if self.workspace.is_none()
+            || self.roots().any(|root| {
+                path.starts_with(&root.path_buf) || root.path_buf.starts_with(&path)
+            })
+        {
+            return;
+        }
+
+        self.extra_roots.push(FileNodeItem::new(path.clone(), true));
+        self.rebuild_index();
+        self.reading_dirs.insert(path.clone());
+        Self::read_dir(
+            &path,
+            true,
+            self.tab_id,
+            &self.proxy,
+            self.event_sink.clone(),
+        );
+    }
+
+    /// The paths of the folders added to the workspace, in the order they're
+    /// shown, which are saved with the workspace
+    pub fn extra_root_paths(&self) -> Vec<PathBuf> {
+        self.extra_roots
+            .iter()
+            .map(|root| root.path_buf.clone())
+            .collect()
+    }
+
+    /// The index in `extra_roots` of the added folder the path is in
+    pub fn extra_root_position(&self, path: &Path) -> Option<usize> {
+        self.extra_roots
+            .iter()
+            .position(|root| path.starts_with(&root.path_buf))
+    }
+
+    /// Move the added folder to the position among the added folders. The
+    /// workspace folder always stays first, as git and search are rooted
+    /// there.
+    pub fn move_root(&mut self, path: &Path, to: usize) {
+        let from = match self
+            .extra_roots
+            .iter()
+            .position(|root| root.path_buf == path)
+        {
+            Some(from) => from,
+            None => return,
+        };
+        let to = to.min(self.extra_roots.len() - 1);
+        if from == to {
+            return;
+        }
+        let root = self.extra_roots.remove(from);
+        self.extra_roots.insert(to, root);
+        self.rebuild_index();
+    }
+
+    /// A path in the directory for a new file with the name and extension,
+    /// numbered if the directory already has a file of that name
+    pub fn unused_file_path(&self, dir: &Path, name: &str, ext: &str) -> PathBuf {
+        let children = self.get_file_node(dir).map(|node| &node.children);
+        let is_used = |path: &PathBuf| {
+            children.map_or(false, |children| children.contains_key(path))
+        };
+        let mut path = dir.join(format!("{name}.{ext}"));
+        let mut i = 1;
+        while is_used(&path) {
+            path = dir.join(format!("{name}-{i}.{ext}"));
+            i += 1;
+        }
+        path
+    }
+
+    /// The number of rows in the file list, not counting the workspace folder
+    /// itself which isn't shown
+    pub fn row_count(&self) -> usize {
+        self.index_paths.len().saturating_sub(1)
+    }
+
+    pub fn update_node_count(&mut self, path: &Path) -> Option<()> {
+        let matches = self.matches.as_ref();
+        let node = find_file_node_mut(
+            self.workspace.iter_mut().chain(self.extra_roots.iter_mut()),
+            path,
+        )?;
+        node.children_open_count = open_children_count(
+            node,
+            matches,
+            &self.flat_dirs,
+            self.compact_folders,
+        );
         None
     }
 
+    pub fn compact_folders(&self) -> bool {
+        self.compact_folders
+    }
+
+    /// Switch whether chains of single directories are shown as one row,
+    /// which changes the rows of the whole tree
+    pub fn set_compact_folders(&mut self, compact_folders: bool) {
+        if self.compact_folders == compact_folders {
+            return;
+        }
+        self.compact_folders = compact_folders;
+        let matches = self.matches.as_ref();
+        for root in self.workspace.iter_mut().chain(self.extra_roots.iter_mut()) {
+            update_all_node_counts(root, matches, &self.flat_dirs, compact_folders);
+        }
+        self.rebuild_index();
+    }
+
     pub fn node_tree(&mut self, path: &Path) -> Option<Vec<PathBuf>> {
-        let root = &self.workspace.as_ref()?.path_buf;
+        let root = &self
+            .roots()
+            .find(|root| path.starts_with(&root.path_buf))?
+            .path_buf;
         let path = path.strip_prefix(root).ok()?;
         Some(
             path.ancestors()
@@ -132,11 +566,43 @@ impl FileExplorerData {
         )
     }
 
+    /// The index into the file list that the naming input is shown at, from
+    /// the rows of the tree as it is now. `None` when not naming, or when the
+    /// row the input belongs with is no longer in view.
+    pub fn naming_list_index(&self) -> Option<usize> {
+        match self.naming.as_ref()? {
+            Naming::Renaming { path, .. } => {
+                self.get_node_index(path).map(|(index, _)| index)
+            }
+            Naming::Naming {
+                after, base_path, ..
+            } => naming_row(&self.index_paths, base_path, after.as_deref()),
+        }
+    }
+
+    /// The node at the index into the file list, or `None` past the end and
+    /// for the loading rows of directories that are still being read
+    pub fn node_at_index(&self, index: usize) -> Option<&FileNodeItem> {
+        self.get_node_by_index(index).map(|(_, node)| node)
+    }
+
+    /// The indentation level and path of every row in the file list, indexed
+    /// like `selected_index`. Loading rows are `None`.
+    pub fn visible_rows(&self) -> &[Option<(usize, PathBuf)>] {
+        &self.index_paths
+    }
+
+    /// The indices of the rows of the directories that the row at the index
+    /// is in, outermost first. The first of them is the hidden row of the
+    /// workspace folder, unless the row is in an added folder.
+    pub fn ancestor_rows(&self, index: usize) -> Vec<usize> {
+        ancestor_rows(&self.index_paths, index)
+    }
+
     /// Get the node by its index into the file list
     /// Returns the node and its indentation level
     pub fn get_node_by_index(&self, index: usize) -> Option<(usize, &FileNodeItem)> {
-        let (_, node) = get_item_children(0, index, 0, self.workspace.as_ref()?);
-        node
+        row_node(&self.index_paths, index, self.roots(), &self.flat_dirs)
     }
 
     /// Get the node by its index into the file list
@@ -145,24 +611,110 @@ impl FileExplorerData {
         &mut self,
         index: usize,
     ) -> Option<(usize, &mut FileNodeItem)> {
-        let (_, node) = get_item_children_mut(0, index, 0, self.workspace.as_mut()?);
-        node
+        let (indent, path) = self.index_paths.get(index)?.as_ref()?;
+        if let Some(node) = find_flat_file_node_mut(&mut self.flat_dirs, path) {
+            return Some((*indent, node));
+        }
+        let node = find_file_node_mut(
+            self.workspace.iter_mut().chain(self.extra_roots.iter_mut()),
+            path,
+        )?;
+        Some((*indent, node))
     }
 
-    pub fn get_node_mut(&mut self, path: &Path) -> Option<&mut FileNodeItem> {
-        let mut node = self.workspace.as_mut()?;
-        if node.path_buf == path {
-            return Some(node);
+    /// Get the node of a file that is shown in the flat list of a directory
+    pub fn get_flat_file_node(&self, path: &Path) -> Option<&FileNodeItem> {
+        find_flat_file_node(&self.flat_dirs, path)
+    }
+
+    pub fn is_flat(&self, path: &Path) -> bool {
+        self.flat_dirs.contains_key(path)
+    }
+
+    /// Switch the directory between being shown as a tree and as a flat list
+    /// of all the files beneath it, which are listed when it is switched on
+    pub fn toggle_flat(&mut self, path: &Path) {
+        if self.flat_dirs.remove(path).is_none() {
+            self.flat_dirs.insert(path.to_path_buf(), Vec::new());
+            if let Some(node) = self.get_file_node_mut(path) {
+                node.open = true;
+            }
+
+            let tab_id = self.tab_id;
+            let event_sink = self.event_sink.clone();
+            let local_path = path.to_path_buf();
+            self.proxy.get_files_in(path, move |result| {
+                if let Ok(files) = result {
+                    let _ = event_sink.submit_command(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::UpdateExplorerFlatFiles {
+                            path: local_path,
+                            files,
+                        },
+                        Target::Widget(tab_id),
+                    );
+                }
+            });
         }
-        let root = node.path_buf.clone();
-        let path = path.strip_prefix(&root).ok()?;
-        for path in path.ancestors().collect::<Vec<&Path>>().iter().rev() {
-            if path.to_str()?.is_empty() {
-                continue;
+        self.update_counts(path);
+    }
+
+    /// Set the files of a directory that is shown as a flat list
+    pub fn update_flat_files(&mut self, path: &Path, mut files: Vec<PathBuf>) {
+        let flat_files = match self.flat_dirs.get_mut(path) {
+            Some(flat_files) => flat_files,
+            None => return,
+        };
+        files.sort();
+        *flat_files = files
+            .into_iter()
+            .map(|path_buf| FileNodeItem::new(path_buf, false))
+            .collect();
+        self.update_counts(path);
+    }
+
+    /// Rebuild the cache of the rows in the file list, which has to be done
+    /// whenever the tree or the expansion of a directory changes
+    pub fn rebuild_index(&mut self) {
+        let mut index_paths = Vec::new();
+        // The added folders are only shown along with the whole workspace
+        if let Some(root) = self
+            .display_root
+            .as_ref()
+            .and_then(|path| self.workspace.as_ref()?.get_file_node(path))
+        {
+            collect_index_paths(
+                root,
+                0,
+                self.matches.as_ref(),
+                &self.flat_dirs,
+                self.compact_folders,
+                &mut index_paths,
+            );
+        } else if self.workspace.is_some() {
+            for root in self.roots() {
+                collect_index_paths(
+                    root,
+                    0,
+                    self.matches.as_ref(),
+                    &self.flat_dirs,
+                    self.compact_folders,
+                    &mut index_paths,
+                );
             }
-            node = node.children.get_mut(&root.join(path))?;
         }
-        Some(node)
+        self.index_paths = index_paths;
+    }
+
+    /// Get the index into the file list of the node at the path
+    /// Returns the index and the indentation level, or `None` if the node isn't in view
+    pub fn get_node_index(&self, path: &Path) -> Option<(usize, usize)> {
+        row_index(&self.index_paths, path)
+    }
+
+    /// Get the node at the path, in whichever root contains it
+    pub fn get_node_mut(&mut self, path: &Path) -> Option<&mut FileNodeItem> {
+        self.get_file_node_mut(path)
     }
 
     pub fn update_children(
@@ -170,58 +722,44 @@ impl FileExplorerData {
         path: &Path,
         children: HashMap<PathBuf, FileNodeItem>,
         expand: bool,
+        more: bool,
+        generation: u64,
     ) -> Option<()> {
-        // Ignore updates while naming a file
-        if self.naming.is_some() {
+        // The directory was collapsed or read again since the read was made
+        if self.is_stale_read(path, generation) {
             return None;
         }
+        self.reading_dirs.remove(path);
+        self.read_cutoffs.insert(path.to_path_buf(), generation);
 
-        let node = self.workspace.as_mut()?.get_file_node_mut(path)?;
-
-        let removed_paths: Vec<PathBuf> = node
-            .children
-            .keys()
-            .filter(|p| !children.contains_key(*p))
-            .map(PathBuf::from)
-            .collect();
-        for path in removed_paths {
-            node.children.remove(&path);
-        }
-
-        for (path, child) in children.into_iter() {
-            if let Some(existing) = node.children.get(&path) {
-                if existing.read {
-                    Self::read_dir(
-                        &path,
-                        existing.open,
-                        self.tab_id,
-                        &self.proxy,
-                        self.event_sink.clone(),
-                    );
-                }
-            } else {
-                node.children.insert(child.path_buf.clone(), child);
-            }
-        }
-
+        // The first chunk starts a new listing of the directory
+        let mut listed = HashSet::new();
+        let node = self.get_file_node_mut(path)?;
+        let reread = merge_listing(node, children, more, &mut listed);
         node.read = true;
+        node.loading = more;
         if expand {
             node.open = true;
         }
-
-        for p in path.ancestors() {
-            self.update_node_count(p);
+        if more {
+            self.listed_children.insert(path.to_path_buf(), listed);
+        } else {
+            self.listed_children.remove(path);
         }
+        self.read_dirs_again(reread);
+
+        self.update_counts(path);
 
         Some(())
     }
 
-    pub fn reload(&self) {
-        if let Some(workspace) = self.workspace.as_ref() {
-            let workspace = workspace.clone();
+    /// Read the directories that were already read again, so that the
+    /// changes beneath them show up too
+    fn read_dirs_again(&self, dirs: Vec<(PathBuf, bool)>) {
+        for (dir, open) in dirs {
             Self::read_dir(
-                &workspace.path_buf,
-                true,
+                &dir,
+                open,
                 self.tab_id,
                 &self.proxy,
                 self.event_sink.clone(),
@@ -229,256 +767,2721 @@ impl FileExplorerData {
         }
     }
 
-    pub fn read_dir(
-        path: &Path,
-        expand: bool,
-        tab_id: WidgetId,
-        proxy: &LapceProxy,
-        event_sink: ExtEventSink,
-    ) {
-        FileExplorerData::read_dir_cb::<fn()>(
-            path, expand, tab_id, proxy, event_sink, None,
-        )
+    /// Whether a read of the directory made at the generation is out of date
+    fn is_stale_read(&self, path: &Path, generation: u64) -> bool {
+        self.read_cutoffs
+            .get(path)
+            .map_or(false, |cutoff| generation < *cutoff)
     }
 
-    pub fn read_dir_cb<F: FnOnce() + Send + 'static>(
+    /// Add a further chunk of children to a directory that is still loading
+    pub fn append_children(
+        &mut self,
         path: &Path,
-        expand: bool,
-        tab_id: WidgetId,
-        proxy: &LapceProxy,
-        event_sink: ExtEventSink,
-        mut on_finished: Option<F>,
-    ) {
-        let path = PathBuf::from(path);
-        let local_path = path.clone();
-        proxy.read_dir(&local_path, move |result| {
-            if let Ok(resp) = result {
-                let path = path.clone();
-                let _ = event_sink.submit_command(
-                    LAPCE_UI_COMMAND,
-                    LapceUICommand::UpdateExplorerItems(path, resp.items, expand),
-                    Target::Widget(tab_id),
-                );
+        children: HashMap<PathBuf, FileNodeItem>,
+        more: bool,
+        generation: u64,
+    ) -> Option<()> {
+        // Chunks of an earlier read of the directory are left out, as are
+        // those of a listing that was cancelled or never applied
+        if self.is_stale_read(path, generation) {
+            return None;
+        }
+        let mut listed = self.listed_children.remove(path)?;
+        let node = self.get_file_node_mut(path)?;
+        let reread = merge_listing(node, children, more, &mut listed);
+        node.loading = more;
+        if more {
+            self.listed_children.insert(path.to_path_buf(), listed);
+        }
+        self.read_dirs_again(reread);
 
-                if let Some(on_finished) = on_finished.take() {
-                    on_finished();
-                }
-            }
-        });
-    }
+        self.update_counts(path);
 
-    /// Stop naming the file/directory, discarding any changes
-    pub fn cancel_naming(&mut self) {
-        self.naming = None;
+        Some(())
     }
 
-    /// Apply the current naming/renaming text (if it is nonempty and not the same as before)
-    /// Also stops the naming.
-    pub fn apply_naming(
-        &mut self,
-        ctx: &mut EventCtx,
-        main_split: &LapceMainSplitData,
-    ) {
-        let naming = if let Some(naming) = &self.naming {
-            naming
-        } else {
-            return;
-        };
-
-        // Get the text in the input
-        let doc = main_split
-            .local_docs
-            .get(&LocalBufferKind::PathName)
-            .unwrap();
-        let target_name = doc.buffer().text().to_string();
-        // If the name is empty, then we just ignore it
-        if target_name.is_empty() {
-            self.cancel_naming();
-            return;
+    /// Add or remove the node at the index from the selection, keeping the
+    /// node that was selected before it
+    pub fn toggle_selected(&mut self, index: usize) {
+        if self.selected_paths.is_empty() {
+            if let Some((_, node)) =
+                self.selected_index.and_then(|i| self.get_node_by_index(i))
+            {
+                self.selected_paths.insert(node.path_buf.clone());
+            }
         }
+        if let Some((_, node)) = self.get_node_by_index(index) {
+            let path = node.path_buf.clone();
+            if !self.selected_paths.remove(&path) {
+                self.selected_paths.insert(path);
+            }
+            self.selected_index = Some(index);
+        }
+    }
 
-        match naming {
-            Naming::Renaming { list_index, .. } => {
-                let renaming =
-                    if let Some((_, node)) = self.get_node_by_index(*list_index) {
-                        &node.path_buf
-                    } else {
-                        // There was either nothing we were renaming, or the index disappeared
-                        return;
-                    };
-
-                let target_path = renaming.with_file_name(target_name);
+    /// Select the nodes from the selected one up to the one at the index
+    pub fn select_range(&mut self, index: usize) {
+        let anchor = self.selected_index.unwrap_or(index);
+        let (start, end) = (anchor.min(index), anchor.max(index));
+        self.selected_paths = self.index_paths
+            [start.max(1)..=end.min(self.row_count())]
+            .iter()
+            .flatten()
+            .map(|(_, path)| path.clone())
+            .collect();
+    }
 
-                // If it is the same, then we don't bother renaming it
-                if &target_path == renaming {
-                    return;
-                }
+    /// The selected paths in the order that they are listed
+    pub fn selection(&self) -> Vec<PathBuf> {
+        if self.selected_paths.is_empty() {
+            return self
+                .selected_index
+                .and_then(|index| self.get_node_by_index(index))
+                .map(|(_, node)| vec![node.path_buf.clone()])
+                .unwrap_or_default();
+        }
+        self.index_paths
+            .iter()
+            .flatten()
+            .filter(|(_, path)| self.selected_paths.contains(path))
+            .map(|(_, path)| path.clone())
+            .collect()
+    }
 
-                ctx.submit_command(Command::new(
-                    LAPCE_UI_COMMAND,
-                    LapceUICommand::RenamePath {
-                        from: renaming.clone(),
-                        to: target_path,
-                    },
-                    Target::Auto,
-                ));
-            }
-            Naming::Naming {
-                is_dir, base_path, ..
-            } => {
-                let mut path = base_path.clone();
-                path.push(target_name);
+    /// The index of the row to select for the name typed while the list has
+    /// the focus, starting from the selected row
+    pub fn type_ahead_index(&self, typed: &str) -> Option<usize> {
+        let names: Vec<Option<String>> = self
+            .index_paths
+            .iter()
+            .map(|row| {
+                let (_, path) = row.as_ref()?;
+                Some(path.file_name()?.to_string_lossy().to_lowercase())
+            })
+            .collect();
+        type_ahead_match(&names, typed, self.selected_index)
+    }
 
-                let cmd = if *is_dir {
-                    LapceUICommand::CreateDirectory { path }
-                } else {
-                    LapceUICommand::CreateFileOpen { path }
-                };
-                ctx.submit_command(Command::new(
-                    LAPCE_UI_COMMAND,
-                    cmd,
-                    Target::Auto,
-                ));
+    /// Collapse all the directories, leaving only the top-level rows of the
+    /// workspace and of the added folders
+    pub fn collapse_all(&mut self) {
+        for root in self.workspace.iter_mut().chain(self.extra_roots.iter_mut()) {
+            for child in root.children.values_mut() {
+                collapse_dirs(child);
             }
+            update_all_node_counts(
+                root,
+                self.matches.as_ref(),
+                &self.flat_dirs,
+                self.compact_folders,
+            );
         }
+        self.rebuild_index();
+    }
 
+    /// Select the row of the path, like clicking it does, without opening it.
+    /// Returns `false` and leaves the selection alone if the path isn't in
+    /// view, because a directory above it is collapsed or hasn't been read.
+    pub fn select_path(&mut self, path: &Path) -> bool {
+        let (index, _) = match self.get_node_index(path) {
+            Some(row) => row,
+            None => return false,
+        };
+        self.selected_index = Some(index);
+        self.selected_paths.clear();
+        self.active_selected = Some(path.to_path_buf());
+        true
+    }
+
+    /// Expand the directory if it is collapsed, and collapse it otherwise.
+    /// Returns whether it is expanded now, which isn't the case yet for a
+    /// directory that hasn't been read: it is expanded once its entries
+    /// arrive. Paths that aren't directories in the tree are ignored.
+    pub fn toggle_path(&mut self, path: &Path) -> bool {
+        let open = match self.get_file_node(path) {
+            Some(node) if node.is_dir => !node.open,
+            _ => return false,
+        };
+        self.set_open(path, open);
+        self.get_file_node(path).map_or(false, |node| node.open)
+    }
+
+    /// Expand the directory, reading it first if it hasn't been
+    pub fn expand_dir(&mut self, path: &Path) {
+        self.set_open(path, true);
+    }
+
+    /// Expand or collapse the directory. A directory that hasn't been read
+    /// yet is read first, and expanded once its entries arrive. Paths that
+    /// aren't directories in the tree are ignored.
+    pub fn set_open(&mut self, path: &Path, open: bool) {
+        if !open {
+            self.cancel_dir_sizes(path);
+            self.cancel_read(path);
+        }
+        let node = match self.get_file_node_mut(path) {
+            Some(node) if node.is_dir => node,
+            _ => return,
+        };
+        if set_dir_open(node, open) {
+            self.reading_dirs.insert(path.to_path_buf());
+            Self::read_dir(
+                path,
+                true,
+                self.tab_id,
+                &self.proxy,
+                self.event_sink.clone(),
+            );
+        }
+        // Not `update_counts`, which would expand the directories with
+        // matches again while filtering
+        for p in path.ancestors() {
+            self.update_node_count(p);
+        }
+        self.rebuild_index();
+    }
+
+    /// Expand the directory and the directories beneath it, `levels` levels
+    /// deep counting the directory itself, or all the way down for `None`.
+    /// The directories within reach that haven't been read yet are read, and
+    /// expanded further once their entries arrive. Those continue with
+    /// `continued` set, which stops at a directory collapsed meanwhile.
+    pub fn expand_levels(
+        &mut self,
+        path: &Path,
+        levels: Option<usize>,
+        continued: bool,
+    ) {
+        let node = match find_file_node_mut(
+            self.workspace.iter_mut().chain(self.extra_roots.iter_mut()),
+            path,
+        ) {
+            Some(node) if node.is_dir => node,
+            _ => return,
+        };
+        if continued && !node.open {
+            return;
+        }
+        let mut unread = Vec::new();
+        open_levels(node, levels, &mut unread);
+        // The counts of the whole subtree are updated at once, rather than
+        // for every directory opened
+        update_all_node_counts(
+            node,
+            self.matches.as_ref(),
+            &self.flat_dirs,
+            self.compact_folders,
+        );
+        for p in path.ancestors().skip(1) {
+            self.update_node_count(p);
+        }
+        self.rebuild_index();
+
+        for (dir, levels) in unread {
+            self.reading_dirs.insert(dir.clone());
+            let event_sink = self.event_sink.clone();
+            let tab_id = self.tab_id;
+            let local_dir = dir.clone();
+            Self::read_dir_cb(
+                &dir,
+                true,
+                self.tab_id,
+                &self.proxy,
+                self.event_sink.clone(),
+                Some(move || {
+                    let _ = event_sink.submit_command(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::ExpandExplorerDir {
+                            path: local_dir,
+                            levels,
+                            continued: true,
+                        },
+                        Target::Widget(tab_id),
+                    );
+                }),
+            );
+        }
+    }
+
+    /// Whether any directory is still being read
+    pub fn is_loading(&self) -> bool {
+        !self.reading_dirs.is_empty() || self.roots().any(is_node_loading)
+    }
+
+    /// Update the counts of the node and its ancestors after its children changed
+    fn update_counts(&mut self, path: &Path) {
+        if self.matches.is_some() {
+            // Newly read children may contain matches for the active filter
+            self.apply_filter();
+        } else {
+            for p in path.ancestors() {
+                self.update_node_count(p);
+            }
+        }
+        self.rebuild_index();
+    }
+
+    /// Only show the nodes whose name matches the pattern, expanding the
+    /// directories that contain matches.
+    /// An empty pattern clears the filter and restores the expansion state
+    /// from before the filter was applied.
+    pub fn set_filter(&mut self, pattern: &str) {
+        self.filter = pattern.to_string();
+        self.update_filter();
+    }
+
+    /// Replace the match counts with the ones of a new search result
+    pub fn set_search_matches(&mut self, matches: HashMap<PathBuf, usize>) {
+        self.search_matches = matches;
+        self.search_matches_changed();
+    }
+
+    /// Count one more match in the file, as the matches of a search limited
+    /// to a folder are streamed in
+    pub fn add_search_match(&mut self, path: &Path) {
+        let count = self.search_matches.entry(path.to_path_buf()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            self.search_matches_changed();
+        }
+    }
+
+    /// Only show the files with search matches, along with the directories
+    /// containing them, or show all of them again
+    pub fn set_search_filter(&mut self, search_filter: bool) {
+        self.search_filter = search_filter;
+        if search_filter {
+            self.read_search_match_dirs();
+        }
+        self.update_filter();
+    }
+
+    fn search_matches_changed(&mut self) {
+        if self.search_filter {
+            self.read_search_match_dirs();
+            self.update_filter();
+        }
+    }
+
+    /// Read the directories containing search matches that haven't been read
+    /// yet, so that the files show up while filtering by the search
+    fn read_search_match_dirs(&mut self) {
+        let mut unread: HashMap<PathBuf, HashSet<PathBuf>> = HashMap::new();
+        for path in self.search_matches.keys() {
+            let root =
+                match self.roots().find(|root| path.starts_with(&root.path_buf)) {
+                    Some(root) => root.path_buf.clone(),
+                    None => continue,
+                };
+            // Reading the outermost directory that hasn't been read, expanding
+            // down to the file, shows the file
+            let dir = path
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| dir.starts_with(&root))
+                .filter(|dir| !self.reading_dirs.contains(*dir))
+                .filter(|dir| self.get_file_node(dir).map_or(false, |n| !n.read))
+                .last();
+            if let Some(dir) = dir {
+                unread
+                    .entry(dir.to_path_buf())
+                    .or_default()
+                    .extend(dirs_between(dir, path));
+            }
+        }
+        for (dir, open_dirs) in unread {
+            self.reading_dirs.insert(dir.clone());
+            Self::read_dir_expanding(
+                dir,
+                Arc::new(open_dirs),
+                self.tab_id,
+                self.proxy.clone(),
+                self.event_sink.clone(),
+            );
+        }
+    }
+
+    /// Apply the name filter and the search filter, or clear them when
+    /// neither is active
+    fn update_filter(&mut self) {
+        if self.filter.is_empty() && !self.search_filter {
+            self.matches = None;
+            let open = self.pre_filter_open.take();
+            for root in self.workspace.iter_mut().chain(self.extra_roots.iter_mut())
+            {
+                if let Some(open) = open.as_ref() {
+                    for child in root.children.values_mut() {
+                        restore_open_dirs(child, open);
+                    }
+                }
+                update_all_node_counts(
+                    root,
+                    None,
+                    &self.flat_dirs,
+                    self.compact_folders,
+                );
+            }
+            self.rebuild_index();
+            return;
+        }
+
+        if self.pre_filter_open.is_none() {
+            let mut open = HashSet::new();
+            for root in self.roots() {
+                collect_open_dirs(root, &mut open);
+            }
+            self.pre_filter_open = Some(open);
+        }
+        self.apply_filter();
+    }
+
+    fn apply_filter(&mut self) {
+        let pattern = self.filter.to_lowercase();
+        let search = self.search_filter.then(|| &self.search_matches);
+        let mut matches = HashSet::new();
+        for root in self.roots() {
+            collect_filter_matches(root, &pattern, search, &mut matches);
+            // The roots are always shown, even when nothing matches
+            matches.insert(root.path_buf.clone());
+        }
+        for root in self.workspace.iter_mut().chain(self.extra_roots.iter_mut()) {
+            for child in root.children.values_mut() {
+                expand_filter_matches(child, &matches);
+            }
+            update_all_node_counts(
+                root,
+                Some(&matches),
+                &self.flat_dirs,
+                self.compact_folders,
+            );
+        }
+        self.matches = Some(matches);
+        self.rebuild_index();
+    }
+
+    /// Have the proxy add up the sizes of the subdirectories of the directory
+    /// that don't have one yet. The sizes arrive as `UpdateExplorerDirSize`.
+    pub fn request_dir_sizes(&mut self, path: &Path) {
+        let dirs: Vec<PathBuf> = match self.get_file_node(path) {
+            Some(node) => node
+                .children
+                .values()
+                .filter(|child| child.is_dir && !child.is_symlink)
+                .map(|child| child.path_buf.clone())
+                .filter(|dir| {
+                    !self.dir_sizes.contains_key(dir)
+                        && !self.sizing_dirs.contains(dir)
+                })
+                .collect(),
+            None => return,
+        };
+        for dir in dirs {
+            self.proxy.compute_dir_size(&dir);
+            self.sizing_dirs.insert(dir);
+        }
+    }
+
+    pub fn set_dir_size(&mut self, path: &Path, size: u64) {
+        self.sizing_dirs.remove(path);
+        self.dir_sizes.insert(path.to_path_buf(), size);
+    }
+
+    /// Have the proxy read the metadata of the paths, which the listings of
+    /// the directories leave out. The metadata arrives as
+    /// `UpdateExplorerMetadata`.
+    pub fn read_metadata(&self, paths: Vec<PathBuf>) {
+        let tab_id = self.tab_id;
+        let event_sink = self.event_sink.clone();
+        self.proxy.read_paths_metadata(paths, move |result| {
+            if let Ok(resp) = result {
+                let _ = event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::UpdateExplorerMetadata(resp.metadata),
+                    Target::Widget(tab_id),
+                );
+            }
+        });
+    }
+
+    pub fn set_metadata(&mut self, metadata: &HashMap<PathBuf, PathMetadata>) {
+        for (path, metadata) in metadata {
+            if let Some(node) = find_flat_file_node_mut(&mut self.flat_dirs, path) {
+                node.set_metadata(metadata);
+            }
+            if let Some(node) = self.get_file_node_mut(path) {
+                node.set_metadata(metadata);
+            }
+        }
+    }
+
+    /// Stop reading the directory if it's still being read, such as when it
+    /// is collapsed before its entries are all in. What still arrives of the
+    /// read is ignored, and a directory left partly read is read again the
+    /// next time it is expanded.
+    fn cancel_read(&mut self, path: &Path) {
+        let listing = self.listed_children.remove(path).is_some();
+        if !self.reading_dirs.remove(path) && !listing {
+            return;
+        }
+        self.read_cutoffs
+            .insert(path.to_path_buf(), new_read_generation());
+        self.proxy.cancel_read_dir(path);
+        if let Some(node) = self.get_file_node_mut(path) {
+            if node.loading {
+                node.loading = false;
+                node.read = false;
+            }
+        }
+    }
+
+    /// Stop adding up the sizes of the directories beneath the directory,
+    /// whose rows are no longer shown
+    fn cancel_dir_sizes(&mut self, path: &Path) {
+        let proxy = &self.proxy;
+        self.sizing_dirs.retain(|dir| {
+            let cancel = dir != path && dir.starts_with(path);
+            if cancel {
+                proxy.cancel_dir_size(dir);
+            }
+            !cancel
+        });
+    }
+
+    pub fn reload(&self) {
+        if let Some(workspace) = self.workspace.as_ref() {
+            Self::read_dir(
+                &workspace.path_buf,
+                true,
+                self.tab_id,
+                &self.proxy,
+                self.event_sink.clone(),
+            );
+        }
+        // The added folders are refreshed independently, keeping them collapsed
+        // if they were
+        for root in self.extra_roots.iter() {
+            Self::read_dir(
+                &root.path_buf,
+                root.open,
+                self.tab_id,
+                &self.proxy,
+                self.event_sink.clone(),
+            );
+        }
+    }
+
+    /// Add an entry to the context menu, replacing the one the plugin
+    /// registered for the same command before
+    pub fn add_plugin_menu_item(&mut self, item: ExplorerMenuItem) {
+        if let Some(existing) = self
+            .plugin_menu_items
+            .iter_mut()
+            .find(|i| i.plugin == item.plugin && i.command == item.command)
+        {
+            *existing = item;
+        } else {
+            self.plugin_menu_items.push(item);
+        }
+    }
+
+    /// The plugin entries to show in the context menu opened on the node
+    pub fn plugin_menu_items(
+        &self,
+        node: &FileNodeItem,
+    ) -> impl Iterator<Item = &ExplorerMenuItem> {
+        let is_dir = node.is_dir;
+        self.plugin_menu_items
+            .iter()
+            .filter(move |item| item.applies_to(is_dir))
+    }
+
+    /// Read the listed directories of the files again, so that they show how
+    /// the files changed
+    pub fn refresh_parents(&self, paths: &[PathBuf]) {
+        let dirs: HashSet<&Path> =
+            paths.iter().filter_map(|path| path.parent()).collect();
+        for dir in dirs {
+            if let Some(node) = self.get_file_node(dir) {
+                if node.read {
+                    Self::read_dir(
+                        dir,
+                        node.open,
+                        self.tab_id,
+                        &self.proxy,
+                        self.event_sink.clone(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Read the directory again, for changes that the file watcher missed.
+    /// The children are reconciled with the ones it had, so subdirectories
+    /// that still exist keep being open and are read again as well.
+    pub fn refresh_dir(&self, path: &Path) {
+        if let Some(node) = self.get_file_node(path) {
+            if node.is_dir {
+                Self::read_dir(
+                    path,
+                    node.open,
+                    self.tab_id,
+                    &self.proxy,
+                    self.event_sink.clone(),
+                );
+            }
+        }
+    }
+
+    pub fn read_dir(
+        path: &Path,
+        expand: bool,
+        tab_id: WidgetId,
+        proxy: &LapceProxy,
+        event_sink: ExtEventSink,
+    ) {
+        FileExplorerData::read_dir_cb::<fn()>(
+            path, expand, tab_id, proxy, event_sink, None,
+        )
+    }
+
+    pub fn read_dir_cb<F: FnOnce() + Send + 'static>(
+        path: &Path,
+        expand: bool,
+        tab_id: WidgetId,
+        proxy: &LapceProxy,
+        event_sink: ExtEventSink,
+        on_finished: Option<F>,
+    ) {
+        let path = PathBuf::from(path);
+        let local_path = path.clone();
+        let generation = new_read_generation();
+        if let Some(on_finished) = on_finished {
+            proxy.on_read_dir_finished(&path, generation, on_finished);
+        }
+        proxy.read_dir(
+            &local_path,
+            false,
+            Some(READ_DIR_CHUNK_SIZE),
+            generation,
+            move |result| {
+                if let Ok(resp) = result {
+                    let path = path.clone();
+                    let _ = event_sink.submit_command(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::UpdateExplorerItems {
+                            path,
+                            items: resp.items,
+                            expand,
+                            more: resp.more,
+                            generation,
+                        },
+                        Target::Widget(tab_id),
+                    );
+                } else {
+                    let _ = event_sink.submit_command(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::ExplorerReadDirFailed { path },
+                        Target::Widget(tab_id),
+                    );
+                }
+            },
+        );
+    }
+
+    /// Read the directory and expand it, then do the same for each of its
+    /// children that is in `open_dirs`. Each level is only read once all the
+    /// entries of its parent are in, so that the nodes exist when the update
+    /// arrives. Directories that no longer exist aren't read, and symbolic
+    /// links to their own ancestors are left collapsed.
+    fn read_dir_expanding(
+        path: PathBuf,
+        open_dirs: Arc<HashSet<PathBuf>>,
+        tab_id: WidgetId,
+        proxy: Arc<LapceProxy>,
+        event_sink: ExtEventSink,
+    ) {
+        let generation = new_read_generation();
+        let local_path = path.clone();
+        let finished_path = path.clone();
+        let local_event_sink = event_sink.clone();
+        proxy.on_read_dir_finished(&path, generation, move || {
+            let _ = local_event_sink.submit_command(
+                LAPCE_UI_COMMAND,
+                LapceUICommand::RestoreExplorerDirs {
+                    path: finished_path,
+                    open_dirs,
+                },
+                Target::Widget(tab_id),
+            );
+        });
+        proxy.read_dir(
+            &local_path,
+            false,
+            Some(READ_DIR_CHUNK_SIZE),
+            generation,
+            move |result| {
+                let command = match result {
+                    Ok(resp) => LapceUICommand::UpdateExplorerItems {
+                        path,
+                        items: resp.items,
+                        expand: true,
+                        more: resp.more,
+                        generation,
+                    },
+                    Err(_) => LapceUICommand::ExplorerReadDirFailed { path },
+                };
+                let _ = event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    command,
+                    Target::Widget(tab_id),
+                );
+            },
+        );
+    }
+
+    /// Read and expand the children of the directory that are in
+    /// `open_dirs`, along with the directories in `open_dirs` beneath them
+    pub fn restore_open_dirs(
+        &mut self,
+        path: &Path,
+        open_dirs: Arc<HashSet<PathBuf>>,
+    ) {
+        let node = match self.get_file_node(path) {
+            Some(node) if node.open => node,
+            _ => return,
+        };
+        let dirs: Vec<PathBuf> = open_dirs
+            .iter()
+            .filter(|dir| dir.parent() == Some(path))
+            .filter(|dir| {
+                node.children
+                    .get(*dir)
+                    .map_or(false, |child| child.is_dir && !child.is_symlink_loop())
+            })
+            .cloned()
+            .collect();
+        for dir in dirs {
+            Self::read_dir_expanding(
+                dir,
+                open_dirs.clone(),
+                self.tab_id,
+                self.proxy.clone(),
+                self.event_sink.clone(),
+            );
+        }
+    }
+
+    /// Rename the path, going through a temporary name when only the case of
+    /// the name changes on a file system that ignores case, since renaming it
+    /// directly would do nothing there
+    pub fn rename_path(
+        proxy: Arc<LapceProxy>,
+        from: PathBuf,
+        to: PathBuf,
+        f: Box<dyn Callback>,
+    ) {
+        let case_only = from != to
+            && from.parent() == to.parent()
+            && from.to_string_lossy().to_lowercase()
+                == to.to_string_lossy().to_lowercase();
+        if !case_only {
+            proxy.rename_path(&from, &to, f);
+            return;
+        }
+
+        let local_proxy = proxy.clone();
+        let local_from = from.clone();
+        proxy.is_case_sensitive(&local_from, move |result| {
+            if result.map_or(true, |resp| resp.case_sensitive) {
+                local_proxy.rename_path(&from, &to, f);
+                return;
+            }
+
+            let name = from
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let temp = from.with_file_name(format!(".{name}.lapce-rename"));
+            let proxy = local_proxy.clone();
+            let local_from = from.clone();
+            let local_temp = temp.clone();
+            local_proxy.rename_path(
+                &local_from,
+                &local_temp,
+                Box::new(move |result| match result {
+                    Ok(_) => {
+                        let local_proxy = proxy.clone();
+                        let local_temp = temp.clone();
+                        proxy.rename_path(
+                            &local_temp,
+                            &to,
+                            Box::new(move |result| match result {
+                                Ok(resp) => f.call(Ok(resp)),
+                                // Give the path its name back rather than
+                                // leaving it under the temporary one
+                                Err(err) => local_proxy.rename_path(
+                                    &temp,
+                                    &from,
+                                    Box::new(move |_| f.call(Err(err))),
+                                ),
+                            }),
+                        );
+                    }
+                    Err(err) => f.call(Err(err)),
+                }),
+            );
+        });
+    }
+
+    /// Read the directory again, and the directories beneath it down to the
+    /// path, expanding them all, such as after the path was created
+    pub fn read_dir_revealing(&self, dir: &Path, path: &Path) {
+        Self::read_dir_expanding(
+            dir.to_path_buf(),
+            Arc::new(dirs_between(dir, path)),
+            self.tab_id,
+            self.proxy.clone(),
+            self.event_sink.clone(),
+        );
+    }
+
+    /// Expand the directories containing the path so that it is shown in the
+    /// list. Directories that haven't been read yet are read and expanded
+    /// one level after another.
+    pub fn reveal_path(&mut self, path: &Path) {
+        let root = match self.roots().find(|root| path.starts_with(&root.path_buf)) {
+            Some(root) => root.path_buf.clone(),
+            None => return,
+        };
+        let mut dirs: Vec<PathBuf> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&root))
+            .map(Path::to_path_buf)
+            .collect();
+        dirs.reverse();
+
+        for (i, dir) in dirs.iter().enumerate() {
+            let node = match self.get_file_node_mut(dir) {
+                Some(node) => node,
+                None => break,
+            };
+            if !node.read {
+                let open_dirs = dirs[i + 1..].iter().cloned().collect();
+                self.reading_dirs.insert(dir.clone());
+                Self::read_dir_expanding(
+                    dir.clone(),
+                    Arc::new(open_dirs),
+                    self.tab_id,
+                    self.proxy.clone(),
+                    self.event_sink.clone(),
+                );
+                break;
+            }
+            node.open = true;
+        }
+
+        self.update_counts(path);
+    }
+
+    /// The open directories, relative to the workspace, for persisting the
+    /// expansion state. While filtering, the state from before the filter
+    /// was applied is used instead.
+    pub fn open_dirs(&self) -> Vec<PathBuf> {
+        let workspace = match self.workspace.as_ref() {
+            Some(workspace) => workspace,
+            None => return Vec::new(),
+        };
+        let open = match self.pre_filter_open.as_ref() {
+            Some(open) => open.clone(),
+            None => {
+                let mut open = HashSet::new();
+                collect_open_dirs(workspace, &mut open);
+                open
+            }
+        };
+        let mut dirs: Vec<PathBuf> = open
+            .iter()
+            .filter_map(|dir| dir.strip_prefix(&workspace.path_buf).ok())
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(|dir| dir.to_path_buf())
+            .collect();
+        dirs.sort();
+        dirs
+    }
+
+    /// Stop naming the file/directory, discarding any changes
+    pub fn cancel_naming(&mut self) {
+        self.naming = None;
+        self.naming_error = None;
+        self.naming_completion_prefix = None;
+    }
+
+    /// Complete the naming text to the name of another entry in the directory,
+    /// or to the name with one of the extensions. Completing again goes through
+    /// the completions in turn.
+    pub fn complete_naming(
+        &mut self,
+        main_split: &mut LapceMainSplitData,
+        extensions: &[String],
+    ) {
+        let dir = match &self.naming {
+            Some(Naming::Naming { base_path, .. }) => base_path.clone(),
+            Some(Naming::Renaming { path, .. }) => match path.parent() {
+                Some(parent) => parent.to_path_buf(),
+                None => return,
+            },
+            None => return,
+        };
+        let siblings: Vec<String> = self
+            .get_file_node(&dir)
+            .map(|node| {
+                node.children
+                    .keys()
+                    .filter_map(|path| path.file_name())
+                    .map(|name| name.to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let doc = main_split
+            .local_docs
+            .get_mut(&LocalBufferKind::PathName)
+            .unwrap();
+        let text = doc.buffer().text().to_string();
+        // Keep going through the completions of the text that was completed,
+        // unless the text was changed since
+        let (prefix, completions) = match self
+            .naming_completion_prefix
+            .take()
+            .map(|prefix| {
+                let completions = naming_completions(&prefix, &siblings, extensions);
+                (prefix, completions)
+            })
+            .filter(|(_, completions)| completions.contains(&text))
+        {
+            Some(completing) => completing,
+            None => {
+                let completions = naming_completions(&text, &siblings, extensions);
+                (text.clone(), completions)
+            }
+        };
+        let completion = match next_naming_completion(&text, &prefix, &completions) {
+            Some(completion) => completion,
+            None => return,
+        };
+
+        Arc::make_mut(doc).reload(Rope::from(completion.as_str()), true);
+        let editor = main_split
+            .editors
+            .get_mut(&self.renaming_editor_view_id)
+            .unwrap();
+        Arc::make_mut(editor).cursor.mode =
+            CursorMode::Insert(Selection::caret(completion.len()));
+        self.naming_error = None;
+        self.naming_completion_prefix = Some(prefix);
+    }
+
+    /// Apply the current naming/renaming text (if it is nonempty and not the same as before)
+    /// Also stops the naming.
+    pub fn apply_naming(
+        &mut self,
+        ctx: &mut EventCtx,
+        main_split: &LapceMainSplitData,
+    ) {
+        let naming = if let Some(naming) = &self.naming {
+            naming
+        } else {
+            return;
+        };
+
+        // Get the text in the input
+        let doc = main_split
+            .local_docs
+            .get(&LocalBufferKind::PathName)
+            .unwrap();
+        let target_name = doc.buffer().text().to_string();
+        // If the name is empty, then we just ignore it
+        if target_name.is_empty() {
+            self.cancel_naming();
+            return;
+        }
+
+        match naming {
+            Naming::Renaming { path, .. } => {
+                let renaming = path.clone();
+
+                // The name can also be a path relative to the parent directory,
+                // which moves the file as part of renaming it
+                let target_path = match renaming.parent() {
+                    Some(parent) => normalize_path(&parent.join(&target_name)),
+                    None => renaming.with_file_name(&target_name),
+                };
+
+                // Don't allow moving the file out of the workspace
+                if let Some(root) = self.workspace.as_ref().map(|w| &w.path_buf) {
+                    if !target_path.starts_with(root) || &target_path == root {
+                        self.naming_error = Some(
+                            "The new path must be inside the workspace".to_string(),
+                        );
+                        return;
+                    }
+                }
+
+                // If it is the same, then we don't bother renaming it
+                if target_path == renaming {
+                    return;
+                }
+
+                ctx.submit_command(Command::new(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::RenamePath {
+                        from: renaming,
+                        to: target_path,
+                    },
+                    Target::Auto,
+                ));
+            }
+            Naming::Naming {
+                is_dir,
+                base_path,
+                template,
+                content,
+                ..
+            } => {
+                let mut path = base_path.clone();
+                if *is_dir {
+                    // A path like `a/b/c` creates the whole chain of directories
+                    match new_dir_path(&target_name) {
+                        Ok(dir_path) => path.push(dir_path),
+                        Err(err) => {
+                            self.naming_error = Some(err);
+                            return;
+                        }
+                    }
+                } else {
+                    path.push(target_name);
+                }
+
+                let cmd = if *is_dir {
+                    LapceUICommand::CreateDirectory { path }
+                } else {
+                    LapceUICommand::CreateFileOpen {
+                        path,
+                        template: template.clone(),
+                        content: content.clone(),
+                    }
+                };
+                ctx.submit_command(Command::new(
+                    LAPCE_UI_COMMAND,
+                    cmd,
+                    Target::Auto,
+                ));
+            }
+        }
+
+        self.cancel_naming();
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn start_naming(
+        &mut self,
+        ctx: &mut EventCtx,
+        main_split: &mut LapceMainSplitData,
+        list_index: usize,
+        indent_level: usize,
+        is_dir: bool,
+        base_path: PathBuf,
+        template: Option<PathBuf>,
+        content: Option<String>,
+    ) {
         self.cancel_naming();
+        // The input is anchored to the row it was asked to come after, which
+        // is either the directory itself or the entry of it that was selected
+        let after = list_index
+            .checked_sub(1)
+            .and_then(|index| self.get_node_by_index(index))
+            .map(|(_, node)| node.path_buf.clone())
+            .filter(|path| path != &base_path);
+        self.naming = Some(Naming::Naming {
+            after,
+            indent_level,
+            is_dir,
+            base_path,
+            template,
+            content,
+        });
+
+        // Clear the text of the input
+        let doc = main_split
+            .local_docs
+            .get_mut(&LocalBufferKind::PathName)
+            .unwrap();
+        Arc::make_mut(doc).reload(Rope::from(String::new()), true);
+
+        // Make sure the cursor is at the right position
+        let editor = main_split
+            .editors
+            .get_mut(&self.renaming_editor_view_id)
+            .unwrap();
+        Arc::make_mut(editor).cursor.mode = CursorMode::Insert(Selection::caret(0));
+
+        // Focus on the input
+        ctx.submit_command(Command::new(
+            LAPCE_UI_COMMAND,
+            LapceUICommand::Focus,
+            Target::Widget(editor.view_id),
+        ));
+    }
+
+    /// Show the renaming input for the given file at the index
+    /// Requires `main_split` for getting the input to set its content
+    /// Requires `ctx` to switch focus to the input
+    pub fn start_renaming(
+        &mut self,
+        ctx: &mut EventCtx,
+        main_split: &mut LapceMainSplitData,
+        list_index: usize,
+        indent_level: usize,
+        text: String,
+    ) {
+        self.cancel_naming();
+        let path = match self.get_node_by_index(list_index) {
+            Some((_, node)) => node.path_buf.clone(),
+            None => return,
+        };
+        self.naming = Some(Naming::Renaming { path, indent_level });
+
+        // Set the text of the input
+        let doc = main_split
+            .local_docs
+            .get_mut(&LocalBufferKind::PathName)
+            .unwrap();
+        Arc::make_mut(doc).reload(Rope::from(text), true);
+
+        // TODO: We could provide a configuration option to only select the filename at first,
+        // which would fit a common case of just wanting to change the filename and not the ext
+        // (or that could be the default)
+
+        // Select all of the text, allowing them to quickly completely change the name if they wish
+        let editor = main_split
+            .editors
+            .get_mut(&self.renaming_editor_view_id)
+            .unwrap();
+        let offset = doc.buffer().line_end_offset(0, true);
+        Arc::make_mut(editor).cursor.mode =
+            CursorMode::Insert(Selection::region(0, offset));
+
+        // Focus on the input
+        ctx.submit_command(Command::new(
+            LAPCE_UI_COMMAND,
+            LapceUICommand::Focus,
+            Target::Widget(editor.view_id),
+        ));
+    }
+}
+
+/// The first row at or after the current one whose lowercase name starts with
+/// the typed text, wrapping around to the top. Typing the same letter again
+/// moves on to the next row starting with it instead.
+fn type_ahead_match(
+    names: &[Option<String>],
+    typed: &str,
+    current: Option<usize>,
+) -> Option<usize> {
+    let typed = typed.to_lowercase();
+    let first = typed.chars().next()?;
+    let (prefix, start) = if typed.chars().all(|c| c == first) {
+        (first.to_string(), current.map_or(0, |index| index + 1))
+    } else {
+        (typed, current.unwrap_or(0))
+    };
+    (0..names.len())
+        .map(|i| (start + i) % names.len())
+        .find(|&index| {
+            names[index]
+                .as_ref()
+                .map_or(false, |name| name.starts_with(&prefix))
+        })
+}
+
+/// The names that the naming text completes to: the names in the directory
+/// that start with it, sorted, followed by the name with each of the
+/// extensions that start like its extension. Without an extension, the
+/// extensions are only offered when no name in the directory matches.
+pub fn naming_completions(
+    text: &str,
+    siblings: &[String],
+    extensions: &[String],
+) -> Vec<String> {
+    let mut completions: Vec<String> = siblings
+        .iter()
+        .filter(|name| name.starts_with(text))
+        .cloned()
+        .collect();
+    completions.sort();
+
+    let stem_and_extension = match text.rsplit_once('.') {
+        Some(split) => Some(split),
+        None if completions.is_empty() => Some((text, "")),
+        None => None,
+    };
+    if let Some((stem, partial)) =
+        stem_and_extension.filter(|(stem, _)| !stem.is_empty())
+    {
+        for extension in extensions.iter().filter(|ext| ext.starts_with(partial)) {
+            let name = format!("{stem}.{extension}");
+            if !completions.contains(&name) {
+                completions.push(name);
+            }
+        }
+    }
+    completions
+}
+
+/// The text after completing it once more, which is the longest common start
+/// of the completions when that is longer than the text that was completed,
+/// and otherwise the completion after the current one
+fn next_naming_completion(
+    text: &str,
+    prefix: &str,
+    completions: &[String],
+) -> Option<String> {
+    let first = completions.first()?;
+    if text == prefix {
+        let common = completions
+            .iter()
+            .skip(1)
+            .fold(first.as_str(), |common, c| {
+                let len = common
+                    .char_indices()
+                    .zip(c.chars())
+                    .find(|((_, a), b)| a != b)
+                    .map_or(common.len().min(c.len()), |((i, _), _)| i);
+                &common[..len]
+            });
+        if common.len() > text.len() {
+            return Some(common.to_string());
+        }
+    }
+    let next = completions
+        .iter()
+        .position(|completion| completion == text)
+        .map_or(0, |i| (i + 1) % completions.len());
+    Some(completions[next].clone())
+}
+
+/// The path to import the file by, relative to the source root if it's beneath
+/// it and otherwise to the workspace, with its components joined by the
+/// separator. Module files that stand for their directory, like `mod.rs` and
+/// `__init__.py`, are left out, and Rust paths start from the crate.
+pub fn import_path(
+    path: &Path,
+    workspace: &Path,
+    source_root: &str,
+    separator: &str,
+) -> Option<String> {
+    let relative = path.strip_prefix(workspace).ok()?;
+    let relative = relative.strip_prefix(source_root).unwrap_or(relative);
+    let extension = path.extension()?.to_str()?;
+    let mut components: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    let stand_for_dir: &[&str] = match extension {
+        "rs" => &["mod", "lib", "main"],
+        "py" => &["__init__"],
+        _ => &["index"],
+    };
+    if components
+        .last()
+        .map_or(false, |last| stand_for_dir.contains(&last.as_str()))
+    {
+        components.pop();
+    }
+    if extension == "rs" {
+        components.insert(0, "crate".to_string());
+    }
+    if components.is_empty() {
+        return None;
+    }
+    Some(components.join(separator))
+}
+
+/// The text inserted into an editor when the path is dropped onto it, which is
+/// relative to the directory of the file being edited. An image dropped into a
+/// markdown file becomes a reference to the image.
+pub fn dropped_path_text(path: &Path, doc_path: Option<&Path>) -> String {
+    let relative = doc_path
+        .and_then(Path::parent)
+        .and_then(|dir| relative_path(path, dir))
+        .unwrap_or_else(|| path.to_path_buf());
+    let is_markdown = doc_path
+        .and_then(|p| p.extension())
+        .map_or(false, |ext| ext == "md" || ext == "markdown");
+    let is_image =
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map_or(false, |ext| {
+                ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp"]
+                    .contains(&ext.to_lowercase().as_str())
+            });
+    if is_markdown && is_image {
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        format!("![]({relative})")
+    } else {
+        relative.to_string_lossy().to_string()
+    }
+}
+
+/// The path as it's shown to the user, with the home directory abbreviated to
+/// `~` when it's given and the path is beneath it
+pub fn display_path(path: &Path, home: Option<&Path>) -> String {
+    home.and_then(|home| abbreviate_home(path, home))
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
+
+/// The path with the home directory replaced by `~`, or `None` if it isn't
+/// beneath it. Windows paths are compared case-insensitively, like the file
+/// system there does.
+pub fn abbreviate_home(path: &Path, home: &Path) -> Option<String> {
+    if home.as_os_str().is_empty() {
+        return None;
+    }
+    let mut components = path.components();
+    for home_component in home.components() {
+        let component = components.next()?;
+        let same = if cfg!(windows) {
+            component.as_os_str().to_string_lossy().to_lowercase()
+                == home_component.as_os_str().to_string_lossy().to_lowercase()
+        } else {
+            component == home_component
+        };
+        if !same {
+            return None;
+        }
+    }
+    let rest = components.as_path();
+    if rest.as_os_str().is_empty() {
+        Some("~".to_string())
+    } else {
+        Some(format!(
+            "~{}{}",
+            std::path::MAIN_SEPARATOR,
+            rest.to_string_lossy()
+        ))
+    }
+}
+
+/// The name of the file without its extension, which is only what follows
+/// its last dot, so that `foo.test.ts` gives `foo.test`. A dot at the start
+/// doesn't begin an extension, so `.gitignore` stays `.gitignore`, and
+/// `.env.local` gives `.env`.
+pub fn name_without_extension(path: &Path) -> Option<String> {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+}
+
+/// The path relative to the directory, going up with `..` from the directory
+/// to where they share a parent, or `None` if they don't share a root
+fn relative_path(path: &Path, dir: &Path) -> Option<PathBuf> {
+    let mut path_components = path.components().peekable();
+    let mut dir_components = dir.components().peekable();
+    if path_components.peek() != dir_components.peek() {
+        return None;
+    }
+    while let (Some(a), Some(b)) = (path_components.peek(), dir_components.peek()) {
+        if a != b {
+            break;
+        }
+        path_components.next();
+        dir_components.next();
+    }
+    let mut relative: PathBuf =
+        dir_components.map(|_| Component::ParentDir).collect();
+    relative.extend(path_components);
+    Some(relative)
+}
+
+/// The relative path of the directories to create for the name typed into the
+/// New Directory input, without the trailing separators. Every directory has
+/// to have a name.
+fn new_dir_path(name: &str) -> Result<PathBuf, String> {
+    let name = name.trim_end_matches(std::path::is_separator);
+    if name.is_empty() {
+        return Err("The directory name can't be empty".to_string());
+    }
+    let mut path = PathBuf::new();
+    for segment in name.split(std::path::is_separator) {
+        match segment {
+            "" => {
+                return Err("The directory names can't be empty".to_string());
+            }
+            "." | ".." => {
+                return Err(format!("\"{segment}\" isn't a valid directory name"));
+            }
+            segment => path.push(segment),
+        }
+    }
+    Ok(path)
+}
+
+/// The directories that are expanded to show the path created beneath `dir`,
+/// which are the ones between them
+pub fn dirs_between(dir: &Path, path: &Path) -> HashSet<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .take_while(|ancestor| *ancestor != dir && ancestor.starts_with(dir))
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// Move the path to the front of the recent paths, or add it there, and drop
+/// the oldest ones beyond the limit
+fn push_recent(recent: &mut Vec<PathBuf>, path: &Path, limit: usize) {
+    recent.retain(|recent| recent != path);
+    recent.insert(0, path.to_path_buf());
+    recent.truncate(limit);
+}
+
+/// The paths without the ones inside another of them, which go along with it
+pub fn outermost_paths(paths: &[PathBuf]) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .filter(|path| {
+            !paths
+                .iter()
+                .any(|other| other != *path && path.starts_with(other))
+        })
+        .cloned()
+        .collect()
+}
+
+/// The names of the first few paths, and how many more there are
+pub fn path_names_summary(paths: &[PathBuf], max: usize) -> String {
+    let names: Vec<String> = paths
+        .iter()
+        .take(max)
+        .map(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.to_string_lossy().to_string())
+        })
+        .collect();
+    let names = names.join(", ");
+    if paths.len() > max {
+        format!("{names} and {} more", paths.len() - max)
+    } else {
+        names
+    }
+}
+
+/// The path named by text pasted into the file explorer, like a path copied
+/// from a terminal. Quotes around it are dropped, `file://` URLs and `~` are
+/// understood, and relative paths are taken from the workspace.
+pub fn pasted_path(
+    text: &str,
+    workspace: &Path,
+    home: Option<&Path>,
+) -> Option<PathBuf> {
+    let text = text.trim().trim_matches(|c: char| c == '"' || c == '\'');
+    if text.is_empty() || text.contains('\n') {
+        return None;
+    }
+    let path = if text.starts_with("file://") {
+        Url::parse(text).ok()?.to_file_path().ok()?
+    } else if let (Some(rest), Some(home)) = (text.strip_prefix('~'), home) {
+        home.join(rest.trim_start_matches(['/', '\\']))
+    } else {
+        workspace.join(text)
+    };
+    Some(normalize_path(&path))
+}
+
+/// The row that the input for naming a new entry of `dir` is shown at: right
+/// after the rows of the entry `after` and of everything open beneath it, or
+/// right after the row of `dir` when there is no such entry (anymore)
+fn naming_row(
+    index_paths: &[Option<(usize, PathBuf)>],
+    dir: &Path,
+    after: Option<&Path>,
+) -> Option<usize> {
+    let find = |path: &Path| {
+        index_paths
+            .iter()
+            .position(|row| matches!(row, Some((_, p)) if p == path))
+    };
+    if let Some(index) = after.and_then(find) {
+        let indent = index_paths[index].as_ref()?.0;
+        // Loading rows are always beneath the directory being read
+        let end = index_paths[index + 1..]
+            .iter()
+            .position(|row| matches!(row, Some((i, _)) if *i <= indent))
+            .map_or(index_paths.len(), |n| index + 1 + n);
+        return Some(end);
+    }
+    find(dir).map(|index| index + 1)
+}
+
+/// Resolve the `.` and `..` components of the path without touching the file system
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Read the template and substitute `${name}` with the file name of `path`
+/// and `${date}` with the current date
+pub fn render_template(template: &Path, path: &Path) -> Result<String> {
+    let content = std::fs::read_to_string(template)?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+    Ok(content.replace("${name}", &name).replace("${date}", &date))
+}
+
+/// Whether the node is shown in the file list with the current filter matches
+pub fn is_node_visible(
+    item: &FileNodeItem,
+    matches: Option<&HashSet<PathBuf>>,
+) -> bool {
+    matches.map(|m| m.contains(&item.path_buf)).unwrap_or(true)
+}
+
+/// Case-insensitive subsequence match of `pattern` against `name`.
+/// Returns `None` if it doesn't match, otherwise a score that is higher for
+/// consecutive characters and matches at the start of the name.
+/// `pattern` is expected to already be lowercase.
+pub fn filter_match_score(pattern: &str, name: &str) -> Option<usize> {
+    let name = name.to_lowercase();
+    let mut name_chars = name.chars().enumerate();
+    let mut score = 0;
+    let mut last_index = None;
+    for c in pattern.chars() {
+        let (index, _) = name_chars.find(|(_, n)| *n == c)?;
+        score += match last_index {
+            Some(last) if last + 1 == index => 3,
+            None if index == 0 => 3,
+            _ => 1,
+        };
+        last_index = Some(index);
+    }
+    Some(score)
+}
+
+/// Merge a chunk of the listing of the directory into its children by path.
+/// The directories that are still there keep being open along with their
+/// children, and only take the metadata of the listing, or have theirs read
+/// again when the listing doesn't include it. Once the last chunk
+/// is in, the children that weren't listed are removed. Returns the
+/// directories that had been read, and whether they're open, to read them
+/// again.
+fn merge_listing(
+    node: &mut FileNodeItem,
+    children: HashMap<PathBuf, FileNodeItem>,
+    more: bool,
+    listed: &mut HashSet<PathBuf>,
+) -> Vec<(PathBuf, bool)> {
+    let mut reread = Vec::new();
+    for (path, child) in children {
+        listed.insert(path.clone());
+        match node.children.get_mut(&path) {
+            Some(existing) if existing.is_dir == child.is_dir => {
+                if child.metadata_read {
+                    existing.size = child.size;
+                    existing.modified = child.modified;
+                    existing.readonly = child.readonly;
+                    existing.git_ignored = child.git_ignored;
+                }
+                existing.metadata_read = child.metadata_read;
+                existing.is_symlink = child.is_symlink;
+                existing.symlink_target = child.symlink_target;
+                if existing.read {
+                    reread.push((path, existing.open));
+                }
+            }
+            _ => {
+                node.children.insert(path, child);
+            }
+        }
+    }
+    if !more {
+        node.children.retain(|path, _| listed.contains(path));
+        listed.clear();
+    }
+    reread
+}
+
+/// The size in bytes with the largest unit it is at least one of, with a
+/// decimal below 10 of that unit
+pub fn human_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if size < 1024 {
+        return format!("{size} B");
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    if value < 10.0 {
+        format!("{value:.1} {unit}")
+    } else {
+        format!("{value:.0} {unit}")
+    }
+}
+
+/// Insert the paths of all the nodes matching the pattern, and their ancestors,
+/// into `matches`. When filtering by a search, only the files with search
+/// matches can match. Returns whether this node or any of its descendants
+/// matched.
+fn collect_filter_matches(
+    item: &FileNodeItem,
+    pattern: &str,
+    search: Option<&HashMap<PathBuf, usize>>,
+    matches: &mut HashSet<PathBuf>,
+) -> bool {
+    let mut matched = item
+        .path_buf
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| filter_match_score(pattern, name))
+        .is_some()
+        && search.map_or(true, |search| search.contains_key(&item.path_buf));
+    for child in item.children.values() {
+        if collect_filter_matches(child, pattern, search, matches) {
+            matched = true;
+        }
+    }
+    if matched {
+        matches.insert(item.path_buf.clone());
+    }
+    matched
+}
+
+/// Open the directories that contain matches, and close the ones that don't
+fn expand_filter_matches(item: &mut FileNodeItem, matches: &HashSet<PathBuf>) {
+    if !item.is_dir {
+        return;
     }
+    item.open = item
+        .children
+        .values()
+        .any(|child| matches.contains(&child.path_buf));
+    for child in item.children.values_mut() {
+        expand_filter_matches(child, matches);
+    }
+}
 
-    pub fn start_naming(
-        &mut self,
-        ctx: &mut EventCtx,
-        main_split: &mut LapceMainSplitData,
-        list_index: usize,
-        indent_level: usize,
-        is_dir: bool,
-        base_path: PathBuf,
-    ) {
-        self.cancel_naming();
-        self.naming = Some(Naming::Naming {
-            list_index,
-            indent_level,
-            is_dir,
-            base_path,
-        });
+fn collapse_dirs(item: &mut FileNodeItem) {
+    item.open = false;
+    for child in item.children.values_mut() {
+        collapse_dirs(child);
+    }
+}
 
-        // Clear the text of the input
-        let doc = main_split
-            .local_docs
-            .get_mut(&LocalBufferKind::PathName)
-            .unwrap();
-        Arc::make_mut(doc).reload(Rope::from(String::new()), true);
+fn collect_open_dirs(item: &FileNodeItem, open: &mut HashSet<PathBuf>) {
+    if item.is_dir && item.open {
+        open.insert(item.path_buf.clone());
+    }
+    for child in item.children.values() {
+        collect_open_dirs(child, open);
+    }
+}
 
-        // Make sure the cursor is at the right position
-        let editor = main_split
-            .editors
-            .get_mut(&self.renaming_editor_view_id)
-            .unwrap();
-        Arc::make_mut(editor).cursor.mode = CursorMode::Insert(Selection::caret(0));
+fn restore_open_dirs(item: &mut FileNodeItem, open: &HashSet<PathBuf>) {
+    if item.is_dir {
+        item.open = open.contains(&item.path_buf);
+    }
+    for child in item.children.values_mut() {
+        restore_open_dirs(child, open);
+    }
+}
 
-        // Focus on the input
-        ctx.submit_command(Command::new(
-            LAPCE_UI_COMMAND,
-            LapceUICommand::Focus,
-            Target::Widget(editor.view_id),
-        ));
+/// Set whether the directory is open, returning whether it has to be read
+/// first, in which case it stays closed until it has been
+fn set_dir_open(node: &mut FileNodeItem, open: bool) -> bool {
+    if open && !node.read {
+        return true;
     }
+    node.open = open;
+    false
+}
 
-    /// Show the renaming input for the given file at the index
-    /// Requires `main_split` for getting the input to set its content
-    /// Requires `ctx` to switch focus to the input
-    pub fn start_renaming(
-        &mut self,
-        ctx: &mut EventCtx,
-        main_split: &mut LapceMainSplitData,
-        list_index: usize,
-        indent_level: usize,
-        text: String,
-    ) {
-        self.cancel_naming();
-        self.naming = Some(Naming::Renaming {
-            list_index,
-            indent_level,
-        });
+/// Open the directory and the directories beneath it, `levels` levels deep
+/// counting the directory itself, or all of them for `None`. The directories
+/// within reach that have to be read first are added to `unread`, along with
+/// the levels left to open from them. Symbolic links to their own ancestors
+/// are left alone, as there would be no end to them.
+fn open_levels(
+    node: &mut FileNodeItem,
+    levels: Option<usize>,
+    unread: &mut Vec<(PathBuf, Option<usize>)>,
+) {
+    if !node.is_dir || levels == Some(0) || node.is_symlink_loop() {
+        return;
+    }
+    if !node.read {
+        unread.push((node.path_buf.clone(), levels));
+        return;
+    }
+    node.open = true;
+    let levels = levels.map(|levels| levels - 1);
+    for child in node.children.values_mut() {
+        open_levels(child, levels, unread);
+    }
+}
 
-        // Set the text of the input
-        let doc = main_split
-            .local_docs
-            .get_mut(&LocalBufferKind::PathName)
-            .unwrap();
-        Arc::make_mut(doc).reload(Rope::from(text), true);
+/// Recompute `children_open_count` for the node and all of its descendants,
+/// only counting the children that are visible with the filter matches
+fn update_all_node_counts(
+    item: &mut FileNodeItem,
+    matches: Option<&HashSet<PathBuf>>,
+    flat_dirs: &HashMap<PathBuf, Vec<FileNodeItem>>,
+    compact_folders: bool,
+) {
+    for child in item.children.values_mut() {
+        update_all_node_counts(child, matches, flat_dirs, compact_folders);
+    }
+    item.children_open_count =
+        open_children_count(item, matches, flat_dirs, compact_folders);
+}
 
-        // TODO: We could provide a configuration option to only select the filename at first,
-        // which would fit a common case of just wanting to change the filename and not the ext
-        // (or that could be the default)
+/// The number of rows below the node's own row, from the counts of its
+/// children, which have to be up to date
+fn open_children_count(
+    item: &FileNodeItem,
+    matches: Option<&HashSet<PathBuf>>,
+    flat_dirs: &HashMap<PathBuf, Vec<FileNodeItem>>,
+    compact_folders: bool,
+) -> usize {
+    if !item.is_dir || !item.open {
+        return 0;
+    }
+    if let Some(flat_files) = flat_dirs.get(&item.path_buf) {
+        return flat_files.len();
+    }
+    item.children
+        .values()
+        .filter(|child| is_node_visible(child, matches))
+        .map(|child| {
+            let row = if compact_folders {
+                compact_tail(child, matches)
+            } else {
+                child
+            };
+            row.children_open_count + 1
+        })
+        .sum::<usize>()
+        + has_placeholder_row(item) as usize
+}
 
-        // Select all of the text, allowing them to quickly completely change the name if they wish
-        let editor = main_split
-            .editors
-            .get_mut(&self.renaming_editor_view_id)
-            .unwrap();
-        let offset = doc.buffer().line_end_offset(0, true);
-        Arc::make_mut(editor).cursor.mode =
-            CursorMode::Insert(Selection::region(0, offset));
+/// Whether an open directory is followed by a row that isn't a node, either
+/// "Loading…" while it is being read or "(empty)" when it has no children
+pub fn has_placeholder_row(item: &FileNodeItem) -> bool {
+    item.loading || is_empty_dir(item)
+}
 
-        // Focus on the input
-        ctx.submit_command(Command::new(
-            LAPCE_UI_COMMAND,
-            LapceUICommand::Focus,
-            Target::Widget(editor.view_id),
-        ));
+/// Whether the directory has been read and turned out to have no children
+pub fn is_empty_dir(item: &FileNodeItem) -> bool {
+    item.is_dir && item.read && !item.loading && item.children.is_empty()
+}
+
+/// The only child of the directory, if it is shown in the same row as the
+/// directory when compacting folders. That is when it is the one visible child
+/// and is a directory that has been read.
+pub fn compact_child<'a>(
+    item: &'a FileNodeItem,
+    matches: Option<&HashSet<PathBuf>>,
+) -> Option<&'a FileNodeItem> {
+    if !item.is_dir || !item.read || item.loading {
+        return None;
     }
+    let mut children = item
+        .children
+        .values()
+        .filter(|child| is_node_visible(child, matches));
+    let child = children.next()?;
+    if children.next().is_some() || !child.is_dir || !child.read {
+        return None;
+    }
+    Some(child)
 }
 
-/// Returns (current index, Option<(indentation level of item, item)>)
-pub fn get_item_children(
-    i: usize,
-    index: usize,
-    indent: usize,
-    item: &FileNodeItem,
-) -> (usize, Option<(usize, &FileNodeItem)>) {
-    if i == index {
-        return (i, Some((indent, item)));
+/// The last directory of the chain of single directories starting at the item,
+/// which is the node of the row the chain is shown as when compacting folders
+pub fn compact_tail<'a>(
+    item: &'a FileNodeItem,
+    matches: Option<&HashSet<PathBuf>>,
+) -> &'a FileNodeItem {
+    let mut tail = item;
+    while let Some(child) = compact_child(tail, matches) {
+        tail = child;
     }
-    let mut i = i;
-    if item.open {
+    tail
+}
+
+fn find_file_node_mut<'a>(
+    mut roots: impl Iterator<Item = &'a mut FileNodeItem>,
+    path: &Path,
+) -> Option<&'a mut FileNodeItem> {
+    roots
+        .find(|root| path.starts_with(&root.path_buf))?
+        .get_file_node_mut(path)
+}
+
+fn find_flat_file_node<'a>(
+    flat_dirs: &'a HashMap<PathBuf, Vec<FileNodeItem>>,
+    path: &Path,
+) -> Option<&'a FileNodeItem> {
+    flat_dirs
+        .iter()
+        .filter(|(dir, _)| path.starts_with(dir))
+        .find_map(|(_, files)| {
+            let i = files
+                .binary_search_by(|file| file.path_buf.as_path().cmp(path))
+                .ok()?;
+            files.get(i)
+        })
+}
+
+fn find_flat_file_node_mut<'a>(
+    flat_dirs: &'a mut HashMap<PathBuf, Vec<FileNodeItem>>,
+    path: &Path,
+) -> Option<&'a mut FileNodeItem> {
+    flat_dirs
+        .iter_mut()
+        .filter(|(dir, _)| path.starts_with(dir))
+        .find_map(|(_, files)| {
+            let i = files
+                .binary_search_by(|file| file.path_buf.as_path().cmp(path))
+                .ok()?;
+            files.get_mut(i)
+        })
+}
+
+/// Push the rows of the node and its visible descendants, in the order they
+/// are listed in the explorer
+fn collect_index_paths(
+    item: &FileNodeItem,
+    indent: usize,
+    matches: Option<&HashSet<PathBuf>>,
+    flat_dirs: &HashMap<PathBuf, Vec<FileNodeItem>>,
+    compact_folders: bool,
+    index_paths: &mut Vec<Option<(usize, PathBuf)>>,
+) {
+    index_paths.push(Some((indent, item.path_buf.clone())));
+    if let (true, Some(flat_files)) = (item.open, flat_dirs.get(&item.path_buf)) {
+        for file in flat_files {
+            index_paths.push(Some((indent + 1, file.path_buf.clone())));
+        }
+    } else if item.open {
         for child in item.sorted_children() {
-            let count = child.children_open_count;
-            if i + count + 1 >= index {
-                let (new_index, node) =
-                    get_item_children(i + 1, index, indent + 1, child);
-                if new_index == index {
-                    return (new_index, node);
-                }
+            if is_node_visible(child, matches) {
+                let row = if compact_folders {
+                    compact_tail(child, matches)
+                } else {
+                    child
+                };
+                collect_index_paths(
+                    row,
+                    indent + 1,
+                    matches,
+                    flat_dirs,
+                    compact_folders,
+                    index_paths,
+                );
             }
-            i += count + 1;
+        }
+        if has_placeholder_row(item) {
+            index_paths.push(None);
         }
     }
-    (i, None)
 }
 
-pub fn get_item_children_mut(
-    i: usize,
+/// The index and indentation level of the row of the path, or `None` if it
+/// has none, because a directory above it is collapsed or hasn't been read
+fn row_index(
+    index_paths: &[Option<(usize, PathBuf)>],
+    path: &Path,
+) -> Option<(usize, usize)> {
+    index_paths
+        .iter()
+        .enumerate()
+        .find_map(|(index, row)| match row {
+            Some((indent, row_path)) if row_path == path => Some((index, *indent)),
+            _ => None,
+        })
+}
+
+/// The indentation level and node of the row at the index, which is looked up
+/// in the flat lists of the directories shown flat, or else in the roots.
+/// `None` past the end and for loading rows.
+fn row_node<'a>(
+    index_paths: &[Option<(usize, PathBuf)>],
     index: usize,
-    indent: usize,
-    item: &mut FileNodeItem,
-) -> (usize, Option<(usize, &mut FileNodeItem)>) {
-    if i == index {
-        return (i, Some((indent, item)));
-    }
-    let mut i = i;
-    if item.open {
-        for child in item.sorted_children_mut() {
-            let count = child.children_open_count;
-            if i + count + 1 >= index {
-                let (new_index, node) =
-                    get_item_children_mut(i + 1, index, indent + 1, child);
-                if new_index == index {
-                    return (new_index, node);
+    mut roots: impl Iterator<Item = &'a FileNodeItem>,
+    flat_dirs: &'a HashMap<PathBuf, Vec<FileNodeItem>>,
+) -> Option<(usize, &'a FileNodeItem)> {
+    let (indent, path) = index_paths.get(index)?.as_ref()?;
+    let node = find_flat_file_node(flat_dirs, path).or_else(|| {
+        roots
+            .find(|root| path.starts_with(&root.path_buf))?
+            .get_file_node(path)
+    })?;
+    Some((*indent, node))
+}
+
+/// The indices of the rows that the row at the index is nested in, which are
+/// the closest rows before it with a smaller indentation. Loading rows don't
+/// know their indentation, so they have none.
+fn ancestor_rows(
+    index_paths: &[Option<(usize, PathBuf)>],
+    index: usize,
+) -> Vec<usize> {
+    let mut indent = match index_paths.get(index) {
+        Some(Some((indent, _))) => *indent,
+        _ => return Vec::new(),
+    };
+    let mut rows = Vec::new();
+    for (row, path) in index_paths[..index].iter().enumerate().rev() {
+        if indent == 0 {
+            break;
+        }
+        if let Some((row_indent, _)) = path {
+            if *row_indent < indent {
+                rows.push(row);
+                indent = *row_indent;
+            }
+        }
+    }
+    rows.reverse();
+    rows
+}
+
+fn is_node_loading(item: &FileNodeItem) -> bool {
+    item.loading || item.children.values().any(is_node_loading)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use std::collections::{HashMap, HashSet};
+    use std::time::{Duration, Instant};
+
+    use lapce_rpc::file::FileNodeItem;
+
+    use super::{
+        abbreviate_home, ancestor_rows, collect_filter_matches, collect_index_paths,
+        dirs_between, display_path, dropped_path_text, filter_match_score,
+        human_size, import_path, merge_listing, name_without_extension,
+        naming_completions, naming_row, new_dir_path, next_naming_completion,
+        normalize_path, open_levels, outermost_paths, pasted_path,
+        path_names_summary, push_recent, row_index, row_node, set_dir_open,
+        type_ahead_match, update_all_node_counts, RepaintCoalescer, RepaintRequest,
+        REPAINT_COALESCE_WINDOW,
+    };
+
+    #[test]
+    fn test_outermost_paths() {
+        let paths = vec![
+            PathBuf::from("/project/src"),
+            PathBuf::from("/project/src/main.rs"),
+            PathBuf::from("/project/srcs"),
+            PathBuf::from("/project/README.md"),
+        ];
+        assert_eq!(
+            outermost_paths(&paths),
+            vec![
+                PathBuf::from("/project/src"),
+                PathBuf::from("/project/srcs"),
+                PathBuf::from("/project/README.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_push_recent() {
+        let mut recent = Vec::new();
+        for path in ["/a.rs", "/b.rs", "/c.rs", "/b.rs"] {
+            push_recent(&mut recent, Path::new(path), 3);
+        }
+        assert_eq!(
+            recent,
+            vec![
+                PathBuf::from("/b.rs"),
+                PathBuf::from("/c.rs"),
+                PathBuf::from("/a.rs"),
+            ]
+        );
+
+        push_recent(&mut recent, Path::new("/d.rs"), 3);
+        assert_eq!(recent[0], PathBuf::from("/d.rs"));
+        assert_eq!(recent.len(), 3);
+        assert!(!recent.contains(&PathBuf::from("/a.rs")));
+
+        push_recent(&mut recent, Path::new("/e.rs"), 0);
+        assert!(recent.is_empty());
+    }
+
+    #[test]
+    fn test_path_names_summary() {
+        let paths = vec![
+            PathBuf::from("/project/a.rs"),
+            PathBuf::from("/project/b.rs"),
+            PathBuf::from("/project/src"),
+            PathBuf::from("/project/c.rs"),
+            PathBuf::from("/project/d.rs"),
+        ];
+        assert_eq!(path_names_summary(&paths[..2], 3), "a.rs, b.rs");
+        assert_eq!(path_names_summary(&paths[..3], 3), "a.rs, b.rs, src");
+        assert_eq!(path_names_summary(&paths, 3), "a.rs, b.rs, src and 2 more");
+    }
+
+    #[test]
+    fn test_pasted_path() {
+        let workspace = Path::new("/home/user/project");
+        let home = Some(Path::new("/home/user"));
+        assert_eq!(
+            pasted_path("  src/main.rs\n", workspace, home),
+            Some(PathBuf::from("/home/user/project/src/main.rs"))
+        );
+        assert_eq!(
+            pasted_path("\"/home/user/project/a b.rs\"", workspace, home),
+            Some(PathBuf::from("/home/user/project/a b.rs"))
+        );
+        assert_eq!(
+            pasted_path("~/project/./lib/../Cargo.toml", workspace, home),
+            Some(PathBuf::from("/home/user/project/Cargo.toml"))
+        );
+        assert_eq!(
+            pasted_path("file:///home/user/project/a%20b.rs", workspace, home),
+            Some(PathBuf::from("/home/user/project/a b.rs"))
+        );
+        assert_eq!(pasted_path("one\ntwo", workspace, home), None);
+        assert_eq!(pasted_path("  ", workspace, home), None);
+    }
+
+    #[test]
+    fn test_type_ahead_match() {
+        let names: Vec<Option<String>> =
+            [None, Some("src"), Some("cargo.toml"), Some("scripts")]
+                .iter()
+                .map(|name| name.map(str::to_string))
+                .collect();
+        assert_eq!(type_ahead_match(&names, "s", None), Some(1));
+        // The same letter again cycles through the matches
+        assert_eq!(type_ahead_match(&names, "ss", Some(1)), Some(3));
+        assert_eq!(type_ahead_match(&names, "sss", Some(3)), Some(1));
+        // A longer prefix keeps the current row while it matches
+        assert_eq!(type_ahead_match(&names, "sc", Some(1)), Some(3));
+        assert_eq!(type_ahead_match(&names, "Ca", Some(3)), Some(2));
+        assert_eq!(type_ahead_match(&names, "x", Some(1)), None);
+    }
+
+    #[test]
+    fn test_filter_match_score() {
+        assert_eq!(filter_match_score("main", "main.rs"), Some(12));
+        assert!(filter_match_score("mrs", "main.rs").is_some());
+        assert!(filter_match_score("main", "MAIN.rs").is_some());
+        assert_eq!(filter_match_score("lib", "main.rs"), None);
+        assert!(
+            filter_match_score("ma", "main.rs") > filter_match_score("ma", "xmxa")
+        );
+    }
+
+    #[test]
+    fn test_collect_filter_matches_by_search() {
+        let mut root = node("/ws", true, true);
+        let mut src = node("/ws/src", true, true);
+        for name in ["main.rs", "lib.rs"] {
+            let child = node(&format!("/ws/src/{name}"), false, false);
+            src.children.insert(child.path_buf.clone(), child);
+        }
+        root.children.insert(src.path_buf.clone(), src);
+        let readme = node("/ws/README.md", false, false);
+        root.children.insert(readme.path_buf.clone(), readme);
+
+        let search: HashMap<PathBuf, usize> =
+            [(PathBuf::from("/ws/src/lib.rs"), 2)].into_iter().collect();
+
+        // Only the files with search matches and their ancestors are shown
+        let mut matches = HashSet::new();
+        collect_filter_matches(&root, "", Some(&search), &mut matches);
+        let expected: HashSet<PathBuf> = ["/ws", "/ws/src", "/ws/src/lib.rs"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(matches, expected);
+
+        // The name filter still applies on top of the search
+        let mut matches = HashSet::new();
+        collect_filter_matches(&root, "main", Some(&search), &mut matches);
+        assert!(matches.is_empty());
+
+        // Without the search, the name filter alone decides
+        let mut matches = HashSet::new();
+        collect_filter_matches(&root, "main", None, &mut matches);
+        assert!(matches.contains(Path::new("/ws/src/main.rs")));
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1536), "1.5 KB");
+        assert_eq!(human_size(20 * 1024 * 1024), "20 MB");
+        assert_eq!(human_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path(&PathBuf::from("/a/b/../c/./d.rs")),
+            PathBuf::from("/a/c/d.rs")
+        );
+        assert_eq!(
+            normalize_path(&PathBuf::from("/a/b/../../..")),
+            PathBuf::from("/")
+        );
+    }
+
+    #[test]
+    fn test_import_path() {
+        let workspace = Path::new("/w");
+        assert_eq!(
+            import_path(Path::new("/w/src/foo/bar.rs"), workspace, "src", "::"),
+            Some("crate::foo::bar".to_string())
+        );
+        assert_eq!(
+            import_path(Path::new("/w/src/foo/mod.rs"), workspace, "src", "::"),
+            Some("crate::foo".to_string())
+        );
+        assert_eq!(
+            import_path(Path::new("/w/pkg/__init__.py"), workspace, "src", "."),
+            Some("pkg".to_string())
+        );
+        assert_eq!(
+            import_path(Path::new("/w/src/ui/index.ts"), workspace, "src", "/"),
+            Some("ui".to_string())
+        );
+    }
+
+    #[test]
+    fn test_dropped_path_text() {
+        let doc = Path::new("/w/docs/guide.md");
+        assert_eq!(
+            dropped_path_text(Path::new("/w/docs/img/a.png"), Some(doc)),
+            "![](img/a.png)"
+        );
+        assert_eq!(
+            dropped_path_text(Path::new("/w/src/main.rs"), Some(doc)),
+            "../src/main.rs"
+        );
+        assert_eq!(
+            dropped_path_text(Path::new("/w/src/main.rs"), None),
+            "/w/src/main.rs"
+        );
+    }
+
+    #[test]
+    fn test_abbreviate_home() {
+        let home = Path::new("/home/user");
+        let sep = std::path::MAIN_SEPARATOR;
+        assert_eq!(
+            abbreviate_home(Path::new("/home/user/src/main.rs"), home),
+            Some(format!("~{sep}src{sep}main.rs"))
+        );
+        assert_eq!(
+            abbreviate_home(Path::new("/home/user"), home),
+            Some("~".to_string())
+        );
+        assert_eq!(abbreviate_home(Path::new("/home/username"), home), None);
+        assert_eq!(abbreviate_home(Path::new("/etc/hosts"), home), None);
+        assert_eq!(
+            display_path(Path::new("/etc/hosts"), Some(home)),
+            Path::new("/etc/hosts").to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn test_name_without_extension() {
+        let stem = |path: &str| name_without_extension(Path::new(path));
+        assert_eq!(stem("/ws/src/main.rs"), Some("main".to_string()));
+        assert_eq!(stem("/ws/foo.test.ts"), Some("foo.test".to_string()));
+        assert_eq!(stem("/ws/.gitignore"), Some(".gitignore".to_string()));
+        assert_eq!(stem("/ws/.env.local"), Some(".env".to_string()));
+        assert_eq!(stem("/ws/Makefile"), Some("Makefile".to_string()));
+    }
+
+    #[test]
+    fn test_naming_completions() {
+        let siblings: Vec<String> = ["main.rs", "mod.rs", "lib.rs"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let extensions: Vec<String> =
+            ["rs", "toml"].iter().map(|s| s.to_string()).collect();
+
+        assert_eq!(
+            naming_completions("m", &siblings, &extensions),
+            vec!["main.rs", "mod.rs"]
+        );
+        assert_eq!(
+            naming_completions("util", &siblings, &extensions),
+            vec!["util.rs", "util.toml"]
+        );
+        assert_eq!(
+            naming_completions("util.t", &siblings, &extensions),
+            vec!["util.toml"]
+        );
+        assert_eq!(
+            naming_completions("main.", &siblings, &extensions),
+            vec!["main.rs", "main.toml"]
+        );
+        assert!(naming_completions(".", &siblings, &extensions).is_empty());
+    }
+
+    #[test]
+    fn test_next_naming_completion() {
+        let completions: Vec<String> = ["main.rs", "mod.rs", "module.rs"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        assert_eq!(next_naming_completion("m", "m", &[]), None);
+        assert_eq!(
+            next_naming_completion("mo", "mo", &completions[1..]),
+            Some("mod".to_string())
+        );
+        assert_eq!(
+            next_naming_completion("m", "m", &completions),
+            Some("main.rs".to_string())
+        );
+        assert_eq!(
+            next_naming_completion("main.rs", "m", &completions),
+            Some("mod.rs".to_string())
+        );
+        assert_eq!(
+            next_naming_completion("module.rs", "m", &completions),
+            Some("main.rs".to_string())
+        );
+    }
+
+    fn node(path: &str, is_dir: bool, read: bool) -> FileNodeItem {
+        FileNodeItem {
+            read,
+            ..FileNodeItem::new(PathBuf::from(path), is_dir)
+        }
+    }
+
+    #[test]
+    fn test_set_dir_open() {
+        let mut root = node("/ws", true, true);
+        root.open = true;
+        let mut read = node("/ws/read", true, true);
+        for name in ["a.rs", "b.rs"] {
+            let child = node(&format!("/ws/read/{name}"), false, false);
+            read.children.insert(child.path_buf.clone(), child);
+        }
+        root.children.insert(read.path_buf.clone(), read);
+        let unread = node("/ws/unread", true, false);
+        root.children.insert(unread.path_buf.clone(), unread);
+
+        let read_path = Path::new("/ws/read");
+        let unread_path = Path::new("/ws/unread");
+
+        // A read directory is expanded right away
+        let read = root.children.get_mut(read_path).unwrap();
+        assert!(!set_dir_open(read, true));
+        assert!(read.open);
+        update_all_node_counts(&mut root, None, &HashMap::new(), false);
+        assert_eq!(root.children_open_count, 4);
+
+        // An unread one has to be read first and stays closed until then
+        let unread = root.children.get_mut(unread_path).unwrap();
+        assert!(set_dir_open(unread, true));
+        assert!(!unread.open);
+        // Collapsing it doesn't need it to be read
+        assert!(!set_dir_open(unread, false));
+        assert!(!unread.open);
+
+        let read = root.children.get_mut(read_path).unwrap();
+        assert!(!set_dir_open(read, false));
+        assert!(!read.open);
+        update_all_node_counts(&mut root, None, &HashMap::new(), false);
+        assert_eq!(root.children_open_count, 2);
+    }
+
+    #[test]
+    fn test_open_levels() {
+        let mut root = node("/ws", true, true);
+        let mut src = node("/ws/src", true, true);
+        let mut ui = node("/ws/src/ui", true, true);
+        let widgets = node("/ws/src/ui/widgets", true, false);
+        ui.children.insert(widgets.path_buf.clone(), widgets);
+        src.children.insert(ui.path_buf.clone(), ui);
+        let target = node("/ws/target", true, false);
+        root.children.insert(src.path_buf.clone(), src);
+        root.children.insert(target.path_buf.clone(), target);
+
+        let src_path = Path::new("/ws/src");
+        let ui_path = Path::new("/ws/src/ui");
+
+        // Two levels open the directory and its children, and the unread
+        // children are left to be read with no levels left beneath them
+        let mut unread = Vec::new();
+        open_levels(&mut root, Some(2), &mut unread);
+        assert!(root.open);
+        assert!(root.children[src_path].open);
+        assert!(!root.children[src_path].children[ui_path].open);
+        assert_eq!(unread, vec![(PathBuf::from("/ws/target"), Some(1))]);
+
+        // All the levels go down to the unread directories at any depth
+        let mut unread = Vec::new();
+        open_levels(&mut root, None, &mut unread);
+        assert!(root.children[src_path].children[ui_path].open);
+        unread.sort();
+        assert_eq!(
+            unread,
+            vec![
+                (PathBuf::from("/ws/src/ui/widgets"), None),
+                (PathBuf::from("/ws/target"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_listing() {
+        let mut root = node("/ws", true, true);
+        let mut src = node("/ws/src", true, true);
+        src.open = true;
+        let main = node("/ws/src/main.rs", false, false);
+        src.children.insert(main.path_buf.clone(), main);
+        root.children.insert(src.path_buf.clone(), src);
+        let gone = node("/ws/gone.rs", false, false);
+        root.children.insert(gone.path_buf.clone(), gone);
+
+        let listing = |paths: &[(&str, bool)]| -> HashMap<PathBuf, FileNodeItem> {
+            paths
+                .iter()
+                .map(|(path, is_dir)| {
+                    (PathBuf::from(path), node(path, *is_dir, false))
+                })
+                .collect()
+        };
+
+        // Nothing is removed before the last chunk is in
+        let mut listed = HashSet::new();
+        let mut first = listing(&[("/ws/new.rs", false)]);
+        first.get_mut(Path::new("/ws/new.rs")).unwrap().size = 3;
+        assert!(merge_listing(&mut root, first, true, &mut listed).is_empty());
+        assert_eq!(root.children.len(), 3);
+
+        // The open directory keeps its children, and is read again
+        let mut last = listing(&[("/ws/src", true)]);
+        let listed_src = last.get_mut(Path::new("/ws/src")).unwrap();
+        listed_src.readonly = true;
+        listed_src.metadata_read = true;
+        let reread = merge_listing(&mut root, last, false, &mut listed);
+        assert_eq!(reread, vec![(PathBuf::from("/ws/src"), true)]);
+        assert!(listed.is_empty());
+
+        let src = root.children.get(Path::new("/ws/src")).unwrap();
+        assert!(src.open && src.read && src.readonly);
+        assert!(src.children.contains_key(Path::new("/ws/src/main.rs")));
+        assert!(root.children.contains_key(Path::new("/ws/new.rs")));
+        assert!(!root.children.contains_key(Path::new("/ws/gone.rs")));
+    }
+
+    #[test]
+    fn test_ancestor_rows() {
+        let row = |indent: usize, path: &str| Some((indent, PathBuf::from(path)));
+        let rows = vec![
+            row(0, "/ws"),
+            row(1, "/ws/a"),
+            row(2, "/ws/a/b"),
+            row(3, "/ws/a/b/c.rs"),
+            None,
+            row(2, "/ws/a/d.rs"),
+            row(1, "/ws/e.rs"),
+            row(0, "/extra"),
+            row(1, "/extra/f.rs"),
+        ];
+        assert_eq!(ancestor_rows(&rows, 3), vec![0, 1, 2]);
+        assert_eq!(ancestor_rows(&rows, 5), vec![0, 1]);
+        assert_eq!(ancestor_rows(&rows, 6), vec![0]);
+        assert_eq!(ancestor_rows(&rows, 8), vec![7]);
+        assert!(ancestor_rows(&rows, 7).is_empty());
+        // Loading rows don't know where they're nested
+        assert!(ancestor_rows(&rows, 4).is_empty());
+    }
+
+    #[test]
+    fn test_visible_rows_after_toggling() {
+        let mut root = node("/ws", true, true);
+        root.open = true;
+        let mut src = node("/ws/src", true, true);
+        let main = node("/ws/src/main.rs", false, false);
+        src.children.insert(main.path_buf.clone(), main);
+        root.children.insert(src.path_buf.clone(), src);
+        let target = node("/ws/target", true, false);
+        root.children.insert(target.path_buf.clone(), target);
+
+        let rows = |root: &FileNodeItem| {
+            let mut index_paths = Vec::new();
+            collect_index_paths(
+                root,
+                0,
+                None,
+                &HashMap::new(),
+                false,
+                &mut index_paths,
+            );
+            index_paths
+        };
+        let row = |indent: usize, path: &str| Some((indent, PathBuf::from(path)));
+
+        assert_eq!(
+            rows(&root),
+            vec![row(0, "/ws"), row(1, "/ws/src"), row(1, "/ws/target")]
+        );
+
+        let src = root.children.get_mut(Path::new("/ws/src")).unwrap();
+        set_dir_open(src, true);
+        update_all_node_counts(&mut root, None, &HashMap::new(), false);
+        assert_eq!(
+            rows(&root),
+            vec![
+                row(0, "/ws"),
+                row(1, "/ws/src"),
+                row(2, "/ws/src/main.rs"),
+                row(1, "/ws/target"),
+            ]
+        );
+
+        // The unread directory stays collapsed until it has been read
+        let target = root.children.get_mut(Path::new("/ws/target")).unwrap();
+        assert!(set_dir_open(target, true));
+        update_all_node_counts(&mut root, None, &HashMap::new(), false);
+        assert_eq!(rows(&root).len(), 4);
+    }
+
+    #[test]
+    fn test_naming_row() {
+        let row = |indent: usize, path: &str| Some((indent, PathBuf::from(path)));
+        let mut index_paths = vec![
+            row(0, "/ws"),
+            row(1, "/ws/a"),
+            row(2, "/ws/a/x.rs"),
+            None,
+            row(1, "/ws/b.rs"),
+            row(1, "/ws/c.rs"),
+        ];
+        let dir = Path::new("/ws");
+
+        // After the entry and everything shown beneath it
+        assert_eq!(
+            naming_row(&index_paths, dir, Some(Path::new("/ws/a"))),
+            Some(4)
+        );
+        assert_eq!(
+            naming_row(&index_paths, dir, Some(Path::new("/ws/c.rs"))),
+            Some(6)
+        );
+        assert_eq!(naming_row(&index_paths, dir, None), Some(1));
+
+        // Entries added above it move it down with them
+        index_paths.insert(1, row(1, "/ws/.git"));
+        assert_eq!(
+            naming_row(&index_paths, dir, Some(Path::new("/ws/b.rs"))),
+            Some(6)
+        );
+
+        // The entry it came after is gone, so it is the first entry again
+        index_paths.remove(5);
+        assert_eq!(
+            naming_row(&index_paths, dir, Some(Path::new("/ws/b.rs"))),
+            Some(1)
+        );
+        assert_eq!(naming_row(&index_paths, Path::new("/other"), None), None);
+    }
+
+    #[test]
+    fn test_naming_row_after_listing() {
+        let mut root = node("/ws", true, true);
+        root.open = true;
+        for path in ["/ws/b.rs", "/ws/c.rs"] {
+            let file = node(path, false, false);
+            root.children.insert(file.path_buf.clone(), file);
+        }
+        let index_paths = |root: &FileNodeItem| {
+            let mut index_paths = Vec::new();
+            collect_index_paths(
+                root,
+                0,
+                None,
+                &HashMap::new(),
+                false,
+                &mut index_paths,
+            );
+            index_paths
+        };
+        let dir = Path::new("/ws");
+        let after = Some(Path::new("/ws/b.rs"));
+        assert_eq!(naming_row(&index_paths(&root), dir, after), Some(2));
+
+        // A listing that arrives while the name is being entered adds an
+        // entry above the input and removes one below it, and the input
+        // stays right after the entry it was opened after
+        let listing: HashMap<PathBuf, FileNodeItem> = ["/ws/a.rs", "/ws/b.rs"]
+            .iter()
+            .map(|path| (PathBuf::from(path), node(path, false, false)))
+            .collect();
+        merge_listing(&mut root, listing, false, &mut HashSet::new());
+        let rows = index_paths(&root);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(naming_row(&rows, dir, after), Some(3));
+    }
+
+    /// A workspace with an expanded directory, a collapsed one with a directory
+    /// in it, one that hasn't been read, and a file
+    fn row_tree() -> FileNodeItem {
+        let mut root = node("/ws", true, true);
+        root.open = true;
+        let mut read = node("/ws/read", true, true);
+        read.open = true;
+        let file = node("/ws/read/a.rs", false, false);
+        read.children.insert(file.path_buf.clone(), file);
+        let mut closed = node("/ws/closed", true, true);
+        let mut inner = node("/ws/closed/inner", true, true);
+        let file = node("/ws/closed/inner/b.rs", false, false);
+        inner.children.insert(file.path_buf.clone(), file);
+        closed.children.insert(inner.path_buf.clone(), inner);
+        for item in [
+            read,
+            closed,
+            node("/ws/unread", true, false),
+            node("/ws/z.rs", false, false),
+        ] {
+            root.children.insert(item.path_buf.clone(), item);
+        }
+        root
+    }
+
+    fn rows(
+        root: &FileNodeItem,
+        flat_dirs: &HashMap<PathBuf, Vec<FileNodeItem>>,
+    ) -> Vec<Option<(usize, PathBuf)>> {
+        let mut index_paths = Vec::new();
+        collect_index_paths(root, 0, None, flat_dirs, false, &mut index_paths);
+        index_paths
+    }
+
+    fn node_at(
+        root: &FileNodeItem,
+        flat_dirs: &HashMap<PathBuf, Vec<FileNodeItem>>,
+        index: usize,
+    ) -> Option<(usize, PathBuf)> {
+        row_node(
+            &rows(root, flat_dirs),
+            index,
+            std::iter::once(root),
+            flat_dirs,
+        )
+        .map(|(indent, node)| (indent, node.path_buf.clone()))
+    }
+
+    /// Flip the directory like `toggle_path` does, returning whether it is
+    /// expanded now and whether it has to be read first
+    fn toggle(root: &mut FileNodeItem, path: &str) -> (bool, bool) {
+        let node = root.get_file_node_mut(Path::new(path)).unwrap();
+        let open = !node.open;
+        let read = set_dir_open(node, open);
+        (node.open, read)
+    }
+
+    #[test]
+    fn test_node_at_index() {
+        let mut root = row_tree();
+        let flat_dirs = HashMap::new();
+        assert_eq!(
+            node_at(&root, &flat_dirs, 0),
+            Some((0, PathBuf::from("/ws")))
+        );
+        // The directory in the collapsed directory has no row
+        assert_eq!(
+            node_at(&root, &flat_dirs, 2),
+            Some((1, PathBuf::from("/ws/read")))
+        );
+        assert_eq!(
+            node_at(&root, &flat_dirs, 3),
+            Some((2, PathBuf::from("/ws/read/a.rs")))
+        );
+        assert_eq!(
+            node_at(&root, &flat_dirs, 4),
+            Some((1, PathBuf::from("/ws/unread")))
+        );
+        assert_eq!(
+            node_at(&root, &flat_dirs, 5),
+            Some((1, PathBuf::from("/ws/z.rs")))
+        );
+        assert_eq!(node_at(&root, &flat_dirs, 6), None);
+
+        // The loading row of a directory that is being read has no node
+        let unread = root.children.get_mut(Path::new("/ws/unread")).unwrap();
+        unread.open = true;
+        unread.loading = true;
+        assert_eq!(node_at(&root, &flat_dirs, 5), None);
+        assert_eq!(
+            node_at(&root, &flat_dirs, 6),
+            Some((1, PathBuf::from("/ws/z.rs")))
+        );
+
+        // The files of a directory shown flat come from its flat list, even
+        // when the directories they are in haven't been read
+        let flat_dirs = HashMap::from([(
+            PathBuf::from("/ws/closed"),
+            vec![node("/ws/closed/inner/deep/c.rs", false, false)],
+        )]);
+        root.children.get_mut(Path::new("/ws/closed")).unwrap().open = true;
+        assert_eq!(
+            node_at(&root, &flat_dirs, 2),
+            Some((2, PathBuf::from("/ws/closed/inner/deep/c.rs")))
+        );
+    }
+
+    #[test]
+    fn test_select_path() {
+        let mut root = row_tree();
+        let flat_dirs = HashMap::new();
+        let index_paths = rows(&root, &flat_dirs);
+        assert_eq!(
+            row_index(&index_paths, Path::new("/ws/read/a.rs")),
+            Some((3, 2))
+        );
+        assert_eq!(
+            row_index(&index_paths, Path::new("/ws/unread")),
+            Some((4, 1))
+        );
+        // Paths beneath a collapsed directory or one that hasn't been read
+        // aren't in view
+        assert_eq!(row_index(&index_paths, Path::new("/ws/closed/inner")), None);
+        assert_eq!(row_index(&index_paths, Path::new("/ws/unread/c.rs")), None);
+
+        // Expanding the directory above the path brings it into view
+        let closed = root.children.get_mut(Path::new("/ws/closed")).unwrap();
+        assert!(!set_dir_open(closed, true));
+        let index_paths = rows(&root, &flat_dirs);
+        assert_eq!(
+            row_index(&index_paths, Path::new("/ws/closed/inner")),
+            Some((2, 2))
+        );
+    }
+
+    #[test]
+    fn test_toggle_path() {
+        let mut root = row_tree();
+        let flat_dirs = HashMap::new();
+        assert_eq!(rows(&root, &flat_dirs).len(), 6);
+
+        // A read directory collapses and expands right away
+        assert_eq!(toggle(&mut root, "/ws/read"), (false, false));
+        assert_eq!(rows(&root, &flat_dirs).len(), 5);
+        assert_eq!(toggle(&mut root, "/ws/read"), (true, false));
+        assert_eq!(rows(&root, &flat_dirs).len(), 6);
+
+        // A directory that hasn't been read stays collapsed until its
+        // entries arrive
+        assert_eq!(toggle(&mut root, "/ws/unread"), (false, true));
+        assert_eq!(rows(&root, &flat_dirs).len(), 6);
+
+        // A directory in a collapsed directory is expanded, but its entries
+        // only show up once the directory above it is expanded too
+        assert_eq!(toggle(&mut root, "/ws/closed/inner"), (true, false));
+        let index_paths = rows(&root, &flat_dirs);
+        assert_eq!(index_paths.len(), 6);
+        assert_eq!(
+            row_index(&index_paths, Path::new("/ws/closed/inner/b.rs")),
+            None
+        );
+        assert_eq!(toggle(&mut root, "/ws/closed"), (true, false));
+        assert_eq!(
+            row_index(&rows(&root, &flat_dirs), Path::new("/ws/closed/inner/b.rs")),
+            Some((3, 3))
+        );
+    }
+
+    #[test]
+    fn test_new_dir_path() {
+        assert_eq!(new_dir_path("a"), Ok(PathBuf::from("a")));
+        assert_eq!(new_dir_path("a/"), Ok(PathBuf::from("a")));
+        assert_eq!(new_dir_path("a/b/c//"), Ok(PathBuf::from("a/b/c")));
+        assert!(new_dir_path("/").is_err());
+        assert!(new_dir_path("a//b").is_err());
+        assert!(new_dir_path("/a").is_err());
+        assert!(new_dir_path("a/../b").is_err());
+    }
+
+    #[test]
+    fn test_dirs_between() {
+        let dirs = dirs_between(Path::new("/ws/src"), Path::new("/ws/src/a/b/c"));
+        assert_eq!(dirs.len(), 2);
+        assert!(dirs.contains(Path::new("/ws/src/a")));
+        assert!(dirs.contains(Path::new("/ws/src/a/b")));
+        assert!(dirs_between(Path::new("/ws"), Path::new("/ws/a")).is_empty());
+    }
+
+    #[test]
+    fn test_repaint_coalescer() {
+        // A cold open of a large workspace, with a change every 2ms for 200ms
+        let start = Instant::now();
+        let mut coalescer = RepaintCoalescer::default();
+        let mut fire_at = None;
+        let changes = 100;
+        for i in 0..changes {
+            let now = start + Duration::from_millis(2 * i);
+            if let Some(at) = fire_at.filter(|at| *at <= now) {
+                coalescer.fire(at);
+                fire_at = None;
+            }
+            match coalescer.request(now) {
+                RepaintRequest::Now => {}
+                RepaintRequest::After(delay) => {
+                    assert!(delay <= REPAINT_COALESCE_WINDOW);
+                    fire_at = Some(now + delay);
                 }
+                RepaintRequest::Waiting => assert!(fire_at.is_some()),
             }
-            i += count + 1;
         }
+        // The last changes are shown once the wait is over
+        if let Some(at) = fire_at {
+            coalescer.fire(at);
+        }
+
+        let window = REPAINT_COALESCE_WINDOW.as_millis() as usize;
+        assert!(coalescer.repaints() <= 200 / window + 2);
+        assert!(coalescer.repaints() < changes as usize / 5);
+
+        // A change after a quiet moment is shown right away
+        let later = start + Duration::from_secs(1);
+        assert_eq!(coalescer.request(later), RepaintRequest::Now);
     }
-    (i, None)
 }
@@ -23,6 +23,14 @@ use crate::{
     document::{BufferContent, Document, TextLayoutCache, TextLayoutLine},
 };
 
+/// The version of the history holding the content a file changed to on disk
+/// while it had unsaved changes, to diff them against each other
+pub const DISK_HISTORY_VERSION: &str = "disk";
+
+/// The version of the history holding the content of another file that a file
+/// is compared with
+pub const COMPARE_HISTORY_VERSION: &str = "compare";
+
 #[derive(Clone)]
 pub struct DocumentHistory {
     version: String,
@@ -10,6 +10,8 @@ use lapce_core::command::{EditCommand, FocusCommand};
 use lapce_core::language::LapceLanguage;
 use lapce_core::mode::Mode;
 use lapce_core::movement::Movement;
+use lapce_rpc::buffer::BufferId;
+use lapce_rpc::proxy::WorkspaceTrashEntry;
 use lsp_types::{DocumentSymbolResponse, Position, Range, SymbolKind};
 use std::cmp::Ordering;
 use std::collections::HashSet;
@@ -17,10 +19,11 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::alert::AlertContentData;
 use crate::command::CommandKind;
 use crate::data::{LapceWorkspace, LapceWorkspaceType};
 use crate::document::BufferContent;
-use crate::editor::EditorLocation;
+use crate::editor::{split_path_position, EditorLocation};
 use crate::panel::PanelKind;
 use crate::proxy::path_from_url;
 use crate::{
@@ -48,6 +51,17 @@ pub enum PaletteType {
     Theme,
     SshHost,
     Language,
+    /// The encodings a file can be reopened with
+    Encoding,
+    /// The paths in the trash folder of the workspace
+    Trash,
+    /// The new name of the symbol at the position, which starts out as the
+    /// placeholder
+    Rename {
+        buffer_id: BufferId,
+        position: Position,
+        placeholder: String,
+    },
 }
 
 impl PaletteType {
@@ -64,6 +78,9 @@ impl PaletteType {
             PaletteType::Theme => "".to_string(),
             PaletteType::SshHost => "".to_string(),
             PaletteType::Language => "".to_string(),
+            PaletteType::Encoding => "".to_string(),
+            PaletteType::Trash => "".to_string(),
+            PaletteType::Rename { placeholder, .. } => placeholder.clone(),
         }
     }
 
@@ -85,7 +102,10 @@ impl PaletteType {
             PaletteType::Reference
             | PaletteType::SshHost
             | PaletteType::Theme
-            | PaletteType::Language => {
+            | PaletteType::Language
+            | PaletteType::Encoding
+            | PaletteType::Trash
+            | PaletteType::Rename { .. } => {
                 return current_type.clone();
             }
             _ => (),
@@ -142,6 +162,9 @@ pub enum PaletteItemContent {
     Command(LapceCommand),
     Theme(String),
     Language(String),
+    Encoding(String),
+    /// The path relative to the workspace, and the entry in the trash
+    TrashEntry(PathBuf, WorkspaceTrashEntry),
 }
 
 impl PaletteItemContent {
@@ -235,6 +258,26 @@ impl PaletteItemContent {
                     ))
                 }
             }
+            PaletteItemContent::Encoding(name) => {
+                if !preview {
+                    ctx.submit_command(Command::new(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::ReopenWithEncoding(name.to_string()),
+                        Target::Auto,
+                    ))
+                }
+            }
+            PaletteItemContent::TrashEntry(_, entry) => {
+                if !preview {
+                    ctx.submit_command(Command::new(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::RestoreFromTrash {
+                            id: entry.id.clone(),
+                        },
+                        Target::Auto,
+                    ));
+                }
+            }
             PaletteItemContent::Command(command) => {
                 if !preview {
                     ctx.submit_command(Command::new(
@@ -496,6 +539,9 @@ impl PaletteData {
             PaletteType::Reference => &self.input,
             PaletteType::Theme => &self.input,
             PaletteType::Language => &self.input,
+            PaletteType::Encoding => &self.input,
+            PaletteType::Trash => &self.input,
+            PaletteType::Rename { .. } => &self.input,
             PaletteType::SshHost => &self.input,
             PaletteType::Line => &self.input[1..],
             PaletteType::DocumentSymbol => &self.input[1..],
@@ -510,6 +556,28 @@ impl PaletteData {
 // TODO: Make this configurable
 /// The maximum number of palette items to display per 'page'
 pub const MAX_PALETTE_ITEMS: usize = 15;
+/// The most files that the proxy sends for the input of the file palette
+const MAX_FILE_MATCHES: usize = 500;
+/// The encodings offered for reopening a file, by the names the proxy knows
+/// them by
+const ENCODINGS: &[&str] = &[
+    "UTF-8",
+    "UTF-16LE",
+    "UTF-16BE",
+    "windows-1252",
+    "ISO-8859-2",
+    "ISO-8859-15",
+    "windows-1250",
+    "windows-1251",
+    "KOI8-R",
+    "Shift_JIS",
+    "EUC-JP",
+    "ISO-2022-JP",
+    "EUC-KR",
+    "GBK",
+    "gb18030",
+    "Big5",
+];
 impl PaletteViewData {
     pub fn cancel(&mut self, ctx: &mut EventCtx) {
         let palette = Arc::make_mut(&mut self.palette);
@@ -633,6 +701,13 @@ impl PaletteViewData {
             PaletteType::Language => {
                 self.get_languages(ctx);
             }
+            PaletteType::Encoding => {
+                self.get_encodings(ctx);
+            }
+            PaletteType::Trash => {
+                self.get_trash_entries(ctx);
+            }
+            PaletteType::Rename { .. } => {}
         }
     }
 
@@ -658,6 +733,9 @@ impl PaletteViewData {
             PaletteType::Reference => 0,
             PaletteType::Theme => 0,
             PaletteType::Language => 0,
+            PaletteType::Encoding => 0,
+            PaletteType::Trash => 0,
+            PaletteType::Rename { .. } => 0,
             PaletteType::SshHost => 0,
             PaletteType::Line => 1,
             PaletteType::DocumentSymbol => 1,
@@ -725,6 +803,28 @@ impl PaletteViewData {
                 Target::Widget(*self.main_split.tab_id),
             ));
         }
+        if self.palette.palette_type == PaletteType::File {
+            // `file:line:column` opens the file at the position
+            if let (
+                Some(PaletteItemContent::File(_, path)),
+                (_, Some((line, column))),
+            ) = (
+                self.palette.get_item().map(|item| &item.content),
+                split_path_position(self.palette.get_input()),
+            ) {
+                ctx.submit_command(Command::new(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::OpenFileAt {
+                        path: path.clone(),
+                        line,
+                        column,
+                    },
+                    Target::Auto,
+                ));
+                self.cancel(ctx);
+                return;
+            }
+        }
         let palette = Arc::make_mut(&mut self.palette);
         if let Some(item) = palette.get_item() {
             if item.content.select(ctx, false, palette.preview_editor) {
@@ -751,10 +851,47 @@ impl PaletteViewData {
                 ));
                 return;
             }
+            if let PaletteType::Rename {
+                buffer_id,
+                position,
+                ..
+            } = &self.palette.palette_type
+            {
+                self.rename(ctx, *buffer_id, *position);
+            }
             self.cancel(ctx);
         }
     }
 
+    /// Ask the language server to rename the symbol at the position to the
+    /// input, and apply the edits it makes to the workspace
+    fn rename(&self, ctx: &mut EventCtx, buffer_id: BufferId, position: Position) {
+        let new_name = self.palette.get_input().trim().to_string();
+        if new_name.is_empty() {
+            return;
+        }
+        let event_sink = ctx.get_external_handle();
+        let tab_id = *self.main_split.tab_id;
+        self.palette
+            .proxy
+            .rename(buffer_id, position, new_name, move |result| {
+                let cmd = match result {
+                    Ok(Some(edit)) => LapceUICommand::ApplyWorkspaceEdit(edit),
+                    Ok(None) => return,
+                    Err(err) => LapceUICommand::ShowAlert(AlertContentData {
+                        title: "Couldn't rename the symbol".to_string(),
+                        msg: err.message(),
+                        buttons: Vec::new(),
+                    }),
+                };
+                let _ = event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    cmd,
+                    Target::Widget(tab_id),
+                );
+            });
+    }
+
     pub fn update_input(&mut self, ctx: &mut EventCtx, input: String) {
         let palette = Arc::make_mut(&mut self.palette);
 
@@ -786,6 +923,12 @@ impl PaletteViewData {
             return;
         }
 
+        // The files are matched by the proxy, which has all of them indexed
+        if self.palette.palette_type == PaletteType::File {
+            self.get_files(ctx);
+            return;
+        }
+
         if self.palette.get_input() != "" {
             let _ = self.palette.sender.send((
                 self.palette.run_id.clone(),
@@ -797,42 +940,46 @@ impl PaletteViewData {
         }
     }
 
+    /// Ask the proxy for the files matching the input, which are already
+    /// ranked, so they are the filtered items unless the input is empty
     fn get_files(&self, ctx: &mut EventCtx) {
         let run_id = self.palette.run_id.clone();
         let widget_id = self.palette.widget_id;
-        let workspace = self.workspace.clone();
+        let input = self.palette.get_input().to_string();
+        // The position of `file:line:column` isn't part of the file's name
+        let (query, _) = split_path_position(&input);
+        let query = query.to_string();
         let event_sink = ctx.get_external_handle();
-        self.palette.proxy.get_files(move |result| {
-            if let Ok(resp) = result {
-                let items: Vec<PaletteItem> = resp
-                    .iter()
-                    .enumerate()
-                    .map(|(_index, path)| {
-                        let full_path = path.clone();
-                        let mut path = path.clone();
-                        if let Some(workspace_path) = workspace.path.as_ref() {
-                            path = path
-                                .strip_prefix(workspace_path)
-                                .unwrap_or(&full_path)
-                                .to_path_buf();
-                        }
-                        let filter_text = path.to_str().unwrap_or("").to_string();
-                        PaletteItem {
-                            content: PaletteItemContent::File(path, full_path),
-                            filter_text,
-                            score: 0,
-                            indices: Vec::new(),
-                        }
-                    })
-                    .collect();
-
-                let _ = event_sink.submit_command(
-                    LAPCE_UI_COMMAND,
-                    LapceUICommand::UpdatePaletteItems(run_id, items),
-                    Target::Widget(widget_id),
-                );
-            }
-        });
+        self.palette
+            .proxy
+            .query_files(&query, MAX_FILE_MATCHES, move |result| {
+                if let Ok(resp) = result {
+                    let items: Vec<PaletteItem> = resp
+                        .items
+                        .into_iter()
+                        .map(|file| PaletteItem {
+                            content: PaletteItemContent::File(
+                                PathBuf::from(&file.relative_path),
+                                file.path,
+                            ),
+                            filter_text: file.relative_path,
+                            score: file.score,
+                            indices: file.indices,
+                        })
+                        .collect();
+
+                    let command = if input.is_empty() {
+                        LapceUICommand::UpdatePaletteItems(run_id, items)
+                    } else {
+                        LapceUICommand::FilterPaletteItems(run_id, input, items)
+                    };
+                    let _ = event_sink.submit_command(
+                        LAPCE_UI_COMMAND,
+                        command,
+                        Target::Widget(widget_id),
+                    );
+                }
+            });
     }
 
     fn get_ssh_hosts(&mut self, _ctx: &mut EventCtx) {
@@ -922,6 +1069,55 @@ impl PaletteViewData {
             .collect();
     }
 
+    fn get_encodings(&mut self, _ctx: &mut EventCtx) {
+        let palette = Arc::make_mut(&mut self.palette);
+        palette.items = ENCODINGS
+            .iter()
+            .map(|n| PaletteItem {
+                content: PaletteItemContent::Encoding(n.to_string()),
+                filter_text: n.to_string(),
+                score: 0,
+                indices: vec![],
+            })
+            .collect();
+    }
+
+    /// Ask the proxy for the paths in the trash folder of the workspace
+    fn get_trash_entries(&self, ctx: &mut EventCtx) {
+        let run_id = self.palette.run_id.clone();
+        let widget_id = self.palette.widget_id;
+        let workspace_path = self.workspace.path.clone();
+        let event_sink = ctx.get_external_handle();
+        self.palette.proxy.get_workspace_trash(move |result| {
+            if let Ok(resp) = result {
+                let items: Vec<PaletteItem> = resp
+                    .entries
+                    .into_iter()
+                    .map(|entry| {
+                        let path = workspace_path
+                            .as_ref()
+                            .and_then(|workspace_path| {
+                                entry.original_path.strip_prefix(workspace_path).ok()
+                            })
+                            .unwrap_or(&entry.original_path)
+                            .to_path_buf();
+                        PaletteItem {
+                            filter_text: path.to_str().unwrap_or("").to_string(),
+                            content: PaletteItemContent::TrashEntry(path, entry),
+                            score: 0,
+                            indices: vec![],
+                        }
+                    })
+                    .collect();
+                let _ = event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::UpdatePaletteItems(run_id, items),
+                    Target::Widget(widget_id),
+                );
+            }
+        });
+    }
+
     fn get_commands(&mut self, _ctx: &mut EventCtx) {
         const EXCLUDED_ITEMS: &[&str] = &["palette.command"];
 
@@ -1,7 +1,4 @@
-use std::{
-    collections::HashMap,
-    path::{Path, PathBuf},
-};
+use std::path::{Path, PathBuf};
 
 use druid::{ExtEventSink, Target, WidgetId};
 use lapce_rpc::file::FileNodeItem;
@@ -24,14 +21,7 @@ pub struct FilePickerData {
 
 impl FilePickerData {
     pub fn new() -> Self {
-        let root = FileNodeItem {
-            path_buf: PathBuf::from("/"),
-            is_dir: true,
-            read: false,
-            open: false,
-            children: HashMap::new(),
-            children_open_count: 0,
-        };
+        let root = FileNodeItem::new(PathBuf::from("/"), true);
         let home = PathBuf::from("/");
         let pwd = PathBuf::from("/");
         Self {
@@ -47,14 +37,7 @@ impl FilePickerData {
 
     pub fn init_home(&mut self, home: &Path) {
         self.home = home.to_path_buf();
-        let mut current_file_node = FileNodeItem {
-            path_buf: home.to_path_buf(),
-            is_dir: true,
-            read: false,
-            open: false,
-            children: HashMap::new(),
-            children_open_count: 0,
-        };
+        let mut current_file_node = FileNodeItem::new(home.to_path_buf(), true);
         let mut current_path = home.to_path_buf();
 
         let mut ancestors = home.ancestors();
@@ -62,12 +45,8 @@ impl FilePickerData {
 
         for p in ancestors {
             let mut file_node = FileNodeItem {
-                path_buf: PathBuf::from(p),
-                is_dir: true,
-                read: false,
                 open: true,
-                children: HashMap::new(),
-                children_open_count: 0,
+                ..FileNodeItem::new(PathBuf::from(p), true)
             };
             file_node
                 .children
@@ -87,7 +66,7 @@ impl FilePickerData {
     ) {
         let path = PathBuf::from(path);
         let local_path = path.clone();
-        proxy.read_dir(&local_path, move |result| {
+        proxy.read_dir(&local_path, false, None, 0, move |result| {
             if let Ok(resp) = result {
                 let path = path.clone();
                 let _ = event_sink.submit_command(
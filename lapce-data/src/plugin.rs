@@ -23,6 +23,18 @@ impl Default for PluginData {
     }
 }
 
+/// Where the install of a plugin is at, until it shows up as installed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PluginInstallStatus {
+    /// How much of the file being downloaded was received, and its size if
+    /// it's known
+    Installing {
+        received: u64,
+        total: Option<u64>,
+    },
+    Failed(String),
+}
+
 #[derive(Display, PartialEq)]
 pub enum PluginStatus {
     Installed,
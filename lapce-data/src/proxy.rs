@@ -4,9 +4,12 @@ use std::io::BufReader;
 use std::os::windows::process::CommandExt;
 use std::path::Path;
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::thread;
+use std::time::Duration;
 use std::{path::PathBuf, sync::Arc};
 
+use alacritty_terminal::term::SizeInfo;
 use anyhow::{anyhow, Result};
 use crossbeam_channel::Receiver;
 use crossbeam_channel::Sender;
@@ -17,7 +20,12 @@ use lapce_proxy::dispatch::Dispatcher;
 use lapce_rpc::buffer::{BufferHeadResponse, BufferId, NewBufferResponse};
 use lapce_rpc::core::{CoreNotification, CoreRequest};
 use lapce_rpc::plugin::PluginDescription;
-use lapce_rpc::proxy::{ProxyRequest, ReadDirResponse};
+use lapce_rpc::proxy::{
+    CaseSensitiveResponse, DuplicatePathResponse, PathWritableResponse,
+    PathsMetadataResponse, ProxyRequest, QueryFilesResponse, ReadDirResponse,
+    ReadDirTreeResponse, ReadFileResponse, RestoreFromTrashResponse,
+    TrashPathResponse, WorkspaceTrashResponse,
+};
 use lapce_rpc::source_control::FileDiff;
 use lapce_rpc::style::SemanticStyles;
 use lapce_rpc::terminal::TermId;
@@ -29,7 +37,9 @@ use lsp_types::{
     CodeActionResponse, CompletionItem, CompletionResponse, DocumentSymbolResponse,
     GotoDefinitionResponse, InlayHint, SymbolInformation, TextEdit,
 };
-use lsp_types::{Hover, Position};
+use lsp_types::{
+    Hover, Position, PrepareRenameResponse, PublishDiagnosticsParams, WorkspaceEdit,
+};
 use lsp_types::{Location, Url};
 use parking_lot::Mutex;
 use serde::de::DeserializeOwned;
@@ -42,6 +52,7 @@ use crate::command::LapceUICommand;
 use crate::command::LAPCE_UI_COMMAND;
 use crate::config::Config;
 use crate::data::{LapceWorkspace, LapceWorkspaceType};
+use crate::plugin::PluginInstallStatus;
 use crate::terminal::RawTerminal;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -68,6 +79,20 @@ pub enum RequestError {
     Rpc(Value),
 }
 
+impl RequestError {
+    /// The message to show for the error, which is the one the proxy sent
+    /// along with it when there is one
+    pub fn message(&self) -> String {
+        match self {
+            RequestError::Rpc(err) => err["message"]
+                .as_str()
+                .map(|message| message.to_string())
+                .unwrap_or_else(|| self.to_string()),
+            RequestError::Deser(_) => self.to_string(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct LapceProxy {
     pub tab_id: WidgetId,
@@ -75,8 +100,21 @@ pub struct LapceProxy {
     proxy_receiver: Arc<Receiver<Value>>,
     term_tx: Sender<(TermId, TermEvent)>,
     event_sink: ExtEventSink,
+    /// How long (in ms) diagnostics are held back, so that quick successive
+    /// updates for the same file are only shown once
+    diagnostics_debounce: Arc<AtomicU64>,
+    /// The latest diagnostics of each file that are being held back
+    pending_diagnostics: Arc<Mutex<HashMap<Url, PublishDiagnosticsParams>>>,
+    /// The terminals that are still open, for the requests that write to or
+    /// resize them
+    terminals: Arc<Mutex<HashMap<TermId, Arc<Mutex<RawTerminal>>>>>,
+    /// What to do once the last chunk of a directory listing is in, by the
+    /// directory and the generation of the read
+    read_dir_finishes: Arc<Mutex<HashMap<(PathBuf, u64), ReadDirFinished>>>,
 }
 
+type ReadDirFinished = Box<dyn FnOnce() + Send>;
+
 impl Handler for LapceProxy {
     type Notification = CoreNotification;
     type Request = CoreRequest;
@@ -94,6 +132,23 @@ impl Handler for LapceProxy {
                     Target::Widget(self.tab_id),
                 );
             }
+            BufferSaved {
+                path,
+                rev,
+                success,
+                error,
+            } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::BufferSaved {
+                        path,
+                        rev,
+                        success,
+                        error,
+                    },
+                    Target::Widget(self.tab_id),
+                );
+            }
             ReloadBuffer { path, content, rev } => {
                 let _ = self.event_sink.submit_command(
                     LAPCE_UI_COMMAND,
@@ -105,13 +160,16 @@ impl Handler for LapceProxy {
                     Target::Widget(self.tab_id),
                 );
             }
-            PublishDiagnostics { diagnostics } => {
+            ReloadBufferDelta { path, rev, changes } => {
                 let _ = self.event_sink.submit_command(
                     LAPCE_UI_COMMAND,
-                    LapceUICommand::PublishDiagnostics(diagnostics),
+                    LapceUICommand::ReloadBufferDelta { path, rev, changes },
                     Target::Widget(self.tab_id),
                 );
             }
+            PublishDiagnostics { diagnostics } => {
+                self.publish_diagnostics(diagnostics);
+            }
             WorkDoneProgress { progress } => {
                 let _ = self.event_sink.submit_command(
                     LAPCE_UI_COMMAND,
@@ -138,6 +196,78 @@ impl Handler for LapceProxy {
                     Target::Auto,
                 );
             }
+            FileOperationError {
+                operation,
+                path,
+                kind,
+                message,
+            } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::FileOperationError {
+                        operation,
+                        path,
+                        kind,
+                        message,
+                    },
+                    Target::Widget(self.tab_id),
+                );
+            }
+            PluginInstalling {
+                plugin_id,
+                received,
+                total,
+            } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::UpdatePluginInstall {
+                        plugin_id,
+                        status: Some(PluginInstallStatus::Installing {
+                            received,
+                            total,
+                        }),
+                    },
+                    Target::Widget(self.tab_id),
+                );
+            }
+            PluginInstallFailed { plugin_id, error } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::UpdatePluginInstall {
+                        plugin_id,
+                        status: Some(PluginInstallStatus::Failed(error)),
+                    },
+                    Target::Widget(self.tab_id),
+                );
+            }
+            PluginActivationFailed { plugin_id, error } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::PluginActivationFailed { plugin_id, error },
+                    Target::Widget(self.tab_id),
+                );
+            }
+            LanguageServerStatus { language, state } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::UpdateLanguageServerStatus { language, state },
+                    Target::Widget(self.tab_id),
+                );
+            }
+            PluginStateChanged { plugin_id, state } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::UpdatePluginState { plugin_id, state },
+                    Target::Widget(self.tab_id),
+                );
+            }
+            RegisterExplorerMenuItem { item } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::RegisterExplorerMenuItem(item),
+                    Target::Widget(self.tab_id),
+                );
+            }
             DiffInfo { diff } => {
                 let _ = self.event_sink.submit_command(
                     LAPCE_UI_COMMAND,
@@ -151,6 +281,7 @@ impl Handler for LapceProxy {
                     .send((term_id, TermEvent::UpdateContent(content)));
             }
             CloseTerminal { term_id } => {
+                self.terminals.lock().remove(&term_id);
                 let _ = self.term_tx.send((term_id, TermEvent::CloseTerminal));
                 let _ = self.event_sink.submit_command(
                     LAPCE_UI_COMMAND,
@@ -158,6 +289,31 @@ impl Handler for LapceProxy {
                     Target::Widget(self.tab_id),
                 );
             }
+            TerminalCwdChanged { term_id, cwd } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::TerminalCwdChanged { term_id, cwd },
+                    Target::Widget(self.tab_id),
+                );
+            }
+            TerminalExited { term_id, code } => {
+                // Shown in case the terminal is kept open after the exit
+                let message = match code {
+                    Some(code) => {
+                        format!("\r\n[exited with code {code}] press Enter to close")
+                    }
+                    None => "\r\n[process exited] press Enter to close".to_string(),
+                };
+                let _ = self.term_tx.send((
+                    term_id,
+                    TermEvent::UpdateContent(base64::encode(message)),
+                ));
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::TerminalExited { term_id, code },
+                    Target::Widget(self.tab_id),
+                );
+            }
             ProxyConnected {} => {
                 let _ = self.event_sink.submit_command(
                     LAPCE_UI_COMMAND,
@@ -172,11 +328,79 @@ impl Handler for LapceProxy {
                     Target::Widget(self.tab_id),
                 );
             }
-            ListDir { .. } | DiffFiles { .. } => {}
-            WorkspaceFileChange {} => {
+            ListDir {
+                path,
+                items,
+                more,
+                generation,
+            } => {
+                let finished = if more {
+                    None
+                } else {
+                    self.read_dir_finishes
+                        .lock()
+                        .remove(&(path.clone(), generation))
+                };
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::AppendExplorerItems {
+                        path,
+                        items,
+                        more,
+                        generation,
+                    },
+                    Target::Widget(self.tab_id),
+                );
+                if let Some(finished) = finished {
+                    finished();
+                }
+            }
+            SearchResult {
+                query_id,
+                path,
+                line,
+                column,
+                end_column,
+                text,
+            } => {
                 let _ = self.event_sink.submit_command(
                     LAPCE_UI_COMMAND,
-                    LapceUICommand::WorkspaceFileChange,
+                    LapceUICommand::SearchResult {
+                        query_id,
+                        path,
+                        line,
+                        column,
+                        end_column,
+                        text,
+                    },
+                    Target::Widget(self.tab_id),
+                );
+            }
+            DirSize { path, size } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::UpdateExplorerDirSize { path, size },
+                    Target::Widget(self.tab_id),
+                );
+            }
+            DiffFiles { files } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::UpdateChangedFiles(files),
+                    Target::Widget(self.tab_id),
+                );
+            }
+            WorkspaceFileChange { created } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::WorkspaceFileChange { created },
+                    Target::Widget(self.tab_id),
+                );
+            }
+            WorkspaceRootChanged { old, new } => {
+                let _ = self.event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::WorkspaceRootChanged { old, new },
                     Target::Widget(self.tab_id),
                 );
             }
@@ -184,8 +408,51 @@ impl Handler for LapceProxy {
         ControlFlow::Continue
     }
 
-    fn handle_request(&mut self, _rpc: Self::Request) -> Result<Value, Value> {
-        Err(json!("unimplemented"))
+    fn handle_request(&mut self, rpc: Self::Request) -> Result<Value, Value> {
+        use lapce_rpc::core::CoreRequest::*;
+        match rpc {
+            OpenFileAt { path, line, column } => {
+                self.event_sink
+                    .submit_command(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::OpenFileAt { path, line, column },
+                        Target::Widget(self.tab_id),
+                    )
+                    .map_err(|e| json!(e.to_string()))?;
+                Ok(json!({}))
+            }
+            TerminalWrite { term_id, data } => {
+                if !self.terminals.lock().contains_key(&term_id) {
+                    return Err(json!("the terminal is closed"));
+                }
+                self.terminal_write(term_id, &data);
+                Ok(json!({}))
+            }
+            TerminalResize {
+                term_id,
+                rows,
+                cols,
+            } => {
+                let raw = self
+                    .terminals
+                    .lock()
+                    .get(&term_id)
+                    .cloned()
+                    .ok_or_else(|| json!("the terminal is closed"))?;
+                let size = SizeInfo::new(
+                    cols as f32,
+                    rows as f32,
+                    1.0,
+                    1.0,
+                    0.0,
+                    0.0,
+                    true,
+                );
+                raw.lock().term.resize(size);
+                self.terminal_resize(term_id, cols, rows);
+                Ok(json!({}))
+            }
+        }
     }
 }
 
@@ -204,6 +471,10 @@ impl LapceProxy {
             proxy_receiver: Arc::new(proxy_receiver),
             term_tx,
             event_sink: event_sink.clone(),
+            diagnostics_debounce: Arc::new(AtomicU64::new(0)),
+            pending_diagnostics: Arc::new(Mutex::new(HashMap::new())),
+            terminals: Arc::new(Mutex::new(HashMap::new())),
+            read_dir_finishes: Arc::new(Mutex::new(HashMap::new())),
         };
 
         let local_proxy = proxy.clone();
@@ -333,6 +604,7 @@ impl LapceProxy {
     }
 
     pub fn terminal_close(&self, term_id: TermId) {
+        self.terminals.lock().remove(&term_id);
         self.rpc.send_rpc_notification(
             "terminal_close",
             &json!({
@@ -369,6 +641,7 @@ impl LapceProxy {
         shell: String,
         raw: Arc<Mutex<RawTerminal>>,
     ) {
+        self.terminals.lock().insert(term_id, raw.clone());
         let _ = self.term_tx.send((term_id, TermEvent::NewTerminal(raw)));
         self.rpc.send_rpc_notification(
             "new_terminal",
@@ -380,6 +653,142 @@ impl LapceProxy {
         )
     }
 
+    pub fn open_with_default_application(&self, path: &Path) {
+        self.rpc.send_rpc_notification(
+            "open_with_default_application",
+            &json!({
+                "path": path,
+            }),
+        )
+    }
+
+    pub fn reveal_in_file_manager(&self, path: &Path) {
+        self.rpc.send_rpc_notification(
+            "reveal_in_file_manager",
+            &json!({
+                "path": path,
+            }),
+        )
+    }
+
+    pub fn cancel_work_done_progress(&self, token: &str) {
+        self.rpc.send_rpc_notification(
+            "cancel_work_done_progress",
+            &json!({
+                "token": token,
+            }),
+        )
+    }
+
+    pub fn start_search(&self, query_id: u64, pattern: &str, path: &Path) {
+        self.rpc.send_rpc_notification(
+            "start_search",
+            &json!({
+                "query_id": query_id,
+                "pattern": pattern,
+                "path": path,
+            }),
+        )
+    }
+
+    pub fn cancel_search(&self, query_id: u64) {
+        self.rpc.send_rpc_notification(
+            "cancel_search",
+            &json!({
+                "query_id": query_id,
+            }),
+        )
+    }
+
+    pub fn compute_dir_size(&self, path: &Path) {
+        self.rpc.send_rpc_notification(
+            "compute_dir_size",
+            &json!({
+                "path": path,
+            }),
+        )
+    }
+
+    pub fn cancel_dir_size(&self, path: &Path) {
+        self.rpc.send_rpc_notification(
+            "cancel_dir_size",
+            &json!({
+                "path": path,
+            }),
+        )
+    }
+
+    pub fn set_diagnostics_debounce(&self, debounce: u64) {
+        self.diagnostics_debounce.store(debounce, Ordering::Relaxed);
+    }
+
+    /// Forward the diagnostics to the UI once no newer ones for the same file
+    /// arrived within the debounce interval
+    fn publish_diagnostics(&self, diagnostics: PublishDiagnosticsParams) {
+        let debounce = self.diagnostics_debounce.load(Ordering::Relaxed);
+        if debounce == 0 {
+            let _ = self.event_sink.submit_command(
+                LAPCE_UI_COMMAND,
+                LapceUICommand::PublishDiagnostics(diagnostics),
+                Target::Widget(self.tab_id),
+            );
+            return;
+        }
+
+        let uri = diagnostics.uri.clone();
+        // Only the first update of the interval waits, the later ones just
+        // replace what it will send
+        if self
+            .pending_diagnostics
+            .lock()
+            .insert(uri.clone(), diagnostics)
+            .is_some()
+        {
+            return;
+        }
+
+        let pending_diagnostics = self.pending_diagnostics.clone();
+        let event_sink = self.event_sink.clone();
+        let tab_id = self.tab_id;
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(debounce));
+            if let Some(diagnostics) = pending_diagnostics.lock().remove(&uri) {
+                let _ = event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::PublishDiagnostics(diagnostics),
+                    Target::Widget(tab_id),
+                );
+            }
+        });
+    }
+
+    pub fn set_file_exclude(&self, patterns: &[String]) {
+        self.rpc.send_rpc_notification(
+            "set_file_exclude",
+            &json!({
+                "patterns": patterns,
+            }),
+        )
+    }
+
+    pub fn restart_language_server(&self, language: &str) {
+        self.rpc.send_rpc_notification(
+            "restart_language_server",
+            &json!({
+                "language": language,
+            }),
+        )
+    }
+
+    pub fn open_in_system_terminal(&self, path: &Path) {
+        self.rpc.send_rpc_notification(
+            "open_in_system_terminal",
+            &json!({
+                "path": path,
+            }),
+        )
+    }
+
     pub fn git_init(&self) {
         self.rpc.send_rpc_notification("git_init", &json!({}));
     }
@@ -431,6 +840,13 @@ impl LapceProxy {
             .send_rpc_notification("install_plugin", &json!({ "plugin": plugin }));
     }
 
+    pub fn cancel_plugin_install(&self, plugin_id: &str) {
+        self.rpc.send_rpc_notification(
+            "cancel_plugin_install",
+            &json!({ "plugin_id": plugin_id }),
+        );
+    }
+
     pub fn disable_plugin(&self, plugin: &PluginDescription) {
         self.rpc
             .send_rpc_notification("disable_plugin", &json!({ "plugin": plugin }))
@@ -446,6 +862,17 @@ impl LapceProxy {
             .send_rpc_notification("remove_plugin", &json!({ "plugin": plugin }));
     }
 
+    pub fn run_plugin_command(&self, plugin: &str, command: &str, args: Value) {
+        self.rpc.send_rpc_notification(
+            "run_plugin_command",
+            &json!({
+                "plugin": plugin,
+                "command": command,
+                "args": args,
+            }),
+        );
+    }
+
     pub fn get_buffer_head(
         &self,
         buffer_id: BufferId,
@@ -491,6 +918,19 @@ impl LapceProxy {
         );
     }
 
+    pub fn reopen_with_encoding(
+        &self,
+        buffer_id: BufferId,
+        encoding: &str,
+        f: impl FnOnce(Result<NewBufferResponse, RequestError>) + Send + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "reopen_with_encoding",
+            &json!({ "buffer_id": buffer_id, "encoding": encoding }),
+            box_json_cb(f),
+        );
+    }
+
     pub fn save_buffer_as(
         &self,
         buffer_id: BufferId,
@@ -530,11 +970,33 @@ impl LapceProxy {
         );
     }
 
-    pub fn create_file(&self, path: &Path, f: Box<dyn Callback>) {
+    pub fn create_file(
+        &self,
+        path: &Path,
+        content: Option<String>,
+        f: Box<dyn Callback>,
+    ) {
         self.rpc.send_rpc_request_async(
             "create_file",
             &json!({
                 "path": path,
+                "content": content,
+            }),
+            f,
+        );
+    }
+
+    pub fn create_binary_file(
+        &self,
+        path: &Path,
+        content: Vec<u8>,
+        f: Box<dyn Callback>,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "create_binary_file",
+            &json!({
+                "path": path,
+                "content": content,
             }),
             f,
         );
@@ -550,9 +1012,35 @@ impl LapceProxy {
         );
     }
 
-    pub fn trash_path(&self, path: &Path, f: Box<dyn Callback>) {
+    pub fn ensure_file(&self, path: &Path, f: Box<dyn Callback>) {
+        self.rpc.send_rpc_request_async(
+            "ensure_file",
+            &json!({
+                "path": path,
+            }),
+            f,
+        );
+    }
+
+    pub fn trash_path(
+        &self,
+        path: &Path,
+        progress_token: Option<String>,
+        f: impl FnOnce(Result<TrashPathResponse, RequestError>) + Send + 'static,
+    ) {
         self.rpc.send_rpc_request_async(
             "trash_path",
+            &json!({
+                "path": path,
+                "progress_token": progress_token,
+            }),
+            box_json_cb(f),
+        );
+    }
+
+    pub fn delete_path(&self, path: &Path, f: Box<dyn Callback>) {
+        self.rpc.send_rpc_request_async(
+            "delete_path",
             &json!({
                 "path": path,
             }),
@@ -560,6 +1048,55 @@ impl LapceProxy {
         );
     }
 
+    pub fn get_workspace_trash(
+        &self,
+        f: impl FnOnce(Result<WorkspaceTrashResponse, RequestError>) + Send + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "get_workspace_trash",
+            &json!({}),
+            box_json_cb(f),
+        );
+    }
+
+    pub fn restore_from_trash(
+        &self,
+        id: &str,
+        f: impl FnOnce(Result<RestoreFromTrashResponse, RequestError>) + Send + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "restore_from_trash",
+            &json!({
+                "id": id,
+            }),
+            box_json_cb(f),
+        );
+    }
+
+    pub fn git_stage(&self, paths: &[PathBuf], f: Box<dyn Callback>) {
+        self.rpc
+            .send_rpc_request_async("git_stage", &json!({ "paths": paths }), f);
+    }
+
+    pub fn git_unstage(&self, paths: &[PathBuf], f: Box<dyn Callback>) {
+        self.rpc.send_rpc_request_async(
+            "git_unstage",
+            &json!({ "paths": paths }),
+            f,
+        );
+    }
+
+    pub fn set_git_ignored(&self, path: &Path, ignored: bool, f: Box<dyn Callback>) {
+        self.rpc.send_rpc_request_async(
+            "set_git_ignored",
+            &json!({
+                "path": path,
+                "ignored": ignored,
+            }),
+            f,
+        );
+    }
+
     pub fn rename_path(
         &self,
         from_path: &Path,
@@ -576,6 +1113,78 @@ impl LapceProxy {
         );
     }
 
+    pub fn read_paths_metadata(
+        &self,
+        paths: Vec<PathBuf>,
+        f: impl FnOnce(Result<PathsMetadataResponse, RequestError>) + Send + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "read_paths_metadata",
+            &json!({
+                "paths": paths,
+            }),
+            box_json_cb(f),
+        );
+    }
+
+    pub fn is_path_writable(
+        &self,
+        path: &Path,
+        f: impl FnOnce(Result<PathWritableResponse, RequestError>) + Send + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "is_path_writable",
+            &json!({
+                "path": path,
+            }),
+            box_json_cb(f),
+        );
+    }
+
+    pub fn read_file(
+        &self,
+        path: &Path,
+        f: impl FnOnce(Result<ReadFileResponse, RequestError>) + Send + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "read_file",
+            &json!({
+                "path": path,
+            }),
+            box_json_cb(f),
+        );
+    }
+
+    pub fn is_case_sensitive(
+        &self,
+        path: &Path,
+        f: impl FnOnce(Result<CaseSensitiveResponse, RequestError>) + Send + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "is_case_sensitive",
+            &json!({
+                "path": path,
+            }),
+            box_json_cb(f),
+        );
+    }
+
+    pub fn duplicate_path(
+        &self,
+        path: &Path,
+        progress_token: Option<String>,
+        f: impl FnOnce(Result<DuplicatePathResponse, RequestError>) + Send + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "duplicate_path",
+            &json!({
+                "path": path,
+                "progress_token": progress_token,
+            }),
+            box_json_cb(f),
+        );
+    }
+
     pub fn get_completion(
         &self,
         request_id: usize,
@@ -660,6 +1269,42 @@ impl LapceProxy {
         );
     }
 
+    pub fn prepare_rename(
+        &self,
+        buffer_id: BufferId,
+        position: Position,
+        f: impl FnOnce(Result<Option<PrepareRenameResponse>, RequestError>)
+            + Send
+            + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "prepare_rename",
+            &json!({
+                "buffer_id": buffer_id,
+                "position": position,
+            }),
+            box_json_cb(f),
+        );
+    }
+
+    pub fn rename(
+        &self,
+        buffer_id: BufferId,
+        position: Position,
+        new_name: String,
+        f: impl FnOnce(Result<Option<WorkspaceEdit>, RequestError>) + Send + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "rename",
+            &json!({
+                "buffer_id": buffer_id,
+                "position": position,
+                "new_name": new_name,
+            }),
+            box_json_cb(f),
+        );
+    }
+
     pub fn get_files(
         &self,
         f: impl FnOnce(Result<Vec<PathBuf>, RequestError>) + Send + 'static,
@@ -673,15 +1318,119 @@ impl LapceProxy {
         );
     }
 
+    /// Find the files of the workspace matching the query, best matches first
+    pub fn query_files(
+        &self,
+        query: &str,
+        limit: usize,
+        f: impl FnOnce(Result<QueryFilesResponse, RequestError>) + Send + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "query_files",
+            &json!({
+                "query": query,
+                "limit": limit,
+            }),
+            box_json_cb(f),
+        );
+    }
+
+    /// Get all the files beneath the directory, skipping ignored ones
+    pub fn get_files_in(
+        &self,
+        path: &Path,
+        f: impl FnOnce(Result<Vec<PathBuf>, RequestError>) + Send + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "get_files",
+            &json!({
+                "path": path,
+            }),
+            box_json_cb(f),
+        );
+    }
+
     pub fn read_dir(
         &self,
         path: &Path,
+        with_metadata: bool,
+        chunk_size: Option<usize>,
+        generation: u64,
         f: impl FnOnce(Result<ReadDirResponse, RequestError>) + Send + 'static,
     ) {
+        if chunk_size.is_some() {
+            // The proxy stops sending the chunks of the reads before this one
+            self.read_dir_finishes
+                .lock()
+                .retain(|(dir, gen), _| dir != path || *gen == generation);
+        }
+        let finishes = self.read_dir_finishes.clone();
+        let key = (path.to_path_buf(), generation);
         self.rpc.send_rpc_request_async(
             "read_dir",
             &json!({
                 "path": path,
+                "with_metadata": with_metadata,
+                "chunk_size": chunk_size,
+                "generation": generation,
+            }),
+            box_json_cb(move |result: Result<ReadDirResponse, RequestError>| {
+                // A failed read never finishes
+                let finished = match result.as_ref() {
+                    Ok(resp) if resp.more => None,
+                    Ok(_) => finishes.lock().remove(&key),
+                    Err(_) => {
+                        finishes.lock().remove(&key);
+                        None
+                    }
+                };
+                f(result);
+                if let Some(finished) = finished {
+                    finished();
+                }
+            }),
+        );
+    }
+
+    /// Call `f` once the last chunk of the read of the directory made at the
+    /// generation has been passed on, which has to be asked for before the
+    /// read is made. It isn't called if the read fails or is cancelled.
+    pub fn on_read_dir_finished(
+        &self,
+        path: &Path,
+        generation: u64,
+        f: impl FnOnce() + Send + 'static,
+    ) {
+        self.read_dir_finishes
+            .lock()
+            .insert((path.to_path_buf(), generation), Box::new(f));
+    }
+
+    pub fn cancel_read_dir(&self, path: &Path) {
+        self.read_dir_finishes
+            .lock()
+            .retain(|(dir, _), _| dir != path);
+        self.rpc.send_rpc_notification(
+            "cancel_read_dir",
+            &json!({
+                "path": path,
+            }),
+        )
+    }
+
+    pub fn read_dir_tree(
+        &self,
+        path: &Path,
+        max_depth: usize,
+        exclude: &[String],
+        f: impl FnOnce(Result<ReadDirTreeResponse, RequestError>) + Send + 'static,
+    ) {
+        self.rpc.send_rpc_request_async(
+            "read_dir_tree",
+            &json!({
+                "path": path,
+                "max_depth": max_depth,
+                "exclude": exclude,
             }),
             box_json_cb(f),
         );
@@ -10,6 +10,12 @@ pub struct SearchData {
     pub split_id: WidgetId,
     pub editor_view_id: WidgetId,
     pub matches: Arc<HashMap<PathBuf, Vec<Match>>>,
+    /// The folder that the search is limited to, set by finding in a folder
+    /// from the explorer
+    pub scope: Option<PathBuf>,
+    /// The id of the latest search of the scope, whose streamed matches are
+    /// collected into `matches`
+    pub query_id: u64,
 }
 
 impl SearchData {
@@ -21,6 +27,8 @@ impl SearchData {
             split_id: WidgetId::next(),
             editor_view_id,
             matches: Arc::new(HashMap::new()),
+            scope: None,
+            query_id: 0,
         }
     }
 }
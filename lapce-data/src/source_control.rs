@@ -4,7 +4,7 @@ use lapce_core::{
     mode::Mode,
     movement::Movement,
 };
-use lapce_rpc::source_control::FileDiff;
+use lapce_rpc::source_control::{ChangedFile, FileDiff};
 
 use crate::{
     command::{CommandExecuted, CommandKind, LapceUICommand, LAPCE_UI_COMMAND},
@@ -26,6 +26,9 @@ pub struct SourceControlData {
     pub editor_view_id: WidgetId,
     pub commit_button_id: WidgetId,
     pub file_diffs: Vec<(FileDiff, bool)>,
+    /// The changed files split into the staged and unstaged ones, with the
+    /// staged ones first, in the configured order
+    pub changed_files: Vec<ChangedFile>,
     pub branch: String,
     pub branches: Vec<String>,
 }
@@ -44,12 +47,41 @@ impl SourceControlData {
             split_id: WidgetId::next(),
             split_direction: SplitDirection::Horizontal,
             file_diffs: Vec::new(),
+            changed_files: Vec::new(),
             branch: "".to_string(),
             branches: Vec::new(),
         }
     }
 }
 
+/// Sort the changed files with the staged ones first. With the "status"
+/// order, they're grouped by their kind of change before being sorted by
+/// their path, otherwise they're sorted by their path only.
+pub fn sort_changed_files(files: &mut [ChangedFile], order: &str) {
+    let by_status = order == "status";
+    files.sort_by(|a, b| {
+        b.staged
+            .cmp(&a.staged)
+            .then_with(|| {
+                if by_status {
+                    status_rank(&a.diff).cmp(&status_rank(&b.diff))
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .then_with(|| a.diff.path().cmp(b.diff.path()))
+    });
+}
+
+fn status_rank(diff: &FileDiff) -> u8 {
+    match diff {
+        FileDiff::Modified(_) => 0,
+        FileDiff::Added(_) => 1,
+        FileDiff::Renamed(..) => 2,
+        FileDiff::Deleted(_) => 3,
+    }
+}
+
 impl Default for SourceControlData {
     fn default() -> Self {
         Self::new()
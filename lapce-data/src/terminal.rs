@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use alacritty_terminal::{
     ansi,
@@ -513,6 +513,11 @@ pub struct LapceTerminalData {
     pub visual_mode: VisualMode,
     pub raw: Arc<Mutex<RawTerminal>>,
     pub proxy: Arc<LapceProxy>,
+    /// Whether the process has exited while the terminal is kept open
+    pub exited: bool,
+    /// The working directory last reported by the shell, which shells only
+    /// do when they are set up to send OSC 7 escapes
+    pub cwd: Option<PathBuf>,
 }
 
 impl LapceTerminalData {
@@ -550,6 +555,8 @@ impl LapceTerminalData {
             visual_mode: VisualMode::Normal,
             raw,
             proxy,
+            exited: false,
+            cwd: None,
         }
     }
 
@@ -1,4 +1,6 @@
 use anyhow::{anyhow, Result};
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
 use lapce_core::buffer::CharIndicesJoin;
 use lapce_core::encoding::offset_utf8_to_utf16;
 use lapce_rpc::buffer::BufferId;
@@ -9,7 +11,15 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::path::PathBuf;
 use std::{borrow::Cow, path::Path, time::SystemTime};
-use xi_rope::{interval::IntervalBounds, rope::Rope, RopeDelta};
+use xi_rope::{
+    diff::{Diff, LineHashDiff},
+    interval::IntervalBounds,
+    rope::Rope,
+    DeltaElement, RopeDelta,
+};
+
+/// Roughly how many bytes an edit takes up besides its text once serialized
+const TEXT_EDIT_SIZE: usize = 64;
 
 #[derive(Clone)]
 pub struct Buffer {
@@ -19,14 +29,15 @@ pub struct Buffer {
     pub path: PathBuf,
     pub rev: u64,
     pub mod_time: Option<SystemTime>,
+    /// The encoding the file is read and saved in
+    pub encoding: &'static Encoding,
 }
 
 impl Buffer {
     pub fn new(id: BufferId, path: PathBuf) -> Buffer {
-        let rope = if let Ok(rope) = load_rope(&path) {
-            rope
-        } else {
-            Rope::from("")
+        let (rope, encoding) = match load_file_detected(&path) {
+            Ok((content, encoding)) => (Rope::from(content), encoding),
+            Err(_) => (Rope::from(""), UTF_8),
         };
         let rev = if rope.is_empty() { 0 } else { 1 };
         let language_id = language_id_from_path(&path).unwrap_or("").to_string();
@@ -38,13 +49,25 @@ impl Buffer {
             language_id,
             rev,
             mod_time,
+            encoding,
         }
     }
 
+    /// Write the buffer to its file as it is, line breaks included, so that
+    /// the file stays the same as the buffers of the editor and the proxy
     pub fn save(&mut self, rev: u64) -> Result<()> {
         if self.rev != rev {
             return Err(anyhow!("not the right rev"));
         }
+        // The file is replaced by renaming over it, which a read-only file
+        // doesn't stop, so its permissions are checked first
+        let metadata = fs::metadata(&self.path).ok();
+        if metadata
+            .as_ref()
+            .map_or(false, |metadata| metadata.permissions().readonly())
+        {
+            return Err(anyhow!("You don't have permission to write to the file."));
+        }
         let tmp_extension = self.path.extension().map_or_else(
             || OsString::from("swp"),
             |ext| {
@@ -56,13 +79,16 @@ impl Buffer {
         let tmp_path = &self.path.with_extension(tmp_extension);
 
         let mut f = File::create(tmp_path)?;
-        for chunk in self.rope.iter_chunks(..self.rope.len()) {
-            f.write_all(chunk.as_bytes())?;
+        if self.encoding == UTF_8 {
+            for chunk in self.rope.iter_chunks(..self.rope.len()) {
+                f.write_all(chunk.as_bytes())?;
+            }
+        } else {
+            f.write_all(&encode(&self.rope.to_string(), self.encoding))?;
         }
 
-        if let Ok(metadata) = fs::metadata(&self.path) {
-            let perm = metadata.permissions();
-            fs::set_permissions(tmp_path, perm)?;
+        if let Some(metadata) = metadata {
+            fs::set_permissions(tmp_path, metadata.permissions())?;
         }
 
         fs::rename(tmp_path, &self.path)?;
@@ -107,6 +133,48 @@ impl Buffer {
         (line, offset - self.offset_of_line(line))
     }
 
+    /// The edits that turn the buffer's content into the new content, or `None`
+    /// when sending them would take more than sending the new content whole
+    pub fn diff_edits(&self, content: &Rope) -> Option<Vec<TextEdit>> {
+        let delta = LineHashDiff::compute_delta(&self.rope, content);
+        let mut edits = Vec::new();
+        let mut size = 0;
+        // The start of the text that is replaced by the next edit
+        let mut start = 0;
+        let mut new_text = String::new();
+        let mut push_edit = |start: usize, end: usize, new_text: String| {
+            size += new_text.len() + TEXT_EDIT_SIZE;
+            edits.push(TextEdit {
+                range: Range {
+                    start: self.offset_to_position(start)?,
+                    end: self.offset_to_position(end)?,
+                },
+                new_text,
+            });
+            Some(())
+        };
+        for el in delta.els.iter() {
+            match el {
+                DeltaElement::Copy(b, e) => {
+                    if *b > start || !new_text.is_empty() {
+                        push_edit(start, *b, std::mem::take(&mut new_text))?;
+                    }
+                    start = *e;
+                }
+                DeltaElement::Insert(text) => {
+                    new_text.push_str(&text.slice_to_cow(..));
+                }
+            }
+        }
+        if self.rope.len() > start || !new_text.is_empty() {
+            push_edit(start, self.rope.len(), new_text)?;
+        }
+        if size > content.len() {
+            return None;
+        }
+        Some(edits)
+    }
+
     /// Converts a UTF8 offset to a UTF16 LSP position  
     /// Returns `None` if it is not a valid UTF16 offset
     pub fn offset_to_position(&self, offset: usize) -> Option<Position> {
@@ -145,12 +213,58 @@ impl Buffer {
     }
 }
 
-pub fn load_file(path: &Path) -> Result<String> {
-    Ok(read_path_to_string_lossy(path)?)
+/// Read the file in the encoding, replacing what isn't valid in it with the
+/// replacement character
+pub fn load_file(path: &Path, encoding: &'static Encoding) -> Result<String> {
+    let bytes = fs::read(path)?;
+    Ok(decode(&bytes, encoding))
 }
 
-fn load_rope(path: &Path) -> Result<Rope> {
-    Ok(Rope::from(read_path_to_string_lossy(path)?))
+/// Read the file in the encoding detected from its content
+fn load_file_detected(path: &Path) -> Result<(String, &'static Encoding)> {
+    let bytes = fs::read(path)?;
+    let encoding = detect_encoding(&bytes);
+    Ok((decode(&bytes, encoding), encoding))
+}
+
+/// Guess the encoding of the content of a file. A byte order mark decides it,
+/// and otherwise valid UTF-8 is taken as UTF-8 before falling back to the
+/// guess of the detector.
+pub fn detect_encoding(bytes: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
+
+/// Decode the content in the encoding. A byte order mark is kept as part of
+/// the text, so that saving the text writes it back.
+fn decode(bytes: &[u8], encoding: &'static Encoding) -> String {
+    if encoding == UTF_8 {
+        return String::from_utf8_lossy(bytes).to_string();
+    }
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// Encode the text in the encoding. `encoding_rs` only encodes UTF-16 as
+/// UTF-8, so that is done here.
+fn encode(text: &str, encoding: &'static Encoding) -> Vec<u8> {
+    if encoding == UTF_16LE {
+        text.encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect()
+    } else if encoding == UTF_16BE {
+        text.encode_utf16()
+            .flat_map(|unit| unit.to_be_bytes())
+            .collect()
+    } else {
+        encoding.encode(text).0.into_owned()
+    }
 }
 
 pub fn read_path_to_string_lossy<P: AsRef<Path>>(
@@ -1,32 +1,49 @@
 use crate::buffer::{get_mod_time, load_file, Buffer};
+use crate::file_index::FileIndex;
 use crate::lsp::LspCatalog;
 use crate::plugin::PluginCatalog;
 use crate::terminal::Terminal;
 use crate::watcher::{FileWatcher, Notify, WatchToken};
+use crate::workspace_trash::{WorkspaceTrash, WORKSPACE_TRASH_DIR};
 use alacritty_terminal::event_loop::Msg;
 use alacritty_terminal::term::SizeInfo;
 use anyhow::{anyhow, Context, Result};
 use crossbeam_channel::{Receiver, Sender};
 use directories::BaseDirs;
+use encoding_rs::Encoding;
 use git2::build::CheckoutBuilder;
 use git2::{DiffOptions, Repository};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use grep_matcher::Matcher;
 use grep_regex::RegexMatcherBuilder;
 use grep_searcher::sinks::UTF8;
 use grep_searcher::SearcherBuilder;
-use lapce_rpc::buffer::{BufferHeadResponse, BufferId, NewBufferResponse};
+use lapce_rpc::buffer::{
+    BufferHeadResponse, BufferId, LineEnding, NewBufferResponse,
+};
 use lapce_rpc::core::CoreNotification;
-use lapce_rpc::file::FileNodeItem;
-use lapce_rpc::proxy::{ProxyNotification, ProxyRequest, ReadDirResponse};
-use lapce_rpc::source_control::{DiffInfo, FileDiff};
+use lapce_rpc::file::{FileErrorKind, FileNodeItem, FileOperation, PathMetadata};
+use lapce_rpc::proxy::{
+    CaseSensitiveResponse, DuplicatePathResponse, PathWritableResponse,
+    PathsMetadataResponse, ProxyNotification, ProxyRequest, QueryFilesResponse,
+    ReadDirResponse, ReadDirTreeResponse, ReadFileResponse,
+    RestoreFromTrashResponse, TrashLocation, TrashPathResponse,
+    WorkspaceTrashResponse,
+};
+use lapce_rpc::source_control::{ChangedFile, DiffInfo, FileDiff};
 use lapce_rpc::terminal::TermId;
 use lapce_rpc::{self, Call, RequestId, RpcObject};
+use lsp_types::{
+    ProgressParams, ProgressParamsValue, ProgressToken, WorkDoneProgress,
+    WorkDoneProgressBegin, WorkDoneProgressEnd, WorkDoneProgressReport,
+};
 use parking_lot::Mutex;
 use serde_json::json;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
@@ -35,6 +52,9 @@ use xi_rope::Rope;
 
 const OPEN_FILE_EVENT_TOKEN: WatchToken = WatchToken(1);
 const WORKSPACE_EVENT_TOKEN: WatchToken = WatchToken(2);
+const WORKSPACE_ROOT_EVENT_TOKEN: WatchToken = WatchToken(3);
+/// The most entries that a `ReadDirTree` response includes
+const MAX_DIR_TREE_ENTRIES: usize = 10_000;
 
 #[derive(Clone)]
 pub struct Dispatcher {
@@ -51,6 +71,29 @@ pub struct Dispatcher {
     pub file_watcher: Arc<Mutex<Option<FileWatcher>>>,
     workspace_fs_change_handler: Arc<Mutex<Option<Sender<bool>>>>,
     last_diff: Arc<Mutex<DiffInfo>>,
+    /// The files last sent in `DiffFiles`
+    last_changed_files: Arc<Mutex<Vec<ChangedFile>>>,
+    /// The flags that cancel the running filesystem operations, by their
+    /// progress token
+    progress_cancels: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+    /// The paths that are never listed, set by `SetFileExclude`
+    file_exclude: Arc<Mutex<GlobSet>>,
+    /// The files of the workspace for `QueryFiles`, built by the first query
+    /// and kept up to date with the changes in the workspace
+    file_index: Arc<Mutex<Option<FileIndex>>>,
+    /// The flags that cancel the running searches, by their query id
+    search_cancels: Arc<Mutex<HashMap<u64, Arc<AtomicBool>>>>,
+    /// The flags that cancel the running directory size computations, by
+    /// their directory
+    dir_size_cancels: Arc<Mutex<HashMap<PathBuf, Arc<AtomicBool>>>>,
+    /// The flags that stop sending the chunks of the directories being read,
+    /// by their directory. A new read of a directory stops the one before it.
+    read_dir_cancels: Arc<Mutex<HashMap<PathBuf, Arc<AtomicBool>>>>,
+    /// Whether the workspace folder was found renamed or deleted, which is
+    /// only reported once
+    workspace_root_gone: Arc<AtomicBool>,
+    /// The flags that cancel the plugins being installed, by their name
+    plugin_install_cancels: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
 }
 
 impl Notify for Dispatcher {
@@ -72,7 +115,16 @@ impl Dispatcher {
             lsp: Arc::new(Mutex::new(LspCatalog::new())),
             file_watcher: Arc::new(Mutex::new(None)),
             last_diff: Arc::new(Mutex::new(DiffInfo::default())),
+            last_changed_files: Arc::new(Mutex::new(Vec::new())),
             workspace_fs_change_handler: Arc::new(Mutex::new(None)),
+            progress_cancels: Arc::new(Mutex::new(HashMap::new())),
+            file_exclude: Arc::new(Mutex::new(GlobSet::empty())),
+            file_index: Arc::new(Mutex::new(None)),
+            search_cancels: Arc::new(Mutex::new(HashMap::new())),
+            dir_size_cancels: Arc::new(Mutex::new(HashMap::new())),
+            read_dir_cancels: Arc::new(Mutex::new(HashMap::new())),
+            workspace_root_gone: Arc::new(AtomicBool::new(false)),
+            plugin_install_cancels: Arc::new(Mutex::new(HashMap::new())),
         };
         *dispatcher.file_watcher.lock() = Some(FileWatcher::new(dispatcher.clone()));
         dispatcher.lsp.lock().dispatcher = Some(dispatcher.clone());
@@ -225,11 +277,98 @@ impl Dispatcher {
             match token {
                 OPEN_FILE_EVENT_TOKEN => self.handle_open_file_fs_event(event),
                 WORKSPACE_EVENT_TOKEN => self.handle_workspace_fs_event(event),
+                WORKSPACE_ROOT_EVENT_TOKEN => {
+                    self.handle_workspace_root_fs_event(event)
+                }
                 _ => {}
             }
         }
     }
 
+    /// Search the files beneath the path for the pattern, sending each match
+    /// as it's found until it's done or cancelled
+    fn search_path(
+        &self,
+        query_id: u64,
+        pattern: &str,
+        path: PathBuf,
+        exclude: GlobSet,
+        cancelled: &AtomicBool,
+    ) {
+        let pattern = regex::escape(pattern);
+        let matcher = match RegexMatcherBuilder::new()
+            .case_insensitive(true)
+            .build_literals(&[&pattern])
+        {
+            Ok(matcher) => matcher,
+            Err(_) => return,
+        };
+        let mut searcher = SearcherBuilder::new().build();
+        let walk = ignore::WalkBuilder::new(path)
+            .filter_entry(move |entry| !is_excluded(&exclude, entry.path()))
+            .build();
+        for entry in walk.flatten() {
+            if cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+            if !entry.file_type().map_or(false, |t| t.is_file()) {
+                continue;
+            }
+            let path = entry.into_path();
+            let _ = searcher.search_path(
+                &matcher,
+                path.clone(),
+                UTF8(|lnum, line| {
+                    if let Some(found) = matcher.find(line.as_bytes())? {
+                        self.send_rpc_notification(CoreNotification::SearchResult {
+                            query_id,
+                            path: path.clone(),
+                            line: lnum as usize,
+                            column: found.start(),
+                            end_column: found.end(),
+                            text: line.to_string(),
+                        });
+                    }
+                    Ok(!cancelled.load(Ordering::Relaxed))
+                }),
+            );
+        }
+    }
+
+    /// Make the buffers of the renamed path, or of the files beneath it, save
+    /// to their new path and watch it for changes
+    fn rename_open_files(&self, from: &Path, to: &Path) {
+        let mut open_files = self.open_files.lock();
+        let mut buffers = self.buffers.lock();
+        let renamed: Vec<(String, BufferId)> = open_files
+            .iter()
+            .filter(|(path, _)| Path::new(path).starts_with(from))
+            .map(|(path, buffer_id)| (path.clone(), *buffer_id))
+            .collect();
+        for (old_path, buffer_id) in renamed {
+            open_files.remove(&old_path);
+            let suffix = match Path::new(&old_path).strip_prefix(from) {
+                Ok(suffix) => suffix,
+                Err(_) => continue,
+            };
+            let new_path = if suffix.as_os_str().is_empty() {
+                to.to_path_buf()
+            } else {
+                to.join(suffix)
+            };
+            if let Some(watcher) = self.file_watcher.lock().as_mut() {
+                watcher.unwatch(Path::new(&old_path), OPEN_FILE_EVENT_TOKEN);
+                watcher.watch(&new_path, false, OPEN_FILE_EVENT_TOKEN);
+            }
+            if let Some(path) = new_path.to_str() {
+                open_files.insert(path.to_string(), buffer_id);
+            }
+            if let Some(buffer) = buffers.get_mut(&buffer_id) {
+                buffer.path = new_path;
+            }
+        }
+    }
+
     fn handle_open_file_fs_event(&self, event: notify::Event) {
         use notify::event::*;
         let path = match event.kind {
@@ -245,13 +384,21 @@ impl Dispatcher {
                     if get_mod_time(&buffer.path) == buffer.mod_time {
                         return;
                     }
-                    if let Ok(content) = load_file(&buffer.path) {
-                        self.send_rpc_notification(
-                            CoreNotification::OpenFileChanged {
+                    if let Ok(content) = load_file(&buffer.path, buffer.encoding) {
+                        let notification = match buffer
+                            .diff_edits(&Rope::from(&content))
+                        {
+                            Some(changes) => CoreNotification::ReloadBufferDelta {
+                                path: buffer.path.clone(),
+                                rev: buffer.rev,
+                                changes,
+                            },
+                            None => CoreNotification::OpenFileChanged {
                                 path: buffer.path.clone(),
                                 content,
                             },
-                        );
+                        };
+                        self.send_rpc_notification(notification);
                     }
                 }
             }
@@ -270,6 +417,12 @@ impl Dispatcher {
                 _ => return,
             };
 
+            if explorer_change {
+                if let Some(file_index) = self.file_index.lock().as_mut() {
+                    file_index.update(&event.paths);
+                }
+            }
+
             let mut handler = self.workspace_fs_change_handler.lock();
             if let Some(sender) = handler.as_mut() {
                 if explorer_change {
@@ -302,10 +455,13 @@ impl Dispatcher {
                 }
                 if explorer_change {
                     local_dispatcher.send_rpc_notification(
-                        CoreNotification::WorkspaceFileChange {},
+                        CoreNotification::WorkspaceFileChange {
+                            created: Vec::new(),
+                        },
                     );
                 }
-                if let Some(diff) = git_diff_new(&workspace) {
+                if let Some((diff, changed_files)) = git_diff_new(&workspace) {
+                    local_dispatcher.send_changed_files(changed_files);
                     let mut last_diff = local_dispatcher.last_diff.lock();
                     if diff != *last_diff {
                         local_dispatcher.send_notification(
@@ -322,25 +478,146 @@ impl Dispatcher {
         }
     }
 
+    /// Let the core know when the workspace folder itself was renamed or
+    /// deleted, which the watch of the workspace doesn't report
+    fn handle_workspace_root_fs_event(&self, event: notify::Event) {
+        let workspace = match self.workspace.lock().clone() {
+            Some(workspace) => workspace,
+            None => return,
+        };
+        if workspace.exists() {
+            return;
+        }
+        match (&event.kind, event.paths.as_slice()) {
+            (
+                notify::EventKind::Modify(notify::event::ModifyKind::Name(
+                    notify::event::RenameMode::Both,
+                )),
+                [_, new],
+            ) => {
+                self.send_workspace_root_changed(&workspace, Some(new.clone()));
+            }
+            _ => {
+                // The pair of a rename within the same directory can come
+                // after the event of the folder leaving, while a move to
+                // another directory is only seen leaving, so wait for the pair
+                // before reporting the folder as gone
+                let local_dispatcher = self.clone();
+                thread::spawn(move || {
+                    thread::sleep(Duration::from_millis(500));
+                    if !workspace.exists() {
+                        local_dispatcher
+                            .send_workspace_root_changed(&workspace, None);
+                    }
+                });
+            }
+        }
+    }
+
+    fn send_workspace_root_changed(&self, old: &Path, new: Option<PathBuf>) {
+        if self.workspace_root_gone.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        self.send_rpc_notification(CoreNotification::WorkspaceRootChanged {
+            old: old.to_path_buf(),
+            new,
+        });
+    }
+
+    /// Forget the flag of the read of the directory once it's done, unless a
+    /// newer read of it already took its place
+    fn finish_read_dir(&self, path: &Path, cancelled: &Arc<AtomicBool>) {
+        let mut cancels = self.read_dir_cancels.lock();
+        if cancels
+            .get(path)
+            .map_or(false, |current| Arc::ptr_eq(current, cancelled))
+        {
+            cancels.remove(path);
+        }
+    }
+
+    /// Let the core know about the git status of the workspace right away,
+    /// such as after changing it
+    fn send_diff_info(&self, workspace: &Path) {
+        if let Some((diff, changed_files)) = git_diff_new(workspace) {
+            self.send_changed_files(changed_files);
+            self.send_notification(
+                "diff_info",
+                json!({
+                    "diff": diff,
+                }),
+            );
+            *self.last_diff.lock() = diff;
+        }
+    }
+
+    /// Let the core know whether the buffer was written to disk
+    fn send_buffer_saved(&self, path: PathBuf, rev: u64, result: &Result<()>) {
+        self.send_rpc_notification(CoreNotification::BufferSaved {
+            path,
+            rev,
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|err| err.to_string()),
+        });
+    }
+
+    /// Let the core know why a filesystem operation of the explorer failed,
+    /// unless it was because it was cancelled
+    fn send_file_operation_error(
+        &self,
+        operation: FileOperation,
+        path: &Path,
+        err: &anyhow::Error,
+    ) {
+        let kind = err
+            .chain()
+            .find_map(|err| err.downcast_ref::<std::io::Error>())
+            .map_or(FileErrorKind::Other, FileErrorKind::from_io);
+        if kind == FileErrorKind::Cancelled {
+            return;
+        }
+        self.send_rpc_notification(CoreNotification::FileOperationError {
+            operation,
+            path: path.to_path_buf(),
+            kind,
+            message: err.to_string(),
+        });
+    }
+
+    /// Let the core know about the changed files, if they're not the same as
+    /// the ones it last got
+    fn send_changed_files(&self, files: Vec<ChangedFile>) {
+        let mut last_changed_files = self.last_changed_files.lock();
+        if files != *last_changed_files {
+            self.send_rpc_notification(CoreNotification::DiffFiles {
+                files: files.clone(),
+            });
+            *last_changed_files = files;
+        }
+    }
+
     fn handle_notification(&self, rpc: ProxyNotification) {
         use ProxyNotification::*;
         match rpc {
             Initialize { workspace } => {
                 *self.workspace.lock() = Some(workspace.clone());
-                self.file_watcher.lock().as_mut().unwrap().watch(
-                    &workspace,
-                    true,
-                    WORKSPACE_EVENT_TOKEN,
-                );
-                if let Some(diff) = git_diff_new(&workspace) {
-                    self.send_notification(
-                        "diff_info",
-                        json!({
-                            "diff": diff,
-                        }),
-                    );
-                    *self.last_diff.lock() = diff;
+                let mut file_watcher = self.file_watcher.lock();
+                let file_watcher = file_watcher.as_mut().unwrap();
+                file_watcher.watch(&workspace, true, WORKSPACE_EVENT_TOKEN);
+                // The folder itself is only seen being renamed or deleted from
+                // the directory it is in
+                if let Ok(root) = workspace.canonicalize() {
+                    if let Some(parent) = root.parent() {
+                        let watched = root.clone();
+                        file_watcher.watch_filtered(
+                            parent,
+                            false,
+                            WORKSPACE_ROOT_EVENT_TOKEN,
+                            move |path| path == watched,
+                        );
+                    }
                 }
+                self.send_diff_info(&workspace);
             }
             Shutdown {} => {}
             Update {
@@ -357,11 +634,29 @@ impl Dispatcher {
             InstallPlugin { plugin } => {
                 let catalog = self.plugins.clone();
                 let dispatcher = self.clone();
+                let cancelled = Arc::new(AtomicBool::new(false));
+                self.plugin_install_cancels
+                    .lock()
+                    .insert(plugin.name.clone(), cancelled.clone());
                 std::thread::spawn(move || {
-                    if let Err(e) =
-                        catalog.lock().install_plugin(dispatcher.clone(), plugin)
-                    {
+                    let plugin_id = plugin.name.clone();
+                    let result = catalog.lock().install_plugin(
+                        dispatcher.clone(),
+                        plugin,
+                        &cancelled,
+                    );
+                    dispatcher.plugin_install_cancels.lock().remove(&plugin_id);
+                    if let Err(e) = result {
                         eprintln!("install plugin error {e}");
+                        // The core already forgot about a cancelled install
+                        if !cancelled.load(Ordering::Relaxed) {
+                            dispatcher.send_rpc_notification(
+                                CoreNotification::PluginInstallFailed {
+                                    plugin_id,
+                                    error: format!("{e:#}"),
+                                },
+                            );
+                        }
                     }
                     let plugins = { dispatcher.plugins.lock().items.clone() };
                     dispatcher.send_notification(
@@ -372,6 +667,13 @@ impl Dispatcher {
                     );
                 });
             }
+            CancelPluginInstall { plugin_id } => {
+                if let Some(cancelled) =
+                    self.plugin_install_cancels.lock().get(&plugin_id)
+                {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+            }
             DisablePlugin { plugin } => {
                 let catalog = self.plugins.clone();
                 let dispatcher = self.clone();
@@ -437,6 +739,13 @@ impl Dispatcher {
                     );
                 });
             }
+            RunPluginCommand {
+                plugin,
+                command,
+                args,
+            } => {
+                self.plugins.lock().run_command(&plugin, command, args);
+            }
             NewTerminal {
                 term_id,
                 cwd,
@@ -485,6 +794,90 @@ impl Dispatcher {
                     let _ = tx.send(Msg::Resize(size));
                 }
             }
+            RevealInFileManager { path } => {
+                if let Err(e) = reveal_in_file_manager(&path) {
+                    eprintln!("{e:?}");
+                }
+            }
+            CancelWorkDoneProgress { token } => {
+                if let Some(cancelled) = self.progress_cancels.lock().get(&token) {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+            }
+            StartSearch {
+                query_id,
+                pattern,
+                path,
+            } => {
+                let cancelled = Arc::new(AtomicBool::new(false));
+                self.search_cancels
+                    .lock()
+                    .insert(query_id, cancelled.clone());
+                let local_dispatcher = self.clone();
+                let exclude = self.file_exclude.lock().clone();
+                thread::spawn(move || {
+                    local_dispatcher
+                        .search_path(query_id, &pattern, path, exclude, &cancelled);
+                    local_dispatcher.search_cancels.lock().remove(&query_id);
+                });
+            }
+            CancelSearch { query_id } => {
+                if let Some(cancelled) = self.search_cancels.lock().get(&query_id) {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+            }
+            ComputeDirSize { path } => {
+                let cancelled = Arc::new(AtomicBool::new(false));
+                self.dir_size_cancels
+                    .lock()
+                    .insert(path.clone(), cancelled.clone());
+                let local_dispatcher = self.clone();
+                let exclude = self.file_exclude.lock().clone();
+                thread::spawn(move || {
+                    let size = dir_size(&path, exclude, &cancelled);
+                    local_dispatcher.dir_size_cancels.lock().remove(&path);
+                    if let Some(size) = size {
+                        local_dispatcher.send_rpc_notification(
+                            CoreNotification::DirSize { path, size },
+                        );
+                    }
+                });
+            }
+            CancelDirSize { path } => {
+                if let Some(cancelled) = self.dir_size_cancels.lock().get(&path) {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+            }
+            CancelReadDir { path } => {
+                if let Some(cancelled) = self.read_dir_cancels.lock().remove(&path) {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+            }
+            SetFileExclude { patterns } => {
+                *self.file_exclude.lock() = build_file_exclude(&patterns);
+                // Rebuilt with the new patterns by the next query
+                self.file_index.lock().take();
+            }
+            RestartLanguageServer { language } => {
+                // Starting the server waits for it to initialize
+                let local_dispatcher = self.clone();
+                thread::spawn(move || {
+                    local_dispatcher
+                        .lsp
+                        .lock()
+                        .restart_language_server(&language);
+                });
+            }
+            OpenInSystemTerminal { path } => {
+                if let Err(e) = open_in_system_terminal(&path) {
+                    eprintln!("{e:?}");
+                }
+            }
+            OpenWithDefaultApplication { path } => {
+                if let Err(e) = open_with_default_application(&path) {
+                    eprintln!("{e:?}");
+                }
+            }
             GitInit {} => {
                 if let Some(workspace) = self.workspace.lock().clone() {
                     match git_init(&workspace) {
@@ -556,8 +949,14 @@ impl Dispatcher {
                     .insert(path.to_str().unwrap().to_string(), buffer_id);
                 let buffer = Buffer::new(buffer_id, path);
                 let content = buffer.rope.to_string();
+                let encoding = buffer.encoding.name().to_string();
+                let line_ending = LineEnding::detect(&content);
                 self.buffers.lock().insert(buffer_id, buffer);
-                let resp = NewBufferResponse { content };
+                let resp = NewBufferResponse {
+                    content,
+                    encoding,
+                    line_ending,
+                };
                 let _ = self.sender.send(json!({
                     "id": id,
                     "result": resp,
@@ -578,6 +977,30 @@ impl Dispatcher {
                     }
                 }
             }
+            ReopenWithEncoding {
+                buffer_id,
+                encoding,
+            } => {
+                let mut buffers = self.buffers.lock();
+                let resp = match (
+                    buffers.get_mut(&buffer_id),
+                    Encoding::for_label(encoding.as_bytes()),
+                ) {
+                    (Some(buffer), Some(encoding)) => {
+                        load_file(&buffer.path, encoding).map(|content| {
+                            buffer.encoding = encoding;
+                            NewBufferResponse {
+                                line_ending: LineEnding::detect(&content),
+                                content,
+                                encoding: encoding.name().to_string(),
+                            }
+                        })
+                    }
+                    (None, _) => Err(anyhow!("buffer not found")),
+                    (_, None) => Err(anyhow!("unknown encoding {encoding}")),
+                };
+                self.respond_rpc(id, resp);
+            }
             GetCompletion {
                 buffer_id,
                 position,
@@ -624,6 +1047,23 @@ impl Dispatcher {
                 let buffer = buffers.get(&buffer_id).unwrap();
                 self.lsp.lock().get_references(id, buffer, position);
             }
+            PrepareRename {
+                buffer_id,
+                position,
+            } => {
+                let buffers = self.buffers.lock();
+                let buffer = buffers.get(&buffer_id).unwrap();
+                self.lsp.lock().prepare_rename(id, buffer, position);
+            }
+            Rename {
+                buffer_id,
+                position,
+                new_name,
+            } => {
+                let buffers = self.buffers.lock();
+                let buffer = buffers.get(&buffer_id).unwrap();
+                self.lsp.lock().rename(id, buffer, position, new_name);
+            }
             GetDefinition {
                 buffer_id,
                 position,
@@ -679,44 +1119,134 @@ impl Dispatcher {
                 let buffer = buffers.get(&buffer_id).unwrap();
                 self.lsp.lock().get_document_formatting(id, buffer);
             }
-            ReadDir { path } => {
+            ReadDir {
+                path,
+                with_metadata,
+                chunk_size,
+                generation,
+            } => {
+                // Only a listing in chunks takes long enough to be stopped
+                let cancelled = Arc::new(AtomicBool::new(false));
+                if chunk_size.is_some() {
+                    if let Some(previous) = self
+                        .read_dir_cancels
+                        .lock()
+                        .insert(path.clone(), cancelled.clone())
+                    {
+                        previous.store(true, Ordering::Relaxed);
+                    }
+                }
                 let local_dispatcher = self.clone();
+                let exclude = self.file_exclude.lock().clone();
                 thread::spawn(move || {
-                    let result = fs::read_dir(path)
-                        .map(|entries| {
-                            let items = entries
-                                .into_iter()
-                                .filter_map(|entry| {
-                                    entry
-                                        .map(|e| {
-                                            (
-                                                e.path(),
-                                                FileNodeItem {
-                                                    path_buf: e.path(),
-                                                    is_dir: e.path().is_dir(),
-                                                    open: false,
-                                                    read: false,
-                                                    children: HashMap::new(),
-                                                    children_open_count: 0,
-                                                },
-                                            )
-                                        })
-                                        .ok()
-                                })
-                                .collect::<HashMap<PathBuf, FileNodeItem>>();
-
-                            ReadDirResponse { items }
-                        })
-                        .map_err(|e| anyhow!(e));
-                    local_dispatcher.respond_rpc(id, result);
+                    // Whether git ignores the entries is told along with the
+                    // rest of their metadata
+                    let repo = with_metadata
+                        .then(|| Repository::discover(&path).ok())
+                        .flatten();
+                    let mut entries = match fs::read_dir(&path) {
+                        Ok(entries) => entries
+                            .filter_map(|entry| entry.ok())
+                            .filter(|entry| !is_excluded(&exclude, &entry.path()))
+                            .map(|entry| {
+                                let mut item = read_dir_entry(&entry, with_metadata);
+                                item.git_ignored =
+                                    repo.as_ref().map_or(false, |repo| {
+                                        is_git_ignored(repo, &item.path_buf)
+                                    });
+                                (entry.path(), item)
+                            })
+                            .peekable(),
+                        Err(e) => {
+                            local_dispatcher
+                                .respond_rpc::<ReadDirResponse>(id, Err(anyhow!(e)));
+                            local_dispatcher.finish_read_dir(&path, &cancelled);
+                            return;
+                        }
+                    };
+                    let chunk_size = chunk_size.unwrap_or(usize::MAX).max(1);
+
+                    let items = entries.by_ref().take(chunk_size).collect::<HashMap<
+                        PathBuf,
+                        FileNodeItem,
+                    >>(
+                    );
+                    let mut more = entries.peek().is_some();
+                    local_dispatcher
+                        .respond_rpc(id, Ok(ReadDirResponse { items, more }));
+
+                    // Send the rest of a large directory in chunks, so that the
+                    // explorer can show the entries as they are read
+                    while more && !cancelled.load(Ordering::Relaxed) {
+                        let items = entries
+                            .by_ref()
+                            .take(chunk_size)
+                            .collect::<HashMap<PathBuf, FileNodeItem>>();
+                        more = entries.peek().is_some();
+                        local_dispatcher.send_notification(
+                            "list_dir",
+                            json!({
+                                "path": path,
+                                "items": items,
+                                "more": more,
+                                "generation": generation,
+                            }),
+                        );
+                    }
+                    local_dispatcher.finish_read_dir(&path, &cancelled);
                 });
             }
-            GetFiles { .. } => {
-                if let Some(workspace) = self.workspace.lock().clone() {
+            ReadDirTree {
+                path,
+                max_depth,
+                exclude,
+            } => {
+                let local_dispatcher = self.clone();
+                let configured = self.file_exclude.lock().clone();
+                thread::spawn(move || {
+                    let exclude = build_file_exclude(&exclude);
+                    let mut item = FileNodeItem::new(path.clone(), path.is_dir());
+                    if !item.is_dir {
+                        local_dispatcher.respond_rpc::<ReadDirTreeResponse>(
+                            id,
+                            Err(anyhow!("{:?} is not a directory", path)),
+                        );
+                        return;
+                    }
+                    let mut remaining = MAX_DIR_TREE_ENTRIES;
+                    let truncated = read_dir_tree(
+                        &mut item,
+                        max_depth,
+                        &|path| {
+                            is_excluded(&configured, path)
+                                || is_excluded(&exclude, path)
+                        },
+                        &mut remaining,
+                    );
+                    local_dispatcher.respond_rpc(
+                        id,
+                        Ok(ReadDirTreeResponse { item, truncated }),
+                    );
+                });
+            }
+            GetFiles { path } => {
+                // A directory lists the files beneath it, otherwise it's all the
+                // files of the workspace
+                let root = Some(PathBuf::from(path))
+                    .filter(|path| path.is_absolute() && path.is_dir())
+                    .or_else(|| self.workspace.lock().clone());
+                if let Some(root) = root {
                     let local_dispatcher = self.clone();
+                    let exclude = self.file_exclude.lock().clone();
                     thread::spawn(move || {
                         let mut items = Vec::new();
-                        for path in ignore::Walk::new(workspace).flatten() {
+                        // Excluded directories are skipped without being read
+                        let walk = ignore::WalkBuilder::new(root)
+                            .filter_entry(move |entry| {
+                                !is_excluded(&exclude, entry.path())
+                            })
+                            .build();
+                        for path in walk.flatten() {
                             if let Some(file_type) = path.file_type() {
                                 if file_type.is_file() {
                                     items.push(path.into_path());
@@ -728,13 +1258,55 @@ impl Dispatcher {
                     });
                 }
             }
+            QueryFiles { query, limit } => {
+                let workspace = match self.workspace.lock().clone() {
+                    Some(workspace) => workspace,
+                    None => {
+                        self.respond_rpc(
+                            id,
+                            Ok(QueryFilesResponse { items: Vec::new() }),
+                        );
+                        return;
+                    }
+                };
+                let local_dispatcher = self.clone();
+                thread::spawn(move || {
+                    // The workspace is walked without holding the lock, so that
+                    // file changes aren't held up waiting for it meanwhile
+                    if local_dispatcher.file_index.lock().is_none() {
+                        let exclude = local_dispatcher.file_exclude.lock().clone();
+                        let file_index = FileIndex::new(workspace, exclude);
+                        local_dispatcher.file_index.lock().get_or_insert(file_index);
+                    }
+                    let items = local_dispatcher
+                        .file_index
+                        .lock()
+                        .as_ref()
+                        .map(|file_index| file_index.query(&query, limit))
+                        .unwrap_or_default();
+                    local_dispatcher
+                        .respond_rpc(id, Ok(QueryFilesResponse { items }));
+                });
+            }
             Save { rev, buffer_id } => {
                 if let Some(workspace) = self.workspace.lock().as_ref() {
                     let mut buffers = self.buffers.lock();
                     let buffer = buffers.get_mut(&buffer_id).unwrap();
-                    let resp = buffer.save(rev).map(|_r| json!({}));
+                    let result = buffer.save(rev);
+                    self.send_buffer_saved(buffer.path.clone(), rev, &result);
                     self.lsp.lock().save_buffer(buffer, workspace);
-                    self.respond(id, resp);
+                    let saved = result.is_ok();
+                    self.respond(id, result.map(|_r| json!({})));
+                    // The file may have new changes for git now, which are
+                    // looked for off the dispatcher thread like the other
+                    // refreshes after changes on disk
+                    if saved {
+                        let local_dispatcher = self.clone();
+                        let workspace = workspace.clone();
+                        thread::spawn(move || {
+                            local_dispatcher.send_diff_info(&workspace);
+                        });
+                    }
                 }
             }
             SaveBufferAs {
@@ -746,7 +1318,13 @@ impl Dispatcher {
                 let mut buffer = Buffer::new(buffer_id, path.clone());
                 buffer.rope = Rope::from(content);
                 buffer.rev = rev;
-                let resp = buffer.save(rev).map(|_r| json!({}));
+                // The new file keeps the encoding of the buffer, if it had one
+                if let Some(old) = self.buffers.lock().get(&buffer_id) {
+                    buffer.encoding = old.encoding;
+                }
+                let result = buffer.save(rev);
+                self.send_buffer_saved(path.clone(), rev, &result);
+                let resp = result.map(|_r| json!({}));
                 if resp.is_ok() {
                     self.buffers.lock().insert(buffer_id, buffer);
                     self.open_files
@@ -755,42 +1333,302 @@ impl Dispatcher {
                 }
                 self.respond(id, resp);
             }
-            CreateFile { path } => {
-                // Create the file, specifically choosing to error if it already exists
-                // We also throw away the file object because we only want to create it,
-                // and return any errors that occur
-                let resp = std::fs::OpenOptions::new()
-                    .write(true)
-                    .create_new(true)
-                    .open(path)
+            CreateFile { path, content } => {
+                let content = content.as_deref().unwrap_or_default();
+                let resp = create_new_file(&path, content.as_bytes())
                     .map(|_| json!({}))
                     .map_err(anyhow::Error::from);
+                let created = resp.is_ok();
+                if let Err(err) = &resp {
+                    self.send_file_operation_error(
+                        FileOperation::Create,
+                        &path,
+                        err,
+                    );
+                }
                 self.respond(id, resp);
+                // The explorer learns of the file without waiting for the
+                // file watcher, which files made for workspace edits rely on
+                if created {
+                    self.send_rpc_notification(
+                        CoreNotification::WorkspaceFileChange {
+                            created: vec![path],
+                        },
+                    );
+                }
             }
-            CreateDirectory { path } => {
-                let resp = std::fs::create_dir(path)
+            CreateBinaryFile { path, content } => {
+                let resp = create_new_file(&path, &content)
                     .map(|_| json!({}))
                     .map_err(anyhow::Error::from);
                 self.respond(id, resp);
             }
-            TrashPath { path } => {
-                let resp = trash::delete(path)
-                    .map(|_| json!({}))
+            CreateDirectory { path } => {
+                let resp = if path.exists() {
+                    Err(already_exists(&path))
+                } else {
+                    std::fs::create_dir_all(&path)
+                        .map(|_| json!({}))
+                        .map_err(anyhow::Error::from)
+                };
+                if let Err(err) = &resp {
+                    self.send_file_operation_error(
+                        FileOperation::Create,
+                        &path,
+                        err,
+                    );
+                }
+                self.respond(id, resp);
+            }
+            EnsureFile { path } => {
+                let resp = ensure_file(&path);
+                let created = matches!(resp, Ok(true));
+                self.respond(
+                    id,
+                    resp.map(|_| json!({})).map_err(anyhow::Error::from),
+                );
+                if created {
+                    self.send_rpc_notification(
+                        CoreNotification::WorkspaceFileChange {
+                            created: vec![path],
+                        },
+                    );
+                }
+            }
+            TrashPath {
+                path,
+                progress_token,
+            } => {
+                let local_dispatcher = self.clone();
+                thread::spawn(move || {
+                    // Trashing is a single operation, so it can only be shown as
+                    // happening, not counted or cancelled
+                    let progress = progress_token.map(|token| {
+                        FsProgress::begin(
+                            &local_dispatcher,
+                            token,
+                            "Moving to Trash",
+                            None,
+                        )
+                    });
+                    // Without a desktop trash service, as is common on headless
+                    // machines, fall back to the trash folder of the workspace
+                    let workspace = local_dispatcher.workspace.lock().clone();
+                    let location = match trash::delete(&path) {
+                        Ok(()) => Ok(TrashLocation::Os),
+                        Err(err) => match workspace {
+                            Some(workspace) => {
+                                log::warn!(
+                                    "Failed to move {:?} to the OS trash, moving it to the workspace trash: {}",
+                                    path,
+                                    err
+                                );
+                                WorkspaceTrash::new(&workspace)
+                                    .trash(&path)
+                                    .map(|_| TrashLocation::Workspace)
+                            }
+                            None => Err(anyhow::Error::from(err)),
+                        },
+                    };
+                    if let Some(progress) = progress {
+                        progress.end();
+                    }
+                    if let Err(err) = &location {
+                        local_dispatcher.send_file_operation_error(
+                            FileOperation::Trash,
+                            &path,
+                            err,
+                        );
+                    }
+                    local_dispatcher.respond_rpc(
+                        id,
+                        location.map(|location| TrashPathResponse { location }),
+                    );
+                });
+            }
+            DeletePath { path } => {
+                let local_dispatcher = self.clone();
+                thread::spawn(move || {
+                    let resp = if path.is_dir() && !path.is_symlink() {
+                        fs::remove_dir_all(&path)
+                    } else {
+                        fs::remove_file(&path)
+                    }
                     .map_err(anyhow::Error::from);
+                    if let Err(err) = &resp {
+                        local_dispatcher.send_file_operation_error(
+                            FileOperation::Delete,
+                            &path,
+                            err,
+                        );
+                    }
+                    local_dispatcher.respond(id, resp.map(|_| json!({})));
+                });
+            }
+            GetWorkspaceTrash {} => {
+                if let Some(workspace) = self.workspace.lock().clone() {
+                    let resp = WorkspaceTrash::new(&workspace)
+                        .entries()
+                        .map(|entries| WorkspaceTrashResponse { entries });
+                    self.respond_rpc(id, resp);
+                } else {
+                    self.respond(id, Err(anyhow!("no workspace")));
+                }
+            }
+            RestoreFromTrash { id: entry_id } => {
+                if let Some(workspace) = self.workspace.lock().clone() {
+                    let resp = WorkspaceTrash::new(&workspace)
+                        .restore(&entry_id)
+                        .map(|path| RestoreFromTrashResponse { path });
+                    self.respond_rpc(id, resp);
+                } else {
+                    self.respond(id, Err(anyhow!("no workspace")));
+                }
+            }
+            GitStage { paths } => {
+                if let Some(workspace) = self.workspace.lock().clone() {
+                    let resp = git_stage(&workspace, &paths).map(|_| json!({}));
+                    self.respond(id, resp);
+                    self.send_diff_info(&workspace);
+                } else {
+                    self.respond(id, Err(anyhow!("no workspace")));
+                }
+            }
+            GitUnstage { paths } => {
+                if let Some(workspace) = self.workspace.lock().clone() {
+                    let resp = git_unstage(&workspace, &paths).map(|_| json!({}));
+                    self.respond(id, resp);
+                    self.send_diff_info(&workspace);
+                } else {
+                    self.respond(id, Err(anyhow!("no workspace")));
+                }
+            }
+            SetGitIgnored { path, ignored } => {
+                let resp = set_git_ignored(&path, ignored).map(|_| json!({}));
                 self.respond(id, resp);
+                if let Some(workspace) = self.workspace.lock().clone() {
+                    self.send_diff_info(&workspace);
+                }
             }
             RenamePath { from, to } => {
                 // We first check if the destination already exists, because rename can overwrite it
                 // and that's not the default behavior we want for when a user renames a document.
+                let operation = if from.parent() == to.parent() {
+                    FileOperation::Rename
+                } else {
+                    FileOperation::Move
+                };
                 if to.exists() {
-                    self.respond(id, Err(anyhow!("{:?} already exists", to)));
+                    let err = already_exists(&to);
+                    self.send_file_operation_error(operation, &to, &err);
+                    self.respond(id, Err(err));
                 } else {
-                    let resp = std::fs::rename(from, to)
+                    // Renaming can also move the path into a directory that doesn't exist yet
+                    let resp = to
+                        .parent()
+                        .map_or(Ok(()), std::fs::create_dir_all)
+                        .and_then(|_| std::fs::rename(&from, &to))
                         .map(|_| json!({}))
                         .map_err(anyhow::Error::from);
+                    match &resp {
+                        Ok(_) => self.rename_open_files(&from, &to),
+                        Err(err) => {
+                            self.send_file_operation_error(operation, &from, err)
+                        }
+                    }
                     self.respond(id, resp);
                 }
             }
+            ReadPathsMetadata { paths } => {
+                let local_dispatcher = self.clone();
+                thread::spawn(move || {
+                    // The rows being shown mostly share a few directories, so
+                    // the repository is only looked up once for each of them
+                    let mut repos = HashMap::new();
+                    let metadata = paths
+                        .into_iter()
+                        .filter_map(|path| {
+                            let metadata = fs::symlink_metadata(&path).ok()?;
+                            let parent = path.parent().unwrap_or(&path);
+                            let repo =
+                                repos.entry(parent.to_path_buf()).or_insert_with(
+                                    || Repository::discover(parent).ok(),
+                                );
+                            let metadata = PathMetadata {
+                                size: metadata.len(),
+                                modified: metadata.modified().ok(),
+                                readonly: metadata.permissions().readonly(),
+                                git_ignored: repo.as_ref().map_or(false, |repo| {
+                                    is_git_ignored(repo, &path)
+                                }),
+                            };
+                            Some((path, metadata))
+                        })
+                        .collect();
+                    local_dispatcher
+                        .respond_rpc(id, Ok(PathsMetadataResponse { metadata }));
+                });
+            }
+            IsPathWritable { path } => {
+                // A path that doesn't exist yet is writable as long as we can
+                // create it, so we only report read-only for existing files
+                let writable = fs::metadata(&path)
+                    .map(|metadata| !metadata.permissions().readonly())
+                    .unwrap_or(true);
+                self.respond_rpc(id, Ok(PathWritableResponse { writable }));
+            }
+            IsCaseSensitive { path } => {
+                // Renaming directly is the safe choice when we can't tell
+                let case_sensitive = is_case_sensitive(&path).unwrap_or(true);
+                self.respond_rpc(id, Ok(CaseSensitiveResponse { case_sensitive }));
+            }
+            ReadFile { path } => {
+                let resp = fs::read_to_string(&path)
+                    .map(|content| ReadFileResponse { content })
+                    .map_err(anyhow::Error::from);
+                self.respond_rpc(id, resp);
+            }
+            DuplicatePath {
+                path,
+                progress_token,
+            } => {
+                let local_dispatcher = self.clone();
+                thread::spawn(move || {
+                    let to = duplicate_path_name(&path);
+                    let progress = progress_token.map(|token| {
+                        FsProgress::begin(
+                            &local_dispatcher,
+                            token,
+                            "Copying",
+                            Some(count_files(&path)),
+                        )
+                    });
+                    let resp = copy_path(&path, &to, progress.as_ref());
+                    if resp.is_err() && to.exists() {
+                        // Don't leave a partial copy behind, such as when it was
+                        // cancelled
+                        let _ = if to.is_dir() {
+                            fs::remove_dir_all(&to)
+                        } else {
+                            fs::remove_file(&to)
+                        };
+                    }
+                    if let Some(progress) = progress {
+                        progress.end();
+                    }
+                    let resp = resp
+                        .map(|_| DuplicatePathResponse { path: to })
+                        .map_err(anyhow::Error::from);
+                    if let Err(err) = &resp {
+                        local_dispatcher.send_file_operation_error(
+                            FileOperation::Duplicate,
+                            &path,
+                            err,
+                        );
+                    }
+                    local_dispatcher.respond_rpc(id, resp);
+                });
+            }
             GlobalSearch { pattern } => {
                 if let Some(workspace) = self.workspace.lock().clone() {
                     let local_dispatcher = self.clone();
@@ -848,6 +1686,448 @@ pub struct DiffHunk {
     pub header: String,
 }
 
+/// The error of creating or renaming to a path that is already taken
+fn already_exists(path: &Path) -> anyhow::Error {
+    anyhow::Error::from(std::io::Error::new(
+        std::io::ErrorKind::AlreadyExists,
+        format!("{:?} already exists", path),
+    ))
+}
+
+/// Create the file with the content, specifically choosing to error if it
+/// already exists
+fn create_new_file(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    std::io::Write::write_all(&mut file, content)
+}
+
+/// Create the empty file and its missing parent directories, unless it exists.
+/// Returns whether it was created.
+fn ensure_file(path: &Path) -> std::io::Result<bool> {
+    if path.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match create_new_file(path, &[]) {
+        Ok(()) => Ok(true),
+        // Created by someone else in the meantime
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Check whether the file system of the existing path tells apart names that
+/// only differ in case, by looking the path up under another case of its name
+fn is_case_sensitive(path: &Path) -> std::io::Result<bool> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let swapped: String = name
+        .chars()
+        .flat_map(|c| {
+            if c.is_uppercase() {
+                c.to_lowercase().collect::<Vec<_>>()
+            } else {
+                c.to_uppercase().collect()
+            }
+        })
+        .collect();
+    if swapped == name {
+        // There is no other case of the name to look the file up by
+        return Ok(true);
+    }
+    let metadata = fs::metadata(path)?;
+    match fs::metadata(path.with_file_name(swapped)) {
+        Ok(other) => Ok(!is_same_file(&metadata, &other)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+/// Without inode numbers to compare, finding the file by another case of its
+/// name is taken to mean it's the same file
+#[cfg(not(unix))]
+fn is_same_file(_a: &fs::Metadata, _b: &fs::Metadata) -> bool {
+    true
+}
+
+/// Build the set of exclude patterns, leaving out the invalid ones
+fn build_file_exclude(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        match Glob::new(pattern) {
+            Ok(glob) => {
+                builder.add(glob);
+            }
+            Err(e) => eprintln!("invalid exclude pattern {pattern}: {e}"),
+        }
+    }
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
+/// Whether the path is excluded, by either its name or its whole path, so
+/// that `node_modules` and `**/build/*.o` both work. The trash folder of the
+/// workspace is always excluded.
+pub(crate) fn is_excluded(exclude: &GlobSet, path: &Path) -> bool {
+    let name = path.file_name();
+    name.map_or(false, |name| name == WORKSPACE_TRASH_DIR)
+        || (!exclude.is_empty()
+            && (name.map_or(false, |name| exclude.is_match(name))
+                || exclude.is_match(path)))
+}
+
+/// Read the children of the directory and of the directories beneath it, down
+/// to `depth` levels, until `remaining` entries have been read. Returns whether
+/// it ran out of entries before it was done.
+fn read_dir_tree(
+    item: &mut FileNodeItem,
+    depth: usize,
+    is_excluded: &dyn Fn(&Path) -> bool,
+    remaining: &mut usize,
+) -> bool {
+    if depth == 0 || !item.is_dir || item.is_symlink_loop() {
+        return false;
+    }
+    let entries = match fs::read_dir(&item.path_buf) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+    for entry in entries.flatten() {
+        if is_excluded(&entry.path()) {
+            continue;
+        }
+        if *remaining == 0 {
+            return true;
+        }
+        *remaining -= 1;
+        let mut child = read_dir_entry(&entry, true);
+        let truncated = read_dir_tree(&mut child, depth - 1, is_excluded, remaining);
+        item.children.insert(child.path_buf.clone(), child);
+        if truncated {
+            return true;
+        }
+    }
+    item.read = true;
+    false
+}
+
+fn read_dir_entry(entry: &fs::DirEntry, with_metadata: bool) -> FileNodeItem {
+    let path = entry.path();
+    let is_dir = path.is_dir();
+    let mut item = FileNodeItem::new(path, is_dir);
+    // The entry's own file type doesn't follow symbolic links
+    if entry
+        .file_type()
+        .map_or(false, |file_type| file_type.is_symlink())
+    {
+        item.is_symlink = true;
+        item.symlink_target = fs::canonicalize(&item.path_buf).ok();
+    }
+    if with_metadata {
+        if let Ok(metadata) = entry.metadata() {
+            item.size = metadata.len();
+            item.modified = metadata.modified().ok();
+            item.readonly = metadata.permissions().readonly();
+            item.metadata_read = true;
+        }
+    }
+    item
+}
+
+/// Find an unused name for a copy of the path, in the form of
+/// `foo copy.rs`, `foo copy 2.rs`, ...
+fn duplicate_path_name(path: &Path) -> PathBuf {
+    let (stem, extension) = if path.is_dir() {
+        (path.file_name(), None)
+    } else {
+        (path.file_stem(), path.extension())
+    };
+    let stem = stem
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = extension
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut i = 1;
+    loop {
+        let name = if i == 1 {
+            format!("{stem} copy{extension}")
+        } else {
+            format!("{stem} copy {i}{extension}")
+        };
+        let to = path.with_file_name(name);
+        if !to.exists() {
+            return to;
+        }
+        i += 1;
+    }
+}
+
+/// Copy the file, or the directory along with all of its contents.
+/// Symbolic links are copied as links instead of being followed, so that a
+/// link to one of its own ancestors doesn't copy it over and over.
+/// Each copied file is counted in the progress, which stops the copy with an
+/// `Interrupted` error once it is cancelled.
+pub(crate) fn copy_path(
+    from: &Path,
+    to: &Path,
+    progress: Option<&FsProgress>,
+) -> std::io::Result<()> {
+    let file_type = fs::symlink_metadata(from)?.file_type();
+    if file_type.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()), progress)?;
+        }
+        Ok(())
+    } else {
+        if progress.map_or(false, FsProgress::is_cancelled) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "cancelled",
+            ));
+        }
+        if file_type.is_symlink() {
+            copy_symlink(from, to)?;
+        } else {
+            fs::copy(from, to)?;
+        }
+        if let Some(progress) = progress {
+            progress.file_done();
+        }
+        Ok(())
+    }
+}
+
+/// Make a symbolic link at `to` pointing where the one at `from` does
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+/// Make a symbolic link at `to` pointing where the one at `from` does. Links
+/// to directories are a different kind of link on Windows.
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> std::io::Result<()> {
+    let target = fs::read_link(from)?;
+    // A relative target is relative to the directory the link is in
+    let is_dir = from
+        .parent()
+        .map_or(false, |parent| parent.join(&target).is_dir());
+    if is_dir {
+        std::os::windows::fs::symlink_dir(target, to)
+    } else {
+        std::os::windows::fs::symlink_file(target, to)
+    }
+}
+
+/// The number of files in the directory and all of its subdirectories, or 1
+/// for a file. Symbolic links count as a file, like they are copied.
+fn count_files(path: &Path) -> usize {
+    if !fs::symlink_metadata(path).map_or(false, |metadata| metadata.is_dir()) {
+        return 1;
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| {
+                    // The entry's own file type doesn't follow symbolic links
+                    if entry.file_type().map_or(false, |t| t.is_dir()) {
+                        count_files(&entry.path())
+                    } else {
+                        1
+                    }
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Reports the progress of a filesystem operation to the UI as
+/// `WorkDoneProgress`, where it can be cancelled if the files are counted
+pub(crate) struct FsProgress {
+    dispatcher: Dispatcher,
+    token: String,
+    title: &'static str,
+    /// The number of files the operation will go through, if it is known
+    total: Option<usize>,
+    done: AtomicUsize,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl FsProgress {
+    fn begin(
+        dispatcher: &Dispatcher,
+        token: String,
+        title: &'static str,
+        total: Option<usize>,
+    ) -> Self {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        dispatcher
+            .progress_cancels
+            .lock()
+            .insert(token.clone(), cancelled.clone());
+        let progress = Self {
+            dispatcher: dispatcher.clone(),
+            token,
+            title,
+            total,
+            done: AtomicUsize::new(0),
+            cancelled,
+        };
+        progress.send(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: title.to_string(),
+            cancellable: Some(total.is_some()),
+            message: progress.message(0),
+            percentage: total.map(|_| 0),
+        }));
+        progress
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn file_done(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        let total = match self.total {
+            Some(total) => total,
+            None => return,
+        };
+        // Reporting every file would flood the UI for large directories
+        if done % 10 == 0 || done == total {
+            self.send(WorkDoneProgress::Report(WorkDoneProgressReport {
+                cancellable: Some(true),
+                message: self.message(done),
+                percentage: Some((done * 100 / total.max(1)).min(100) as u32),
+            }));
+        }
+    }
+
+    fn message(&self, done: usize) -> Option<String> {
+        self.total.map(|total| format!("{done}/{total} files"))
+    }
+
+    fn end(self) {
+        self.dispatcher.progress_cancels.lock().remove(&self.token);
+        self.send(WorkDoneProgress::End(WorkDoneProgressEnd { message: None }));
+    }
+
+    fn send(&self, progress: WorkDoneProgress) {
+        self.dispatcher.send_notification(
+            "work_done_progress",
+            json!({
+                "progress": ProgressParams {
+                    token: ProgressToken::String(self.token.clone()),
+                    value: ProgressParamsValue::WorkDone(progress),
+                },
+            }),
+        );
+    }
+}
+
+/// Show the path in the platform's file manager.
+/// Files are selected in their folder where the file manager supports it.
+fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = std::process::Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    };
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut arg = std::ffi::OsString::from("/select,");
+        arg.push(path);
+        let mut command = std::process::Command::new("explorer");
+        command.arg(arg);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = {
+        // xdg-open has no way of selecting a file, so open its folder instead
+        let dir = if path.is_dir() {
+            path
+        } else {
+            path.parent().unwrap_or(path)
+        };
+        let mut command = std::process::Command::new("xdg-open");
+        command.arg(dir);
+        command
+    };
+    spawn_detached(&mut command)
+}
+
+/// Open the platform's terminal emulator with the directory as its working directory
+fn open_in_system_terminal(dir: &Path) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = std::process::Command::new("open");
+        command.arg("-a").arg("Terminal").arg(dir);
+        command
+    };
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", "cmd"]).current_dir(dir);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = {
+        let terminal = std::env::var("TERMINAL")
+            .unwrap_or_else(|_| "x-terminal-emulator".to_string());
+        let mut command = std::process::Command::new(terminal);
+        command.current_dir(dir);
+        command
+    };
+    spawn_detached(&mut command)
+}
+
+/// Open the file with the application the platform associates with its type
+fn open_with_default_application(path: &Path) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = std::process::Command::new("open");
+        command.arg(path);
+        command
+    };
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        // The empty argument is the title of the window `start` would open
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]).arg(path);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = {
+        let mut command = std::process::Command::new("xdg-open");
+        command.arg(path);
+        command
+    };
+    spawn_detached(&mut command)
+}
+
+/// Spawn the command without waiting for it to finish, reaping it in the background
+fn spawn_detached(command: &mut std::process::Command) -> Result<()> {
+    let mut child = command.spawn()?;
+    thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
 fn git_init(workspace_path: &Path) -> Result<()> {
     Repository::init(workspace_path)?;
     Ok(())
@@ -936,6 +2216,145 @@ fn git_discard_files_changes<'a>(
     Ok(())
 }
 
+fn git_stage(workspace_path: &Path, paths: &[PathBuf]) -> Result<()> {
+    let repo = Repository::open(workspace_path)?;
+    let mut index = repo.index()?;
+    for path in paths {
+        let relative = path.strip_prefix(workspace_path)?;
+        // A deleted file is staged by taking it out of the index
+        if path.exists() {
+            index.add_path(relative)?;
+        } else {
+            index.remove_path(relative)?;
+        }
+    }
+    index.write()?;
+    Ok(())
+}
+
+/// The total size of the files beneath the directory, or `None` if it was
+/// cancelled. Files ignored by git are counted, since they take up the space
+/// all the same.
+fn dir_size(path: &Path, exclude: GlobSet, cancelled: &AtomicBool) -> Option<u64> {
+    let walk = ignore::WalkBuilder::new(path)
+        .standard_filters(false)
+        .filter_entry(move |entry| !is_excluded(&exclude, entry.path()))
+        .build();
+    let mut size = 0;
+    for entry in walk.flatten() {
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        if entry.file_type().map_or(false, |t| t.is_file()) {
+            size += entry.metadata().map_or(0, |metadata| metadata.len());
+        }
+    }
+    Some(size)
+}
+
+/// Whether the path in the working directory of the repository is ignored
+fn is_git_ignored(repo: &Repository, path: &Path) -> bool {
+    repo.workdir()
+        .and_then(|workdir| path.strip_prefix(workdir).ok())
+        .map_or(false, |relative| {
+            repo.is_path_ignored(relative).unwrap_or(false)
+        })
+}
+
+/// Add the pattern of the path to the nearest `.gitignore` in the directories
+/// above it, or to the one at the root of the repository when there is none,
+/// or take it out of it
+fn set_git_ignored(path: &Path, ignored: bool) -> Result<()> {
+    let repo = Repository::discover(path)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("the repository has no working directory"))?
+        .to_path_buf();
+    let dir = path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(&workdir))
+        .find(|dir| dir.join(".gitignore").is_file())
+        .unwrap_or(&workdir);
+    let ignore_file = dir.join(".gitignore");
+
+    // Anchored to the directory of the .gitignore, so that only this path
+    // matches
+    let relative = path.strip_prefix(dir)?;
+    let mut pattern = format!("/{}", relative.to_string_lossy().replace('\\', "/"));
+    if path.is_dir() {
+        pattern.push('/');
+    }
+
+    let content = match fs::read_to_string(&ignore_file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let is_pattern = |line: &str| {
+        let line = line.trim();
+        line == pattern
+            || line == pattern.trim_start_matches('/')
+            || line == pattern.trim_end_matches('/')
+            || line == pattern.trim_matches('/')
+    };
+    if ignored {
+        if content.lines().any(is_pattern) {
+            return Ok(());
+        }
+        let mut content = content;
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&pattern);
+        content.push('\n');
+        fs::write(&ignore_file, content)?;
+    } else {
+        if content.lines().any(is_pattern) {
+            let mut kept: String = content
+                .lines()
+                .filter(|line| !is_pattern(line))
+                .collect::<Vec<_>>()
+                .join("\n");
+            if !kept.is_empty() {
+                kept.push('\n');
+            }
+            fs::write(&ignore_file, kept)?;
+        }
+        if is_git_ignored(&repo, path) {
+            return Err(anyhow!(
+                "It is still ignored by another pattern, like a wildcard or one \
+                 of a directory above it."
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn git_unstage(workspace_path: &Path, paths: &[PathBuf]) -> Result<()> {
+    let repo = Repository::open(workspace_path)?;
+    let relative = paths
+        .iter()
+        .map(|path| path.strip_prefix(workspace_path))
+        .collect::<Result<Vec<_>, _>>()?;
+    match repo
+        .head()
+        .and_then(|head| head.peel(git2::ObjectType::Commit))
+    {
+        Ok(head) => repo.reset_default(Some(&head), relative)?,
+        // Before the first commit, there's nothing to reset the files to, so
+        // they're taken out of the index
+        Err(_) => {
+            let mut index = repo.index()?;
+            for path in relative {
+                index.remove_path(path)?;
+            }
+            index.write()?;
+        }
+    }
+    Ok(())
+}
+
 fn git_discard_workspace_changes(workspace_path: &Path) -> Result<()> {
     let repo = Repository::open(workspace_path)?;
     let mut checkout_b = CheckoutBuilder::new();
@@ -970,7 +2389,9 @@ fn git_delta_format(
     }
 }
 
-fn git_diff_new(workspace_path: &Path) -> Option<DiffInfo> {
+/// The git status of the workspace, along with the changed files split into
+/// the staged and unstaged ones
+fn git_diff_new(workspace_path: &Path) -> Option<(DiffInfo, Vec<ChangedFile>)> {
     let repo = Repository::open(workspace_path.to_str()?).ok()?;
     let head = repo.head().ok()?;
     let name = head.shorthand()?.to_string();
@@ -985,11 +2406,17 @@ fn git_diff_new(workspace_path: &Path) -> Option<DiffInfo> {
     let diff = repo
         .diff_index_to_workdir(None, Some(diff_options.include_untracked(true)))
         .ok()?;
+    // The trash folder of the workspace is untracked without being a change
+    let trash_dir = workspace_path.join(WORKSPACE_TRASH_DIR);
     for delta in diff.deltas() {
         if let Some(delta) = git_delta_format(workspace_path, &delta) {
-            deltas.push(delta);
+            if !delta.2.starts_with(&trash_dir) {
+                deltas.push(delta);
+            }
         }
     }
+    // The deltas from here on are the ones in the index
+    let staged_start = deltas.len();
     let cached_diff = repo
         .diff_tree_to_index(
             repo.find_tree(repo.revparse_single("HEAD^{tree}").ok()?.id())
@@ -1021,11 +2448,14 @@ fn git_diff_new(workspace_path: &Path) -> Option<DiffInfo> {
     }
 
     let mut file_diffs = Vec::new();
+    let mut changed_files = Vec::new();
     for (i, j) in renames.iter() {
-        file_diffs.push(FileDiff::Renamed(
-            deltas[*i].2.clone(),
-            deltas[*j].2.clone(),
-        ));
+        let diff = FileDiff::Renamed(deltas[*i].2.clone(), deltas[*j].2.clone());
+        changed_files.push(ChangedFile {
+            diff: diff.clone(),
+            staged: *i >= staged_start && *j >= staged_start,
+        });
+        file_diffs.push(diff);
     }
     for (i, delta) in deltas.iter().enumerate() {
         if renamed_deltas.contains(&i) {
@@ -1037,6 +2467,10 @@ fn git_diff_new(workspace_path: &Path) -> Option<DiffInfo> {
             git2::Delta::Modified => FileDiff::Modified(delta.2.clone()),
             _ => continue,
         };
+        changed_files.push(ChangedFile {
+            diff: diff.clone(),
+            staged: i >= staged_start,
+        });
         file_diffs.push(diff);
     }
     file_diffs.sort_by_key(|d| match d {
@@ -1045,11 +2479,14 @@ fn git_diff_new(workspace_path: &Path) -> Option<DiffInfo> {
         | FileDiff::Renamed(p, _)
         | FileDiff::Deleted(p) => p.clone(),
     });
-    Some(DiffInfo {
-        head: name,
-        branches,
-        diffs: file_diffs,
-    })
+    Some((
+        DiffInfo {
+            head: name,
+            branches,
+            diffs: file_diffs,
+        },
+        changed_files,
+    ))
 }
 
 fn file_get_head(workspace_path: &Path, path: &Path) -> Result<(String, String)> {
@@ -0,0 +1,153 @@
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use globset::GlobSet;
+use lapce_rpc::proxy::FileMatch;
+
+use crate::dispatch::is_excluded;
+
+/// The files of the workspace, for finding them by name without walking the
+/// workspace every time. Ignored and excluded files are left out, the same
+/// as with `GetFiles`.
+pub struct FileIndex {
+    root: PathBuf,
+    exclude: GlobSet,
+    /// The indexed files, sorted so that the files beneath a directory are
+    /// next to each other
+    files: BTreeSet<PathBuf>,
+    /// The directories that aren't ignored, which files created in them
+    /// are added to the index for
+    dirs: HashSet<PathBuf>,
+}
+
+impl FileIndex {
+    /// Index all the files beneath the root
+    pub fn new(root: PathBuf, exclude: GlobSet) -> Self {
+        let mut index = Self {
+            root: root.clone(),
+            exclude,
+            files: BTreeSet::new(),
+            dirs: HashSet::new(),
+        };
+        index.add_tree(&root);
+        index
+    }
+
+    /// Bring the paths up to date with the filesystem after they were created,
+    /// removed or renamed. A directory is updated along with everything
+    /// beneath it.
+    pub fn update(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            if !path.starts_with(&self.root) || path == &self.root {
+                continue;
+            }
+            self.remove_tree(path);
+            if self.is_indexed(path) {
+                self.add_tree(path);
+            }
+        }
+    }
+
+    /// The files whose path relative to the root fuzzily matches the query,
+    /// best matches first, or the first files by path for an empty query
+    pub fn query(&self, query: &str, limit: usize) -> Vec<FileMatch> {
+        let relative_files = self.files.iter().filter_map(|path| {
+            let relative_path = path
+                .strip_prefix(&self.root)
+                .ok()?
+                .to_string_lossy()
+                .to_string();
+            Some((path, relative_path))
+        });
+
+        if query.is_empty() {
+            return relative_files
+                .take(limit)
+                .map(|(path, relative_path)| FileMatch {
+                    path: path.clone(),
+                    relative_path,
+                    score: 0,
+                    indices: Vec::new(),
+                })
+                .collect();
+        }
+
+        let matcher = SkimMatcherV2::default().ignore_case();
+        let mut matches: Vec<FileMatch> = relative_files
+            .filter_map(|(path, relative_path)| {
+                let (score, indices) =
+                    matcher.fuzzy_indices(&relative_path, query)?;
+                Some(FileMatch {
+                    path: path.clone(),
+                    relative_path,
+                    score,
+                    indices,
+                })
+            })
+            .collect();
+        // Of equally good matches, the shorter paths are usually what's meant
+        matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.relative_path.len().cmp(&b.relative_path.len()))
+        });
+        matches.truncate(limit);
+        matches
+    }
+
+    /// Whether the path exists and is listed in its directory, which isn't
+    /// the case when it or its directory is ignored or excluded
+    fn is_indexed(&self, path: &Path) -> bool {
+        let parent = match path.parent() {
+            Some(parent) => parent,
+            None => return false,
+        };
+        if parent != self.root && !self.dirs.contains(parent) {
+            return false;
+        }
+        let exclude = self.exclude.clone();
+        ignore::WalkBuilder::new(parent)
+            .max_depth(Some(1))
+            .filter_entry(move |entry| !is_excluded(&exclude, entry.path()))
+            .build()
+            .flatten()
+            .any(|entry| entry.depth() == 1 && entry.path() == path)
+    }
+
+    /// Add the path, and the files and directories beneath it if it is a
+    /// directory
+    fn add_tree(&mut self, path: &Path) {
+        let exclude = self.exclude.clone();
+        // Excluded directories are skipped without being read
+        let walk = ignore::WalkBuilder::new(path)
+            .filter_entry(move |entry| !is_excluded(&exclude, entry.path()))
+            .build();
+        for entry in walk.flatten() {
+            match entry.file_type() {
+                Some(file_type) if file_type.is_dir() => {
+                    self.dirs.insert(entry.into_path());
+                }
+                Some(file_type) if file_type.is_file() => {
+                    self.files.insert(entry.into_path());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Remove the path, and everything beneath it
+    fn remove_tree(&mut self, path: &Path) {
+        let beneath: Vec<PathBuf> = self
+            .files
+            .range(path.to_path_buf()..)
+            .take_while(|file| file.starts_with(path))
+            .cloned()
+            .collect();
+        for file in beneath {
+            self.files.remove(&file);
+        }
+        self.dirs.retain(|dir| !dir.starts_with(path));
+    }
+}
@@ -1,9 +1,11 @@
 pub mod buffer;
 pub mod dispatch;
+pub mod file_index;
 pub mod lsp;
 pub mod plugin;
 pub mod terminal;
 pub mod watcher;
+pub mod workspace_trash;
 
 use dispatch::Dispatcher;
 
@@ -6,7 +6,7 @@ use std::{
     path::{Path, PathBuf},
     process::{self, Child, ChildStderr, ChildStdout, Command, Stdio},
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         mpsc::channel,
         Arc,
     },
@@ -19,6 +19,7 @@ use jsonrpc_lite::{Id, JsonRpc, Params};
 use lapce_core::encoding::offset_utf16_to_utf8;
 use lapce_rpc::{
     buffer::BufferId,
+    core::{CoreNotification, LanguageServerState},
     style::{LineStyle, SemanticStyles, Style},
     RequestId,
 };
@@ -33,6 +34,10 @@ pub type Callback = Box<dyn Callable>;
 const HEADER_CONTENT_LENGTH: &str = "content-length";
 const HEADER_CONTENT_TYPE: &str = "content-type";
 
+/// How many times a crashed language server is started again before it's
+/// left stopped and reported as crashed
+const MAX_RESTARTS: usize = 3;
+
 pub trait Callable: Send {
     fn call(self: Box<Self>, client: &LspClient, result: Result<Value>);
 }
@@ -91,12 +96,14 @@ pub struct DidSaveCapability {
 
 #[derive(Clone)]
 pub struct LspClient {
+    language_id: String,
     exec_path: String,
     args: Vec<String>,
     options: Option<Value>,
     state: Arc<Mutex<LspState>>,
     dispatcher: Dispatcher,
     active: Arc<AtomicBool>,
+    restarts: Arc<AtomicUsize>,
 }
 
 impl LspCatalog {
@@ -121,6 +128,25 @@ impl LspCatalog {
         }
     }
 
+    /// Stop the language server of the language and start a new one with
+    /// the same binary and options, which reopens the documents as they're
+    /// used again and allows it to be restarted after crashes again
+    pub fn restart_language_server(&mut self, language_id: &str) {
+        let client = match self.clients.remove(language_id) {
+            Some(client) => client,
+            None => return,
+        };
+        client.stop();
+        let client = LspClient::new(
+            language_id.to_string(),
+            &client.exec_path,
+            client.options.clone(),
+            client.args.clone(),
+            self.dispatcher.clone().unwrap(),
+        );
+        self.clients.insert(language_id.to_string(), client);
+    }
+
     pub fn start_server(
         &mut self,
         exec_path: &str,
@@ -594,6 +620,118 @@ impl LspCatalog {
         }
     }
 
+    pub fn prepare_rename(
+        &self,
+        id: RequestId,
+        buffer: &Buffer,
+        position: Position,
+    ) {
+        let client = match self.rename_client(id, buffer) {
+            Some(client) => client,
+            None => return,
+        };
+        let prepare_support = matches!(
+            client
+                .state
+                .lock()
+                .server_capabilities
+                .as_ref()
+                .and_then(|cap| cap.rename_provider.as_ref()),
+            Some(OneOf::Right(RenameOptions {
+                prepare_provider: Some(true),
+                ..
+            }))
+        );
+
+        // Servers that can rename but not check it first leave it to the
+        // rename itself to fail, starting from the word at the position
+        if !prepare_support {
+            let _ = client.dispatcher.sender.send(json!({
+                "id": id,
+                "result": PrepareRenameResponse::DefaultBehavior {
+                    default_behavior: true,
+                },
+            }));
+            return;
+        }
+
+        let uri = client.get_uri(buffer);
+        client.request_prepare_rename(uri, position, move |lsp_client, result| {
+            let mut resp = json!({ "id": id });
+            match result {
+                Ok(v) => resp["result"] = v,
+                Err(e) => {
+                    resp["error"] = json!({
+                        "code": 0,
+                        "message": format!("{}", e),
+                    })
+                }
+            }
+            let _ = lsp_client.dispatcher.sender.send(resp);
+        });
+    }
+
+    pub fn rename(
+        &self,
+        id: RequestId,
+        buffer: &Buffer,
+        position: Position,
+        new_name: String,
+    ) {
+        let client = match self.rename_client(id, buffer) {
+            Some(client) => client,
+            None => return,
+        };
+
+        let uri = client.get_uri(buffer);
+        client.request_rename(uri, position, new_name, move |lsp_client, result| {
+            let mut resp = json!({ "id": id });
+            match result {
+                Ok(v) => resp["result"] = v,
+                Err(e) => {
+                    resp["error"] = json!({
+                        "code": 0,
+                        "message": format!("{}", e),
+                    })
+                }
+            }
+            let _ = lsp_client.dispatcher.sender.send(resp);
+        });
+    }
+
+    /// The client of the language server of the buffer, if it can rename
+    /// symbols. Otherwise the request is answered with why it can't, so that
+    /// the rename doesn't wait for an answer that never comes.
+    fn rename_client(
+        &self,
+        id: RequestId,
+        buffer: &Buffer,
+    ) -> Option<&Arc<LspClient>> {
+        let error = match self.clients.get(&buffer.language_id) {
+            Some(client) => {
+                let state = client.state.lock();
+                let is_enabled = state
+                    .server_capabilities
+                    .as_ref()
+                    .and_then(|cap| cap.rename_provider.as_ref())
+                    .map(|prov| prov != &OneOf::Left(false))
+                    .unwrap_or(false);
+                if !state.is_initialized {
+                    "The language server hasn't started yet"
+                } else if !is_enabled {
+                    "The language server can't rename symbols"
+                } else {
+                    return Some(client);
+                }
+            }
+            None => "There is no language server for the file",
+        };
+        if let Some(dispatcher) = self.dispatcher.as_ref() {
+            dispatcher.respond_rpc::<Value>(id, Err(anyhow!(error)));
+        }
+        None
+    }
+
     pub fn get_inlay_hints(&self, id: RequestId, buffer: &Buffer) {
         if let Some(client) = self.clients.get(&buffer.language_id) {
             {
@@ -815,6 +953,7 @@ impl LspClient {
 
         let lsp_client = Arc::new(LspClient {
             dispatcher,
+            language_id: language_id.clone(),
             exec_path: exec_path.to_string(),
             args,
             options,
@@ -829,8 +968,10 @@ impl LspClient {
                 did_save_capabilities: Vec::new(),
             })),
             active: Arc::new(AtomicBool::new(true)),
+            restarts: Arc::new(AtomicUsize::new(0)),
         });
 
+        lsp_client.send_status(LanguageServerState::Starting);
         lsp_client.handle_stdout(stdout);
         lsp_client.handle_stderr(stderr, language_id);
         lsp_client.initialize();
@@ -852,7 +993,16 @@ impl LspClient {
                             return;
                         }
                         local_lsp_client.stop();
-                        local_lsp_client.reload();
+                        // It's left stopped after a few restarts rather than
+                        // crashing over and over
+                        let restarts =
+                            local_lsp_client.restarts.fetch_add(1, Ordering::AcqRel);
+                        if restarts < MAX_RESTARTS {
+                            local_lsp_client.reload();
+                        } else {
+                            local_lsp_client
+                                .send_status(LanguageServerState::Crashed);
+                        }
                         return;
                     }
                 };
@@ -914,20 +1064,36 @@ impl LspClient {
         );
         let writer = Box::new(BufWriter::new(process.stdin.take().unwrap()));
         let stdout = process.stdout.take().unwrap();
+        let stderr = process.stderr.take().unwrap();
 
-        let mut state = self.state.lock();
-        state.next_id = 0;
-        state.pending.clear();
-        state.opened_documents.clear();
-        state.server_capabilities = None;
-        state.is_initialized = false;
-        state.writer = writer;
-        state.process = process;
+        {
+            let mut state = self.state.lock();
+            state.next_id = 0;
+            state.pending.clear();
+            state.opened_documents.clear();
+            state.server_capabilities = None;
+            state.is_initialized = false;
+            state.did_save_capabilities.clear();
+            state.writer = writer;
+            state.process = process;
+        }
+        self.active.store(true, Ordering::Release);
 
+        self.send_status(LanguageServerState::Starting);
         self.handle_stdout(stdout);
+        self.handle_stderr(stderr, self.language_id.clone());
         self.initialize();
     }
 
+    fn send_status(&self, state: LanguageServerState) {
+        self.dispatcher.send_rpc_notification(
+            CoreNotification::LanguageServerStatus {
+                language: self.language_id.clone(),
+                state,
+            },
+        );
+    }
+
     fn stop(&self) {
         self.active.store(false, Ordering::Release);
         let _ = self.state.lock().process.kill();
@@ -1077,7 +1243,17 @@ impl LspClient {
                 // the discussion about handling plugins logs before doing anything
             }
             "experimental/serverStatus" => {
-                //TODO: Logging of server status
+                // Servers like rust-analyzer aren't quiescent while they load
+                // and index the workspace
+                let quiescent =
+                    json!(params).get("quiescent").and_then(Value::as_bool);
+                if let Some(quiescent) = quiescent {
+                    self.send_status(if quiescent {
+                        LanguageServerState::Ready
+                    } else {
+                        LanguageServerState::Indexing
+                    });
+                }
             }
             method => {
                 println!("Received unhandled notification {}", method);
@@ -1160,6 +1336,7 @@ impl LspClient {
                         state.is_initialized = true;
                     }
                     lsp_client.send_initialized();
+                    lsp_client.send_status(LanguageServerState::Ready);
                 }
                 let _ = sender.send(true);
             });
@@ -1292,6 +1469,10 @@ impl LspClient {
                     link_support: Some(false),
                     ..Default::default()
                 }),
+                rename: Some(RenameClientCapabilities {
+                    prepare_support: Some(true),
+                    ..Default::default()
+                }),
                 ..Default::default()
             }),
             window: Some(WindowClientCapabilities {
@@ -1450,6 +1631,43 @@ impl LspClient {
         self.send_request("textDocument/references", params, Box::new(cb));
     }
 
+    pub fn request_prepare_rename<CB>(
+        &self,
+        document_uri: Url,
+        position: Position,
+        cb: CB,
+    ) where
+        CB: 'static + Send + FnOnce(&LspClient, Result<Value>),
+    {
+        let params = TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: document_uri },
+            position,
+        };
+        let params = Params::from(serde_json::to_value(params).unwrap());
+        self.send_request("textDocument/prepareRename", params, Box::new(cb));
+    }
+
+    pub fn request_rename<CB>(
+        &self,
+        document_uri: Url,
+        position: Position,
+        new_name: String,
+        cb: CB,
+    ) where
+        CB: 'static + Send + FnOnce(&LspClient, Result<Value>),
+    {
+        let params = RenameParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: document_uri },
+                position,
+            },
+            new_name,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        let params = Params::from(serde_json::to_value(params).unwrap());
+        self.send_request("textDocument/rename", params, Box::new(cb));
+    }
+
     pub fn request_definition<CB>(
         &self,
         document_uri: Url,
@@ -2,19 +2,20 @@ use anyhow::{anyhow, Result};
 use home::home_dir;
 use hotwatch::Hotwatch;
 use lapce_rpc::counter::Counter;
-use lapce_rpc::plugin::{PluginDescription, PluginId, PluginInfo};
+use lapce_rpc::plugin::{ExplorerMenuItem, PluginDescription, PluginId, PluginInfo};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::fs;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::sync::mpsc::Sender;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use toml_edit::easy as toml;
 use wasmer::ChainableNamedResolver;
 use wasmer::ImportObject;
@@ -59,6 +60,7 @@ pub struct PluginCatalog {
 enum PluginTransmissionMessage {
     Initialize,
     Stop,
+    RunCommand { command: String, args: Value },
 }
 
 impl PluginCatalog {
@@ -115,6 +117,7 @@ impl PluginCatalog {
         &mut self,
         dispatcher: Dispatcher,
         plugin: PluginDescription,
+        cancelled: &AtomicBool,
     ) -> Result<()> {
         let home = home_dir().unwrap();
         let path = home.join(".lapce").join("plugins").join(&plugin.name);
@@ -131,22 +134,33 @@ impl PluginCatalog {
             file.write_all(&toml::to_vec(&plugin)?)?;
         }
 
-        let mut plugin = plugin;
-        if let Some(wasm) = plugin.wasm.clone() {
-            {
-                let url = format!(
-                    "https://raw.githubusercontent.com/{}/master/{}",
-                    plugin.repository, wasm
-                );
-                let mut resp = reqwest::blocking::get(url)?;
-                let mut file = fs::OpenOptions::new()
-                    .create(true)
-                    .truncate(true)
-                    .write(true)
-                    .open(path.join(&wasm))?;
-                std::io::copy(&mut resp, &mut file)?;
+        // Everything is downloaded before the plugin is started, and a plugin
+        // that is only partly downloaded isn't left behind
+        let files = plugin
+            .wasm
+            .iter()
+            .chain(plugin.themes.iter().flatten())
+            .cloned()
+            .collect::<Vec<_>>();
+        for file in files {
+            let url = format!(
+                "https://raw.githubusercontent.com/{}/master/{}",
+                plugin.repository, file
+            );
+            if let Err(e) = download_plugin_file(
+                &dispatcher,
+                &plugin.name,
+                &url,
+                &path.join(&file),
+                cancelled,
+            ) {
+                let _ = fs::remove_dir_all(&path);
+                return Err(e);
             }
+        }
 
+        let mut plugin = plugin;
+        if let Some(wasm) = plugin.wasm.clone() {
             plugin.dir = Some(path.clone());
             plugin.wasm = Some(
                 path.join(&wasm)
@@ -155,26 +169,12 @@ impl PluginCatalog {
                     .to_string(),
             );
 
-            if let Ok((p, tx)) = self.start_plugin(dispatcher, plugin.clone()) {
-                self.plugins.insert(plugin.name.clone(), p);
-                self.senders.insert(plugin.name.clone(), tx);
-            }
-        }
-        if let Some(themes) = plugin.themes.as_ref() {
-            for theme in themes {
-                {
-                    let url = format!(
-                        "https://raw.githubusercontent.com/{}/master/{}",
-                        plugin.repository, theme
-                    );
-                    let mut resp = reqwest::blocking::get(url)?;
-                    let mut file = fs::OpenOptions::new()
-                        .create(true)
-                        .truncate(true)
-                        .write(true)
-                        .open(path.join(theme))?;
-                    std::io::copy(&mut resp, &mut file)?;
+            match self.start_plugin(dispatcher.clone(), plugin.clone()) {
+                Ok((p, tx)) => {
+                    self.plugins.insert(plugin.name.clone(), p);
+                    self.senders.insert(plugin.name.clone(), tx);
                 }
+                Err(e) => notify_activation_failed(&dispatcher, &plugin.name, &e),
             }
         }
         self.items.insert(plugin.name.clone(), plugin);
@@ -200,10 +200,14 @@ impl PluginCatalog {
     pub fn start_all(&mut self, dispatcher: Dispatcher) {
         for (_, plugin) in self.items.clone().iter() {
             if !self.disabled.contains_key(&plugin.name) {
-                if let Ok((p, _tx)) =
-                    self.start_plugin(dispatcher.clone(), plugin.clone())
-                {
-                    self.plugins.insert(plugin.name.clone(), p);
+                match self.start_plugin(dispatcher.clone(), plugin.clone()) {
+                    Ok((p, tx)) => {
+                        self.plugins.insert(plugin.name.clone(), p);
+                        self.senders.insert(plugin.name.clone(), tx);
+                    }
+                    Err(e) => {
+                        notify_activation_failed(&dispatcher, &plugin.name, &e)
+                    }
                 }
             }
         }
@@ -250,11 +254,22 @@ impl PluginCatalog {
         thread::spawn(move || loop {
             match rx.recv() {
                 Ok(PluginTransmissionMessage::Initialize) => {
-                    let initialize = local_plugin
+                    let dispatcher = &local_plugin.env.dispatcher;
+                    let initialize = match local_plugin
                         .instance
                         .exports
                         .get_function("initialize")
-                        .unwrap();
+                    {
+                        Ok(initialize) => initialize,
+                        Err(e) => {
+                            notify_activation_failed(
+                                dispatcher,
+                                &plugin_desc.name,
+                                &anyhow!(e),
+                            );
+                            continue;
+                        }
+                    };
                     wasi_write_object(
                         &local_plugin.env.wasi_env,
                         &PluginInfo {
@@ -263,7 +278,34 @@ impl PluginCatalog {
                             configuration: plugin_desc.clone().configuration,
                         },
                     );
-                    initialize.call(&[]).unwrap();
+                    match initialize.call(&[]) {
+                        Ok(_) => notify_state_changed(
+                            dispatcher,
+                            &plugin_desc.name,
+                            PluginState::Active,
+                        ),
+                        Err(e) => notify_activation_failed(
+                            dispatcher,
+                            &plugin_desc.name,
+                            &anyhow!(e),
+                        ),
+                    }
+                }
+                Ok(PluginTransmissionMessage::RunCommand { command, args }) => {
+                    // Plugins that don't register any commands don't need to
+                    // export the handler
+                    if let Ok(handle_command) =
+                        local_plugin.instance.exports.get_function("handle_command")
+                    {
+                        wasi_write_object(
+                            &local_plugin.env.wasi_env,
+                            &json!({
+                                "command": command,
+                                "args": args,
+                            }),
+                        );
+                        let _ = handle_command.call(&[]);
+                    }
                 }
                 Ok(PluginTransmissionMessage::Stop) => {
                     let stop = local_plugin.instance.exports.get_function("stop");
@@ -281,6 +323,11 @@ impl PluginCatalog {
                                 .stop_language_lsp(lang);
                         }
                     }
+                    notify_state_changed(
+                        &local_plugin.env.dispatcher,
+                        &plugin_desc.name,
+                        PluginState::Stopped,
+                    );
                     break;
                 }
                 // There was an error when receiving, which means that the other end was closed.
@@ -292,6 +339,13 @@ impl PluginCatalog {
         Ok((plugin, tx))
     }
 
+    /// Send a command the plugin registered to it, to be run on its own thread
+    pub fn run_command(&self, plugin: &str, command: String, args: Value) {
+        if let Some(tx) = self.senders.get(plugin) {
+            let _ = tx.send(PluginTransmissionMessage::RunCommand { command, args });
+        }
+    }
+
     pub fn disable_plugin(
         &mut self,
         _dispatcher: Dispatcher,
@@ -343,7 +397,14 @@ impl PluginCatalog {
                     .ok_or_else(|| anyhow!("path can't to string"))?
                     .to_string(),
             );
-            self.start_plugin(dispatcher, plugin.clone())?;
+            let (p, tx) = self
+                .start_plugin(dispatcher.clone(), plugin.clone())
+                .map_err(|e| {
+                    notify_activation_failed(&dispatcher, &plugin.name, &e);
+                    e
+                })?;
+            self.plugins.insert(plugin.name.clone(), p);
+            self.senders.insert(plugin.name.clone(), tx);
             self.disabled.remove(&plugin_desc.name);
             let config_path = home.join(".lapce").join("config");
             let disabled_plugin_list =
@@ -413,6 +474,13 @@ pub enum PluginNotification {
     MakeFileExecutable {
         path: PathBuf,
     },
+    /// Add an entry to the file explorer's context menu, which sends the
+    /// command to the plugin with the path of the node it was opened on
+    RegisterExplorerMenuItem {
+        label: String,
+        command: String,
+        when: Option<String>,
+    },
 }
 
 fn host_handle_notification(plugin_env: &PluginEnv) {
@@ -490,10 +558,117 @@ fn host_handle_notification(plugin_env: &PluginEnv) {
                     .arg(&plugin_env.desc.dir.clone().unwrap().join(path))
                     .output();
             }
+            PluginNotification::RegisterExplorerMenuItem {
+                label,
+                command,
+                when,
+            } => {
+                let item = ExplorerMenuItem {
+                    plugin: plugin_env.desc.name.clone(),
+                    label,
+                    command,
+                    when,
+                };
+                plugin_env.dispatcher.send_notification(
+                    "register_explorer_menu_item",
+                    json!({
+                        "item": item,
+                    }),
+                );
+            }
         }
     }
 }
 
+/// How often the progress of downloading a file of a plugin is reported
+const PLUGIN_DOWNLOAD_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Download the file of the plugin at the url to the path, reporting how much
+/// of it was received as it comes in, until it's done or cancelled
+fn download_plugin_file(
+    dispatcher: &Dispatcher,
+    plugin_name: &str,
+    url: &str,
+    path: &Path,
+    cancelled: &AtomicBool,
+) -> Result<()> {
+    let mut resp = reqwest::blocking::get(url)?.error_for_status()?;
+    let total = resp.content_length();
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(path)?;
+    let mut buf = vec![0; 64 * 1024];
+    let mut received = 0;
+    let mut reported = Instant::now();
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(anyhow!("the install was cancelled"));
+        }
+        let n = resp.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buf[..n])?;
+        received += n as u64;
+        if reported.elapsed() >= PLUGIN_DOWNLOAD_PROGRESS_INTERVAL {
+            reported = Instant::now();
+            notify_installing(dispatcher, plugin_name, received, total);
+        }
+    }
+    notify_installing(dispatcher, plugin_name, received, total);
+    Ok(())
+}
+
+fn notify_installing(
+    dispatcher: &Dispatcher,
+    plugin_name: &str,
+    received: u64,
+    total: Option<u64>,
+) {
+    dispatcher.send_notification(
+        "plugin_installing",
+        json!({
+            "plugin_id": plugin_name,
+            "received": received,
+            "total": total,
+        }),
+    );
+}
+
+/// Let the core know the plugin failed to start, with the whole chain of
+/// errors so that it's useful in a bug report
+fn notify_activation_failed(
+    dispatcher: &Dispatcher,
+    plugin_name: &str,
+    error: &anyhow::Error,
+) {
+    eprintln!("plugin {plugin_name} failed to start: {error:#}");
+    dispatcher.send_notification(
+        "plugin_activation_failed",
+        json!({
+            "plugin_id": plugin_name,
+            "error": format!("{error:#}"),
+        }),
+    );
+    notify_state_changed(dispatcher, plugin_name, PluginState::Failed);
+}
+
+fn notify_state_changed(
+    dispatcher: &Dispatcher,
+    plugin_name: &str,
+    state: PluginState,
+) {
+    dispatcher.send_notification(
+        "plugin_state_changed",
+        json!({
+            "plugin_id": plugin_name,
+            "state": state,
+        }),
+    );
+}
+
 pub fn wasi_read_string(wasi_env: &WasiEnv) -> Result<String> {
     let mut state = wasi_env.state();
     let wasi_file = state
@@ -26,6 +26,11 @@ use serde_json::json;
 use crate::dispatch::Dispatcher;
 
 const READ_BUFFER_SIZE: usize = 0x10_0000;
+/// The start of the OSC 7 escape, which shells use to report their working
+/// directory as a `file://` url
+const OSC7_START: &[u8] = b"\x1b]7;";
+/// The longest OSC 7 escape that is waited for across reads
+const MAX_OSC7_LEN: usize = 4096;
 
 pub type TermConfig = alacritty_terminal::config::Config;
 
@@ -33,6 +38,8 @@ pub struct Terminal {
     term_id: TermId,
     poll: mio::Poll,
     pty: alacritty_terminal::tty::Pty,
+    /// The process id of the shell, to get its exit status from
+    shell_pid: Option<i32>,
 
     #[allow(deprecated)]
     rx: Receiver<Msg>,
@@ -75,8 +82,9 @@ impl Terminal {
 
         let size =
             SizeInfo::new(width as f32, height as f32, 1.0, 1.0, 0.0, 0.0, true);
-        let pty =
+        let mut pty =
             alacritty_terminal::tty::new(&config.pty_config, &size, None).unwrap();
+        let shell_pid = shell_pid(&mut pty);
 
         #[allow(deprecated)]
         let (tx, rx) = channel();
@@ -85,6 +93,7 @@ impl Terminal {
             term_id,
             poll,
             pty,
+            shell_pid,
             tx,
             rx,
         }
@@ -106,6 +115,7 @@ impl Terminal {
         let mut buf = [0u8; READ_BUFFER_SIZE];
         let mut events = Events::with_capacity(1024);
         let mut state = State::default();
+        let mut cwd_reporter = CwdReporter::default();
 
         'event_loop: loop {
             let _ = self.poll.poll(&mut events, None);
@@ -118,17 +128,23 @@ impl Terminal {
                     }
 
                     token if token == self.pty.child_event_token() => {
-                        if let Some(tty::ChildEvent::Exited) =
-                            self.pty.next_child_event()
-                        {
-                            dispatcher.send_notification(
-                                "close_terminal",
-                                json!({
-                                    "term_id": self.term_id,
-                                }),
-                            );
-                            break 'event_loop;
-                        }
+                        // The status is read before alacritty_terminal reaps the
+                        // shell, as it doesn't hand it out
+                        let exited = self.shell_pid.and_then(shell_exit_code);
+                        let event = self.pty.next_child_event();
+                        let code = match (exited, event) {
+                            (Some(code), _) => code,
+                            (None, Some(tty::ChildEvent::Exited)) => None,
+                            _ => continue,
+                        };
+                        dispatcher.send_notification(
+                            "terminal_exited",
+                            json!({
+                                "term_id": self.term_id,
+                                "code": code,
+                            }),
+                        );
+                        break 'event_loop;
                     }
                     token
                         if token == self.pty.read_token()
@@ -143,6 +159,15 @@ impl Terminal {
                         if event.readiness().is_readable() {
                             match self.pty.reader().read(&mut buf) {
                                 Ok(n) => {
+                                    if let Some(cwd) = cwd_reporter.feed(&buf[..n]) {
+                                        dispatcher.send_notification(
+                                            "terminal_cwd_changed",
+                                            json!({
+                                                "term_id": self.term_id,
+                                                "cwd": cwd,
+                                            }),
+                                        );
+                                    }
                                     dispatcher.send_notification(
                                         "update_terminal",
                                         json!({
@@ -311,6 +336,145 @@ impl State {
     }
 }
 
+/// Picks up the working directory reported by the shell with OSC 7 escapes,
+/// which can be split between reads
+#[derive(Default)]
+struct CwdReporter {
+    /// The unfinished escape at the end of the last read
+    partial: Vec<u8>,
+    /// The last reported working directory, since shells report it again
+    /// with every prompt
+    cwd: Option<PathBuf>,
+}
+
+impl CwdReporter {
+    /// The working directory reported in the output, if it changed
+    fn feed(&mut self, bytes: &[u8]) -> Option<PathBuf> {
+        let cwd = self.parse(bytes)?;
+        if self.cwd.as_ref() == Some(&cwd) {
+            return None;
+        }
+        self.cwd = Some(cwd.clone());
+        Some(cwd)
+    }
+
+    /// The last working directory reported in the output, if any
+    fn parse(&mut self, bytes: &[u8]) -> Option<PathBuf> {
+        let output: Cow<[u8]> = if self.partial.is_empty() {
+            Cow::Borrowed(bytes)
+        } else {
+            let mut output = std::mem::take(&mut self.partial);
+            output.extend_from_slice(bytes);
+            Cow::Owned(output)
+        };
+
+        let mut cwd = None;
+        let mut rest = &output[..];
+        while let Some(start) = find_bytes(rest, OSC7_START) {
+            let url = &rest[start + OSC7_START.len()..];
+            // Ended by BEL or by ST, which is ESC \
+            match url.iter().position(|b| *b == 0x07 || *b == 0x1b) {
+                Some(end) => {
+                    cwd = parse_cwd_url(&url[..end]).or(cwd);
+                    rest = &url[end..];
+                }
+                None => {
+                    if url.len() < MAX_OSC7_LEN {
+                        self.partial = rest[start..].to_vec();
+                    }
+                    return cwd;
+                }
+            }
+        }
+
+        // The start of the escape itself may be cut off at the end
+        if let Some(len) = (1..OSC7_START.len())
+            .rev()
+            .find(|len| rest.ends_with(&OSC7_START[..*len]))
+        {
+            self.partial = rest[rest.len() - len..].to_vec();
+        }
+        cwd
+    }
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// The path of a `file://host/path` url, ignoring the host since shells
+/// report their own host name there
+fn parse_cwd_url(url: &[u8]) -> Option<PathBuf> {
+    let url = std::str::from_utf8(url).ok()?;
+    let rest = url.strip_prefix("file://")?;
+    let path = &rest[rest.find('/')?..];
+    lsp_types::Url::parse(&format!("file://{path}"))
+        .ok()?
+        .to_file_path()
+        .ok()
+}
+
+/// The process id of the shell, which leads the session of the terminal
+#[cfg(unix)]
+fn shell_pid(pty: &mut tty::Pty) -> Option<i32> {
+    use std::os::unix::io::AsRawFd;
+
+    let pid = unsafe { libc::tcgetsid(pty.reader().as_raw_fd()) };
+    if pid > 0 {
+        Some(pid)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn shell_pid(_pty: &mut tty::Pty) -> Option<i32> {
+    None
+}
+
+/// The exit code of the shell once it has exited, which is `Some(None)` when
+/// it was killed by a signal. The shell is left to be reaped, so that
+/// alacritty_terminal still sees it exit.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn shell_exit_code(pid: i32) -> Option<Option<i32>> {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let ret = unsafe {
+        libc::waitid(
+            libc::P_PID,
+            pid as libc::id_t,
+            &mut info,
+            libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+        )
+    };
+    // The pid is left at zero while the shell is still running
+    let (info_pid, status) = siginfo_pid_status(&info);
+    if ret != 0 || info_pid != pid {
+        return None;
+    }
+    if info.si_code == libc::CLD_EXITED {
+        Some(Some(status))
+    } else {
+        Some(None)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn shell_exit_code(_pid: i32) -> Option<Option<i32>> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn siginfo_pid_status(info: &libc::siginfo_t) -> (i32, i32) {
+    unsafe { (info.si_pid(), info.si_status()) }
+}
+
+#[cfg(target_os = "macos")]
+fn siginfo_pid_status(info: &libc::siginfo_t) -> (i32, i32) {
+    (info.si_pid, info.si_status)
+}
+
 #[cfg(target_os = "macos")]
 fn set_locale_environment() {
     let locale = locale_config::Locale::global_default()
@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use lapce_rpc::proxy::WorkspaceTrashEntry;
+
+use crate::dispatch::copy_path;
+
+/// The folder at the root of the workspace that paths are moved to when
+/// there is no OS trash, like on a headless machine or over SSH
+pub const WORKSPACE_TRASH_DIR: &str = ".lapce-trash";
+
+/// The trash folder of the workspace. Every trashed path is moved into it
+/// under the id of its entry, next to a `<id>.json` record of the entry that
+/// it is restored from.
+pub struct WorkspaceTrash {
+    dir: PathBuf,
+}
+
+impl WorkspaceTrash {
+    pub fn new(workspace: &Path) -> Self {
+        Self {
+            dir: workspace.join(WORKSPACE_TRASH_DIR),
+        }
+    }
+
+    /// Move the path into the trash
+    pub fn trash(&self, path: &Path) -> Result<WorkspaceTrashEntry> {
+        if path.starts_with(&self.dir) {
+            return Err(anyhow!("{:?} is already in the trash", path));
+        }
+        fs::create_dir_all(&self.dir)?;
+
+        let trashed_at = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        // Paths trashed in the same instant still get their own entry
+        let mut id = trashed_at.as_nanos().to_string();
+        let mut n = 0;
+        while self.item_path(&id).exists() || self.record_path(&id).exists() {
+            n += 1;
+            id = format!("{}-{n}", trashed_at.as_nanos());
+        }
+
+        let entry = WorkspaceTrashEntry {
+            id,
+            original_path: path.to_path_buf(),
+            trashed_at: trashed_at.as_secs(),
+        };
+        fs::write(self.record_path(&entry.id), serde_json::to_vec(&entry)?)?;
+        if let Err(err) = move_path(path, &self.item_path(&entry.id)) {
+            let _ = fs::remove_file(self.record_path(&entry.id));
+            return Err(err)
+                .with_context(|| format!("Failed to move {:?} to the trash", path));
+        }
+        Ok(entry)
+    }
+
+    /// The entries of the trash, most recently trashed first. Records whose
+    /// path is missing are left out.
+    pub fn entries(&self) -> Result<Vec<WorkspaceTrashEntry>> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Vec::new())
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut entries: Vec<WorkspaceTrashEntry> = read_dir
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+            .filter_map(|path| {
+                let entry: WorkspaceTrashEntry =
+                    serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+                Some(entry)
+            })
+            .filter(|entry| self.item_path(&entry.id).exists())
+            .collect();
+        entries.sort_by(|a, b| {
+            b.trashed_at
+                .cmp(&a.trashed_at)
+                .then_with(|| b.id.cmp(&a.id))
+        });
+        Ok(entries)
+    }
+
+    /// Move the path of the entry back to where it was trashed from,
+    /// returning that path
+    pub fn restore(&self, id: &str) -> Result<PathBuf> {
+        // The id names a path in the trash folder, so it can't be allowed to
+        // point outside of it
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit() || c == '-') {
+            return Err(anyhow!("Invalid trash entry {id}"));
+        }
+        let record_path = self.record_path(id);
+        let entry: WorkspaceTrashEntry = serde_json::from_slice(
+            &fs::read(&record_path)
+                .with_context(|| format!("No entry {id} in the trash"))?,
+        )?;
+        let to = entry.original_path;
+        if to.exists() {
+            return Err(anyhow!("{:?} already exists", to));
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_path(&self.item_path(id), &to)?;
+        let _ = fs::remove_file(record_path);
+        Ok(to)
+    }
+
+    fn item_path(&self, id: &str) -> PathBuf {
+        self.dir.join(id)
+    }
+
+    fn record_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+}
+
+/// Move the path, or copy it and remove the original when it is on another
+/// file system than where it goes, like a folder mounted in the workspace,
+/// which renaming can't move across
+fn move_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    match fs::rename(from, to) {
+        Err(err) if is_cross_device(&err) => {
+            copy_path(from, to, None)?;
+            if fs::symlink_metadata(from)?.is_dir() {
+                fs::remove_dir_all(from)
+            } else {
+                fs::remove_file(from)
+            }
+        }
+        result => result,
+    }
+}
+
+#[cfg(unix)]
+fn is_cross_device(err: &std::io::Error) -> bool {
+    err.raw_os_error() == Some(libc::EXDEV)
+}
+
+#[cfg(windows)]
+fn is_cross_device(err: &std::io::Error) -> bool {
+    // ERROR_NOT_SAME_DEVICE
+    err.raw_os_error() == Some(17)
+}
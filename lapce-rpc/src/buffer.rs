@@ -12,9 +12,53 @@ impl BufferId {
     }
 }
 
+/// The line ending used by the lines of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    /// Some lines end with LF and others with CRLF
+    Mixed,
+}
+
+impl LineEnding {
+    /// Find the line ending that the lines of the text use. Text without any
+    /// line breaks is taken as LF.
+    pub fn detect(text: &str) -> LineEnding {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        match (lf, crlf) {
+            (_, 0) => LineEnding::Lf,
+            (0, _) => LineEnding::CrLf,
+            _ => LineEnding::Mixed,
+        }
+    }
+
+    /// The line break the line ending writes, which `Mixed` has none of
+    pub fn line_break(&self) -> Option<&'static str> {
+        match self {
+            LineEnding::Lf => Some("\n"),
+            LineEnding::CrLf => Some("\r\n"),
+            LineEnding::Mixed => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+            LineEnding::Mixed => "Mixed",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewBufferResponse {
     pub content: String,
+    /// The name of the encoding the file was read in, which it's saved in too
+    pub encoding: String,
+    pub line_ending: LineEnding,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
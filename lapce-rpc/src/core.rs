@@ -1,9 +1,11 @@
-use lsp_types::{ProgressParams, PublishDiagnosticsParams};
+use lsp_types::{ProgressParams, PublishDiagnosticsParams, TextEdit};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, path::PathBuf};
 
 use crate::{
-    file::FileNodeItem, plugin::PluginDescription, source_control::DiffInfo,
+    file::{FileErrorKind, FileNodeItem, FileOperation},
+    plugin::{ExplorerMenuItem, PluginDescription, PluginState},
+    source_control::{ChangedFile, DiffInfo},
     terminal::TermId,
 };
 
@@ -21,7 +23,34 @@ pub enum CoreNotification {
         content: String,
         rev: u64,
     },
-    WorkspaceFileChange {},
+    /// An open file was written to disk at the revision, or failed to be, in
+    /// which case it still has unsaved changes
+    BufferSaved {
+        path: PathBuf,
+        rev: u64,
+        success: bool,
+        error: Option<String>,
+    },
+    /// An open file changed on disk, given as the edits that turn the buffer
+    /// at the revision into the new content. `OpenFileChanged` with the whole
+    /// content is sent instead when the edits would be larger than it.
+    ReloadBufferDelta {
+        path: PathBuf,
+        rev: u64,
+        changes: Vec<TextEdit>,
+    },
+    /// Files in the workspace changed. `created` has the files that the proxy
+    /// created itself, so that only their directories need to be read again.
+    WorkspaceFileChange {
+        #[serde(default)]
+        created: Vec<PathBuf>,
+    },
+    /// The workspace folder itself was renamed to `new`, or moved away or
+    /// deleted if it is `None`
+    WorkspaceRootChanged {
+        old: PathBuf,
+        new: Option<PathBuf>,
+    },
     PublishDiagnostics {
         diagnostics: PublishDiagnosticsParams,
     },
@@ -37,15 +66,60 @@ pub enum CoreNotification {
     DisabledPlugins {
         plugins: HashMap<String, PluginDescription>,
     },
+    /// The plugin with the name couldn't be started. The error is meant to be
+    /// shown to the user, so that they can report it.
+    PluginActivationFailed {
+        plugin_id: String,
+        error: String,
+    },
+    /// The files of the plugin with the name are being downloaded to install
+    /// it. `total` is the size of the file being downloaded, if it's known.
+    PluginInstalling {
+        plugin_id: String,
+        received: u64,
+        total: Option<u64>,
+    },
+    /// The plugin with the name couldn't be installed, which isn't reported
+    /// for an install that was cancelled
+    PluginInstallFailed {
+        plugin_id: String,
+        error: String,
+    },
+    /// The plugin with the name was started, stopped, or failed
+    PluginStateChanged {
+        plugin_id: String,
+        state: PluginState,
+    },
+    /// The language server of the language was started, finished starting,
+    /// or crashed
+    LanguageServerStatus {
+        language: String,
+        state: LanguageServerState,
+    },
+    /// A plugin added an entry to the file explorer's context menu
+    RegisterExplorerMenuItem {
+        item: ExplorerMenuItem,
+    },
+    /// A further chunk of a directory listing requested with a chunk size
     ListDir {
-        items: Vec<FileNodeItem>,
+        path: PathBuf,
+        items: HashMap<PathBuf, FileNodeItem>,
+        /// Whether more chunks will follow
+        more: bool,
+        /// The generation the read of the directory was requested with
+        generation: u64,
     },
+    /// The files with changes in the workspace's repository, along with whether
+    /// they're staged. Sent whenever that changes.
     DiffFiles {
-        files: Vec<PathBuf>,
+        files: Vec<ChangedFile>,
     },
     DiffInfo {
         diff: DiffInfo,
     },
+    /// The raw output of the terminal's PTY. It is parsed on the core side,
+    /// which is also where title escapes are picked up to label the terminal,
+    /// so there is no separate notification for title changes.
     UpdateTerminal {
         term_id: TermId,
         content: String,
@@ -53,7 +127,74 @@ pub enum CoreNotification {
     CloseTerminal {
         term_id: TermId,
     },
+    /// The shell of the terminal reported that its working directory changed
+    TerminalCwdChanged {
+        term_id: TermId,
+        cwd: PathBuf,
+    },
+    /// The process of the terminal exited, with its exit code if it's known
+    TerminalExited {
+        term_id: TermId,
+        code: Option<i32>,
+    },
+    /// A create, rename, move, trash or duplicate of the file explorer failed.
+    /// It is sent before the response to the request that failed.
+    FileOperationError {
+        operation: FileOperation,
+        path: PathBuf,
+        kind: FileErrorKind,
+        message: String,
+    },
+    /// A match of the search started with `StartSearch`, sent as soon as it's
+    /// found. The line is one based and the columns are byte offsets.
+    SearchResult {
+        query_id: u64,
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        end_column: usize,
+        text: String,
+    },
+    /// The total size in bytes of the files beneath the directory, as
+    /// requested with `ComputeDirSize`
+    DirSize {
+        path: PathBuf,
+        size: u64,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum CoreRequest {}
+#[serde(rename_all = "snake_case")]
+#[serde(tag = "method", content = "params")]
+pub enum CoreRequest {
+    /// Open the file with the cursor at the position, for plugins and other
+    /// tools. The line and column are one based, and the column counts bytes.
+    OpenFileAt {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+    },
+    /// Send the text to the terminal as if it was typed, for plugins and other
+    /// tools. Fails when the terminal is closed.
+    TerminalWrite { term_id: TermId, data: String },
+    /// Resize the terminal to the number of rows and columns, until its panel
+    /// is laid out again. Fails when the terminal is closed.
+    TerminalResize {
+        term_id: TermId,
+        rows: usize,
+        cols: usize,
+    },
+}
+
+/// Whether a language server is running, as shown in the status bar
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LanguageServerState {
+    Starting,
+    /// Started, but still busy with the workspace, as reported by servers
+    /// that support the `experimental/serverStatus` notification
+    Indexing,
+    Ready,
+    /// The server exited on its own, and stays stopped until it's restarted
+    Crashed,
+}
@@ -2,6 +2,7 @@ use std::{
     cmp::{self, Ordering},
     collections::HashMap,
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
 use serde::{Deserialize, Serialize};
@@ -14,6 +15,49 @@ pub struct FileNodeItem {
     pub open: bool,
     pub children: HashMap<PathBuf, FileNodeItem>,
     pub children_open_count: usize,
+    /// The size of the file in bytes.
+    /// Only populated once the metadata has been read.
+    #[serde(default)]
+    pub size: u64,
+    /// The last modification time, if the metadata has been read and the
+    /// platform supports it.
+    #[serde(default)]
+    pub modified: Option<SystemTime>,
+    /// Whether the file is read-only on disk.
+    /// Only populated once the metadata has been read.
+    #[serde(default)]
+    pub readonly: bool,
+    /// Whether more children of the directory are still being read.
+    /// A loading directory that is open has an extra row for the loading
+    /// indicator, which is included in `children_open_count`. The same goes
+    /// for the "(empty)" row of an open directory that has no children.
+    #[serde(default)]
+    pub loading: bool,
+    /// Whether the path is a symbolic link, in which case `is_dir` is about
+    /// what it points to
+    #[serde(default)]
+    pub is_symlink: bool,
+    /// The resolved path that the symbolic link points to
+    #[serde(default)]
+    pub symlink_target: Option<PathBuf>,
+    /// Whether git ignores the path. Only populated once the metadata has
+    /// been read.
+    #[serde(default)]
+    pub git_ignored: bool,
+    /// Whether the size, modification time, permissions and git status above
+    /// have been read, which the explorer only does for the rows it shows
+    #[serde(default)]
+    pub metadata_read: bool,
+}
+
+/// What is read of a path along with its listing, or later for the rows
+/// that are shown
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PathMetadata {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub readonly: bool,
+    pub git_ignored: bool,
 }
 
 impl std::cmp::PartialOrd for FileNodeItem {
@@ -40,6 +84,44 @@ impl std::cmp::PartialOrd for FileNodeItem {
 }
 
 impl FileNodeItem {
+    /// A node for the path that hasn't been read, is collapsed and has no
+    /// metadata yet
+    pub fn new(path_buf: PathBuf, is_dir: bool) -> Self {
+        Self {
+            path_buf,
+            is_dir,
+            read: false,
+            open: false,
+            children: HashMap::new(),
+            children_open_count: 0,
+            size: 0,
+            modified: None,
+            readonly: false,
+            loading: false,
+            is_symlink: false,
+            symlink_target: None,
+            git_ignored: false,
+            metadata_read: false,
+        }
+    }
+
+    /// Take the metadata that was read for the path
+    pub fn set_metadata(&mut self, metadata: &PathMetadata) {
+        self.size = metadata.size;
+        self.modified = metadata.modified;
+        self.readonly = metadata.readonly;
+        self.git_ignored = metadata.git_ignored;
+        self.metadata_read = true;
+    }
+
+    /// Whether this is a symbolic link to one of its own ancestors, which
+    /// would repeat its ancestors forever when expanded
+    pub fn is_symlink_loop(&self) -> bool {
+        self.symlink_target
+            .as_ref()
+            .map_or(false, |target| self.path_buf.starts_with(target))
+    }
+
     pub fn sorted_children(&self) -> Vec<&FileNodeItem> {
         let mut children = self
             .children
@@ -126,14 +208,7 @@ impl FileNodeItem {
         let node = self.get_file_node_mut(parent)?;
         node.children.insert(
             PathBuf::from(path),
-            FileNodeItem {
-                path_buf: PathBuf::from(path),
-                is_dir,
-                read: false,
-                open: false,
-                children: HashMap::new(),
-                children_open_count: 0,
-            },
+            FileNodeItem::new(PathBuf::from(path), is_dir),
         );
         for p in path.ancestors() {
             self.update_node_count(p);
@@ -166,7 +241,8 @@ impl FileNodeItem {
                     .children
                     .iter()
                     .map(|(_, item)| item.children_open_count + 1)
-                    .sum::<usize>();
+                    .sum::<usize>()
+                    + node.loading as usize;
             } else {
                 node.children_open_count = 0;
             }
@@ -174,3 +250,105 @@ impl FileNodeItem {
         None
     }
 }
+
+/// A filesystem operation of the file explorer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileOperation {
+    Create,
+    Rename,
+    /// Renaming into another directory
+    Move,
+    Trash,
+    Duplicate,
+    /// Deleting permanently, without the trash
+    Delete,
+}
+
+impl FileOperation {
+    /// How the operation is named in the messages shown to the user
+    pub fn verb(&self) -> &'static str {
+        match self {
+            FileOperation::Create => "create",
+            FileOperation::Rename => "rename",
+            FileOperation::Move => "move",
+            FileOperation::Trash => "move to the trash",
+            FileOperation::Duplicate => "duplicate",
+            FileOperation::Delete => "delete",
+        }
+    }
+}
+
+/// Why a filesystem operation failed, so that the user can be told what to
+/// do about it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileErrorKind {
+    AlreadyExists,
+    NotFound,
+    PermissionDenied,
+    NameTooLong,
+    /// The operation was cancelled by the user, which isn't reported
+    Cancelled,
+    Other,
+}
+
+impl FileErrorKind {
+    pub fn from_io(err: &std::io::Error) -> Self {
+        #[cfg(windows)]
+        const NAME_TOO_LONG: i32 = 206;
+        #[cfg(target_os = "linux")]
+        const NAME_TOO_LONG: i32 = 36;
+        #[cfg(not(any(windows, target_os = "linux")))]
+        const NAME_TOO_LONG: i32 = 63;
+
+        match err.kind() {
+            std::io::ErrorKind::AlreadyExists => FileErrorKind::AlreadyExists,
+            std::io::ErrorKind::NotFound => FileErrorKind::NotFound,
+            std::io::ErrorKind::PermissionDenied => FileErrorKind::PermissionDenied,
+            std::io::ErrorKind::Interrupted => FileErrorKind::Cancelled,
+            _ if err.raw_os_error() == Some(NAME_TOO_LONG) => {
+                FileErrorKind::NameTooLong
+            }
+            _ => FileErrorKind::Other,
+        }
+    }
+
+    /// Whether the user can fix it on their own, like by choosing another
+    /// name, rather than it being a problem of the file system
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            FileErrorKind::AlreadyExists
+                | FileErrorKind::NotFound
+                | FileErrorKind::NameTooLong
+        )
+    }
+
+    /// What went wrong, in the words shown to the user
+    pub fn description(&self) -> &'static str {
+        match self {
+            FileErrorKind::AlreadyExists => "File already exists",
+            FileErrorKind::NotFound => "File no longer exists",
+            FileErrorKind::PermissionDenied => "Permission denied",
+            FileErrorKind::NameTooLong => "Name is too long",
+            FileErrorKind::Cancelled => "Cancelled",
+            FileErrorKind::Other => "Something went wrong",
+        }
+    }
+
+    /// What the user can do about it
+    pub fn hint(&self) -> &'static str {
+        match self {
+            FileErrorKind::AlreadyExists => "Choose a different name.",
+            FileErrorKind::NotFound => {
+                "It may have been moved or deleted outside of Lapce."
+            }
+            FileErrorKind::PermissionDenied => {
+                "Check that you are allowed to change the directory."
+            }
+            FileErrorKind::NameTooLong => "Choose a shorter name.",
+            FileErrorKind::Cancelled | FileErrorKind::Other => "",
+        }
+    }
+}
@@ -9,6 +9,7 @@ pub mod source_control;
 mod stdio;
 pub mod style;
 pub mod terminal;
+pub mod traffic;
 
 use std::collections::HashMap;
 use std::io::stdin;
@@ -19,7 +20,7 @@ use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use anyhow::Result;
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::{Receiver, SendError, Sender};
 use parking_lot::Mutex;
 pub use parse::Call;
 pub use parse::RequestId;
@@ -29,6 +30,7 @@ use serde_json::json;
 use serde_json::Value;
 
 pub use stdio::stdio_transport;
+use traffic::{Direction, TrafficLog};
 
 pub fn stdio() -> (Sender<Value>, Receiver<Value>) {
     let stdout = stdout();
@@ -84,6 +86,9 @@ pub struct RpcHandler {
     sender: Sender<Value>,
     id: Arc<AtomicU64>,
     pending: Arc<Mutex<HashMap<u64, ResponseHandler>>>,
+    /// The log of the messages, when it is turned on with the environment
+    /// variable [`traffic::RPC_LOG_ENV`]
+    log: Option<Arc<TrafficLog>>,
 }
 
 impl RpcHandler {
@@ -92,14 +97,25 @@ impl RpcHandler {
             sender,
             id: Arc::new(AtomicU64::new(0)),
             pending: Arc::new(Mutex::new(HashMap::new())),
+            log: TrafficLog::from_env().map(Arc::new),
         }
     }
 
+    fn send(&self, msg: Value) -> Result<(), SendError<Value>> {
+        if let Some(log) = self.log.as_ref() {
+            log.record(Direction::Sent, &msg);
+        }
+        self.sender.send(msg)
+    }
+
     pub fn mainloop<H>(&mut self, receiver: Receiver<Value>, handler: &mut H)
     where
         H: Handler,
     {
         for msg in receiver {
+            if let Some(log) = self.log.as_ref() {
+                log.record(Direction::Received, &msg);
+            }
             let rpc: RpcObject = msg.into();
             if rpc.is_response() {
                 let id = rpc.get_id().unwrap();
@@ -131,7 +147,7 @@ impl RpcHandler {
     }
 
     pub fn send_rpc_notification(&self, method: &str, params: &Value) {
-        if let Err(_e) = self.sender.send(json!({
+        if let Err(_e) = self.send(json!({
             "method": method,
             "params": params,
         })) {}
@@ -148,7 +164,7 @@ impl RpcHandler {
             let mut pending = self.pending.lock();
             pending.insert(id, rh);
         }
-        if let Err(_e) = self.sender.send(json!({
+        if let Err(_e) = self.send(json!({
             "id": id,
             "method": method,
             "params": params,
@@ -176,7 +192,7 @@ impl RpcHandler {
             .unwrap()
             .insert("id".to_string(), json!(id));
 
-        if let Err(_e) = self.sender.send(request) {
+        if let Err(_e) = self.send(request) {
             let mut pending = self.pending.lock();
             if let Some(rh) = pending.remove(&id) {
                 rh.invoke(Err(json!("io error")));
@@ -238,6 +254,6 @@ impl RpcHandler {
         };
 
         #[allow(deprecated)]
-        let _ = self.sender.send(response);
+        let _ = self.send(response);
     }
 }
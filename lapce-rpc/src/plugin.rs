@@ -23,6 +23,41 @@ pub struct PluginDescription {
     pub configuration: Option<Value>,
 }
 
+/// Whether a plugin is running, as shown in the plugins panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginState {
+    Active,
+    /// The plugin couldn't be started or its initialization failed
+    Failed,
+    Stopped,
+}
+
+/// An entry a plugin added to the file explorer's context menu
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExplorerMenuItem {
+    /// The name of the plugin that registered the entry
+    pub plugin: String,
+    pub label: String,
+    /// The command the plugin is sent when the entry is picked
+    pub command: String,
+    /// Either "isDirectory" or "isFile" to only show the entry for that kind
+    /// of node, or nothing to always show it
+    pub when: Option<String>,
+}
+
+impl ExplorerMenuItem {
+    /// Whether the entry should be shown for a node of the kind
+    pub fn applies_to(&self, is_dir: bool) -> bool {
+        match self.when.as_deref() {
+            None | Some("") => true,
+            Some("isDirectory") => is_dir,
+            Some("isFile") => !is_dir,
+            Some(_) => false,
+        }
+    }
+}
+
 #[derive(Serialize, Clone)]
 pub struct PluginInfo {
     pub arch: String,
@@ -2,11 +2,15 @@ use std::{collections::HashMap, path::PathBuf};
 
 use lsp_types::{CompletionItem, Position};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use xi_rope::RopeDelta;
 
 use crate::{
-    buffer::BufferId, file::FileNodeItem, plugin::PluginDescription,
-    source_control::FileDiff, terminal::TermId,
+    buffer::BufferId,
+    file::{FileNodeItem, PathMetadata},
+    plugin::PluginDescription,
+    source_control::FileDiff,
+    terminal::TermId,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +34,11 @@ pub enum ProxyNotification {
     InstallPlugin {
         plugin: PluginDescription,
     },
+    /// Stop downloading the plugin with the name, and remove what was
+    /// downloaded of it
+    CancelPluginInstall {
+        plugin_id: String,
+    },
     DisablePlugin {
         plugin: PluginDescription,
     },
@@ -39,6 +48,12 @@ pub enum ProxyNotification {
     RemovePlugin {
         plugin: PluginDescription,
     },
+    /// Run a command the plugin registered, such as an explorer menu entry
+    RunPluginCommand {
+        plugin: String,
+        command: String,
+        args: Value,
+    },
     GitCommit {
         message: String,
         diffs: Vec<FileDiff>,
@@ -66,6 +81,59 @@ pub enum ProxyNotification {
     TerminalClose {
         term_id: TermId,
     },
+    /// Show the path in the platform's file manager
+    RevealInFileManager {
+        path: PathBuf,
+    },
+    /// Open the platform's terminal emulator in the directory
+    OpenInSystemTerminal {
+        path: PathBuf,
+    },
+    /// Open the file with the application the platform associates with it
+    OpenWithDefaultApplication {
+        path: PathBuf,
+    },
+    /// Stop the operation reporting progress with the token, as given by
+    /// `progress_token` in the request that started it
+    CancelWorkDoneProgress {
+        token: String,
+    },
+    /// Search the contents of the files beneath the path, streaming the matches
+    /// back as `SearchResult` notifications with the query id
+    StartSearch {
+        query_id: u64,
+        pattern: String,
+        path: PathBuf,
+    },
+    /// Stop the search with the query id, if it's still running
+    CancelSearch {
+        query_id: u64,
+    },
+    /// Add up the sizes of all the files beneath the directory, ignored ones
+    /// included, reporting the total as a `DirSize` notification
+    ComputeDirSize {
+        path: PathBuf,
+    },
+    /// Stop adding up the sizes of the files beneath the directory, if it's
+    /// still running
+    CancelDirSize {
+        path: PathBuf,
+    },
+    /// Stop sending the further chunks of the directory being read, if its
+    /// listing is still coming in
+    CancelReadDir {
+        path: PathBuf,
+    },
+    /// Set the glob patterns of the paths that are left out when listing
+    /// directories and files, replacing the previous ones
+    SetFileExclude {
+        patterns: Vec<String>,
+    },
+    /// Stop the language server of the language and start it again, such as
+    /// after it crashed
+    RestartLanguageServer {
+        language: String,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,6 +148,12 @@ pub enum ProxyRequest {
         buffer_id: BufferId,
         path: PathBuf,
     },
+    /// Read the file of the buffer again in the encoding, which the buffer is
+    /// then saved in, responding with a `NewBufferResponse`
+    ReopenWithEncoding {
+        buffer_id: BufferId,
+        encoding: String,
+    },
     GetCompletion {
         request_id: usize,
         buffer_id: BufferId,
@@ -105,6 +179,19 @@ pub enum ProxyRequest {
         buffer_id: BufferId,
         position: Position,
     },
+    /// Ask the language server if the symbol at the position can be renamed,
+    /// which responds with the range of the symbol
+    PrepareRename {
+        buffer_id: BufferId,
+        position: Position,
+    },
+    /// Ask the language server for the edits that rename the symbol at the
+    /// position throughout the workspace
+    Rename {
+        buffer_id: BufferId,
+        position: Position,
+        new_name: String,
+    },
     GetDefinition {
         request_id: usize,
         buffer_id: BufferId,
@@ -140,8 +227,35 @@ pub enum ProxyRequest {
     GetFiles {
         path: String,
     },
+    /// Find the files of the workspace whose path fuzzily matches the query,
+    /// best matches first. An empty query returns the first files by path.
+    QueryFiles {
+        query: String,
+        limit: usize,
+    },
     ReadDir {
         path: PathBuf,
+        /// Whether to also read the size, modification time and permissions
+        /// of every entry, which is slower for large directories
+        #[serde(default)]
+        with_metadata: bool,
+        /// If set, only this many entries are included in the response and
+        /// the rest are sent in chunks of the same size as `ListDir` notifications
+        #[serde(default)]
+        chunk_size: Option<usize>,
+        /// Sent back with the chunks, to tell them apart from the chunks of
+        /// the other reads of the directory
+        #[serde(default)]
+        generation: u64,
+    },
+    /// Read the directory and the directories beneath it down to `max_depth`
+    /// levels at once, leaving out the paths matching the configured or the
+    /// given exclude globs
+    ReadDirTree {
+        path: PathBuf,
+        max_depth: usize,
+        #[serde(default)]
+        exclude: Vec<String>,
     },
     Save {
         rev: u64,
@@ -155,20 +269,186 @@ pub enum ProxyRequest {
     },
     CreateFile {
         path: PathBuf,
+        /// The initial content of the file, if it shouldn't be empty
+        #[serde(default)]
+        content: Option<String>,
+    },
+    /// Create a file with the bytes, like an image, failing if it exists
+    CreateBinaryFile {
+        path: PathBuf,
+        content: Vec<u8>,
     },
+    /// Create the directory along with its missing parents, failing if it
+    /// exists
     CreateDirectory {
         path: PathBuf,
     },
+    /// Create the empty file along with its missing parent directories,
+    /// unless it already exists
+    EnsureFile {
+        path: PathBuf,
+    },
+    /// Move the path to the OS trash, or to the trash folder of the workspace
+    /// if there is no OS trash to move it to
     TrashPath {
         path: PathBuf,
+        /// If set, progress is reported as `WorkDoneProgress` with this token
+        #[serde(default)]
+        progress_token: Option<String>,
+    },
+    /// Delete the file, or the directory with everything in it, without
+    /// moving it to the trash
+    DeletePath {
+        path: PathBuf,
+    },
+    /// List the paths in the trash folder of the workspace
+    GetWorkspaceTrash {},
+    /// Move the path in the trash folder of the workspace back to where it
+    /// was, failing if something else is there now
+    RestoreFromTrash {
+        id: String,
     },
     RenamePath {
         from: PathBuf,
         to: PathBuf,
     },
+    /// Read the size, modification time, permissions and git status of the
+    /// paths, which the explorer leaves out of its listings
+    ReadPathsMetadata {
+        paths: Vec<PathBuf>,
+    },
+    /// Check whether the file at the path can be written to
+    IsPathWritable {
+        path: PathBuf,
+    },
+    /// Check whether the file names in the directory of the existing path are
+    /// case sensitive
+    IsCaseSensitive {
+        path: PathBuf,
+    },
+    /// Read the content of a file without opening it as a buffer
+    ReadFile {
+        path: PathBuf,
+    },
+    /// Add the current content of the files to the git index. The new status
+    /// of the repository follows as `DiffInfo` and `DiffFiles`.
+    GitStage {
+        paths: Vec<PathBuf>,
+    },
+    /// Reset the files in the git index to their content in `HEAD`. The new
+    /// status of the repository follows as `DiffInfo` and `DiffFiles`.
+    GitUnstage {
+        paths: Vec<PathBuf>,
+    },
+    /// Add the path to the nearest `.gitignore` above it, or take it out of
+    /// it. Nothing changes when it's already listed, or already not listed.
+    /// The new status of the repository follows as `DiffInfo` and `DiffFiles`.
+    SetGitIgnored {
+        path: PathBuf,
+        ignored: bool,
+    },
+    /// Copy the file/directory to a sibling with an unused name
+    DuplicatePath {
+        path: PathBuf,
+        /// If set, progress is reported as `WorkDoneProgress` with this token,
+        /// and the copy can be cancelled with `CancelWorkDoneProgress`
+        #[serde(default)]
+        progress_token: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReadDirResponse {
     pub items: HashMap<PathBuf, FileNodeItem>,
+    /// Whether more entries will follow as `ListDir` notifications
+    #[serde(default)]
+    pub more: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadDirTreeResponse {
+    /// The directory, with the read directories beneath it marked as `read`
+    pub item: FileNodeItem,
+    /// Whether the tree was cut short because it had too many entries, in
+    /// which case the directories that weren't fully read aren't `read`
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryFilesResponse {
+    pub items: Vec<FileMatch>,
+}
+
+/// A file matching a `QueryFiles` query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMatch {
+    pub path: PathBuf,
+    /// The path relative to the workspace, which the query was matched against
+    pub relative_path: String,
+    pub score: i64,
+    /// The char indices of the matched characters in `relative_path`
+    pub indices: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathsMetadataResponse {
+    /// The paths that could be read, by path
+    pub metadata: HashMap<PathBuf, PathMetadata>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathWritableResponse {
+    pub writable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseSensitiveResponse {
+    pub case_sensitive: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadFileResponse {
+    pub content: String,
+}
+
+/// Where `TrashPath` moved the path to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashLocation {
+    Os,
+    /// The `.lapce-trash` folder at the root of the workspace
+    Workspace,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashPathResponse {
+    pub location: TrashLocation,
+}
+
+/// A path in the trash folder of the workspace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceTrashEntry {
+    pub id: String,
+    /// Where the path is restored to
+    pub original_path: PathBuf,
+    /// When it was trashed, in seconds since the Unix epoch
+    pub trashed_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceTrashResponse {
+    /// The entries, most recently trashed first
+    pub entries: Vec<WorkspaceTrashEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreFromTrashResponse {
+    /// The path it was restored to
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicatePathResponse {
+    /// The path of the newly created copy
+    pub path: PathBuf,
 }
@@ -17,6 +17,14 @@ pub enum FileDiff {
     Renamed(PathBuf, PathBuf),
 }
 
+/// A file with changes in the working tree, or in the index when `staged`. A
+/// file can be listed twice when it has changes in both.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChangedFile {
+    pub diff: FileDiff,
+    pub staged: bool,
+}
+
 impl FileDiff {
     pub fn path(&self) -> &PathBuf {
         match &self {
@@ -0,0 +1,101 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, LineWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+
+/// The environment variable naming the file that the messages of the
+/// [`RpcHandler`](crate::RpcHandler) are logged to. Nothing is logged when
+/// it isn't set.
+pub const RPC_LOG_ENV: &str = "LAPCE_RPC_LOG";
+
+#[derive(Clone, Copy)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// A log of the messages going through an [`RpcHandler`](crate::RpcHandler),
+/// written as a JSON object a line, to attach to bug reports. Only the method,
+/// id and size of the messages are recorded, not their payloads.
+pub struct TrafficLog {
+    writer: Mutex<LineWriter<File>>,
+}
+
+impl TrafficLog {
+    /// Open the log at the file named by [`RPC_LOG_ENV`], if it is set
+    pub fn from_env() -> Option<Self> {
+        let path = std::env::var_os(RPC_LOG_ENV)?;
+        match Self::open(Path::new(&path)) {
+            Ok(log) => Some(log),
+            Err(e) => {
+                eprintln!("can't open the rpc log {path:?}: {e}");
+                None
+            }
+        }
+    }
+
+    /// Open the log at the file, adding to what is already in it
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            writer: Mutex::new(LineWriter::new(file)),
+        })
+    }
+
+    pub fn record(&self, direction: Direction, msg: &Value) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let kind = match (msg.get("method"), msg.get("id")) {
+            (Some(_), Some(_)) => "request",
+            (Some(_), None) => "notification",
+            (None, _) if msg.get("error").is_some() => "error",
+            (None, _) => "response",
+        };
+        let entry = json!({
+            "timestamp": timestamp as u64,
+            "direction": match direction {
+                Direction::Sent => "sent",
+                Direction::Received => "received",
+            },
+            "kind": kind,
+            "method": msg.get("method"),
+            "id": msg.get("id"),
+            "size": json_size(msg),
+        });
+
+        // The whole line is written at once, so that the lines of messages
+        // recorded from different threads don't get mixed up
+        let mut line = entry.to_string();
+        line.push('\n');
+        let _ = self.writer.lock().write_all(line.as_bytes());
+    }
+}
+
+/// The size in bytes of the value as JSON, which is counted as it is
+/// serialized rather than by building the string, as payloads like the
+/// content of files can be large
+fn json_size(value: &Value) -> usize {
+    struct Counter(usize);
+
+    impl Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
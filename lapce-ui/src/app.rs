@@ -38,7 +38,9 @@ pub fn launch() {
                     return;
                 }
                 "-h" | "--help" => {
-                    println!("lapce [-h|--help] [-v|--version] [PATH]");
+                    println!(
+                        "lapce [-h|--help] [-v|--version] [PATH[:LINE[:COLUMN]]]"
+                    );
                     return;
                 }
                 v => {
@@ -235,47 +237,53 @@ impl AppDelegate<LapceData> for LapceAppDelegate {
         data: &mut LapceData,
         _env: &Env,
     ) -> druid::Handled {
-        if let Some(LapceUICommand::NewWindow(from_window_id)) =
-            cmd.get(LAPCE_UI_COMMAND)
-        {
-            let (size, pos) = data
-                .windows
-                .get(from_window_id)
-                // If maximised, use default dimensions instead
-                .filter(|win| !win.maximised)
-                .map(|win| (win.size, win.pos + (50.0, 50.0)))
-                .unwrap_or((Size::new(800.0, 600.0), Point::new(0.0, 0.0)));
-            let info = WindowInfo {
-                size,
-                pos,
-                maximised: false,
-                tabs: TabsInfo {
-                    active_tab: 0,
-                    workspaces: vec![],
-                },
-            };
-            let mut window_data = LapceWindowData::new(
-                data.keypress.clone(),
-                data.panel_orders.clone(),
-                ctx.get_external_handle(),
-                &info,
-                data.db.clone(),
-            );
-            let root = build_window(&mut window_data);
-            let window_id = window_data.window_id;
-            data.windows.insert(window_id, window_data.clone());
-            let desc = new_window_desc(
+        // A new window is empty, unless it's opened with a workspace
+        let (from_window_id, workspaces) = match cmd.get(LAPCE_UI_COMMAND) {
+            Some(LapceUICommand::NewWindow(from_window_id)) => {
+                (from_window_id, Vec::new())
+            }
+            Some(LapceUICommand::OpenInNewWindow {
                 window_id,
-                root,
-                info.size,
-                info.pos,
-                info.maximised,
-                &window_data.config,
-            );
-            ctx.new_window(desc);
-            return druid::Handled::Yes;
-        }
-        druid::Handled::No
+                workspace,
+            }) => (window_id, vec![workspace.clone()]),
+            _ => return druid::Handled::No,
+        };
+        let (size, pos) = data
+            .windows
+            .get(from_window_id)
+            // If maximised, use default dimensions instead
+            .filter(|win| !win.maximised)
+            .map(|win| (win.size, win.pos + (50.0, 50.0)))
+            .unwrap_or((Size::new(800.0, 600.0), Point::new(0.0, 0.0)));
+        let info = WindowInfo {
+            size,
+            pos,
+            maximised: false,
+            tabs: TabsInfo {
+                active_tab: 0,
+                workspaces,
+            },
+        };
+        let mut window_data = LapceWindowData::new(
+            data.keypress.clone(),
+            data.panel_orders.clone(),
+            ctx.get_external_handle(),
+            &info,
+            data.db.clone(),
+        );
+        let root = build_window(&mut window_data);
+        let window_id = window_data.window_id;
+        data.windows.insert(window_id, window_data.clone());
+        let desc = new_window_desc(
+            window_id,
+            root,
+            info.size,
+            info.pos,
+            info.maximised,
+            &window_data.config,
+        );
+        ctx.new_window(desc);
+        druid::Handled::Yes
     }
 
     fn window_added(
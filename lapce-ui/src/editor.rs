@@ -18,7 +18,7 @@ use lapce_core::{
     mode::{Mode, VisualMode},
 };
 use lapce_data::command::CommandKind;
-use lapce_data::data::{EditorView, LapceData};
+use lapce_data::data::{DragContent, EditorView, LapceData};
 use lapce_data::document::{BufferContent, LocalBufferKind};
 use lapce_data::history::DocumentHistory;
 use lapce_data::keypress::KeyPressFocus;
@@ -295,6 +295,13 @@ impl LapceEditor {
                     data: None,
                 },
             }),
+            MenuKind::Item(MenuItem {
+                desc: None,
+                command: LapceCommand {
+                    kind: CommandKind::Focus(FocusCommand::RenameSymbol),
+                    data: None,
+                },
+            }),
             MenuKind::Separator,
             MenuKind::Item(MenuItem {
                 desc: None,
@@ -1712,9 +1719,25 @@ impl Widget<LapceTabData> for LapceEditor {
                 );
                 data.update_from_editor_buffer_data(editor_data, &editor, &doc);
             }
-            Event::MouseUp(_mouse_event) => {
+            Event::MouseUp(mouse_event) => {
                 self.mouse_mods = Modifiers::empty();
                 ctx.set_active(false);
+                // A path dragged from the file explorer is inserted where it's dropped
+                if let Some((_, _, DragContent::ExplorerPath(path))) =
+                    data.drag.clone().as_ref()
+                {
+                    let doc = data.main_split.editor_doc(self.view_id);
+                    let editor =
+                        data.main_split.editors.get(&self.view_id).unwrap().clone();
+                    let mut editor_data = data.editor_view_content(self.view_id);
+                    editor_data.drop_path(ctx, mouse_event.pos, path, &data.config);
+                    data.update_from_editor_buffer_data(editor_data, &editor, &doc);
+                    ctx.submit_command(Command::new(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::Focus,
+                        Target::Widget(self.view_id),
+                    ));
+                }
             }
             Event::MouseDown(mouse_event) => {
                 self.mouse_mods = mouse_event.mods;
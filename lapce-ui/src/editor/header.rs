@@ -1,4 +1,7 @@
-use std::{iter::Iterator, path::PathBuf};
+use std::{
+    iter::Iterator,
+    path::{Path, PathBuf},
+};
 
 use druid::{
     piet::{Text, TextAttribute, TextLayout as TextLayoutTrait, TextLayoutBuilder},
@@ -13,6 +16,7 @@ use lapce_data::{
     data::{LapceTabData, LapceWorkspace},
     document::BufferContent,
     editor::LapceEditorBufferData,
+    explorer::display_path,
 };
 
 use crate::{
@@ -111,6 +115,7 @@ impl LapceEditorHeader {
         ctx: &mut PaintCtx,
         data: &LapceEditorBufferData,
         workspace: &LapceWorkspace,
+        home: Option<&Path>,
     ) {
         let size = ctx.size();
         let rect = size.to_rect();
@@ -148,7 +153,7 @@ impl LapceEditorHeader {
 
             ctx.with_save(|ctx| {
                 ctx.clip(clip_rect);
-                let (svg, svg_color) = file_svg(&path);
+                let (svg, svg_color) = file_svg(&path, &data.config);
 
                 let font_size = data.config.ui.font_size() as f64;
 
@@ -180,9 +185,8 @@ impl LapceEditorHeader {
                 }
                 let folder = path
                     .parent()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("")
-                    .to_string();
+                    .map(|s| display_path(s, home))
+                    .unwrap_or_default();
                 if !folder.is_empty() {
                     file_name = format!("{} {}", file_name, folder);
                 }
@@ -316,6 +320,7 @@ impl Widget<LapceTabData> for LapceEditorHeader {
             ctx,
             &data.editor_view_content(self.view_id),
             &data.workspace,
+            data.abbreviated_home(),
         );
     }
 }
@@ -285,7 +285,7 @@ impl LapceEditorTab {
                         }
                     }
                 }
-                DragContent::Panel(..) => {}
+                DragContent::Panel(..) | DragContent::ExplorerPath(..) => {}
             }
         }
     }
@@ -15,6 +15,7 @@ use lapce_data::{
     config::LapceTheme,
     data::{EditorTabChild, LapceTabData},
     document::BufferContent,
+    explorer::display_path,
     proxy::VERSION,
 };
 
@@ -99,7 +100,7 @@ impl LapceEditorTabHeader {
                 let editor_buffer = data.editor_view_content(*view_id);
 
                 if let BufferContent::File(path) = &editor_buffer.editor.content {
-                    (svg, _) = file_svg(path);
+                    (svg, _) = file_svg(path, &data.config);
                     if let Some(file_name) = path.file_name() {
                         if let Some(s) = file_name.to_str() {
                             text = s.to_string();
@@ -114,9 +115,8 @@ impl LapceEditorTabHeader {
                     }
                     hint = path
                         .parent()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or("")
-                        .to_string();
+                        .map(|s| display_path(s, data.abbreviated_home()))
+                        .unwrap_or_default();
                 } else if let BufferContent::Scratch(..) =
                     &editor_buffer.editor.content
                 {
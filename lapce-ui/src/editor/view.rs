@@ -169,6 +169,9 @@ impl LapceEditorView {
                     Arc::make_mut(&mut data.source_control).active = self.view_id;
                 }
                 LocalBufferKind::PathName => {}
+                LocalBufferKind::ExplorerFilter => {
+                    data.focus_area = FocusArea::Panel(PanelKind::FileExplorer);
+                }
                 LocalBufferKind::Empty => {
                     data.focus_area = FocusArea::Editor;
                     data.main_split.active = Arc::new(Some(self.view_id));
@@ -1,50 +1,103 @@
 use std::sync::Arc;
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use druid::menu::MenuEventCtx;
 use druid::{
+    kurbo::{Affine, Line},
     piet::{Text, TextLayout as PietTextLayout, TextLayoutBuilder},
-    BoxConstraints, Command, Cursor, Env, Event, EventCtx, LayoutCtx, LifeCycle,
-    LifeCycleCtx, PaintCtx, Point, Rect, RenderContext, Size, Target, UpdateCtx,
-    Widget, WidgetExt, WidgetId, WidgetPod,
+    Application, BoxConstraints, Command, Cursor, Env, Event, EventCtx, LayoutCtx,
+    LifeCycle, LifeCycleCtx, PaintCtx, Point, Rect, RenderContext, Size, Target,
+    TimerToken, UpdateCtx, Vec2, Widget, WidgetExt, WidgetId, WidgetPod,
 };
-use druid::{ExtEventSink, KbKey, WindowId};
-use lapce_data::data::{LapceData, LapceEditorData};
+use druid::{ExtEventSink, KbKey, MouseButton, WindowId};
+use lapce_data::data::{DragContent, LapceData, LapceEditorData, LapceWorkspace};
 use lapce_data::document::{BufferContent, LocalBufferKind};
-use lapce_data::explorer::FileExplorerData;
 use lapce_data::explorer::Naming;
+use lapce_data::explorer::{
+    abbreviate_home, compact_tail, display_path, has_placeholder_row, human_size,
+    import_path, is_node_visible, name_without_extension, outermost_paths,
+    path_names_summary, FileExplorerData, PinnedPath, RepaintCoalescer,
+    RepaintRequest, CLIPBOARD_PNG_FORMAT,
+};
 use lapce_data::panel::PanelKind;
 use lapce_data::proxy::LapceProxy;
 use lapce_data::{
-    command::LapceUICommand,
+    alert::AlertContentData,
     command::LAPCE_UI_COMMAND,
+    command::{CommandKind, LapceCommand, LapceUICommand, LapceWorkbenchCommand},
     config::{Config, LapceTheme},
     data::LapceTabData,
 };
 use lapce_rpc::file::FileNodeItem;
+use serde_json::json;
 
 use crate::editor::view::LapceEditorView;
 use crate::{
-    panel::{LapcePanel, PanelHeaderKind, PanelSizing},
+    panel::{
+        paint_section_header_background, LapcePanel, PanelHeaderKind, PanelSizing,
+    },
     scroll::LapceScroll,
-    svg::{file_svg, get_svg},
+    svg::{file_svg, folder_svg, get_svg},
 };
 
-#[allow(clippy::too_many_arguments)]
-/// Paint the file node item at its position
+/// What painting the rows of a list of files needs besides the rows
+/// themselves, which is the same for all of them
+pub struct RowPaintCtx<'a> {
+    pub line_height: f64,
+    pub width: f64,
+    /// The first and last rows in view, past which nothing is painted
+    pub min: usize,
+    pub max: usize,
+    pub active: Option<&'a Path>,
+    pub hovered: Option<usize>,
+    pub config: &'a Config,
+    /// The chevron of each directory row that is painted, by its index
+    pub toggle_rects: &'a mut HashMap<usize, Rect>,
+}
+
+/// How a single row is shown besides its node
+#[derive(Default)]
+struct RowState {
+    selected: bool,
+    reading: bool,
+    search_matches: Option<usize>,
+    size: Option<u64>,
+}
+
+/// The name input of the file list, which is painted in place of the row it
+/// is shown at, or after it when naming a new file
+pub struct NameInputPaint<'a> {
+    pub naming: Option<(usize, &'a Naming)>,
+    pub input: &'a mut NameEditInput,
+    pub drawn: bool,
+}
+
+/// Paint the file node item at its position, labelled with `name`
 fn paint_single_file_node_item(
     ctx: &mut PaintCtx,
+    rows: &mut RowPaintCtx,
     item: &FileNodeItem,
-    line_height: f64,
-    width: f64,
+    name: &str,
     level: usize,
     current: usize,
-    active: Option<&Path>,
-    hovered: Option<usize>,
-    config: &Config,
-    toggle_rects: &mut HashMap<usize, Rect>,
+    state: RowState,
 ) {
-    let background = if Some(item.path_buf.as_ref()) == active {
+    let RowState {
+        selected,
+        reading,
+        search_matches,
+        size,
+    } = state;
+    let line_height = rows.line_height;
+    let width = rows.width;
+    let active = rows.active;
+    let hovered = rows.hovered;
+    let config = rows.config;
+    let background = if selected || Some(item.path_buf.as_ref()) == active {
         Some(LapceTheme::PANEL_CURRENT)
     } else if Some(current) == hovered {
         Some(LapceTheme::PANEL_HOVERED)
@@ -64,10 +117,37 @@ fn paint_single_file_node_item(
         );
     }
 
+    // Themes can outline the row too, with an accent bar on the left edge of
+    // the active one
+    let border = if selected || Some(item.path_buf.as_ref()) == active {
+        Some((LapceTheme::EXPLORER_ACTIVE_BORDER, true))
+    } else if Some(current) == hovered {
+        Some((LapceTheme::EXPLORER_HOVER_BORDER, false))
+    } else {
+        None
+    };
+    if let Some((color, accent)) = border.and_then(|(name, accent)| {
+        config
+            .get_color(name)
+            .filter(|color| color.as_rgba8().3 > 0)
+            .map(|color| (color, accent))
+    }) {
+        let rect = Size::new(width, line_height)
+            .to_rect()
+            .with_origin(Point::new(
+                0.0,
+                current as f64 * line_height - line_height,
+            ));
+        ctx.stroke(rect.inset(-0.5), color, 1.0);
+        if accent {
+            ctx.fill(rect.with_size(Size::new(2.0, line_height)), color);
+        }
+    }
+
     let y = current as f64 * line_height - line_height;
-    let svg_y = y + 4.0;
     let svg_size = 15.0;
-    let padding = 15.0 * level as f64;
+    let svg_y = y + (line_height - svg_size) / 2.0;
+    let padding = config.ui.explorer_indent_width() as f64 * level as f64;
     if item.is_dir {
         let icon_name = if item.open {
             "chevron-down.svg"
@@ -83,41 +163,171 @@ fn paint_single_file_node_item(
             rect,
             Some(config.get_color_unchecked(LapceTheme::EDITOR_FOREGROUND)),
         );
-        toggle_rects.insert(current, rect);
+        rows.toggle_rects.insert(current, rect);
 
-        let icon_name = if item.open {
-            "default_folder_opened.svg"
-        } else {
-            "default_folder.svg"
-        };
-        let svg = get_svg(icon_name).unwrap();
+        let svg = folder_svg(&item.path_buf, item.open, config);
         let rect = Size::new(svg_size, svg_size)
             .to_rect()
             .with_origin(Point::new(1.0 + 16.0 + padding, svg_y));
         ctx.draw_svg(&svg, rect, None);
     } else {
-        let (svg, svg_color) = file_svg(&item.path_buf);
+        let (svg, svg_color) = file_svg(&item.path_buf, config);
         let rect = Size::new(svg_size, svg_size)
             .to_rect()
             .with_origin(Point::new(1.0 + 16.0 + padding, svg_y));
         ctx.draw_svg(&svg, rect, svg_color);
     }
+    if item.is_symlink {
+        let link_size = 9.0;
+        let svg = get_svg("link.svg").unwrap();
+        let rect =
+            Size::new(link_size, link_size)
+                .to_rect()
+                .with_origin(Point::new(
+                    1.0 + 16.0 + padding + svg_size - link_size + 2.0,
+                    svg_y + svg_size - link_size + 2.0,
+                ));
+        ctx.fill(
+            rect,
+            config.get_color_unchecked(LapceTheme::PANEL_BACKGROUND),
+        );
+        ctx.draw_svg(
+            &svg,
+            rect,
+            Some(config.get_color_unchecked(LapceTheme::EDITOR_FOREGROUND)),
+        );
+    }
+    if item.is_dir && (item.loading || reading) {
+        paint_loading_spinner(
+            ctx,
+            Point::new(width - svg_size / 2.0 - 10.0, svg_y + svg_size / 2.0),
+            config,
+        );
+    } else if item.readonly {
+        let svg = get_svg("lock.svg").unwrap();
+        let rect = Size::new(svg_size, svg_size)
+            .to_rect()
+            .with_origin(Point::new(width - svg_size - 10.0, svg_y));
+        ctx.draw_svg(
+            &svg,
+            rect,
+            Some(config.get_color_unchecked(LapceTheme::EDITOR_DIM)),
+        );
+    }
+    // Files with matches of the search are highlighted, with the number of
+    // matches on the right
+    let name_color = if search_matches.is_some() {
+        LapceTheme::EDITOR_FOCUS
+    } else if item.git_ignored {
+        LapceTheme::EDITOR_DIM
+    } else {
+        LapceTheme::EDITOR_FOREGROUND
+    };
     let text_layout = ctx
         .text()
-        .new_text_layout(
-            item.path_buf
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_string(),
-        )
+        .new_text_layout(name.to_string())
         .font(config.ui.font_family(), config.ui.font_size() as f64)
-        .text_color(
-            config
-                .get_color_unchecked(LapceTheme::EDITOR_FOREGROUND)
-                .clone(),
-        )
+        .text_color(config.get_color_unchecked(name_color).clone())
+        .build()
+        .unwrap();
+    ctx.draw_text(
+        &text_layout,
+        Point::new(
+            38.0 + padding,
+            y + (line_height - text_layout.size().height) / 2.0,
+        ),
+    );
+
+    // Only directories that were read have their children to count, as the
+    // count isn't worth reading a directory for
+    if config.ui.explorer_show_child_count()
+        && item.is_dir
+        && !item.open
+        && item.read
+    {
+        let name_width = text_layout.size().width;
+        let text_layout = ctx
+            .text()
+            .new_text_layout(item.children.len().to_string())
+            .font(config.ui.font_family(), config.ui.font_size() as f64)
+            .text_color(config.get_color_unchecked(LapceTheme::EDITOR_DIM).clone())
+            .build()
+            .unwrap();
+        ctx.draw_text(
+            &text_layout,
+            Point::new(
+                38.0 + padding + name_width + 8.0,
+                y + (line_height - text_layout.size().height) / 2.0,
+            ),
+        );
+    }
+
+    // The match count and the size are laid out from the right, leaving room
+    // for the spinner or the lock icon
+    let mut right = if (item.is_dir && (item.loading || reading)) || item.readonly {
+        width - svg_size - 20.0
+    } else {
+        width - 10.0
+    };
+    let badges = [
+        search_matches.map(|count| (count.to_string(), LapceTheme::EDITOR_FOCUS)),
+        size.map(|size| (human_size(size), LapceTheme::EDITOR_DIM)),
+    ];
+    for (text, color) in badges.into_iter().flatten() {
+        let text_layout = ctx
+            .text()
+            .new_text_layout(text)
+            .font(config.ui.font_family(), config.ui.font_size() as f64)
+            .text_color(config.get_color_unchecked(color).clone())
+            .build()
+            .unwrap();
+        right -= text_layout.size().width;
+        ctx.draw_text(
+            &text_layout,
+            Point::new(right, y + (line_height - text_layout.size().height) / 2.0),
+        );
+        right -= 8.0;
+    }
+}
+
+/// Paint a spinning arc around the center, rotated by the current time so that
+/// it turns as long as it keeps being repainted
+fn paint_loading_spinner(ctx: &mut PaintCtx, center: Point, config: &Config) {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() % 1000)
+        .unwrap_or(0);
+    let arc = druid::kurbo::Arc {
+        center,
+        radii: Vec2::new(5.0, 5.0),
+        start_angle: millis as f64 / 1000.0 * std::f64::consts::TAU,
+        sweep_angle: std::f64::consts::PI * 1.5,
+        x_rotation: 0.0,
+    };
+    ctx.stroke(arc, config.get_color_unchecked(LapceTheme::EDITOR_DIM), 1.5);
+}
+
+/// Paint the row shown after the children of a directory that is still loading,
+/// or in place of the children of a directory that is empty
+fn paint_placeholder_row(
+    ctx: &mut PaintCtx,
+    item: &FileNodeItem,
+    line_height: f64,
+    level: usize,
+    current: usize,
+    config: &Config,
+) {
+    let y = current as f64 * line_height - line_height;
+    let padding = config.ui.explorer_indent_width() as f64 * level as f64;
+    let text_layout = ctx
+        .text()
+        .new_text_layout(if item.loading {
+            "Loading…"
+        } else {
+            "(empty)"
+        })
+        .font(config.ui.font_family(), config.ui.font_size() as f64)
+        .text_color(config.get_color_unchecked(LapceTheme::EDITOR_DIM).clone())
         .build()
         .unwrap();
     ctx.draw_text(
@@ -129,43 +339,104 @@ fn paint_single_file_node_item(
     );
 }
 
+/// Paint the files beneath a directory that is shown as a flat list, all at the
+/// same level and labelled with their path relative to the directory
+fn paint_flat_file_node_items(
+    ctx: &mut PaintCtx,
+    rows: &mut RowPaintCtx,
+    dir: &FileNodeItem,
+    flat_files: &[FileNodeItem],
+    level: usize,
+    current: usize,
+    file_explorer: &FileExplorerData,
+) -> usize {
+    let mut i = current;
+    for file in flat_files {
+        i += 1;
+        if i > rows.max {
+            return i;
+        }
+        if i >= rows.min {
+            let name = file
+                .path_buf
+                .strip_prefix(&dir.path_buf)
+                .unwrap_or(&file.path_buf)
+                .to_string_lossy();
+            let state = RowState {
+                selected: file_explorer.selected_paths.contains(&file.path_buf),
+                reading: false,
+                search_matches: file_explorer
+                    .search_matches
+                    .get(&file.path_buf)
+                    .copied(),
+                size: (rows.config.ui.explorer_show_sizes() && file.metadata_read)
+                    .then(|| file.size),
+            };
+            paint_single_file_node_item(ctx, rows, file, &name, level, i, state);
+        }
+    }
+    i
+}
+
+/// Paint the guide line of an open directory, running down the chevron column
+/// from below the directory's row to the end of its last child's row
+fn paint_indent_guide(
+    ctx: &mut PaintCtx,
+    line_height: f64,
+    level: usize,
+    current: usize,
+    last: usize,
+    config: &Config,
+) {
+    let padding = config.ui.explorer_indent_width() as f64 * level as f64;
+    // Center of the chevron, which is 15 wide and offset by 1
+    let x = (1.0 + padding + 7.5).floor() + 0.5;
+    ctx.stroke(
+        Line::new(
+            Point::new(x, current as f64 * line_height),
+            Point::new(x, last as f64 * line_height),
+        ),
+        config.get_color_unchecked(LapceTheme::PANEL_INDENT_GUIDE),
+        1.0,
+    );
+}
+
 /// Paint the file node item, if it is in view, and its children
 #[allow(clippy::too_many_arguments)]
 pub fn paint_file_node_item(
     ctx: &mut PaintCtx,
     env: &Env,
+    rows: &mut RowPaintCtx,
+    name_input: &mut NameInputPaint,
     item: &FileNodeItem,
-    min: usize,
-    max: usize,
-    line_height: f64,
-    width: f64,
+    name: &str,
     level: usize,
     current: usize,
-    active: Option<&Path>,
-    hovered: Option<usize>,
-    naming: Option<&Naming>,
-    name_edit_input: &mut NameEditInput,
-    drawn_name_input: &mut bool,
     data: &LapceTabData,
-    config: &Config,
-    toggle_rects: &mut HashMap<usize, Rect>,
 ) -> usize {
-    if current > max {
+    if current > rows.max {
         return current;
     }
-    if current + item.children_open_count < min {
+    if current + item.children_open_count < rows.min {
         return current + item.children_open_count;
     }
 
     let mut i = current;
 
-    if current >= min {
+    if current >= rows.min {
         let mut should_paint_file_node = true;
-        if !*drawn_name_input {
-            if let Some(naming) = naming {
-                if current == naming.list_index() {
-                    draw_name_input(ctx, data, env, &mut i, naming, name_edit_input);
-                    *drawn_name_input = true;
+        if !name_input.drawn {
+            if let Some((naming_index, naming)) = name_input.naming {
+                if current == naming_index {
+                    draw_name_input(
+                        ctx,
+                        data,
+                        env,
+                        &mut i,
+                        naming,
+                        name_input.input,
+                    );
+                    name_input.drawn = true;
                     // If it is renaming then don't draw the underlying file node
                     should_paint_file_node =
                         !matches!(naming, Naming::Renaming { .. })
@@ -174,50 +445,104 @@ pub fn paint_file_node_item(
         }
 
         if should_paint_file_node {
-            paint_single_file_node_item(
-                ctx,
-                item,
-                line_height,
-                width,
-                level,
-                i,
-                active,
-                hovered,
-                config,
-                toggle_rects,
-            );
+            let file_explorer = &data.file_explorer;
+            let state = RowState {
+                selected: file_explorer.selected_paths.contains(&item.path_buf),
+                reading: file_explorer.reading_dirs.contains(&item.path_buf),
+                search_matches: file_explorer
+                    .search_matches
+                    .get(&item.path_buf)
+                    .copied(),
+                size: shown_size(data, item),
+            };
+            paint_single_file_node_item(ctx, rows, item, name, level, i, state);
         }
     }
 
-    if item.open {
+    let matches = data.file_explorer.matches.as_ref();
+    if let (true, Some(flat_files)) =
+        (item.open, data.file_explorer.flat_dirs.get(&item.path_buf))
+    {
+        i = paint_flat_file_node_items(
+            ctx,
+            rows,
+            item,
+            flat_files,
+            level + 1,
+            i,
+            &data.file_explorer,
+        );
+    } else if item.open {
         for item in item.sorted_children() {
+            if !is_node_visible(item, matches) {
+                continue;
+            }
+            let (item, name) = child_row(item, data, matches);
             i = paint_file_node_item(
                 ctx,
                 env,
+                rows,
+                name_input,
                 item,
-                min,
-                max,
-                line_height,
-                width,
+                &name,
                 level + 1,
                 i + 1,
-                active,
-                hovered,
-                naming,
-                name_edit_input,
-                drawn_name_input,
                 data,
-                config,
-                toggle_rects,
             );
-            if i > max {
-                return i;
+            if i > rows.max {
+                break;
+            }
+        }
+
+        let config = rows.config;
+        if has_placeholder_row(item) && i <= rows.max {
+            i += 1;
+            if i >= rows.min {
+                paint_placeholder_row(
+                    ctx,
+                    item,
+                    rows.line_height,
+                    level + 1,
+                    i,
+                    config,
+                );
             }
         }
+
+        if config.ui.explorer_indent_guides() && i > current {
+            paint_indent_guide(ctx, rows.line_height, level, current, i, config);
+        }
     }
     i
 }
 
+/// The node and name of the row shown for a child of an open directory. When
+/// compacting folders that is the last of its chain of single directories,
+/// named by their joined names.
+fn child_row<'a>(
+    item: &'a FileNodeItem,
+    data: &LapceTabData,
+    matches: Option<&HashSet<PathBuf>>,
+) -> (&'a FileNodeItem, String) {
+    if !data.file_explorer.compact_folders() {
+        return (item, file_name(&item.path_buf));
+    }
+    let tail = compact_tail(item, matches);
+    let name = item
+        .path_buf
+        .parent()
+        .and_then(|parent| tail.path_buf.strip_prefix(parent).ok())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_name(&tail.path_buf));
+    (tail, name)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
 fn draw_name_input(
     ctx: &mut PaintCtx,
     data: &LapceTabData,
@@ -289,7 +614,31 @@ pub fn get_item_children_mut(
 
 pub struct FileExplorer {
     widget_id: WidgetId,
-    file_list: WidgetPod<LapceTabData, Box<dyn Widget<LapceTabData>>>,
+    filter_input: WidgetPod<LapceTabData, Box<dyn Widget<LapceTabData>>>,
+    filter_input_height: f64,
+    file_list:
+        WidgetPod<LapceTabData, LapceScroll<LapceTabData, FileExplorerFileList>>,
+    /// The list index of the naming input that was last scrolled into view
+    scrolled_naming_index: Option<usize>,
+    /// The active file that was last scrolled into view
+    scrolled_active: Option<PathBuf>,
+    /// The selected row that was last scrolled into view
+    scrolled_selected: Option<usize>,
+    /// The height of the breadcrumb above the list, shown while the tree is
+    /// narrowed to a directory
+    breadcrumb_height: f64,
+    /// The segment of each ancestor in the breadcrumb painted in the last
+    /// frame, and the directory to narrow to when it is clicked
+    breadcrumb_rects: Vec<(Rect, Option<PathBuf>)>,
+    /// The index of the pinned path under the mouse in the favorites, which
+    /// are shown between the breadcrumb and the list
+    favorites_hovered: Option<usize>,
+    /// The row under the mouse in the recent section, which is shown between
+    /// the favorites and the list. The title is row 0.
+    recent_hovered: Option<usize>,
+    /// The scroll offset to go back to once the list is tall enough for it,
+    /// which it isn't until the restored open directories have been read
+    restore_scroll: Option<f64>,
 }
 
 impl FileExplorer {
@@ -311,13 +660,409 @@ impl FileExplorer {
         let view_id = editor.view_id;
         data.main_split.editors.insert(view_id, Arc::new(editor));
         // Create the file listing
-        let file_list = LapceScroll::new(FileExplorerFileList::new(WidgetPod::new(
-            input.boxed(),
-        )));
+        let file_list = LapceScroll::new(FileExplorerFileList::new(
+            WidgetPod::new(input.boxed()),
+            &data.config,
+        ));
+
+        // Create the input for filtering the file listing by name
+        let filter_input = LapceEditorView::new(
+            data.file_explorer.filter_editor_view_id,
+            WidgetId::next(),
+            None,
+        )
+        .hide_header()
+        .hide_gutter()
+        .padding((10.0, 5.0));
 
         Self {
             widget_id: data.file_explorer.widget_id,
-            file_list: WidgetPod::new(file_list.boxed()),
+            filter_input: WidgetPod::new(filter_input.boxed()),
+            filter_input_height: 35.0,
+            file_list: WidgetPod::new(file_list),
+            scrolled_naming_index: None,
+            scrolled_active: None,
+            scrolled_selected: None,
+            breadcrumb_height: 25.0,
+            breadcrumb_rects: Vec::new(),
+            favorites_hovered: None,
+            recent_hovered: None,
+            restore_scroll: Some(data.file_explorer.scroll_offset)
+                .filter(|offset| *offset > 0.0),
+        }
+    }
+
+    /// The height of the breadcrumb in the current state, which is only shown
+    /// while the tree is narrowed to a directory
+    fn current_breadcrumb_height(&self, data: &LapceTabData) -> f64 {
+        if data.file_explorer.display_root.is_some() {
+            self.breadcrumb_height
+        } else {
+            0.0
+        }
+    }
+
+    /// The height of the favorites, which is a title row followed by a row for
+    /// each pinned path, or nothing when there aren't any
+    fn current_favorites_height(&self, data: &LapceTabData) -> f64 {
+        let pinned = data.file_explorer.pinned.len();
+        if pinned == 0 {
+            0.0
+        } else {
+            (pinned + 1) as f64 * self.file_list.widget().child().line_height
+        }
+    }
+
+    fn favorites_top(&self, data: &LapceTabData) -> f64 {
+        self.filter_input_height + self.current_breadcrumb_height(data)
+    }
+
+    /// The index of the pinned path whose row in the favorites is at the point
+    fn pinned_index_at(&self, data: &LapceTabData, pos: Point) -> Option<usize> {
+        let y = pos.y - self.favorites_top(data);
+        if y < 0.0 || y >= self.current_favorites_height(data) {
+            return None;
+        }
+        let row = (y / self.file_list.widget().child().line_height) as usize;
+        // The first row is the title
+        row.checked_sub(1)
+    }
+
+    /// The recent files that are listed, which are no more than the limit, as
+    /// it may have been lowered since they were opened
+    fn shown_recent<'a>(&self, data: &'a LapceTabData) -> &'a [PathBuf] {
+        let recent = &data.file_explorer.recent;
+        &recent[..recent
+            .len()
+            .min(data.config.ui.explorer_recent_files_limit())]
+    }
+
+    /// The height of the recent section, which is a title row followed by a
+    /// row for each recent file while it is expanded, or nothing when there
+    /// aren't any
+    fn current_recent_height(&self, data: &LapceTabData) -> f64 {
+        let recent = self.shown_recent(data).len();
+        if recent == 0 {
+            return 0.0;
+        }
+        let rows = if data.file_explorer.recent_open {
+            recent + 1
+        } else {
+            1
+        };
+        rows as f64 * self.file_list.widget().child().line_height
+    }
+
+    fn recent_top(&self, data: &LapceTabData) -> f64 {
+        self.favorites_top(data) + self.current_favorites_height(data)
+    }
+
+    fn list_top(&self, data: &LapceTabData) -> f64 {
+        self.recent_top(data) + self.current_recent_height(data)
+    }
+
+    /// The row of the recent section at the point, where the title is row 0
+    /// and the recent files follow it
+    fn recent_row_at(&self, data: &LapceTabData, pos: Point) -> Option<usize> {
+        let y = pos.y - self.recent_top(data);
+        if y < 0.0 || y >= self.current_recent_height(data) {
+            return None;
+        }
+        Some((y / self.file_list.widget().child().line_height) as usize)
+    }
+
+    fn show_recent_menu(&self, ctx: &mut EventCtx, pos: Point) {
+        let mut menu = druid::Menu::<LapceData>::new("Recent");
+        let item = druid::MenuItem::new("Clear Recent Files").command(Command::new(
+            LAPCE_UI_COMMAND,
+            LapceUICommand::ClearExplorerRecent,
+            Target::Auto,
+        ));
+        menu = menu.entry(item);
+        ctx.show_context_menu::<LapceData>(menu, ctx.to_window(pos));
+    }
+
+    /// Open a pinned file, or reveal and expand a pinned directory in the tree
+    fn open_pinned(
+        &self,
+        ctx: &mut EventCtx,
+        data: &mut LapceTabData,
+        pinned: &PinnedPath,
+    ) {
+        if pinned.is_dir {
+            let file_explorer = Arc::make_mut(&mut data.file_explorer);
+            file_explorer.reveal_path(&pinned.path);
+            file_explorer.expand_dir(&pinned.path);
+            file_explorer.active_selected = Some(pinned.path.clone());
+            ctx.request_layout();
+        } else {
+            open_file(ctx, data, &pinned.path);
+        }
+    }
+
+    fn show_pinned_menu(&self, ctx: &mut EventCtx, pinned: &PinnedPath, pos: Point) {
+        let mut menu = druid::Menu::<LapceData>::new("Favorites");
+        let item =
+            druid::MenuItem::new("Unpin from Explorer").command(Command::new(
+                LAPCE_UI_COMMAND,
+                LapceUICommand::UnpinExplorerPath {
+                    path: pinned.path.clone(),
+                },
+                Target::Auto,
+            ));
+        menu = menu.entry(item);
+        let item =
+            druid::MenuItem::new("Reveal in File Manager").command(Command::new(
+                LAPCE_UI_COMMAND,
+                LapceUICommand::RevealInFileManager {
+                    path: pinned.path.clone(),
+                },
+                Target::Auto,
+            ));
+        menu = menu.entry(item);
+        ctx.show_context_menu::<LapceData>(menu, ctx.to_window(pos));
+    }
+
+    /// Paint the pinned paths with the same rows as the tree, below a title
+    fn paint_favorites(&self, ctx: &mut PaintCtx, data: &LapceTabData) {
+        let pinned = &data.file_explorer.pinned;
+        if pinned.is_empty() {
+            return;
+        }
+        let line_height = self.file_list.widget().child().line_height;
+        let top = self.favorites_top(data);
+        let width = ctx.size().width;
+
+        let title = ctx
+            .text()
+            .new_text_layout("Favorites")
+            .font(
+                data.config.ui.font_family(),
+                data.config.ui.font_size() as f64,
+            )
+            .text_color(
+                data.config
+                    .get_color_unchecked(LapceTheme::EDITOR_DIM)
+                    .clone(),
+            )
+            .build()
+            .unwrap();
+        ctx.draw_text(
+            &title,
+            Point::new(10.0, top + (line_height - title.size().height) / 2.0),
+        );
+
+        ctx.with_save(|ctx| {
+            ctx.transform(Affine::translate((0.0, top + line_height)));
+            let mut toggle_rects = HashMap::new();
+            let mut rows = RowPaintCtx {
+                line_height,
+                width,
+                min: 0,
+                max: usize::MAX,
+                active: data.file_explorer.active_selected.as_deref(),
+                hovered: self.favorites_hovered.map(|hovered| hovered + 1),
+                config: &data.config,
+                toggle_rects: &mut toggle_rects,
+            };
+            for (i, pinned) in pinned.iter().enumerate() {
+                let node = shown_node(data, &pinned.path, pinned.is_dir);
+                let state = RowState {
+                    search_matches: data
+                        .file_explorer
+                        .search_matches
+                        .get(&pinned.path)
+                        .copied(),
+                    size: shown_size(data, &node),
+                    ..RowState::default()
+                };
+                paint_single_file_node_item(
+                    ctx,
+                    &mut rows,
+                    &node,
+                    &file_name(&pinned.path),
+                    0,
+                    i + 1,
+                    state,
+                );
+            }
+        });
+    }
+
+    /// Paint the recent files with the same rows as the tree, below a title
+    /// that expands and collapses them
+    fn paint_recent(&self, ctx: &mut PaintCtx, data: &LapceTabData) {
+        let recent = self.shown_recent(data);
+        if recent.is_empty() {
+            return;
+        }
+        let line_height = self.file_list.widget().child().line_height;
+        let top = self.recent_top(data);
+        let width = ctx.size().width;
+
+        let svg_size = 15.0;
+        let icon_name = if data.file_explorer.recent_open {
+            "chevron-down.svg"
+        } else {
+            "chevron-right.svg"
+        };
+        let rect = Size::new(svg_size, svg_size)
+            .to_rect()
+            .with_origin(Point::new(5.0, top + (line_height - svg_size) / 2.0));
+        ctx.draw_svg(
+            &get_svg(icon_name).unwrap(),
+            rect,
+            Some(data.config.get_color_unchecked(LapceTheme::EDITOR_DIM)),
+        );
+        let title = ctx
+            .text()
+            .new_text_layout("Recent")
+            .font(
+                data.config.ui.font_family(),
+                data.config.ui.font_size() as f64,
+            )
+            .text_color(
+                data.config
+                    .get_color_unchecked(LapceTheme::EDITOR_DIM)
+                    .clone(),
+            )
+            .build()
+            .unwrap();
+        ctx.draw_text(
+            &title,
+            Point::new(
+                5.0 + svg_size + 4.0,
+                top + (line_height - title.size().height) / 2.0,
+            ),
+        );
+        if !data.file_explorer.recent_open {
+            return;
+        }
+
+        ctx.with_save(|ctx| {
+            ctx.transform(Affine::translate((0.0, top + line_height)));
+            let mut toggle_rects = HashMap::new();
+            let mut rows = RowPaintCtx {
+                line_height,
+                width,
+                min: 0,
+                max: usize::MAX,
+                active: data.file_explorer.active_selected.as_deref(),
+                hovered: self.recent_hovered,
+                config: &data.config,
+                toggle_rects: &mut toggle_rects,
+            };
+            for (i, path) in recent.iter().enumerate() {
+                let node = shown_node(data, path, false);
+                let state = RowState {
+                    search_matches: data
+                        .file_explorer
+                        .search_matches
+                        .get(path)
+                        .copied(),
+                    size: shown_size(data, &node),
+                    ..RowState::default()
+                };
+                paint_single_file_node_item(
+                    ctx,
+                    &mut rows,
+                    &node,
+                    &file_name(path),
+                    0,
+                    i + 1,
+                    state,
+                );
+            }
+        });
+    }
+
+    /// Paint the path from the workspace folder to the directory the tree is
+    /// narrowed to, whose ancestors can be clicked to go back up
+    fn paint_breadcrumb(&mut self, ctx: &mut PaintCtx, data: &LapceTabData) {
+        self.breadcrumb_rects.clear();
+        let (workspace, display_root) = match (
+            data.file_explorer.workspace.as_ref(),
+            data.file_explorer.display_root.as_ref(),
+        ) {
+            (Some(workspace), Some(display_root)) => {
+                (&workspace.path_buf, display_root)
+            }
+            _ => return,
+        };
+        let relative = match display_root.strip_prefix(workspace) {
+            Ok(relative) => relative,
+            Err(_) => return,
+        };
+
+        // The workspace folder goes back to showing the whole workspace
+        let mut segments = vec![(file_name(workspace), None)];
+        let mut dir = workspace.clone();
+        for component in relative.components() {
+            dir = dir.join(component);
+            segments.push((
+                component.as_os_str().to_string_lossy().to_string(),
+                Some(dir.clone()),
+            ));
+        }
+
+        let y = self.filter_input_height;
+        let mut x = 10.0;
+        let last = segments.len() - 1;
+        for (i, (name, path)) in segments.into_iter().enumerate() {
+            if i > 0 {
+                let separator = ctx
+                    .text()
+                    .new_text_layout("\u{203a}")
+                    .font(
+                        data.config.ui.font_family(),
+                        data.config.ui.font_size() as f64,
+                    )
+                    .text_color(
+                        data.config
+                            .get_color_unchecked(LapceTheme::EDITOR_DIM)
+                            .clone(),
+                    )
+                    .build()
+                    .unwrap();
+                let size = separator.size();
+                ctx.draw_text(
+                    &separator,
+                    Point::new(
+                        x + 5.0,
+                        y + (self.breadcrumb_height - size.height) / 2.0,
+                    ),
+                );
+                x += size.width + 10.0;
+            }
+
+            let color = if i == last {
+                LapceTheme::EDITOR_FOREGROUND
+            } else {
+                LapceTheme::EDITOR_LINK
+            };
+            let text_layout = ctx
+                .text()
+                .new_text_layout(name)
+                .font(
+                    data.config.ui.font_family(),
+                    data.config.ui.font_size() as f64,
+                )
+                .text_color(data.config.get_color_unchecked(color).clone())
+                .build()
+                .unwrap();
+            let size = text_layout.size();
+            ctx.draw_text(
+                &text_layout,
+                Point::new(x, y + (self.breadcrumb_height - size.height) / 2.0),
+            );
+            // The directory that is already the root isn't clickable
+            if i != last {
+                self.breadcrumb_rects.push((
+                    Rect::new(x, y, x + size.width, y + self.breadcrumb_height),
+                    path,
+                ));
+            }
+            x += size.width;
         }
     }
 
@@ -329,7 +1074,7 @@ impl FileExplorer {
             split_id,
             vec![(
                 split_id,
-                PanelHeaderKind::None,
+                PanelHeaderKind::Widget(FileExplorerHeader {}.boxed()),
                 Self::new(data).boxed(),
                 PanelSizing::Flex(false),
             )],
@@ -349,26 +1094,152 @@ impl Widget<LapceTabData> for FileExplorer {
         data: &mut LapceTabData,
         env: &Env,
     ) {
-        self.file_list.event(ctx, event, data, env);
-    }
+        match event {
+            Event::MouseMove(mouse_event) => {
+                let favorites_hovered = self.pinned_index_at(data, mouse_event.pos);
+                let recent_hovered = self.recent_row_at(data, mouse_event.pos);
+                if favorites_hovered != self.favorites_hovered
+                    || recent_hovered != self.recent_hovered
+                {
+                    self.favorites_hovered = favorites_hovered;
+                    self.recent_hovered = recent_hovered;
+                    ctx.request_paint();
+                }
 
-    fn lifecycle(
-        &mut self,
+                if self
+                    .breadcrumb_rects
+                    .iter()
+                    .any(|(rect, _)| rect.contains(mouse_event.pos))
+                    || favorites_hovered.is_some()
+                    || recent_hovered.is_some()
+                {
+                    ctx.set_cursor(&Cursor::Pointer);
+                } else if mouse_event.pos.y >= self.filter_input_height
+                    && mouse_event.pos.y < self.list_top(data)
+                {
+                    ctx.clear_cursor();
+                }
+            }
+            Event::MouseDown(mouse_event)
+                if self.pinned_index_at(data, mouse_event.pos).is_some() =>
+            {
+                let pinned = self
+                    .pinned_index_at(data, mouse_event.pos)
+                    .and_then(|index| data.file_explorer.pinned.get(index))
+                    .cloned();
+                if let Some(pinned) = pinned {
+                    if mouse_event.button.is_left() {
+                        self.open_pinned(ctx, data, &pinned);
+                    } else if mouse_event.button.is_right() {
+                        self.show_pinned_menu(ctx, &pinned, mouse_event.pos);
+                    }
+                }
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseDown(mouse_event)
+                if self.recent_row_at(data, mouse_event.pos).is_some() =>
+            {
+                let row = self.recent_row_at(data, mouse_event.pos);
+                if mouse_event.button.is_right() {
+                    self.show_recent_menu(ctx, mouse_event.pos);
+                } else if mouse_event.button.is_left() {
+                    match row {
+                        Some(0) => {
+                            let file_explorer =
+                                Arc::make_mut(&mut data.file_explorer);
+                            file_explorer.recent_open = !file_explorer.recent_open;
+                        }
+                        Some(row) => {
+                            if let Some(path) =
+                                self.shown_recent(data).get(row - 1).cloned()
+                            {
+                                open_file(ctx, data, &path);
+                            }
+                        }
+                        None => {}
+                    }
+                }
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseDown(mouse_event) if mouse_event.button.is_left() => {
+                if let Some((_, path)) = self
+                    .breadcrumb_rects
+                    .iter()
+                    .find(|(rect, _)| rect.contains(mouse_event.pos))
+                {
+                    ctx.submit_command(Command::new(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::SetExplorerDisplayRoot {
+                            path: path.clone(),
+                        },
+                        Target::Widget(data.id),
+                    ));
+                    ctx.set_handled();
+                    return;
+                }
+            }
+            _ => {}
+        }
+        // Scrolling by hand wins over the offset that is waiting to be restored
+        if matches!(event, Event::Wheel(_)) {
+            self.restore_scroll = None;
+        }
+        self.filter_input.event(ctx, event, data, env);
+        self.file_list.event(ctx, event, data, env);
+
+        // Scroll back to the directory of the sticky header that was clicked
+        if let Some(index) =
+            self.file_list.widget_mut().child_mut().scroll_to_row.take()
+        {
+            let file_list = self.file_list.widget_mut();
+            let y = file_list.child().name_input_y(index);
+            if file_list.scroll_to(Point::new(0.0, y)) {
+                ctx.request_paint();
+            }
+        }
+
+        // Keep the offset for saving it with the workspace, but not before the
+        // saved one has been restored
+        let offset = self.file_list.widget().offset().y;
+        if self.restore_scroll.is_none()
+            && offset != data.file_explorer.scroll_offset
+        {
+            Arc::make_mut(&mut data.file_explorer).scroll_offset = offset;
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
         ctx: &mut LifeCycleCtx,
         event: &LifeCycle,
         data: &LapceTabData,
         env: &Env,
     ) {
+        self.filter_input.lifecycle(ctx, event, data, env);
         self.file_list.lifecycle(ctx, event, data, env);
     }
 
     fn update(
         &mut self,
         ctx: &mut UpdateCtx,
-        _old_data: &LapceTabData,
+        old_data: &LapceTabData,
         data: &LapceTabData,
         env: &Env,
     ) {
+        if data.file_explorer.active_selected
+            != old_data.file_explorer.active_selected
+            || data.file_explorer.display_root != old_data.file_explorer.display_root
+            || data.file_explorer.pinned != old_data.file_explorer.pinned
+            || data.file_explorer.recent != old_data.file_explorer.recent
+            || data.file_explorer.recent_open != old_data.file_explorer.recent_open
+            || data.file_explorer.selected_index
+                != old_data.file_explorer.selected_index
+        {
+            ctx.request_layout();
+        }
+        self.filter_input.update(ctx, data, env);
         self.file_list.update(ctx, data, env);
     }
 
@@ -380,32 +1251,873 @@ impl Widget<LapceTabData> for FileExplorer {
         env: &Env,
     ) -> Size {
         let self_size = bc.max();
-        self.file_list.layout(ctx, bc, data, env);
-        self.file_list
+        let input_size = Size::new(self_size.width, self.filter_input_height);
+        self.filter_input
+            .layout(ctx, &BoxConstraints::tight(input_size), data, env);
+        self.filter_input
             .set_origin(ctx, data, env, Point::new(0.0, 0.0));
+
+        let list_top = self.list_top(data);
+        let list_size =
+            Size::new(self_size.width, (self_size.height - list_top).max(0.0));
+        self.file_list.layout(
+            ctx,
+            &BoxConstraints::new(Size::ZERO, list_size),
+            data,
+            env,
+        );
+        self.file_list
+            .set_origin(ctx, data, env, Point::new(0.0, list_top));
+
+        // The list is laid out without any height while the panel is collapsed,
+        // which scrolls it back to the top, so go back to where it was after
+        if list_size.height <= 0.0 && data.file_explorer.scroll_offset > 0.0 {
+            self.restore_scroll = Some(data.file_explorer.scroll_offset);
+        }
+        if let Some(offset) = self.restore_scroll {
+            let file_list = self.file_list.widget_mut();
+            if list_size.height > 0.0
+                && file_list.child_size().height >= offset + list_size.height
+            {
+                file_list.scroll_to(Point::new(0.0, offset));
+                self.restore_scroll = None;
+            }
+        }
+
+        // Bring a newly started naming input into view, now that the list has
+        // been laid out with room for it
+        let naming_index = data.file_explorer.naming_list_index();
+        if naming_index != self.scrolled_naming_index {
+            self.scrolled_naming_index = naming_index;
+            if let Some(index) = naming_index {
+                let file_list = self.file_list.widget_mut();
+                let rect = Size::new(list_size.width, file_list.child().line_height)
+                    .to_rect()
+                    .with_origin(Point::new(
+                        0.0,
+                        file_list.child().name_input_y(index),
+                    ));
+                file_list.scroll_to_visible(rect, env);
+            }
+        }
+
+        // Bring the active file into view once it is in the list, which may be
+        // after its directories have been read
+        if data.file_explorer.active_selected.is_none() {
+            self.scrolled_active = None;
+        }
+        if data.config.ui.explorer_sync_active_editor()
+            && self.restore_scroll.is_none()
+            && data.file_explorer.active_selected != self.scrolled_active
+        {
+            if let Some((index, _)) = data
+                .file_explorer
+                .active_selected
+                .as_ref()
+                .and_then(|path| data.file_explorer.get_node_index(path))
+                .filter(|(index, _)| *index > 0)
+            {
+                self.scrolled_active = data.file_explorer.active_selected.clone();
+                let file_list = self.file_list.widget_mut();
+                let line_height = file_list.child().line_height;
+                let rect = Size::new(list_size.width, line_height)
+                    .to_rect()
+                    .with_origin(Point::new(0.0, (index - 1) as f64 * line_height));
+                file_list.scroll_to_visible(rect, env);
+            }
+        }
+
+        // Keep the row selected from the keyboard in view
+        if data.file_explorer.selected_index != self.scrolled_selected {
+            self.scrolled_selected = data.file_explorer.selected_index;
+            if let Some(index) = self.scrolled_selected.filter(|index| *index > 0) {
+                let file_list = self.file_list.widget_mut();
+                let line_height = file_list.child().line_height;
+                let rect = Size::new(list_size.width, line_height)
+                    .to_rect()
+                    .with_origin(Point::new(0.0, (index - 1) as f64 * line_height));
+                file_list.scroll_to_visible(rect, env);
+            }
+        }
+
         self_size
     }
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &LapceTabData, env: &Env) {
+        self.filter_input.paint(ctx, data, env);
+        self.paint_breadcrumb(ctx, data);
+        self.paint_favorites(ctx, data);
+        self.paint_recent(ctx, data);
+        // The sticky headers are pinned to the part of the list in view
+        let viewport = self.file_list.layout_rect().size().to_rect()
+            + self.file_list.widget().offset();
+        self.file_list.widget_mut().child_mut().viewport = viewport;
         self.file_list.paint(ctx, data, env);
     }
 }
 
+/// The header of the file explorer panel, with the name of the workspace
+/// folder and how many of the open files have unsaved changes
+struct FileExplorerHeader {}
+
+impl Widget<LapceTabData> for FileExplorerHeader {
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut LapceTabData,
+        _env: &Env,
+    ) {
+        let action = data.config.ui.explorer_header_click_action();
+        match event {
+            Event::MouseMove(_) => {
+                if ctx.is_hot() && action != "none" {
+                    ctx.set_cursor(&Cursor::Pointer);
+                } else {
+                    ctx.clear_cursor();
+                }
+            }
+            Event::MouseDown(mouse_event) if mouse_event.button.is_left() => {
+                match action {
+                    "collapse-all" => {
+                        Arc::make_mut(&mut data.file_explorer).collapse_all();
+                    }
+                    "reveal-active-file" => {
+                        if let Some(path) = active_editor_file(data).cloned() {
+                            let file_explorer =
+                                Arc::make_mut(&mut data.file_explorer);
+                            file_explorer.active_selected = Some(path.clone());
+                            file_explorer.reveal_path(&path);
+                        }
+                    }
+                    _ => return,
+                }
+                ctx.set_handled();
+            }
+            _ => {}
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        _ctx: &mut LifeCycleCtx,
+        _event: &LifeCycle,
+        _data: &LapceTabData,
+        _env: &Env,
+    ) {
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &LapceTabData,
+        data: &LapceTabData,
+        _env: &Env,
+    ) {
+        if data.main_split.unsaved_count() != old_data.main_split.unsaved_count()
+            || data.workspace.path != old_data.workspace.path
+        {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &LapceTabData,
+        _env: &Env,
+    ) -> Size {
+        bc.max()
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &LapceTabData, _env: &Env) {
+        paint_section_header_background(ctx, data, PanelKind::FileExplorer);
+
+        let name = data
+            .workspace
+            .path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "No Folder Open".to_string());
+        let unsaved = data.main_split.unsaved_count();
+
+        let rect = ctx.size().to_rect();
+        ctx.with_save(|ctx| {
+            ctx.clip(rect);
+            let height = ctx.size().height;
+            let text_layout = ctx
+                .text()
+                .new_text_layout(name)
+                .font(
+                    data.config.ui.font_family(),
+                    data.config.ui.font_size() as f64,
+                )
+                .text_color(
+                    data.config
+                        .get_color_unchecked(LapceTheme::EDITOR_FOREGROUND)
+                        .clone(),
+                )
+                .build()
+                .unwrap();
+            let x = 10.0;
+            let y = (height - text_layout.size().height) / 2.0;
+            ctx.draw_text(&text_layout, Point::new(x, y));
+
+            if unsaved > 0 {
+                let x = x + text_layout.size().width + 10.0;
+                let text_layout = ctx
+                    .text()
+                    .new_text_layout(format!("{unsaved} unsaved"))
+                    .font(
+                        data.config.ui.font_family(),
+                        data.config.ui.font_size() as f64,
+                    )
+                    .text_color(
+                        data.config
+                            .get_color_unchecked(LapceTheme::EDITOR_DIM)
+                            .clone(),
+                    )
+                    .build()
+                    .unwrap();
+                let y = (height - text_layout.size().height) / 2.0;
+                ctx.draw_text(&text_layout, Point::new(x, y));
+            }
+        });
+    }
+}
+
+/// How long after the last key the name typed to jump to a row starts over
+const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_secs(1);
+
 type NameEditInput = WidgetPod<LapceTabData, Box<dyn Widget<LapceTabData>>>;
 struct FileExplorerFileList {
     line_height: f64,
     hovered: Option<usize>,
     name_edit_input: NameEditInput,
+    /// The chevron of each directory row painted in the last frame, by its index
+    toggle_rects: HashMap<usize, Rect>,
+    /// Where the left mouse button went down on a node, and the node's path,
+    /// until it is released or the node is dragged away
+    drag_start: Option<(Point, PathBuf)>,
+    /// The added folder whose header row is being dragged to reorder it, and
+    /// the position among the added folders it would be dropped at
+    root_drag: Option<(PathBuf, usize)>,
+    /// The number of clicks of the last left mouse button press, which is 2
+    /// for a double click
+    click_count: u8,
+    /// The name typed so far to jump to a row, and when it was last typed
+    type_ahead: String,
+    type_ahead_at: Instant,
+    /// Keeps the rows from being laid out again for every directory listing
+    /// that comes in while a large workspace is opened
+    repaint: RepaintCoalescer,
+    repaint_timer: TimerToken,
+    /// The part of the list that is scrolled into view, kept up to date by the
+    /// explorer as the region being painted can be smaller
+    viewport: Rect,
+    /// The rows of the directories pinned to the top of the viewport in the
+    /// last frame, and their indices
+    sticky_rows: Vec<(Rect, usize)>,
+    /// The row of the sticky header that was clicked, for the explorer to
+    /// scroll to
+    scroll_to_row: Option<usize>,
+    /// The paths whose metadata has been asked for but hasn't arrived yet
+    metadata_requested: HashSet<PathBuf>,
 }
 
 impl FileExplorerFileList {
-    pub fn new(input: NameEditInput) -> Self {
+    pub fn new(input: NameEditInput, config: &Config) -> Self {
         Self {
-            line_height: 25.0,
+            line_height: config.ui.explorer_line_height() as f64,
             hovered: None,
             name_edit_input: input,
+            toggle_rects: HashMap::new(),
+            drag_start: None,
+            root_drag: None,
+            click_count: 0,
+            type_ahead: String::new(),
+            type_ahead_at: Instant::now(),
+            repaint: RepaintCoalescer::default(),
+            repaint_timer: TimerToken::INVALID,
+            viewport: Rect::ZERO,
+            sticky_rows: Vec::new(),
+            scroll_to_row: None,
+            metadata_requested: HashSet::new(),
+        }
+    }
+
+    /// Ask for the metadata of the rows between the indices and of the
+    /// directories they are in, which tells whether they can be renamed,
+    /// since the listings of the directories leave it out
+    fn read_shown_metadata(&mut self, data: &LapceTabData, min: usize, max: usize) {
+        let file_explorer = &data.file_explorer;
+        self.metadata_requested.retain(|path| {
+            file_explorer
+                .get_file_node(path)
+                .or_else(|| file_explorer.get_flat_file_node(path))
+                .map_or(false, |node| !node.metadata_read)
+        });
+
+        let mut paths = Vec::new();
+        for index in min..=max {
+            let node = match file_explorer.node_at_index(index) {
+                Some(node) => node,
+                None => continue,
+            };
+            let parent = node
+                .path_buf
+                .parent()
+                .and_then(|parent| file_explorer.get_file_node(parent));
+            for node in std::iter::once(node).chain(parent) {
+                if !node.metadata_read
+                    && self.metadata_requested.insert(node.path_buf.clone())
+                {
+                    paths.push(node.path_buf.clone());
+                }
+            }
+        }
+        if !paths.is_empty() {
+            file_explorer.read_metadata(paths);
+        }
+    }
+
+    /// The vertical position of the naming input at the list index
+    fn name_input_y(&self, index: usize) -> f64 {
+        (index as f64 * self.line_height) - self.line_height
+    }
+
+    /// The position among the added folders that a dragged folder would be
+    /// dropped at, which is the one of the section under the mouse
+    fn root_drop_position(&self, data: &LapceTabData, y: f64) -> usize {
+        let file_explorer = &data.file_explorer;
+        let index = ((y + self.line_height) / self.line_height) as usize;
+        let rows = file_explorer.visible_rows();
+        // The loading rows and the space past the last row belong to the
+        // section of the row before them
+        rows[..(index + 1).min(rows.len())]
+            .iter()
+            .rev()
+            .flatten()
+            .next()
+            .and_then(|(_, path)| file_explorer.extra_root_position(path))
+            .unwrap_or(0)
+    }
+
+    /// Draw a line where the dragged folder would be dropped, above the
+    /// section it would take the place of
+    fn paint_root_drop(&self, ctx: &mut PaintCtx, data: &LapceTabData, width: f64) {
+        let (path, to) = match self.root_drag.as_ref() {
+            Some(drag) => drag,
+            None => return,
+        };
+        let file_explorer = &data.file_explorer;
+        let from = match file_explorer.extra_root_position(path) {
+            Some(from) => from,
+            None => return,
+        };
+        // Moving down puts the folder after the section under the mouse
+        let next = if *to > from { to + 1 } else { *to };
+        let index = match file_explorer.extra_roots.get(next) {
+            Some(root) => match file_explorer.get_node_index(&root.path_buf) {
+                Some((index, _)) => index,
+                None => return,
+            },
+            None => file_explorer.row_count() + 1,
+        };
+        let y = self.name_input_y(index);
+        ctx.stroke(
+            Line::new(Point::new(0.0, y), Point::new(width, y)),
+            data.config.get_color_unchecked(LapceTheme::EDITOR_CARET),
+            2.0,
+        );
+    }
+
+    /// Paint the rows that are within the region being painted
+    fn paint_rows(&mut self, ctx: &mut PaintCtx, data: &LapceTabData, env: &Env) {
+        let rect = ctx.region().bounding_box();
+        let size = ctx.size();
+        let width = size.width;
+        let min = (rect.y0 / self.line_height).floor() as usize;
+        let max = (rect.y1 / self.line_height) as usize + 2;
+        self.read_shown_metadata(data, min, max);
+        let level = 0;
+        // Found once per paint, as the rows may have moved since naming started
+        let naming = data
+            .file_explorer
+            .naming_list_index()
+            .zip(data.file_explorer.naming.as_ref());
+        self.toggle_rects.clear();
+        let mut rows = RowPaintCtx {
+            line_height: self.line_height,
+            width,
+            min,
+            max,
+            active: data.file_explorer.active_selected.as_deref(),
+            hovered: self.hovered,
+            config: &data.config,
+            toggle_rects: &mut self.toggle_rects,
+        };
+        let mut name_input = NameInputPaint {
+            naming,
+            input: &mut self.name_edit_input,
+            drawn: false,
+        };
+
+        if let Some(item) = data.file_explorer.display_root_node() {
+            let mut i = 0;
+            let matches = data.file_explorer.matches.as_ref();
+            for item in item.sorted_children() {
+                if !is_node_visible(item, matches) {
+                    continue;
+                }
+                let (item, name) = child_row(item, data, matches);
+                i = paint_file_node_item(
+                    ctx,
+                    env,
+                    &mut rows,
+                    &mut name_input,
+                    item,
+                    &name,
+                    level + 1,
+                    i + 1,
+                    data,
+                );
+                if i > max {
+                    return;
+                }
+            }
+
+            if has_placeholder_row(item) {
+                i += 1;
+                if i >= min {
+                    paint_placeholder_row(
+                        ctx,
+                        item,
+                        self.line_height,
+                        level + 1,
+                        i,
+                        &data.config,
+                    );
+                }
+            }
+
+            // The added folders follow the workspace as their own sections,
+            // unless the tree is narrowed to a directory of the workspace
+            let extra_roots = if data.file_explorer.display_root.is_some() {
+                &[][..]
+            } else {
+                &data.file_explorer.extra_roots[..]
+            };
+            for root in extra_roots {
+                i = paint_file_node_item(
+                    ctx,
+                    env,
+                    &mut rows,
+                    &mut name_input,
+                    root,
+                    &file_name(&root.path_buf),
+                    level,
+                    i + 1,
+                    data,
+                );
+                if i > max {
+                    return;
+                }
+            }
+
+            // If we didn't draw the name input then we'll have to draw it here,
+            // but only when its row is actually within the visible range
+            if let Some((naming_index, naming)) = naming {
+                if !name_input.drawn && naming_index >= min && naming_index <= max {
+                    draw_name_input(
+                        ctx,
+                        data,
+                        env,
+                        // This value does not matter here
+                        &mut 0,
+                        naming,
+                        name_input.input,
+                    );
+                }
+            }
+
+            // Show why the name couldn't be applied right below the input
+            if let (Some((naming_index, _)), Some(error)) =
+                (naming, &data.file_explorer.naming_error)
+            {
+                let y = self.name_input_y(naming_index) + self.line_height;
+                ctx.fill(
+                    Size::new(width, self.line_height)
+                        .to_rect()
+                        .with_origin(Point::new(0.0, y)),
+                    data.config
+                        .get_color_unchecked(LapceTheme::PANEL_BACKGROUND),
+                );
+                let text_layout = ctx
+                    .text()
+                    .new_text_layout(error.clone())
+                    .font(
+                        data.config.ui.font_family(),
+                        data.config.ui.font_size() as f64,
+                    )
+                    .text_color(
+                        data.config
+                            .get_color_unchecked(LapceTheme::LAPCE_ERROR)
+                            .clone(),
+                    )
+                    .build()
+                    .unwrap();
+                ctx.draw_text(
+                    &text_layout,
+                    Point::new(
+                        10.0,
+                        y + (self.line_height - text_layout.size().height) / 2.0,
+                    ),
+                );
+            }
+        }
+    }
+
+    /// The rows to pin to the top of the viewport, which are the directories
+    /// that the row right below them is in, whose own rows are scrolled past
+    fn sticky_header_rows(&self, data: &LapceTabData) -> Vec<usize> {
+        let file_explorer = &data.file_explorer;
+        let top = (self.viewport.y0 / self.line_height) as usize + 1;
+        // Leave at least half of the viewport to the rows beneath
+        let limit = (self.viewport.height() / self.line_height / 2.0) as usize;
+        let pinned = |row: usize| -> Vec<usize> {
+            file_explorer
+                .ancestor_rows(row)
+                .into_iter()
+                .filter(|index| *index > 0)
+                .enumerate()
+                .take_while(|(slot, index)| *slot < limit && *index < top + slot)
+                .map(|(_, index)| index)
+                .collect()
+        };
+
+        // Each pinned row covers another row, so look again at the row that
+        // ends up right below them until it's in the same directories
+        let mut rows = pinned(top);
+        loop {
+            let below = pinned(top + rows.len());
+            if below.len() <= rows.len() {
+                return below;
+            }
+            rows = below;
+        }
+    }
+
+    /// Pin the rows of the directories that the topmost row is in to the top
+    /// of the viewport, so where it is in a deep tree isn't lost while scrolling
+    fn paint_sticky_headers(
+        &mut self,
+        ctx: &mut PaintCtx,
+        data: &LapceTabData,
+        width: f64,
+    ) {
+        self.sticky_rows.clear();
+        if !data.config.ui.explorer_sticky_headers()
+            || data.file_explorer.naming.is_some()
+        {
+            return;
+        }
+
+        let file_explorer = &data.file_explorer;
+        let mut toggle_rects = HashMap::new();
+        let mut row_ctx = RowPaintCtx {
+            line_height: self.line_height,
+            width,
+            min: 0,
+            max: usize::MAX,
+            active: file_explorer.active_selected.as_deref(),
+            hovered: self.hovered,
+            config: &data.config,
+            toggle_rects: &mut toggle_rects,
+        };
+        let rows = self.sticky_header_rows(data);
+        for (slot, index) in rows.iter().enumerate() {
+            let (level, item) = match file_explorer.get_node_by_index(*index) {
+                Some(row) => row,
+                None => continue,
+            };
+            // Compacted rows are named after the directories they stand for,
+            // relative to the row they are nested in
+            let parent = match slot {
+                0 if level > 0 => Some(0),
+                0 => None,
+                _ => Some(rows[slot - 1]),
+            };
+            let name = parent
+                .and_then(|parent| file_explorer.visible_rows()[parent].as_ref())
+                .and_then(|(_, parent)| item.path_buf.strip_prefix(parent).ok())
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| file_name(&item.path_buf));
+
+            let rect = Size::new(width, self.line_height).to_rect().with_origin(
+                Point::new(0.0, self.viewport.y0 + slot as f64 * self.line_height),
+            );
+            ctx.fill(
+                rect,
+                data.config
+                    .get_color_unchecked(LapceTheme::PANEL_BACKGROUND),
+            );
+            ctx.with_save(|ctx| {
+                ctx.transform(Affine::translate((
+                    0.0,
+                    rect.y0 - self.name_input_y(*index),
+                )));
+                let state = RowState {
+                    selected: file_explorer.selected_paths.contains(&item.path_buf),
+                    reading: file_explorer.reading_dirs.contains(&item.path_buf),
+                    search_matches: file_explorer
+                        .search_matches
+                        .get(&item.path_buf)
+                        .copied(),
+                    size: shown_size(data, item),
+                };
+                paint_single_file_node_item(
+                    ctx,
+                    &mut row_ctx,
+                    item,
+                    &name,
+                    level,
+                    *index,
+                    state,
+                );
+            });
+            self.sticky_rows.push((rect, *index));
+        }
+
+        if let Some((rect, _)) = self.sticky_rows.last() {
+            ctx.stroke(
+                Line::new(
+                    Point::new(0.0, rect.y1 - 0.5),
+                    Point::new(width, rect.y1 - 0.5),
+                ),
+                data.config.get_color_unchecked(LapceTheme::LAPCE_BORDER),
+                1.0,
+            );
+        }
+    }
+
+    /// The index of the row of the sticky header at the position
+    fn sticky_row_at(&self, pos: Point) -> Option<usize> {
+        self.sticky_rows
+            .iter()
+            .find(|(rect, _)| rect.contains(pos))
+            .map(|(_, index)| *index)
+    }
+
+    /// Show a tip right below the hovered row, with where a symbolic link
+    /// points to, or else why the node can't be renamed or trashed
+    fn paint_hover_tip(&self, ctx: &mut PaintCtx, data: &LapceTabData, width: f64) {
+        let node = match self
+            .hovered
+            .and_then(|index| data.file_explorer.get_node_by_index(index))
+        {
+            Some((_, node)) => node,
+            None => return,
+        };
+        let tip = if let Some(target) = node.symlink_target.as_ref() {
+            format!("\u{2192} {}", display_path(target, data.abbreviated_home()))
+        } else if !data.file_explorer.is_root(&node.path_buf)
+            && data.file_explorer.is_parent_readonly(&node.path_buf)
+        {
+            "Can't be renamed or trashed: the parent directory is read-only"
+                .to_string()
+        } else {
+            return;
+        };
+        let text_layout = ctx
+            .text()
+            .new_text_layout(tip)
+            .font(
+                data.config.ui.font_family(),
+                data.config.ui.font_size() as f64,
+            )
+            .text_color(
+                data.config
+                    .get_color_unchecked(LapceTheme::EDITOR_FOREGROUND)
+                    .clone(),
+            )
+            .build()
+            .unwrap();
+        let y = self.name_input_y(self.hovered.unwrap_or(0)) + self.line_height;
+        let rect = Size::new(
+            (text_layout.size().width + 20.0).min(width),
+            self.line_height,
+        )
+        .to_rect()
+        .with_origin(Point::new(0.0, y));
+        ctx.fill(
+            rect,
+            data.config
+                .get_color_unchecked(LapceTheme::PANEL_BACKGROUND),
+        );
+        ctx.stroke(
+            rect.inset(-0.5),
+            data.config.get_color_unchecked(LapceTheme::LAPCE_BORDER),
+            1.0,
+        );
+        ctx.draw_text(
+            &text_layout,
+            Point::new(
+                10.0,
+                y + (self.line_height - text_layout.size().height) / 2.0,
+            ),
+        );
+    }
+
+    /// Rename the selected node on F2, or ask to move it, or all the selected
+    /// nodes, to the trash on Delete. They are deleted permanently instead if
+    /// `permanent` is set.
+    fn selected_node_key_down(
+        &self,
+        ctx: &mut EventCtx,
+        key: &KbKey,
+        permanent: bool,
+        data: &LapceTabData,
+    ) {
+        let file_explorer = &data.file_explorer;
+        if key == &KbKey::Delete {
+            let selection = file_explorer.selection();
+            if selection.len() > 1 {
+                let paths = trashable_paths(file_explorer, &selection);
+                if !paths.is_empty() && permanent {
+                    ctx.submit_command(delete_paths_command(data.id, paths));
+                } else if !paths.is_empty() {
+                    ctx.submit_command(trash_paths_command(data.id, paths));
+                }
+                return;
+            }
+        }
+
+        let (list_index, (indent_level, node)) = match file_explorer
+            .selected_index
+            .and_then(|index| Some((index, file_explorer.get_node_by_index(index)?)))
+        {
+            Some(selected) => selected,
+            None => return,
+        };
+
+        // Don't allow us to rename or delete the current workspace
+        if Some(&node.path_buf)
+            == file_explorer.workspace.as_ref().map(|x| &x.path_buf)
+        {
+            return;
+        }
+
+        let file_name = node
+            .path_buf
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_else(String::new);
+        match key {
+            KbKey::F2 => {
+                ctx.submit_command(Command::new(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::ExplorerStartRename {
+                        list_index,
+                        indent_level,
+                        text: file_name,
+                    },
+                    Target::Auto,
+                ));
+            }
+            KbKey::Delete if permanent => {
+                ctx.submit_command(delete_paths_command(
+                    data.id,
+                    vec![node.path_buf.clone()],
+                ));
+            }
+            KbKey::Delete => {
+                let trash_text = if node.is_dir {
+                    "Move Directory to Trash"
+                } else {
+                    "Move File to Trash"
+                };
+                ctx.submit_command(Command::new(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::ShowAlert(AlertContentData {
+                        title: format!(
+                            "Do you want to move {file_name} to the trash?"
+                        ),
+                        msg: "You can restore it from the trash.".to_string(),
+                        buttons: vec![(
+                            trash_text.to_string(),
+                            data.id,
+                            LapceCommand {
+                                kind: CommandKind::Workbench(
+                                    LapceWorkbenchCommand::ExplorerTrashPath,
+                                ),
+                                data: serde_json::to_value(&node.path_buf).ok(),
+                            },
+                        )],
+                    }),
+                    Target::Widget(data.id),
+                ));
+            }
+            _ => {}
         }
     }
+
+    /// Start naming a new file right after the selected file, in its directory,
+    /// or as the first child of the selected directory
+    fn new_file_next_to_selected(
+        &self,
+        ctx: &mut EventCtx,
+        data: &mut LapceTabData,
+    ) {
+        let file_explorer = &data.file_explorer;
+        let (index, (indent_level, node)) = match file_explorer
+            .selected_index
+            .and_then(|index| Some((index, file_explorer.get_node_by_index(index)?)))
+        {
+            Some(selected) => selected,
+            None => return,
+        };
+
+        let (base_path, indent_level) = if node.is_dir {
+            (node.path_buf.clone(), indent_level + 1)
+        } else {
+            match node.path_buf.parent() {
+                Some(parent) => (parent.to_path_buf(), indent_level),
+                None => return,
+            }
+        };
+        // Either right after the file, or the first row within the directory
+        let list_index = index + 1;
+
+        let event_sink = ctx.get_external_handle();
+        expand_dir(
+            event_sink.clone(),
+            &data.proxy,
+            data.id,
+            Arc::make_mut(&mut data.file_explorer),
+            index,
+            move || {
+                let res = event_sink.submit_command(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::ExplorerNew {
+                        list_index,
+                        indent_level,
+                        is_dir: false,
+                        base_path,
+                        template: None,
+                        content: None,
+                    },
+                    Target::Auto,
+                );
+
+                if let Err(err) = res {
+                    log::warn!("Failed to start constructing new file: {:?}", err);
+                }
+            },
+        );
+    }
 }
 
 impl Widget<LapceTabData> for FileExplorerFileList {
@@ -423,9 +2135,19 @@ impl Widget<LapceTabData> for FileExplorerFileList {
                 if let LapceUICommand::ActiveFileChanged { path } = command {
                     let file_explorer = Arc::make_mut(&mut data.file_explorer);
                     file_explorer.active_selected = path.clone();
+                    if data.config.ui.explorer_sync_active_editor() {
+                        if let Some(path) = path {
+                            file_explorer.reveal_path(path);
+                        }
+                    }
                     ctx.request_paint();
                 }
             }
+            Event::Timer(token) if *token == self.repaint_timer => {
+                self.repaint.fire(Instant::now());
+                ctx.request_layout();
+                ctx.request_paint();
+            }
             _ => {}
         }
 
@@ -446,6 +2168,14 @@ impl Widget<LapceTabData> for FileExplorerFileList {
                         },
                         Target::Auto,
                     ));
+                } else if key_ev.key == KbKey::Tab {
+                    // Complete the name rather than moving the focus
+                    let extensions =
+                        data.config.ui.explorer_naming_extensions().to_vec();
+                    Arc::make_mut(&mut data.file_explorer)
+                        .complete_naming(&mut data.main_split, &extensions);
+                    ctx.set_handled();
+                    return;
                 }
             }
         }
@@ -474,22 +2204,129 @@ impl Widget<LapceTabData> for FileExplorerFileList {
         }
 
         match event {
+            Event::AnimFrame(_) => {
+                // Keep the loading spinners turning until every directory is read
+                if data.file_explorer.is_loading() {
+                    ctx.request_paint();
+                    ctx.request_anim_frame();
+                }
+            }
+            Event::KeyDown(key_ev) if ctx.has_focus() => {
+                let now = Instant::now();
+                if now.duration_since(self.type_ahead_at) > TYPE_AHEAD_TIMEOUT {
+                    self.type_ahead.clear();
+                }
+
+                if key_ev.key == KbKey::Enter {
+                    let paths = data.file_explorer.selection();
+                    if !paths.is_empty() {
+                        ctx.submit_command(open_paths_command(
+                            &data.file_explorer,
+                            data.id,
+                            paths,
+                        ));
+                    }
+                    ctx.set_handled();
+                } else if key_ev.key == KbKey::F2 || key_ev.key == KbKey::Delete {
+                    // Shift+Delete deletes permanently whatever is configured
+                    let permanent = key_ev.mods.shift()
+                        || data.config.ui.explorer_deletes_permanently();
+                    self.selected_node_key_down(ctx, &key_ev.key, permanent, data);
+                    ctx.set_handled();
+                } else if key_ev.key == KbKey::Character("v".to_string())
+                    && (key_ev.mods.ctrl() || key_ev.mods.meta())
+                {
+                    if let Some(text) =
+                        Application::global().clipboard().get_string()
+                    {
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::ExplorerPastePath { text },
+                            Target::Widget(data.id),
+                        ));
+                    }
+                    ctx.set_handled();
+                } else if key_ev.key == KbKey::Character("a".to_string())
+                    && key_ev.mods.is_empty()
+                    && self.type_ahead.is_empty()
+                {
+                    // Names starting with "a" can only be typed ahead after
+                    // another letter, as "a" on its own adds a file
+                    self.new_file_next_to_selected(ctx, data);
+                    ctx.set_handled();
+                } else if let (KbKey::Character(text), false) = (
+                    &key_ev.key,
+                    key_ev.mods.ctrl() || key_ev.mods.meta() || key_ev.mods.alt(),
+                ) {
+                    self.type_ahead.push_str(text);
+                    self.type_ahead_at = now;
+                    let file_explorer = Arc::make_mut(&mut data.file_explorer);
+                    if let Some(index) =
+                        file_explorer.type_ahead_index(&self.type_ahead)
+                    {
+                        file_explorer.selected_index = Some(index);
+                        file_explorer.selected_paths.clear();
+                        file_explorer.active_selected = file_explorer
+                            .get_node_by_index(index)
+                            .map(|(_, node)| node.path_buf.clone());
+                        ctx.request_paint();
+                    }
+                    ctx.set_handled();
+                }
+            }
             Event::MouseMove(mouse_event) => {
+                if let Some((start, path)) = self.drag_start.as_ref() {
+                    if mouse_event.buttons.contains(MouseButton::Left)
+                        && data.drag.is_none()
+                        && self.root_drag.is_none()
+                        && (mouse_event.window_pos - *start).hypot() > 5.0
+                    {
+                        // The header rows of the added folders are dragged to
+                        // reorder them, and any other row is dragged out
+                        if data
+                            .file_explorer
+                            .extra_roots
+                            .iter()
+                            .any(|root| &root.path_buf == path)
+                        {
+                            let to =
+                                self.root_drop_position(data, mouse_event.pos.y);
+                            self.root_drag = Some((path.clone(), to));
+                            ctx.request_paint();
+                        } else {
+                            *Arc::make_mut(&mut data.drag) = Some((
+                                Vec2::ZERO,
+                                start.to_vec2(),
+                                DragContent::ExplorerPath(path.clone()),
+                            ));
+                        }
+                    }
+                }
+                if self.root_drag.is_some() {
+                    let to = self.root_drop_position(data, mouse_event.pos.y);
+                    if let Some((_, drag_to)) = self.root_drag.as_mut() {
+                        if *drag_to != to {
+                            *drag_to = to;
+                            ctx.request_paint();
+                        }
+                    }
+                }
+
                 if !ctx.is_hot() {
                     return;
                 }
 
-                if let Some(workspace) = data.file_explorer.workspace.as_ref() {
+                if data.file_explorer.workspace.is_some() {
                     let y = mouse_event.pos.y;
                     if y <= self.line_height
-                        * (workspace.children_open_count + 1 + 1) as f64
+                        * (data.file_explorer.row_count() + 1 + 1) as f64
                     {
                         ctx.set_cursor(&Cursor::Pointer);
-                        let hovered = Some(
+                        let hovered = self.sticky_row_at(mouse_event.pos).or(Some(
                             ((mouse_event.pos.y + self.line_height)
                                 / self.line_height)
                                 as usize,
-                        );
+                        ));
 
                         if hovered != self.hovered {
                             ctx.request_paint();
@@ -501,6 +2338,54 @@ impl Widget<LapceTabData> for FileExplorerFileList {
                     }
                 }
             }
+            Event::MouseUp(mouse_event) if mouse_event.button.is_left() => {
+                ctx.set_active(false);
+                if let Some((path, to)) = self.root_drag.take() {
+                    self.drag_start = None;
+                    Arc::make_mut(&mut data.file_explorer).move_root(&path, to);
+                    ctx.request_paint();
+                    return;
+                }
+                // Open the file that was clicked, unless it was dragged away
+                if let Some((_, path)) = self.drag_start.take() {
+                    let file_explorer = &data.file_explorer;
+                    let is_file = file_explorer
+                        .get_flat_file_node(&path)
+                        .or_else(|| file_explorer.get_file_node(&path))
+                        .map_or(false, |node| !node.is_dir);
+                    let gesture = data.config.ui.explorer_file_open_gesture();
+                    let double_click = self.click_count >= 2;
+                    let open = gesture != "double-click" || double_click;
+                    // Files that can't be shown as text, like images, go to the
+                    // application the system opens them with
+                    if is_file
+                        && data.drag.is_none()
+                        && open
+                        && data.config.ui.explorer_opens_externally(&path)
+                    {
+                        data.proxy.open_with_default_application(&path);
+                    } else if is_file && data.drag.is_none() && open {
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::OpenFile(path.clone()),
+                            Target::Widget(data.id),
+                        ));
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::ActiveFileChanged { path: Some(path) },
+                            Target::Widget(data.file_explorer.widget_id),
+                        ));
+                        // A previewed file is kept once it's double clicked
+                        if gesture == "single-click-preview" && double_click {
+                            ctx.submit_command(Command::new(
+                                LAPCE_UI_COMMAND,
+                                LapceUICommand::FocusEditor,
+                                Target::Widget(data.id),
+                            ));
+                        }
+                    }
+                }
+            }
             Event::MouseDown(mouse_event) => {
                 if !ctx.is_hot() {
                     return;
@@ -509,36 +2394,66 @@ impl Widget<LapceTabData> for FileExplorerFileList {
                 let file_explorer = Arc::make_mut(&mut data.file_explorer);
                 let index = ((mouse_event.pos.y + self.line_height)
                     / self.line_height) as usize;
+
+                // Clicking a sticky header scrolls back to its directory, and
+                // otherwise it stands for the row of its directory
+                let sticky = self.sticky_row_at(mouse_event.pos);
+                if let (Some(index), true) = (sticky, mouse_event.button.is_left()) {
+                    if let Some(path) = file_explorer
+                        .get_node_by_index(index)
+                        .map(|(_, node)| node.path_buf.clone())
+                    {
+                        file_explorer.select_path(&path);
+                    }
+                    self.scroll_to_row = Some(index);
+                    ctx.request_focus();
+                    ctx.request_paint();
+                    return;
+                }
+                let index = sticky.unwrap_or(index);
+
+                // Ctrl (or Cmd) clicking adds to the selection and shift clicking
+                // selects a range, without opening anything
+                let mods = &mouse_event.mods;
+                if mouse_event.button.is_left()
+                    && (mods.ctrl() || mods.meta() || mods.shift())
+                    && file_explorer.get_node_by_index(index).is_some()
+                {
+                    if mods.shift() {
+                        file_explorer.select_range(index);
+                    } else {
+                        file_explorer.toggle_selected(index);
+                    }
+                    ctx.request_focus();
+                    ctx.request_paint();
+                    return;
+                }
+
+                if file_explorer.get_node_by_index(index).is_some() {
+                    file_explorer.selected_index = Some(index);
+                    ctx.request_focus();
+                }
+                if mouse_event.button.is_left() {
+                    file_explorer.selected_paths.clear();
+                }
+                // Directories are toggled by their chevron, or by clicking their
+                // name as many times as the configured gesture takes
+                let expand_clicks =
+                    match data.config.ui.explorer_dir_expand_gesture() {
+                        "single-click" => 1,
+                        _ => 2,
+                    };
+                let on_toggle =
+                    self.toggle_rects.get(&index).map_or(false, |rect| {
+                        mouse_event.pos.x >= rect.x0 && mouse_event.pos.x <= rect.x1
+                    }) || mouse_event.count == expand_clicks;
                 if mouse_event.button.is_left() {
+                    self.click_count = mouse_event.count;
                     if let Some((_, node)) =
                         file_explorer.get_node_by_index_mut(index)
                     {
-                        if node.is_dir {
-                            if node.read {
-                                node.open = !node.open;
-                            } else {
-                                let tab_id = data.id;
-                                let event_sink = ctx.get_external_handle();
-                                FileExplorerData::read_dir(
-                                    &node.path_buf,
-                                    true,
-                                    tab_id,
-                                    &data.proxy,
-                                    event_sink,
-                                );
-                            }
-                            let path = node.path_buf.clone();
-                            if let Some(paths) = file_explorer.node_tree(&path) {
-                                for path in paths.iter() {
-                                    file_explorer.update_node_count(path);
-                                }
-                            }
-                        } else {
-                            ctx.submit_command(Command::new(
-                                LAPCE_UI_COMMAND,
-                                LapceUICommand::OpenFile(node.path_buf.clone()),
-                                Target::Widget(data.id),
-                            ));
+                        if node.is_dir && !on_toggle {
+                            // Clicking the name of a directory only selects it
                             ctx.submit_command(Command::new(
                                 LAPCE_UI_COMMAND,
                                 LapceUICommand::ActiveFileChanged {
@@ -546,17 +2461,39 @@ impl Widget<LapceTabData> for FileExplorerFileList {
                                 },
                                 Target::Widget(file_explorer.widget_id),
                             ));
+                        } else if node.is_dir {
+                            let open = !node.open;
+                            let path = node.path_buf.clone();
+                            file_explorer.set_open(&path, open);
                         }
                     }
+                    // The node can be dragged out into an editor, so files are
+                    // only opened when the mouse is released
+                    if let Some((_, node)) = file_explorer.get_node_by_index(index) {
+                        self.drag_start =
+                            Some((mouse_event.window_pos, node.path_buf.clone()));
+                        ctx.set_active(true);
+                    }
                 }
 
                 if mouse_event.button.is_right() {
-                    if let Some((indent_level, node)) = file_explorer
-                        .get_node_by_index(index)
-                        .or_else(|| file_explorer.workspace.as_ref().map(|x| (0, x)))
+                    // The blank space past the last row targets the workspace
+                    // folder, or the directory the tree is narrowed to, which is
+                    // the first row of the index
+                    let index = if index > file_explorer.row_count() {
+                        0
+                    } else {
+                        index
+                    };
+                    if let Some((indent_level, node)) =
+                        file_explorer.get_node_by_index(index).or_else(|| {
+                            file_explorer.display_root_node().map(|x| (0, x))
+                        })
                     {
                         let is_workspace = Some(&node.path_buf)
                             == file_explorer.workspace.as_ref().map(|x| &x.path_buf);
+                        let is_display_root = Some(&node.path_buf)
+                            == file_explorer.display_root.as_ref();
 
                         // The folder that it is, or is within
                         let base = if node.is_dir {
@@ -580,6 +2517,45 @@ impl Widget<LapceTabData> for FileExplorerFileList {
                         // since the context menu only gets access to LapceData
                         let window_id = data.window_id;
                         let tab_id = data.id;
+                        let permanent =
+                            data.config.ui.explorer_deletes_permanently();
+
+                        let selection = file_explorer.selection();
+                        if selection.len() > 1 && selection.contains(&node.path_buf)
+                        {
+                            let item = druid::MenuItem::new(format!(
+                                "Open {} Selected",
+                                selection.len()
+                            ))
+                            .command(
+                                open_paths_command(
+                                    file_explorer,
+                                    tab_id,
+                                    selection.clone(),
+                                ),
+                            );
+                            menu = menu.entry(item);
+
+                            let paths = trashable_paths(file_explorer, &selection);
+                            if !paths.is_empty() {
+                                let item = if permanent {
+                                    druid::MenuItem::new(format!(
+                                        "Delete {} Selected Permanently",
+                                        paths.len()
+                                    ))
+                                    .command(delete_paths_command(tab_id, paths))
+                                } else {
+                                    druid::MenuItem::new(format!(
+                                        "Move {} Selected to Trash",
+                                        paths.len()
+                                    ))
+                                    .command(trash_paths_command(tab_id, paths))
+                                };
+                                menu = menu.entry(item);
+                            }
+                            menu = menu.separator();
+                        }
+
                         let item = druid::MenuItem::new("New File").on_activate(
                             make_new_file_cb(
                                 ctx,
@@ -590,11 +2566,43 @@ impl Widget<LapceTabData> for FileExplorerFileList {
                                 index,
                                 indent_level,
                                 false,
+                                None,
+                                None,
                             ),
                         );
 
                         menu = menu.entry(item);
 
+                        // Templates are listed when the menu opens, so that newly added
+                        // templates show up without restarting
+                        let templates = Config::templates();
+                        if !templates.is_empty() {
+                            let mut templates_menu =
+                                druid::Menu::<LapceData>::new("New From Template");
+                            for template in templates {
+                                let name = template
+                                    .file_name()
+                                    .map(|x| x.to_string_lossy().to_string())
+                                    .unwrap_or_default();
+                                let item = druid::MenuItem::new(name).on_activate(
+                                    make_new_file_cb(
+                                        ctx,
+                                        &base,
+                                        window_id,
+                                        tab_id,
+                                        is_workspace,
+                                        index,
+                                        indent_level,
+                                        false,
+                                        Some(template),
+                                        None,
+                                    ),
+                                );
+                                templates_menu = templates_menu.entry(item);
+                            }
+                            menu = menu.entry(templates_menu);
+                        }
+
                         let item = druid::MenuItem::new("New Directory")
                             .on_activate(make_new_file_cb(
                                 ctx,
@@ -605,16 +2613,415 @@ impl Widget<LapceTabData> for FileExplorerFileList {
                                 index,
                                 indent_level,
                                 true,
+                                None,
+                                None,
                             ));
                         menu = menu.entry(item);
 
-                        // Separator between non destructive and destructive actions
+                        // Images are saved right away, while text asks for a name
+                        let clipboard = Application::global().clipboard();
+                        if clipboard
+                            .preferred_format(&[CLIPBOARD_PNG_FORMAT])
+                            .is_some()
+                        {
+                            let item = druid::MenuItem::new("Paste as New File")
+                                .command(Command::new(
+                                    LAPCE_UI_COMMAND,
+                                    LapceUICommand::PasteImageAsNewFile {
+                                        dir: base.clone(),
+                                    },
+                                    Target::Auto,
+                                ));
+                            menu = menu.entry(item);
+                        } else if let Some(text) = clipboard.get_string() {
+                            let item = druid::MenuItem::new("Paste as New File")
+                                .on_activate(make_new_file_cb(
+                                    ctx,
+                                    &base,
+                                    window_id,
+                                    tab_id,
+                                    is_workspace,
+                                    index,
+                                    indent_level,
+                                    false,
+                                    None,
+                                    Some(text),
+                                ));
+                            menu = menu.entry(item);
+                        }
+
+                        // The children of the top of the tree are always shown as a tree
+                        if node.is_dir && !is_workspace && !is_display_root {
+                            let title = if file_explorer.is_flat(&node.path_buf) {
+                                "Show as Tree"
+                            } else {
+                                "Show as Flat List"
+                            };
+                            let item =
+                                druid::MenuItem::new(title).command(Command::new(
+                                    LAPCE_UI_COMMAND,
+                                    LapceUICommand::ToggleExplorerFlatDir {
+                                        path: node.path_buf.clone(),
+                                    },
+                                    Target::Auto,
+                                ));
+                            menu = menu.entry(item);
+                        }
+
+                        // Only directories in the workspace folder can be the top
+                        // of the tree, since the added folders aren't shown then
+                        let in_workspace =
+                            file_explorer.workspace.as_ref().map_or(false, |x| {
+                                node.path_buf.starts_with(&x.path_buf)
+                            });
+                        if node.is_dir
+                            && in_workspace
+                            && !is_workspace
+                            && !is_display_root
+                        {
+                            let item = druid::MenuItem::new("Set as Explorer Root")
+                                .command(Command::new(
+                                    LAPCE_UI_COMMAND,
+                                    LapceUICommand::SetExplorerDisplayRoot {
+                                        path: Some(node.path_buf.clone()),
+                                    },
+                                    Target::Auto,
+                                ));
+                            menu = menu.entry(item);
+                        } else if is_display_root {
+                            let item = druid::MenuItem::new("Show Whole Workspace")
+                                .command(Command::new(
+                                    LAPCE_UI_COMMAND,
+                                    LapceUICommand::SetExplorerDisplayRoot {
+                                        path: None,
+                                    },
+                                    Target::Auto,
+                                ));
+                            menu = menu.entry(item);
+                        }
+
+                        if node.is_dir {
+                            let mut expand_menu =
+                                druid::Menu::<LapceData>::new("Expand");
+                            for levels in 1..=3 {
+                                let title = if levels == 1 {
+                                    "1 Level".to_string()
+                                } else {
+                                    format!("{levels} Levels")
+                                };
+                                let item = druid::MenuItem::new(title).command(
+                                    Command::new(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::ExpandExplorerDir {
+                                            path: node.path_buf.clone(),
+                                            levels: Some(levels),
+                                            continued: false,
+                                        },
+                                        Target::Auto,
+                                    ),
+                                );
+                                expand_menu = expand_menu.entry(item);
+                            }
+                            let item = druid::MenuItem::new("All Levels").command(
+                                Command::new(
+                                    LAPCE_UI_COMMAND,
+                                    LapceUICommand::ExpandExplorerDirAll {
+                                        path: node.path_buf.clone(),
+                                    },
+                                    Target::Auto,
+                                ),
+                            );
+                            expand_menu = expand_menu.entry(item);
+                            menu = menu.entry(expand_menu);
+
+                            let item = druid::MenuItem::new("Refresh").command(
+                                Command::new(
+                                    LAPCE_UI_COMMAND,
+                                    LapceUICommand::ExplorerRefresh {
+                                        path: node.path_buf.clone(),
+                                    },
+                                    Target::Auto,
+                                ),
+                            );
+                            menu = menu.entry(item);
+                        }
+
                         menu = menu.separator();
 
-                        // Don't allow us to rename or delete the current workspace
                         if !is_workspace {
-                            let item = druid::MenuItem::new("Rename").command(
-                                Command::new(
+                            let item = if file_explorer.is_pinned(&node.path_buf) {
+                                druid::MenuItem::new("Unpin from Explorer").command(
+                                    Command::new(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::UnpinExplorerPath {
+                                            path: node.path_buf.clone(),
+                                        },
+                                        Target::Auto,
+                                    ),
+                                )
+                            } else {
+                                druid::MenuItem::new("Pin to Explorer").command(
+                                    Command::new(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::PinExplorerPath {
+                                            path: node.path_buf.clone(),
+                                        },
+                                        Target::Auto,
+                                    ),
+                                )
+                            };
+                            menu = menu.entry(item);
+                        }
+
+                        if is_workspace {
+                            let item =
+                                druid::MenuItem::new("Open Workspace Settings")
+                                    .command(Command::new(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::OpenWorkspaceSettings,
+                                        Target::Auto,
+                                    ));
+                            menu = menu.entry(item);
+                        }
+
+                        if !node.is_dir {
+                            let item = druid::MenuItem::new(
+                                "Open With Default Application",
+                            )
+                            .command(Command::new(
+                                LAPCE_UI_COMMAND,
+                                LapceUICommand::OpenWithDefaultApplication {
+                                    path: node.path_buf.clone(),
+                                },
+                                Target::Auto,
+                            ));
+                            menu = menu.entry(item);
+
+                            let item = druid::MenuItem::new("Select for Compare")
+                                .command(Command::new(
+                                    LAPCE_UI_COMMAND,
+                                    LapceUICommand::SelectForCompare {
+                                        path: node.path_buf.clone(),
+                                    },
+                                    Target::Auto,
+                                ));
+                            menu = menu.entry(item);
+
+                            if file_explorer
+                                .compare_selected
+                                .as_ref()
+                                .map_or(false, |selected| selected != &node.path_buf)
+                            {
+                                let item =
+                                    druid::MenuItem::new("Compare with Selected")
+                                        .command(Command::new(
+                                            LAPCE_UI_COMMAND,
+                                            LapceUICommand::CompareWithSelected {
+                                                path: node.path_buf.clone(),
+                                            },
+                                            Target::Auto,
+                                        ));
+                                menu = menu.entry(item);
+                            }
+                        }
+
+                        let item = druid::MenuItem::new("Reveal in File Manager")
+                            .command(Command::new(
+                                LAPCE_UI_COMMAND,
+                                LapceUICommand::RevealInFileManager {
+                                    path: node.path_buf.clone(),
+                                },
+                                Target::Auto,
+                            ));
+                        menu = menu.entry(item);
+
+                        let path = node.path_buf.to_string_lossy().to_string();
+                        let item = druid::MenuItem::new("Copy Path").on_activate(
+                            move |_ctx, _data, _env| {
+                                Application::global().clipboard().put_string(&path);
+                            },
+                        );
+                        menu = menu.entry(item);
+
+                        if let Some(name) = name_without_extension(&node.path_buf)
+                            .filter(|_| !node.is_dir)
+                        {
+                            let item = druid::MenuItem::new(
+                                "Copy Filename Without Extension",
+                            )
+                            .on_activate(
+                                move |_ctx, _data, _env| {
+                                    Application::global()
+                                        .clipboard()
+                                        .put_string(&name);
+                                },
+                            );
+                            menu = menu.entry(item);
+                        }
+
+                        let item = druid::MenuItem::new("Copy Contents")
+                            .command(Command::new(
+                                LAPCE_UI_COMMAND,
+                                LapceUICommand::CopyFileContents {
+                                    path: node.path_buf.clone(),
+                                },
+                                Target::Auto,
+                            ))
+                            .enabled(!node.is_dir);
+                        menu = menu.entry(item);
+
+                        // Offered whenever the path is beneath the home directory,
+                        // even if paths aren't shown abbreviated
+                        if let Some(path) = data
+                            .home_dir
+                            .as_ref()
+                            .and_then(|home| abbreviate_home(&node.path_buf, home))
+                        {
+                            let item = druid::MenuItem::new("Copy Path with ~")
+                                .on_activate(move |_ctx, _data, _env| {
+                                    Application::global()
+                                        .clipboard()
+                                        .put_string(&path);
+                                });
+                            menu = menu.entry(item);
+                        }
+
+                        if let Some(import_path) = node
+                            .path_buf
+                            .extension()
+                            .and_then(|ext| {
+                                data.config.ui.explorer_import_separator(
+                                    &ext.to_string_lossy(),
+                                )
+                            })
+                            .zip(data.workspace.path.as_ref())
+                            .and_then(|(separator, workspace)| {
+                                import_path(
+                                    &node.path_buf,
+                                    workspace,
+                                    data.config.ui.explorer_import_source_root(),
+                                    separator,
+                                )
+                            })
+                            .filter(|_| !node.is_dir)
+                        {
+                            let item =
+                                druid::MenuItem::new("Copy as Path for Import")
+                                    .on_activate(move |_ctx, _data, _env| {
+                                        Application::global()
+                                            .clipboard()
+                                            .put_string(&import_path);
+                                    });
+                            menu = menu.entry(item);
+                        }
+
+                        // Only paths in the workspace folder are in its repository
+                        if data.workspace.path.as_ref().map_or(false, |workspace| {
+                            node.path_buf.starts_with(workspace)
+                                && &node.path_buf != workspace
+                        }) {
+                            let (label, ignored) = if node.git_ignored {
+                                ("Remove from .gitignore", false)
+                            } else {
+                                ("Add to .gitignore", true)
+                            };
+                            let item =
+                                druid::MenuItem::new(label).command(Command::new(
+                                    LAPCE_UI_COMMAND,
+                                    LapceUICommand::SetGitIgnored {
+                                        path: node.path_buf.clone(),
+                                        ignored,
+                                    },
+                                    Target::Auto,
+                                ));
+                            menu = menu.entry(item);
+                        }
+
+                        let item = druid::MenuItem::new("Find in Folder").command(
+                            Command::new(
+                                LAPCE_UI_COMMAND,
+                                LapceUICommand::FindInFolder { path: base.clone() },
+                                Target::Auto,
+                            ),
+                        );
+                        menu = menu.entry(item);
+
+                        let item = druid::MenuItem::new("Open Terminal Here")
+                            .command(Command::new(
+                                LAPCE_UI_COMMAND,
+                                LapceUICommand::OpenInSystemTerminal {
+                                    path: base.clone(),
+                                },
+                                Target::Auto,
+                            ));
+                        menu = menu.entry(item);
+
+                        if node.is_dir {
+                            // On the same host as the workspace, if it's remote
+                            let workspace = LapceWorkspace {
+                                kind: data.workspace.kind.clone(),
+                                path: Some(node.path_buf.clone()),
+                                last_open: SystemTime::now()
+                                    .duration_since(UNIX_EPOCH)
+                                    .map(|since| since.as_secs())
+                                    .unwrap_or(0),
+                            };
+                            let item = druid::MenuItem::new("Open in New Window")
+                                .command(Command::new(
+                                    LAPCE_UI_COMMAND,
+                                    LapceUICommand::OpenInNewWindow {
+                                        window_id,
+                                        workspace,
+                                    },
+                                    Target::Global,
+                                ));
+                            menu = menu.entry(item);
+                        }
+
+                        let mut plugin_items = file_explorer
+                            .plugin_menu_items(node)
+                            .filter(|item| {
+                                !data.disabled_plugins.contains_key(&item.plugin)
+                            })
+                            .peekable();
+                        if plugin_items.peek().is_some() {
+                            menu = menu.separator();
+                        }
+                        for plugin_item in plugin_items {
+                            let proxy = data.proxy.clone();
+                            let plugin = plugin_item.plugin.clone();
+                            let command = plugin_item.command.clone();
+                            let path = node.path_buf.clone();
+                            let item =
+                                druid::MenuItem::new(plugin_item.label.clone())
+                                    .on_activate(move |_ctx, _data, _env| {
+                                        proxy.run_plugin_command(
+                                            &plugin,
+                                            &command,
+                                            json!({ "path": path }),
+                                        );
+                                    });
+                            menu = menu.entry(item);
+                        }
+
+                        // Separator between non destructive and destructive actions
+                        menu = menu.separator();
+
+                        // Don't allow us to rename or delete the workspace folders,
+                        // or the directory the tree is narrowed to
+                        if !is_workspace
+                            && !is_display_root
+                            && !file_explorer.is_root(&node.path_buf)
+                        {
+                            // Renaming or trashing requires write access to the
+                            // parent directory. Why is told by the tip of the row,
+                            // as menu entries can't have tooltips.
+                            let parent_readonly =
+                                file_explorer.is_parent_readonly(&node.path_buf);
+
+                            let item = druid::MenuItem::new("Rename")
+                                .enabled(!parent_readonly)
+                                .command(Command::new(
                                     LAPCE_UI_COMMAND,
                                     LapceUICommand::ExplorerStartRename {
                                         list_index: index,
@@ -626,25 +3033,44 @@ impl Widget<LapceTabData> for FileExplorerFileList {
                                             .unwrap_or_else(String::new),
                                     },
                                     Target::Auto,
-                                ),
-                            );
+                                ));
                             menu = menu.entry(item);
 
-                            let trash_text = if node.is_dir {
-                                "Move Directory to Trash"
-                            } else {
-                                "Move File to Trash"
-                            };
-                            let item = druid::MenuItem::new(trash_text).command(
-                                Command::new(
+                            let item = druid::MenuItem::new("Duplicate")
+                                .enabled(!parent_readonly)
+                                .command(Command::new(
                                     LAPCE_UI_COMMAND,
-                                    LapceUICommand::TrashPath {
+                                    LapceUICommand::DuplicatePath {
                                         path: node.path_buf.clone(),
                                     },
                                     Target::Auto,
-                                ),
-                            );
+                                ));
                             menu = menu.entry(item);
+
+                            let item = if permanent {
+                                druid::MenuItem::new("Delete Permanently").command(
+                                    delete_paths_command(
+                                        tab_id,
+                                        vec![node.path_buf.clone()],
+                                    ),
+                                )
+                            } else {
+                                let trash_text = if node.is_dir {
+                                    "Move Directory to Trash"
+                                } else {
+                                    "Move File to Trash"
+                                };
+                                druid::MenuItem::new(trash_text).command(
+                                    Command::new(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::TrashPath {
+                                            path: node.path_buf.clone(),
+                                        },
+                                        Target::Auto,
+                                    ),
+                                )
+                            };
+                            menu = menu.entry(item.enabled(!parent_readonly));
                         }
 
                         ctx.show_context_menu::<LapceData>(
@@ -679,18 +3105,41 @@ impl Widget<LapceTabData> for FileExplorerFileList {
         data: &LapceTabData,
         env: &Env,
     ) {
-        if data
-            .file_explorer
-            .workspace
-            .as_ref()
-            .map(|w| w.children_open_count)
-            != old_data
-                .file_explorer
-                .workspace
-                .as_ref()
-                .map(|w| w.children_open_count)
-        {
+        if data.file_explorer.row_count() != old_data.file_explorer.row_count() {
+            match self.repaint.request(Instant::now()) {
+                RepaintRequest::Now => ctx.request_layout(),
+                RepaintRequest::After(delay) => {
+                    self.repaint_timer = ctx.request_timer(delay, None);
+                }
+                RepaintRequest::Waiting => {}
+            }
+        }
+
+        // The rows follow the font size and density in the config
+        let line_height = data.config.ui.explorer_line_height() as f64;
+        if line_height != self.line_height {
+            self.line_height = line_height;
             ctx.request_layout();
+            ctx.request_paint();
+        }
+
+        // Follow the active editor, which can change without the explorer being
+        // told, such as when a file is opened from the search results
+        if data.config.ui.explorer_sync_active_editor() {
+            let active_file = active_editor_file(data);
+            if active_file.is_some() && active_file != active_editor_file(old_data) {
+                ctx.submit_command(Command::new(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::ActiveFileChanged {
+                        path: active_file.cloned(),
+                    },
+                    Target::Widget(data.file_explorer.widget_id),
+                ));
+            }
+        }
+
+        if data.file_explorer.is_loading() {
+            ctx.request_anim_frame();
         }
 
         if data.file_explorer.naming.is_some() {
@@ -705,25 +3154,22 @@ impl Widget<LapceTabData> for FileExplorerFileList {
         data: &LapceTabData,
         env: &Env,
     ) -> Size {
-        if let Some(naming) = &data.file_explorer.naming {
-            let (&index, &level) = match naming {
-                Naming::Renaming {
-                    list_index,
-                    indent_level,
-                }
-                | Naming::Naming {
-                    list_index,
-                    indent_level,
-                    ..
-                } => (list_index, indent_level),
+        if let (Some(naming), Some(index)) = (
+            &data.file_explorer.naming,
+            data.file_explorer.naming_list_index(),
+        ) {
+            let level = match naming {
+                Naming::Renaming { indent_level, .. }
+                | Naming::Naming { indent_level, .. } => *indent_level,
             };
 
             let max = bc.max();
             let input_bc = bc.shrink(Size::new(max.width / 2.0, 0.0));
             self.name_edit_input.layout(ctx, &input_bc, data, env);
 
-            let y_pos = (index as f64 * self.line_height) - self.line_height;
-            let x_pos = 38.0 + (15.0 * level as f64);
+            let y_pos = self.name_input_y(index);
+            let x_pos = 38.0
+                + (data.config.ui.explorer_indent_width() as f64 * level as f64);
             self.name_edit_input.set_origin(
                 ctx,
                 data,
@@ -732,12 +3178,7 @@ impl Widget<LapceTabData> for FileExplorerFileList {
             );
         }
 
-        let mut height = data
-            .file_explorer
-            .workspace
-            .as_ref()
-            .map(|w| w.children_open_count)
-            .unwrap_or(0);
+        let mut height = data.file_explorer.row_count();
         if matches!(data.file_explorer.naming, Some(Naming::Naming { .. })) {
             height += 1;
         }
@@ -751,59 +3192,198 @@ impl Widget<LapceTabData> for FileExplorerFileList {
     }
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &LapceTabData, env: &Env) {
-        let rect = ctx.region().bounding_box();
-        let size = ctx.size();
-        let width = size.width;
-        let active = data.file_explorer.active_selected.as_deref();
-        let min = (rect.y0 / self.line_height).floor() as usize;
-        let max = (rect.y1 / self.line_height) as usize + 2;
-        let level = 0;
-        let mut drawn_name_input = false;
+        let width = ctx.size().width;
+        self.paint_rows(ctx, data, env);
+        self.paint_sticky_headers(ctx, data, width);
+        self.paint_root_drop(ctx, data, width);
+        self.paint_hover_tip(ctx, data, width);
+    }
+}
 
-        if let Some(item) = data.file_explorer.workspace.as_ref() {
-            let mut i = 0;
-            for item in item.sorted_children() {
-                i = paint_file_node_item(
-                    ctx,
-                    env,
-                    item,
-                    min,
-                    max,
-                    self.line_height,
-                    width,
-                    level + 1,
-                    i + 1,
-                    active,
-                    self.hovered,
-                    data.file_explorer.naming.as_ref(),
-                    &mut self.name_edit_input,
-                    &mut drawn_name_input,
-                    data,
-                    &data.config,
-                    &mut HashMap::new(),
-                );
-                if i > max {
-                    return;
-                }
-            }
+/// Opening more files than this at once asks for confirmation first
+const MAX_OPEN_WITHOUT_CONFIRM: usize = 20;
 
-            // If we didn't draw the name input then we'll have to draw it here
-            if let Some(naming) = &data.file_explorer.naming {
-                if i == 0
-                    || (naming.list_index() >= min && naming.list_index() < max)
-                {
-                    draw_name_input(
-                        ctx,
-                        data,
-                        env,
-                        // This value does not matter here
-                        &mut 0,
-                        naming,
-                        &mut self.name_edit_input,
-                    );
-                }
-            }
-        }
+/// How many of the names of the paths to move to the trash are listed when
+/// asking to do so
+const MAX_TRASH_NAMES_SHOWN: usize = 3;
+
+/// The command to open the files and expand the directories of the paths,
+/// which asks for confirmation first when there are a lot of files
+fn open_paths_command(
+    file_explorer: &FileExplorerData,
+    tab_id: WidgetId,
+    paths: Vec<PathBuf>,
+) -> Command {
+    let file_count = paths
+        .iter()
+        .filter(|path| {
+            !file_explorer
+                .get_file_node(path)
+                .map_or(false, |node| node.is_dir)
+        })
+        .count();
+    if file_count <= MAX_OPEN_WITHOUT_CONFIRM {
+        return Command::new(
+            LAPCE_UI_COMMAND,
+            LapceUICommand::ExplorerOpenPaths { paths },
+            Target::Widget(tab_id),
+        );
+    }
+
+    Command::new(
+        LAPCE_UI_COMMAND,
+        LapceUICommand::ShowAlert(AlertContentData {
+            title: format!("Do you want to open {file_count} files?"),
+            msg: "Each file is opened in its own editor tab.".to_string(),
+            buttons: vec![(
+                format!("Open {file_count} Files"),
+                tab_id,
+                LapceCommand {
+                    kind: CommandKind::Workbench(
+                        LapceWorkbenchCommand::ExplorerOpenPaths,
+                    ),
+                    data: serde_json::to_value(&paths).ok(),
+                },
+            )],
+        }),
+        Target::Widget(tab_id),
+    )
+}
+
+/// The selected paths that can be moved to the trash, which aren't roots of
+/// the explorer, or inside another selected path that is moved along with them
+fn trashable_paths(
+    file_explorer: &FileExplorerData,
+    selection: &[PathBuf],
+) -> Vec<PathBuf> {
+    outermost_paths(selection)
+        .into_iter()
+        .filter(|path| !file_explorer.is_root(path))
+        .collect()
+}
+
+/// Ask whether to delete the paths permanently, which can't be undone
+fn delete_paths_command(tab_id: WidgetId, paths: Vec<PathBuf>) -> Command {
+    let title = match paths.as_slice() {
+        [path] => format!("Do you want to permanently delete {}?", file_name(path)),
+        _ => format!("Do you want to permanently delete {} items?", paths.len()),
+    };
+    Command::new(
+        LAPCE_UI_COMMAND,
+        LapceUICommand::ShowAlert(AlertContentData {
+            title,
+            msg: format!(
+                "{} will be deleted. This can't be undone.",
+                path_names_summary(&paths, MAX_TRASH_NAMES_SHOWN)
+            ),
+            buttons: vec![(
+                "Delete Permanently".to_string(),
+                tab_id,
+                LapceCommand {
+                    kind: CommandKind::Workbench(
+                        LapceWorkbenchCommand::ExplorerDeletePaths,
+                    ),
+                    data: serde_json::to_value(&paths).ok(),
+                },
+            )],
+        }),
+        Target::Widget(tab_id),
+    )
+}
+
+/// Ask whether to expand all the levels beneath the directory, which has too
+/// many entries beneath it to do so right away
+pub fn expand_all_levels_alert(tab_id: WidgetId, path: &Path) -> AlertContentData {
+    AlertContentData {
+        title: format!("Do you want to expand everything in {}?", file_name(path)),
+        msg: "It has a very large number of entries beneath it, which can take a \
+              while to read."
+            .to_string(),
+        buttons: vec![(
+            "Expand All".to_string(),
+            tab_id,
+            LapceCommand {
+                kind: CommandKind::Workbench(
+                    LapceWorkbenchCommand::ExplorerExpandAllLevels,
+                ),
+                data: serde_json::to_value(path).ok(),
+            },
+        )],
+    }
+}
+
+/// Ask once whether to move all the paths to the trash
+fn trash_paths_command(tab_id: WidgetId, paths: Vec<PathBuf>) -> Command {
+    Command::new(
+        LAPCE_UI_COMMAND,
+        LapceUICommand::ShowAlert(AlertContentData {
+            title: format!(
+                "Do you want to move {} items to the trash?",
+                paths.len()
+            ),
+            msg: format!(
+                "{} will be moved. You can restore them from the trash.",
+                path_names_summary(&paths, MAX_TRASH_NAMES_SHOWN)
+            ),
+            buttons: vec![(
+                format!("Move {} Items to Trash", paths.len()),
+                tab_id,
+                LapceCommand {
+                    kind: CommandKind::Workbench(
+                        LapceWorkbenchCommand::ExplorerTrashPaths,
+                    ),
+                    data: serde_json::to_value(&paths).ok(),
+                },
+            )],
+        }),
+        Target::Widget(tab_id),
+    )
+}
+
+/// The size shown on the row of the node, when sizes are shown. Directories
+/// have none until the proxy has added up the sizes of their files, and files
+/// none until their metadata has been read.
+fn shown_size(data: &LapceTabData, item: &FileNodeItem) -> Option<u64> {
+    if !data.config.ui.explorer_show_sizes() {
+        None
+    } else if item.is_dir {
+        data.file_explorer.dir_sizes.get(&item.path_buf).copied()
+    } else {
+        item.metadata_read.then(|| item.size)
+    }
+}
+
+/// The node of the path in the tree, to paint its row outside of the tree.
+/// Paths whose directory hasn't been read get a node of their own.
+fn shown_node(data: &LapceTabData, path: &Path, is_dir: bool) -> FileNodeItem {
+    data.file_explorer
+        .get_flat_file_node(path)
+        .or_else(|| data.file_explorer.get_file_node(path))
+        .cloned()
+        .unwrap_or_else(|| FileNodeItem::new(path.to_path_buf(), is_dir))
+}
+
+/// Open the file in an editor and show it as the active file of the explorer
+fn open_file(ctx: &mut EventCtx, data: &LapceTabData, path: &Path) {
+    ctx.submit_command(Command::new(
+        LAPCE_UI_COMMAND,
+        LapceUICommand::OpenFile(path.to_path_buf()),
+        Target::Widget(data.id),
+    ));
+    ctx.submit_command(Command::new(
+        LAPCE_UI_COMMAND,
+        LapceUICommand::ActiveFileChanged {
+            path: Some(path.to_path_buf()),
+        },
+        Target::Widget(data.file_explorer.widget_id),
+    ));
+}
+
+/// The file open in the active editor, if it is a file
+fn active_editor_file(data: &LapceTabData) -> Option<&PathBuf> {
+    match &data.main_split.active_editor()?.content {
+        BufferContent::File(path) => Some(path),
+        _ => None,
     }
 }
 
@@ -819,6 +3399,8 @@ fn make_new_file_cb(
     index: usize,
     indent_level: usize,
     is_dir: bool,
+    template: Option<PathBuf>,
+    content: Option<String>,
 ) -> impl FnMut(&mut MenuEventCtx, &mut LapceData, &Env) + 'static {
     // If the node we're on is the workspace then we'll appear at the very start
     let display_index = if is_workspace { 1 } else { index + 1 };
@@ -830,6 +3412,8 @@ fn make_new_file_cb(
         // closure
         let event_sink = event_sink.clone();
         let base_path = base_path.clone();
+        let template = template.clone();
+        let content = content.clone();
 
         // Acquire the LapceTabData instance we were within
         let tab_data = data
@@ -858,6 +3442,8 @@ fn make_new_file_cb(
                         indent_level,
                         is_dir,
                         base_path,
+                        template,
+                        content,
                     },
                     Target::Auto,
                 );
@@ -887,6 +3473,7 @@ fn expand_dir(
 ) {
     if let Some((_, node)) = file_explorer.get_node_by_index_mut(index) {
         if node.is_dir {
+            let reading = !node.read;
             if node.read {
                 node.open = true;
                 on_finished();
@@ -901,11 +3488,15 @@ fn expand_dir(
                 );
             }
             let path = node.path_buf.clone();
+            if reading {
+                file_explorer.reading_dirs.insert(path.clone());
+            }
             if let Some(paths) = file_explorer.node_tree(&path) {
                 for path in paths.iter() {
                     file_explorer.update_node_count(path);
                 }
             }
+            file_explorer.rebuild_index();
         } else {
             on_finished();
         }
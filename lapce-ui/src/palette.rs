@@ -137,7 +137,10 @@ impl Widget<LapceTabData> for Palette {
                         if &palette.run_id == run_id {
                             palette.items = items.to_owned();
                             palette.preview(ctx);
-                            if palette.get_input() != "" {
+                            // The files are already matched by the proxy
+                            if palette.get_input() != ""
+                                && palette.palette_type != PaletteType::File
+                            {
                                 let _ = palette.sender.send((
                                     palette.run_id.clone(),
                                     palette.get_input().to_string(),
@@ -569,7 +572,7 @@ impl PaletteContent {
         let (svg, text, text_indices, hint, hint_indices) =
             match palette_item_content {
                 PaletteItemContent::File(path, _) => {
-                    Self::file_paint_items(path, indices)
+                    Self::file_paint_items(path, indices, config)
                 }
                 PaletteItemContent::DocumentSymbol {
                     kind,
@@ -620,7 +623,10 @@ impl PaletteContent {
                     (None, text.clone(), indices.to_vec(), "".to_string(), vec![])
                 }
                 PaletteItemContent::ReferenceLocation(rel_path, _location) => {
-                    Self::file_paint_items(rel_path, indices)
+                    Self::file_paint_items(rel_path, indices, config)
+                }
+                PaletteItemContent::TrashEntry(rel_path, _entry) => {
+                    Self::file_paint_items(rel_path, indices, config)
                 }
                 PaletteItemContent::Workspace(w) => {
                     let text = w.path.as_ref().unwrap().to_str().unwrap();
@@ -653,7 +659,8 @@ impl PaletteContent {
                     "".to_string(),
                     vec![],
                 ),
-                PaletteItemContent::Language(name) => (
+                PaletteItemContent::Language(name)
+                | PaletteItemContent::Encoding(name) => (
                     None,
                     name.to_string(),
                     indices.to_vec(),
@@ -813,8 +820,9 @@ impl PaletteContent {
     fn file_paint_items(
         path: &Path,
         indices: &[usize],
+        config: &Config,
     ) -> (Option<Svg>, String, Vec<usize>, String, Vec<usize>) {
-        let (svg, _) = file_svg(path);
+        let (svg, _) = file_svg(path, config);
         let file_name = path
             .file_name()
             .and_then(|s| s.to_str())
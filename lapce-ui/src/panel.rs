@@ -314,31 +314,9 @@ impl Widget<LapceTabData> for PanelSectionHeader {
     }
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &LapceTabData, _env: &Env) {
-        let rect = ctx.size().to_rect();
-        ctx.fill(
-            rect,
-            data.config
-                .get_color_unchecked(LapceTheme::EDITOR_BACKGROUND),
-        );
-        let shadow_width = data.config.ui.drop_shadow_width() as f64;
-        if shadow_width > 0.0 {
-            ctx.with_save(|ctx| {
-                let (_, pos) = data.panel.panel_position(&self.kind).unwrap();
-                if pos.is_bottom() {
-                    ctx.clip(rect.inset((0.0, 0.0, 0.0, 50.0)));
-                } else {
-                    ctx.clip(rect.inset((0.0, 50.0, 0.0, 50.0)));
-                }
-
-                ctx.blurred_rect(
-                    rect,
-                    shadow_width,
-                    data.config
-                        .get_color_unchecked(LapceTheme::LAPCE_DROPDOWN_SHADOW),
-                );
-            });
-        }
+        paint_section_header_background(ctx, data, self.kind);
 
+        let rect = ctx.size().to_rect();
         ctx.with_save(|ctx| {
             ctx.clip(rect);
             let text_layout = ctx
@@ -362,6 +340,39 @@ impl Widget<LapceTabData> for PanelSectionHeader {
     }
 }
 
+/// Paint the background of a section header of the panel, with the shadow
+/// it casts on the section
+pub fn paint_section_header_background(
+    ctx: &mut PaintCtx,
+    data: &LapceTabData,
+    kind: PanelKind,
+) {
+    let rect = ctx.size().to_rect();
+    ctx.fill(
+        rect,
+        data.config
+            .get_color_unchecked(LapceTheme::EDITOR_BACKGROUND),
+    );
+    let shadow_width = data.config.ui.drop_shadow_width() as f64;
+    if shadow_width > 0.0 {
+        ctx.with_save(|ctx| {
+            let (_, pos) = data.panel.panel_position(&kind).unwrap();
+            if pos.is_bottom() {
+                ctx.clip(rect.inset((0.0, 0.0, 0.0, 50.0)));
+            } else {
+                ctx.clip(rect.inset((0.0, 50.0, 0.0, 50.0)));
+            }
+
+            ctx.blurred_rect(
+                rect,
+                shadow_width,
+                data.config
+                    .get_color_unchecked(LapceTheme::LAPCE_DROPDOWN_SHADOW),
+            );
+        });
+    }
+}
+
 pub struct PanelContainer {
     pub widget_id: WidgetId,
     switcher0: WidgetPod<LapceTabData, PanelSwitcher>,
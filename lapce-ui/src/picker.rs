@@ -19,7 +19,7 @@ use crate::{
     editor::view::LapceEditorView,
     explorer::{get_item_children, get_item_children_mut},
     scroll::LapceScroll,
-    svg::{file_svg, get_svg},
+    svg::{file_svg, folder_svg, get_svg},
     tab::LapceButton,
 };
 
@@ -597,18 +597,13 @@ pub fn paint_file_node_item_by_index(
             );
             toggle_rects.insert(current, rect);
 
-            let icon_name = if item.open {
-                "default_folder_opened.svg"
-            } else {
-                "default_folder.svg"
-            };
-            let svg = get_svg(icon_name).unwrap();
+            let svg = folder_svg(&item.path_buf, item.open, config);
             let rect = Size::new(svg_size, svg_size)
                 .to_rect()
                 .with_origin(Point::new(1.0 + 16.0 + padding, svg_y));
             ctx.draw_svg(&svg, rect, None);
         } else {
-            let (svg, svg_color) = file_svg(&item.path_buf);
+            let (svg, svg_color) = file_svg(&item.path_buf, config);
             let rect = Size::new(svg_size, svg_size)
                 .to_rect()
                 .with_origin(Point::new(1.0 + 16.0 + padding, svg_y));
@@ -1,15 +1,18 @@
+use std::sync::Arc;
+
 use crate::{panel::PanelSizing, scroll::LapceScroll};
 use druid::{
     piet::{Text, TextAttribute, TextLayout as PietTextLayout, TextLayoutBuilder},
-    BoxConstraints, Color, Command, Cursor, Env, Event, EventCtx, FontWeight,
-    LayoutCtx, LifeCycle, LifeCycleCtx, MouseEvent, PaintCtx, Point, RenderContext,
-    Size, Target, UpdateCtx, Widget, WidgetExt, WidgetId,
+    Application, BoxConstraints, Color, Command, Cursor, Env, Event, EventCtx,
+    FontWeight, LayoutCtx, LifeCycle, LifeCycleCtx, MouseEvent, PaintCtx, Point,
+    RenderContext, Size, Target, UpdateCtx, Widget, WidgetExt, WidgetId,
 };
 use lapce_data::{
     command::{LapceUICommand, PluginLoadingStatus, LAPCE_UI_COMMAND},
     config::LapceTheme,
     data::{LapceData, LapceTabData},
     panel::PanelKind,
+    plugin::PluginInstallStatus,
 };
 use lapce_rpc::plugin::PluginDescription;
 use strum_macros::Display;
@@ -22,6 +25,39 @@ enum PluginStatus {
     Install,
     Upgrade,
     Disabled,
+    Failed,
+    /// Being downloaded, which the button cancels
+    #[strum(serialize = "Cancel")]
+    Installing,
+    /// The install failed, which the button tries again
+    #[strum(serialize = "Retry")]
+    InstallFailed,
+}
+
+/// The status of the plugin that is shown on its button
+fn plugin_status(data: &LapceTabData, plugin: &PluginDescription) -> PluginStatus {
+    match data.plugin_installs.get(&plugin.name) {
+        Some(PluginInstallStatus::Installing { .. }) => {
+            return PluginStatus::Installing
+        }
+        Some(PluginInstallStatus::Failed(_)) => return PluginStatus::InstallFailed,
+        None => {}
+    }
+    if data.disabled_plugins.contains_key(&plugin.name) {
+        return PluginStatus::Disabled;
+    }
+    if data.failed_plugins.contains_key(&plugin.name) {
+        return PluginStatus::Failed;
+    }
+    match data
+        .installed_plugins
+        .get(&plugin.name)
+        .map(|installed| installed.version == plugin.version)
+    {
+        Some(true) => PluginStatus::Installed,
+        Some(false) => PluginStatus::Upgrade,
+        None => PluginStatus::Install,
+    }
 }
 
 pub struct Plugin {
@@ -79,6 +115,26 @@ impl Plugin {
             if let Some(plugin) = plugins.get(index) {
                 let local_plugin = plugin.clone();
                 let mut menu = druid::Menu::<LapceData>::new("Plugin");
+                if let Some(error) = data.failed_plugins.get(&plugin.name) {
+                    let local_plugin = plugin.clone();
+                    let item = druid::MenuItem::new("Retry").on_activate(
+                        move |ctx, _data, _env| {
+                            ctx.submit_command(Command::new(
+                                LAPCE_UI_COMMAND,
+                                LapceUICommand::EnablePlugin(local_plugin.clone()),
+                                Target::Auto,
+                            ));
+                        },
+                    );
+                    menu = menu.entry(item);
+                    let error = error.clone();
+                    let item = druid::MenuItem::new("Copy Error").on_activate(
+                        move |_ctx, _data, _env| {
+                            Application::global().clipboard().put_string(&error);
+                        },
+                    );
+                    menu = menu.entry(item).separator();
+                }
                 if plugin.wasm.is_some() {
                     if disabled {
                         let item = druid::MenuItem::new("Enable Plugin")
@@ -126,6 +182,34 @@ impl Plugin {
         }
     }
 
+    /// Paint a bar along the bottom of the row of a plugin being installed,
+    /// filled as far as its download got, if its size is known
+    fn paint_install_progress(
+        &self,
+        ctx: &mut PaintCtx,
+        data: &LapceTabData,
+        bottom_left: Point,
+        width: f64,
+        received: u64,
+        total: Option<u64>,
+    ) {
+        let height = 3.0;
+        let rect = Size::new(width.max(0.0), height)
+            .to_rect()
+            .with_origin(bottom_left - (0.0, height));
+        ctx.fill(
+            rect,
+            data.config.get_color_unchecked(LapceTheme::LAPCE_BORDER),
+        );
+        if let Some(total) = total.filter(|total| *total > 0) {
+            let done = (received as f64 / total as f64).min(1.0);
+            ctx.fill(
+                rect.with_size(Size::new(rect.width() * done, height)),
+                data.config.get_color_unchecked(LapceTheme::EDITOR_CARET),
+            );
+        }
+    }
+
     fn hit_test<'a>(
         &self,
         ctx: &mut EventCtx,
@@ -140,17 +224,7 @@ impl Plugin {
         if let PluginLoadingStatus::Ok(ref plugins) = **fetched_plugins {
             let index = (mouse_event.pos.y / (self.line_height * 3.0)) as usize;
             let plugin = plugins.get(index)?;
-            let mut status =
-                match data.installed_plugins.get(&plugin.name).map(|installed| {
-                    plugin.version.clone() == installed.version.clone()
-                }) {
-                    Some(true) => PluginStatus::Installed,
-                    Some(false) => PluginStatus::Upgrade,
-                    None => PluginStatus::Install,
-                };
-            if data.disabled_plugins.contains_key(&plugin.name) {
-                status = PluginStatus::Disabled;
-            }
+            let status = plugin_status(data, plugin);
 
             let padding = 10.0;
             let text_padding = 5.0;
@@ -213,8 +287,22 @@ impl Widget<LapceTabData> for Plugin {
                     {
                         if status == PluginStatus::Install
                             || status == PluginStatus::Upgrade
+                            || status == PluginStatus::InstallFailed
                         {
+                            // Shown as installing right away, as nothing comes
+                            // in until the download has started
+                            Arc::make_mut(&mut data.plugin_installs).insert(
+                                plugin.name.clone(),
+                                PluginInstallStatus::Installing {
+                                    received: 0,
+                                    total: None,
+                                },
+                            );
                             data.proxy.install_plugin(plugin);
+                        } else if status == PluginStatus::Installing {
+                            data.proxy.cancel_plugin_install(&plugin.name);
+                            Arc::make_mut(&mut data.plugin_installs)
+                                .remove(&plugin.name);
                         } else if status == PluginStatus::Installed {
                             self.enable_or_disable_plugin(
                                 mouse_event,
@@ -229,6 +317,13 @@ impl Widget<LapceTabData> for Plugin {
                                 ctx,
                                 true,
                             );
+                        } else if status == PluginStatus::Failed {
+                            self.enable_or_disable_plugin(
+                                mouse_event,
+                                data,
+                                ctx,
+                                false,
+                            );
                         }
                     }
                 }
@@ -340,18 +435,34 @@ impl Widget<LapceTabData> for Plugin {
                                     / 2.0,
                             ),
                         );
+                        let install = data.plugin_installs.get(&plugin.name);
+                        let error = data.failed_plugins.get(&plugin.name);
+                        // A failed plugin shows why in place of its description
+                        let (description, description_color) = match (install, error)
+                        {
+                            (Some(PluginInstallStatus::Failed(error)), _) => (
+                                format!("Failed to install: {error}"),
+                                LapceTheme::LAPCE_ERROR,
+                            ),
+                            (_, Some(error)) => (
+                                format!("Failed to start: {error}"),
+                                LapceTheme::LAPCE_ERROR,
+                            ),
+                            _ => (
+                                plugin.description.clone(),
+                                LapceTheme::EDITOR_FOREGROUND,
+                            ),
+                        };
                         let text_layout = ctx
                             .text()
-                            .new_text_layout(plugin.description.clone())
+                            .new_text_layout(description.clone())
                             .font(
                                 data.config.ui.font_family(),
                                 data.config.ui.font_size() as f64,
                             )
                             .text_color(
                                 data.config
-                                    .get_color_unchecked(
-                                        LapceTheme::EDITOR_FOREGROUND,
-                                    )
+                                    .get_color_unchecked(description_color)
                                     .clone(),
                             )
                             .build()
@@ -364,7 +475,6 @@ impl Widget<LapceTabData> for Plugin {
                                 self.width - x - 15.0,
                                 0.0,
                             ));
-                            let description = plugin.description.clone();
                             let end = description
                                 .char_indices()
                                 .filter(|(i, _)| {
@@ -387,9 +497,7 @@ impl Widget<LapceTabData> for Plugin {
                                 )
                                 .text_color(
                                     data.config
-                                        .get_color_unchecked(
-                                            LapceTheme::EDITOR_FOREGROUND,
-                                        )
+                                        .get_color_unchecked(description_color)
                                         .clone(),
                                 )
                                 .build()
@@ -443,21 +551,25 @@ impl Widget<LapceTabData> for Plugin {
                             ),
                         );
 
-                        let mut status = match data
-                            .installed_plugins
-                            .get(&plugin.name)
-                            .map(|installed| installed.version == plugin.version)
+                        let status = plugin_status(data, plugin);
+                        if let Some(PluginInstallStatus::Installing {
+                            received,
+                            total,
+                        }) = install
                         {
-                            Some(true) => PluginStatus::Installed,
-                            Some(false) => PluginStatus::Upgrade,
-                            None => PluginStatus::Install,
-                        };
-                        if data.disabled_plugins.contains_key(&plugin.name) {
-                            status = PluginStatus::Disabled;
+                            self.paint_install_progress(
+                                ctx,
+                                data,
+                                Point::new(x, y + self.line_height * 3.0),
+                                size.width - padding - x,
+                                *received,
+                                *total,
+                            );
                         }
 
                         if (status == PluginStatus::Installed)
                             || (status == PluginStatus::Disabled)
+                            || (status == PluginStatus::Failed)
                         {
                             let text_layout = ctx
                                 .text()
@@ -482,7 +594,13 @@ impl Widget<LapceTabData> for Plugin {
                                 - text_padding * 2.0
                                 - padding;
                             let y = y + self.line_height * 2.0;
-                            let color = Color::rgb8(80, 161, 79);
+                            let color = if status == PluginStatus::Failed {
+                                data.config
+                                    .get_color_unchecked(LapceTheme::LAPCE_ERROR)
+                                    .clone()
+                            } else {
+                                Color::rgb8(80, 161, 79)
+                            };
                             ctx.fill(
                                 Size::new(
                                     text_size.width + text_padding * 2.0,
@@ -525,7 +643,13 @@ impl Widget<LapceTabData> for Plugin {
                                 - text_padding * 2.0
                                 - padding;
                             let y = y + self.line_height * 2.0;
-                            let color = Color::rgb8(80, 161, 79);
+                            let color = if status == PluginStatus::InstallFailed {
+                                data.config
+                                    .get_color_unchecked(LapceTheme::LAPCE_ERROR)
+                                    .clone()
+                            } else {
+                                Color::rgb8(80, 161, 79)
+                            };
                             ctx.fill(
                                 Size::new(
                                     text_size.width + text_padding * 2.0,
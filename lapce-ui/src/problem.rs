@@ -243,7 +243,7 @@ impl Widget<LapceTabData> for ProblemContent {
             }
 
             let padding = (line_height - 14.0) / 2.0;
-            let (svg, svg_color) = file_svg(path);
+            let (svg, svg_color) = file_svg(path, &data.config);
             let rect = Size::new(line_height, line_height)
                 .to_rect()
                 .with_origin(Point::new(0.0, line_height * i as f64))
@@ -10,7 +10,6 @@ use lapce_data::{
     command::{LapceUICommand, LAPCE_UI_COMMAND},
     config::LapceTheme,
     data::LapceTabData,
-    editor::{EditorLocation, LineCol},
     panel::PanelKind,
 };
 
@@ -62,6 +61,16 @@ struct SearchContent {
     line_height: f64,
 }
 
+/// The number of rows before the matches, which is the row showing the folder
+/// that the search is limited to, if any
+fn scope_rows(data: &LapceTabData) -> usize {
+    if data.search.scope.is_some() {
+        1
+    } else {
+        0
+    }
+}
+
 impl SearchContent {
     pub fn new() -> Self {
         Self {
@@ -77,8 +86,16 @@ impl SearchContent {
         data: &LapceTabData,
     ) {
         let n = (mouse_event.pos.y / self.line_height).floor() as usize;
+        if n == 0 && data.search.scope.is_some() {
+            ctx.submit_command(Command::new(
+                LAPCE_UI_COMMAND,
+                LapceUICommand::ClearSearchScope,
+                Target::Widget(data.id),
+            ));
+            return;
+        }
 
-        let mut i = 0;
+        let mut i = scope_rows(data);
         for (path, matches) in data.search.matches.iter() {
             if matches.len() + 1 + i < n {
                 i += matches.len() + 1;
@@ -90,18 +107,11 @@ impl SearchContent {
                 if i == n {
                     ctx.submit_command(Command::new(
                         LAPCE_UI_COMMAND,
-                        LapceUICommand::JumpToLineColLocation(
-                            None,
-                            EditorLocation {
-                                path: path.clone(),
-                                position: Some(LineCol {
-                                    line: line_number.saturating_sub(1),
-                                    column: *start,
-                                }),
-                                scroll_offset: None,
-                                history: None,
-                            },
-                        ),
+                        LapceUICommand::OpenFileAt {
+                            path: path.clone(),
+                            line: *line_number,
+                            column: *start + 1,
+                        },
                         Target::Widget(data.id),
                     ));
                     return;
@@ -155,7 +165,9 @@ impl Widget<LapceTabData> for SearchContent {
         data: &LapceTabData,
         _env: &Env,
     ) {
-        if !old_data.search.matches.same(&data.search.matches) {
+        if !old_data.search.matches.same(&data.search.matches)
+            || old_data.search.scope != data.search.scope
+        {
             ctx.request_layout();
         }
     }
@@ -172,7 +184,8 @@ impl Widget<LapceTabData> for SearchContent {
             .matches
             .iter()
             .map(|(_, matches)| matches.len() + 1)
-            .sum::<usize>();
+            .sum::<usize>()
+            + scope_rows(data);
         let height = self.line_height * n as f64;
         Size::new(bc.max().width, height)
     }
@@ -196,14 +209,46 @@ impl Widget<LapceTabData> for SearchContent {
 
         let focus_color = data.config.get_color_unchecked(LapceTheme::EDITOR_FOCUS);
         let padding = (self.line_height - 14.0) / 2.0;
-        let mut i = 0;
+        if let Some(scope) = data.search.scope.as_ref() {
+            let scope = data
+                .workspace
+                .path
+                .as_ref()
+                .and_then(|workspace| scope.strip_prefix(workspace).ok())
+                .unwrap_or(scope);
+            let text_layout = ctx
+                .text()
+                .new_text_layout(format!(
+                    "Searching in {} (Click to Search Everywhere)",
+                    scope.to_string_lossy()
+                ))
+                .font(
+                    data.config.ui.font_family(),
+                    data.config.ui.font_size() as f64,
+                )
+                .text_color(
+                    data.config
+                        .get_color_unchecked(LapceTheme::EDITOR_DIM)
+                        .clone(),
+                )
+                .build()
+                .unwrap();
+            ctx.draw_text(
+                &text_layout,
+                Point::new(
+                    padding,
+                    (self.line_height - text_layout.size().height) / 2.0,
+                ),
+            );
+        }
+        let mut i = scope_rows(data);
         for (path, matches) in data.search.matches.iter() {
             if matches.len() + 1 + i < min {
                 i += matches.len() + 1;
                 continue;
             }
 
-            let (svg, svg_color) = file_svg(path);
+            let (svg, svg_color) = file_svg(path, &data.config);
             let rect = Size::new(self.line_height, self.line_height)
                 .to_rect()
                 .with_origin(Point::new(0.0, self.line_height * i as f64))
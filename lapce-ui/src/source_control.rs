@@ -13,8 +13,9 @@ use lapce_data::{
         LAPCE_COMMAND, LAPCE_UI_COMMAND,
     },
     config::LapceTheme,
-    data::{FocusArea, LapceTabData},
+    data::{FocusArea, LapceData, LapceTabData},
     panel::PanelKind,
+    source_control::SourceControlData,
 };
 use lapce_rpc::source_control::FileDiff;
 
@@ -84,6 +85,54 @@ pub fn new_source_control_panel(data: &LapceTabData) -> LapcePanel {
     )
 }
 
+/// Show the menu to stage or unstage the changes of the file, depending on
+/// where it has them
+fn show_stage_menu(
+    ctx: &mut EventCtx,
+    source_control: &SourceControlData,
+    diff: &FileDiff,
+    pos: Point,
+    tab_id: WidgetId,
+) {
+    // Both sides of a rename need to be staged for git to see it as one
+    let paths = match diff {
+        FileDiff::Renamed(added, deleted) => vec![added.clone(), deleted.clone()],
+        _ => vec![diff.path().clone()],
+    };
+    let has_changes = |staged: bool| {
+        source_control
+            .changed_files
+            .iter()
+            .any(|file| file.staged == staged && &file.diff == diff)
+    };
+
+    let (unstaged, staged) = (has_changes(false), has_changes(true));
+    if !unstaged && !staged {
+        return;
+    }
+
+    let mut menu = druid::Menu::<LapceData>::new("Source Control");
+    if unstaged {
+        let item = druid::MenuItem::new("Stage Changes").command(Command::new(
+            LAPCE_UI_COMMAND,
+            LapceUICommand::GitStage {
+                paths: paths.clone(),
+            },
+            Target::Widget(tab_id),
+        ));
+        menu = menu.entry(item);
+    }
+    if staged {
+        let item = druid::MenuItem::new("Unstage Changes").command(Command::new(
+            LAPCE_UI_COMMAND,
+            LapceUICommand::GitUnstage { paths },
+            Target::Widget(tab_id),
+        ));
+        menu = menu.entry(item);
+    }
+    ctx.show_context_menu::<LapceData>(menu, ctx.to_window(pos));
+}
+
 struct SourceControlFileList {
     widget_id: WidgetId,
     mouse_down: Option<usize>,
@@ -153,7 +202,15 @@ impl Widget<LapceTabData> for SourceControlFileList {
                     let line = (y / self.line_height).floor() as usize;
                     if line < source_control.file_diffs.len() {
                         source_control.file_list_index = line;
-                        if mouse_event.pos.x < self.line_height {
+                        if mouse_event.button.is_right() {
+                            show_stage_menu(
+                                ctx,
+                                source_control,
+                                &source_control.file_diffs[line].0,
+                                mouse_event.pos,
+                                data.id,
+                            );
+                        } else if mouse_event.pos.x < self.line_height {
                             self.mouse_down = Some(line);
                         } else {
                             ctx.submit_command(Command::new(
@@ -284,7 +341,7 @@ impl Widget<LapceTabData> for SourceControlFileList {
                     ctx.stroke(path, &Color::rgb8(0, 0, 0), 2.0);
                 }
             }
-            let (svg, svg_color) = file_svg(&path);
+            let (svg, svg_color) = file_svg(&path, &data.config);
             let width = 13.0;
             let height = 13.0;
             let rect = Size::new(width, height).to_rect().with_origin(Point::new(
@@ -6,11 +6,16 @@ use druid::{
 };
 use lapce_core::mode::Mode;
 use lapce_data::{
-    command::{CommandKind, LapceCommand, LapceWorkbenchCommand, LAPCE_COMMAND},
+    command::{
+        CommandKind, LapceCommand, LapceUICommand, LapceWorkbenchCommand,
+        LAPCE_COMMAND, LAPCE_UI_COMMAND,
+    },
     config::{Config, LapceTheme},
     data::{FocusArea, LapceTabData},
+    document::BufferContent,
     panel::{PanelContainerPosition, PanelKind},
 };
+use lapce_rpc::core::LanguageServerState;
 
 use crate::{svg::get_svg, tab::LapceIcon};
 
@@ -273,7 +278,11 @@ impl Widget<LapceTabData> for LapceStatus {
             return;
         }
 
-        if !old_data.progresses.ptr_eq(&data.progresses) {
+        if !old_data.progresses.ptr_eq(&data.progresses)
+            || !old_data.language_servers.ptr_eq(&data.language_servers)
+            || old_data.file_explorer.reading_dirs.len()
+                != data.file_explorer.reading_dirs.len()
+        {
             ctx.request_paint();
         }
     }
@@ -430,12 +439,47 @@ impl Widget<LapceTabData> for LapceStatus {
             ),
         ));
 
+        // Hint at why the explorer is slow to show directories
+        let reading = data.file_explorer.reading_dirs.len();
+        if reading > 0 {
+            let text = if reading == 1 {
+                "Reading 1 directory".to_string()
+            } else {
+                format!("Reading {reading} directories")
+            };
+            let text_layout = ctx
+                .text()
+                .new_text_layout(text)
+                .font(
+                    data.config.ui.font_family(),
+                    data.config.ui.font_size() as f64,
+                )
+                .text_color(
+                    data.config
+                        .get_color_unchecked(LapceTheme::EDITOR_DIM)
+                        .clone(),
+                )
+                .build()
+                .unwrap();
+            ctx.draw_text(
+                &text_layout,
+                Point::new(
+                    left + 10.0,
+                    (size.height - text_layout.size().height) / 2.0,
+                ),
+            );
+            left += 10.0 + text_layout.size().width;
+        }
+
         for progress in data.progresses.iter() {
             let mut text = progress.title.clone();
             if let Some(message) = progress.message.as_ref() {
                 text += ": ";
                 text += message;
             }
+            if progress.cancellable {
+                text += " (Click to Cancel)";
+            }
             let text_layout = ctx
                 .text()
                 .new_text_layout(text)
@@ -457,6 +501,68 @@ impl Widget<LapceTabData> for LapceStatus {
                     (size.height - text_layout.size().height) / 2.0,
                 ),
             );
+            if progress.cancellable {
+                self.clickable_items.push((
+                    Size::new(text_layout.size().width, size.height)
+                        .to_rect()
+                        .with_origin(Point::new(left + 10.0, 0.0)),
+                    Command::new(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::CancelWorkDoneProgress(
+                            progress.token.clone(),
+                        ),
+                        Target::Widget(data.id),
+                    ),
+                ));
+            }
+            left += 10.0 + text_layout.size().width;
+        }
+
+        for (language, state) in data.language_servers.iter() {
+            let (text, color) = match state {
+                LanguageServerState::Starting => {
+                    (format!("{language}: starting"), LapceTheme::EDITOR_DIM)
+                }
+                LanguageServerState::Indexing => {
+                    (format!("{language}: indexing"), LapceTheme::EDITOR_DIM)
+                }
+                LanguageServerState::Ready => continue,
+                LanguageServerState::Crashed => (
+                    format!("{language}: crashed (Click to Restart)"),
+                    LapceTheme::LAPCE_WARN,
+                ),
+            };
+            let text_layout = ctx
+                .text()
+                .new_text_layout(text)
+                .font(
+                    data.config.ui.font_family(),
+                    data.config.ui.font_size() as f64,
+                )
+                .text_color(data.config.get_color_unchecked(color).clone())
+                .build()
+                .unwrap();
+            ctx.draw_text(
+                &text_layout,
+                Point::new(
+                    left + 10.0,
+                    (size.height - text_layout.size().height) / 2.0,
+                ),
+            );
+            if *state == LanguageServerState::Crashed {
+                self.clickable_items.push((
+                    Size::new(text_layout.size().width, size.height)
+                        .to_rect()
+                        .with_origin(Point::new(left + 10.0, 0.0)),
+                    Command::new(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::RestartLanguageServer {
+                            language: language.clone(),
+                        },
+                        Target::Widget(data.id),
+                    ),
+                ));
+            }
             left += 10.0 + text_layout.size().width;
         }
 
@@ -531,6 +637,81 @@ impl Widget<LapceTabData> for LapceStatus {
                     Target::Widget(data.id),
                 ),
             ));
+
+            let doc = data.main_split.content_doc(&editor.content);
+            if let BufferContent::File(_) = doc.content() {
+                let x1 = right;
+                let (new_right, _, (point, text_layout)) = self
+                    .paint_icon_with_label_from_right(
+                        right - 5.0,
+                        size.height,
+                        "",
+                        doc.line_ending().as_str().to_string(),
+                        ctx,
+                        &data.config,
+                    );
+                right = new_right;
+                let x0 = right - 5.0;
+                let rect = Rect::ZERO
+                    .with_origin(Point::new(x0, 0.0))
+                    .with_size(Size::new(x1 - x0, size.height));
+                if rect.contains(self.mouse_pos) {
+                    ctx.fill(
+                        rect,
+                        data.config.get_color_unchecked(LapceTheme::PANEL_CURRENT),
+                    );
+                }
+                ctx.draw_text(&text_layout, point);
+                self.clickable_items.push((
+                    rect,
+                    Command::new(
+                        LAPCE_COMMAND,
+                        LapceCommand {
+                            kind: CommandKind::Workbench(
+                                LapceWorkbenchCommand::ToggleLineEnding,
+                            ),
+                            data: None,
+                        },
+                        Target::Widget(data.id),
+                    ),
+                ));
+
+                let x1 = right;
+                let (new_right, _, (point, text_layout)) = self
+                    .paint_icon_with_label_from_right(
+                        right - 5.0,
+                        size.height,
+                        "",
+                        doc.encoding().to_string(),
+                        ctx,
+                        &data.config,
+                    );
+                right = new_right;
+                let x0 = right - 5.0;
+                let rect = Rect::ZERO
+                    .with_origin(Point::new(x0, 0.0))
+                    .with_size(Size::new(x1 - x0, size.height));
+                if rect.contains(self.mouse_pos) {
+                    ctx.fill(
+                        rect,
+                        data.config.get_color_unchecked(LapceTheme::PANEL_CURRENT),
+                    );
+                }
+                ctx.draw_text(&text_layout, point);
+                self.clickable_items.push((
+                    rect,
+                    Command::new(
+                        LAPCE_COMMAND,
+                        LapceCommand {
+                            kind: CommandKind::Workbench(
+                                LapceWorkbenchCommand::ReopenWithEncoding,
+                            ),
+                            data: None,
+                        },
+                        Target::Widget(data.id),
+                    ),
+                ));
+            }
         }
     }
 }
@@ -1,16 +1,30 @@
-use std::{collections::HashMap, ffi::OsStr, path::Path, str::FromStr};
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use druid::{piet::Svg, Color};
 use include_dir::{include_dir, Dir};
 use lazy_static::lazy_static;
 use lsp_types::{CompletionItemKind, SymbolKind};
+use parking_lot::Mutex;
+use serde_json::Value;
 
 use lapce_data::config::{Config, LOGO};
 
 const ICONS_DIR: Dir = include_dir!("../icons");
 
+/// The file in an icon theme directory that maps file types to its icons
+const ICON_THEME_MAPPING: &str = "icon-theme.json";
+
 lazy_static! {
     static ref SVG_STORE: SvgStore = SvgStore::new();
+    /// The icon theme directory that was last asked for, along with the theme
+    /// if it could be loaded
+    static ref ICON_THEME: Mutex<Option<(PathBuf, Option<IconTheme>)>> =
+        Mutex::new(None);
 }
 
 struct SvgStore {
@@ -40,6 +54,128 @@ impl SvgStore {
     }
 }
 
+/// An external set of file icons, which is a directory of SVGs along with an
+/// `icon-theme.json` that maps extensions and file names to them, like:
+/// `{ "extensions": { "rs": "rust.svg" }, "filenames": { "Cargo.toml": "cargo.svg" } }`
+///
+/// Folders get their icons from `"folder"` and `"folder_opened"`, and from
+/// `"folder_names"` and `"folder_names_opened"` for folders with the name.
+struct IconTheme {
+    dir: PathBuf,
+    extensions: HashMap<String, String>,
+    filenames: HashMap<String, String>,
+    folder: Option<String>,
+    folder_opened: Option<String>,
+    folder_names: HashMap<String, String>,
+    folder_names_opened: HashMap<String, String>,
+    /// The SVGs that have been read so far, by their file name in the theme
+    svgs: HashMap<String, Option<Svg>>,
+}
+
+impl IconTheme {
+    fn load(dir: &Path) -> Option<Self> {
+        let mapping = std::fs::read_to_string(dir.join(ICON_THEME_MAPPING)).ok()?;
+        let mapping: Value = serde_json::from_str(&mapping).ok()?;
+        let read_map = |key: &str, lowercase: bool| -> HashMap<String, String> {
+            mapping
+                .get(key)
+                .and_then(Value::as_object)
+                .map(|map| {
+                    map.iter()
+                        .filter_map(|(from, icon)| {
+                            let from = if lowercase {
+                                from.to_lowercase()
+                            } else {
+                                from.clone()
+                            };
+                            Some((from, icon.as_str()?.to_string()))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let read_icon = |key: &str| -> Option<String> {
+            Some(mapping.get(key)?.as_str()?.to_string())
+        };
+
+        Some(Self {
+            dir: dir.to_path_buf(),
+            extensions: read_map("extensions", true),
+            filenames: read_map("filenames", false),
+            folder: read_icon("folder"),
+            folder_opened: read_icon("folder_opened"),
+            folder_names: read_map("folder_names", false),
+            folder_names_opened: read_map("folder_names_opened", false),
+            svgs: HashMap::new(),
+        })
+    }
+
+    fn read_svg(&mut self, icon_name: String) -> Option<Svg> {
+        let dir = &self.dir;
+        self.svgs
+            .entry(icon_name)
+            .or_insert_with_key(|icon_name| {
+                std::fs::read_to_string(dir.join(icon_name))
+                    .ok()
+                    .and_then(|svg| Svg::from_str(&svg).ok())
+            })
+            .clone()
+    }
+
+    /// Get the icon for the folder, preferring a mapping of its exact name over
+    /// the theme's icon for all folders
+    fn folder_svg(&mut self, path: &Path, open: bool) -> Option<Svg> {
+        let (names, default) = if open {
+            (&self.folder_names_opened, &self.folder_opened)
+        } else {
+            (&self.folder_names, &self.folder)
+        };
+        let icon_name = path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .and_then(|folder_name| names.get(folder_name))
+            .or(default.as_ref())?
+            .clone();
+        self.read_svg(icon_name)
+    }
+
+    /// Get the icon for the file, preferring a mapping of its exact name over
+    /// one of its extension
+    fn file_svg(&mut self, path: &Path) -> Option<Svg> {
+        let icon_name = path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .and_then(|file_name| self.filenames.get(file_name))
+            .or_else(|| {
+                let extension = path.extension().and_then(OsStr::to_str)?;
+                self.extensions.get(&extension.to_lowercase())
+            })?
+            .clone();
+        self.read_svg(icon_name)
+    }
+}
+
+/// Run `f` on the icon theme set in the config, loading it first if the
+/// setting changed
+fn with_icon_theme(
+    config: &Config,
+    f: impl FnOnce(&mut IconTheme) -> Option<Svg>,
+) -> Option<Svg> {
+    let dir = config.ui.icon_theme()?;
+    let mut icon_theme = ICON_THEME.lock();
+    if icon_theme.as_ref().map(|(loaded, _)| loaded.as_path()) != Some(dir) {
+        *icon_theme = Some((dir.to_path_buf(), IconTheme::load(dir)));
+    }
+    f(icon_theme.as_mut()?.1.as_mut()?)
+}
+
+/// Get the file's icon from the icon theme set in the config, if there is one
+/// and it has an icon for that type of file
+fn icon_theme_file_svg(path: &Path, config: &Config) -> Option<Svg> {
+    with_icon_theme(config, |icon_theme| icon_theme.file_svg(path))
+}
+
 pub fn logo_svg() -> Svg {
     get_svg("lapce_logo").unwrap()
 }
@@ -48,9 +184,13 @@ pub fn get_svg(name: &'static str) -> Option<Svg> {
     SVG_STORE.get_svg(name)
 }
 
-pub fn file_svg(path: &Path) -> (Svg, Option<&Color>) {
+pub fn file_svg(path: &Path, config: &Config) -> (Svg, Option<&'static Color>) {
+    if let Some(svg) = icon_theme_file_svg(path, config) {
+        return (svg, None);
+    }
+
     let icon_name: Option<&str>;
-    let icon_color: Option<&Color>;
+    let icon_color: Option<&'static Color>;
     (icon_name, icon_color) = match path.extension().and_then(OsStr::to_str) {
         Some(extension) => {
             const TYPES: &[(&[&str], &str, Option<&Color>)] = &[
@@ -118,6 +258,60 @@ pub fn file_svg(path: &Path) -> (Svg, Option<&Color>) {
     }
 }
 
+/// Get the folder's icon, from the icon theme set in the config if it has one,
+/// then from the built-in icons of well-known folders, then the default one
+pub fn folder_svg(path: &Path, open: bool, config: &Config) -> Svg {
+    if let Some(svg) =
+        with_icon_theme(config, |icon_theme| icon_theme.folder_svg(path, open))
+    {
+        return svg;
+    }
+
+    const FOLDERS: &[(&[&str], &str, &str)] = &[
+        (
+            &["src", "source"],
+            "folder_type_src.svg",
+            "folder_type_src_opened.svg",
+        ),
+        (
+            &[".git"],
+            "folder_type_git.svg",
+            "folder_type_git_opened.svg",
+        ),
+        (
+            &["node_modules"],
+            "folder_type_node.svg",
+            "folder_type_node_opened.svg",
+        ),
+        (
+            &["test", "tests", "__tests__"],
+            "folder_type_test.svg",
+            "folder_type_test_opened.svg",
+        ),
+    ];
+
+    let folder_name = path.file_name().and_then(OsStr::to_str);
+    let icon_name = FOLDERS
+        .iter()
+        .find(|(names, _, _)| {
+            folder_name
+                .map(|name| names.contains(&name))
+                .unwrap_or(false)
+        })
+        .map(|(_, closed, opened)| if open { *opened } else { *closed });
+
+    icon_name
+        .and_then(get_svg)
+        .or_else(|| {
+            get_svg(if open {
+                "default_folder_opened.svg"
+            } else {
+                "default_folder.svg"
+            })
+        })
+        .unwrap()
+}
+
 pub fn symbol_svg(kind: &SymbolKind) -> Option<Svg> {
     let kind_str = match *kind {
         SymbolKind::ARRAY => "symbol-array.svg",
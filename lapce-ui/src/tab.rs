@@ -1,11 +1,11 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, path::Path, sync::Arc};
 
 use druid::{
     kurbo::Line,
     piet::{PietTextLayout, Text, TextLayout, TextLayoutBuilder},
-    BoxConstraints, Command, Data, Env, Event, EventCtx, InternalLifeCycle,
-    LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx, Point, Rect, RenderContext, Size,
-    Target, UpdateCtx, Widget, WidgetExt, WidgetId, WidgetPod,
+    Application, BoxConstraints, Command, Data, Env, Event, EventCtx,
+    InternalLifeCycle, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx, Point, Rect,
+    RenderContext, Size, Target, UpdateCtx, Widget, WidgetExt, WidgetId, WidgetPod,
 };
 use itertools::Itertools;
 use lapce_core::{
@@ -15,9 +15,11 @@ use lapce_core::{
     selection::Selection,
 };
 use lapce_data::{
+    alert::AlertContentData,
     command::{
         CommandKind, LapceCommand, LapceUICommand, LapceWorkbenchCommand,
-        PluginLoadingStatus, LAPCE_COMMAND, LAPCE_OPEN_FILE, LAPCE_OPEN_FOLDER,
+        PluginLoadingStatus, LAPCE_ADD_FOLDER_TO_WORKSPACE, LAPCE_COMMAND,
+        LAPCE_OPEN_FILE, LAPCE_OPEN_FOLDER, LAPCE_OPEN_FOLDER_IN_NEW_WINDOW,
         LAPCE_SAVE_FILE_AS, LAPCE_UI_COMMAND,
     },
     completion::CompletionStatus,
@@ -27,7 +29,11 @@ use lapce_data::{
         LapceWorkspace, LapceWorkspaceType, WorkProgress,
     },
     document::{BufferContent, LocalBufferKind},
-    editor::EditorLocation,
+    editor::{apply_workspace_edit, EditorLocation, LineCol},
+    explorer::{
+        new_progress_token, pasted_path, path_names_summary, render_template,
+        FileExplorerData, CLIPBOARD_PNG_FORMAT,
+    },
     hover::HoverStatus,
     keypress::{DefaultKeyPressHandler, KeyPressData},
     menu::MenuKind,
@@ -36,21 +42,44 @@ use lapce_data::{
         PanelContainerPosition, PanelKind, PanelPosition, PanelResizePosition,
         PanelStyle,
     },
-    proxy::path_from_url,
+    plugin::PluginInstallStatus,
+    proxy::{path_from_url, TermEvent},
+    source_control::sort_changed_files,
 };
-use lapce_rpc::plugin::PluginDescription;
+use lapce_rpc::file::FileErrorKind;
+use lapce_rpc::plugin::{PluginDescription, PluginState};
+use lapce_rpc::proxy::{TrashLocation, TrashPathResponse};
 use lsp_types::DiagnosticSeverity;
+use parking_lot::Mutex;
 use xi_rope::Rope;
 
 use crate::{
-    alert::AlertBox, completion::CompletionContainer, explorer::FileExplorer,
-    hover::HoverContainer, panel::PanelContainer, picker::FilePicker,
-    plugin::Plugin, problem::new_problem_panel, search::new_search_panel,
-    settings::LapceSettingsPanel, source_control::new_source_control_panel,
-    split::split_data_widget, status::LapceStatus, svg::get_svg,
-    terminal::TerminalPanel, title::Title,
+    alert::AlertBox,
+    completion::CompletionContainer,
+    explorer::{expand_all_levels_alert, FileExplorer},
+    hover::HoverContainer,
+    panel::PanelContainer,
+    picker::FilePicker,
+    plugin::Plugin,
+    problem::new_problem_panel,
+    search::new_search_panel,
+    settings::LapceSettingsPanel,
+    source_control::new_source_control_panel,
+    split::split_data_widget,
+    status::LapceStatus,
+    svg::get_svg,
+    terminal::TerminalPanel,
+    title::Title,
 };
 
+/// How many of the paths that failed to move to the trash are named when
+/// reporting it
+const MAX_FAILED_NAMES_SHOWN: usize = 5;
+
+/// Files bigger than this, in bytes, are too big to copy their contents to the
+/// clipboard
+const MAX_COPY_CONTENTS_SIZE: u64 = 1024 * 1024;
+
 pub struct LapceIcon {
     pub rect: Rect,
     pub command: Command,
@@ -579,6 +608,48 @@ impl LapceTab {
                         ),
                     );
                 }
+                DragContent::ExplorerPath(path) => {
+                    let name = path
+                        .file_name()
+                        .map(|x| x.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let text_layout = ctx
+                        .text()
+                        .new_text_layout(name)
+                        .font(
+                            data.config.ui.font_family(),
+                            data.config.ui.font_size() as f64,
+                        )
+                        .text_color(
+                            data.config
+                                .get_color_unchecked(LapceTheme::EDITOR_FOREGROUND)
+                                .clone(),
+                        )
+                        .build()
+                        .unwrap();
+                    let text_size = text_layout.size();
+                    let rect =
+                        Size::new(text_size.width + 20.0, text_size.height + 10.0)
+                            .to_rect()
+                            .with_origin(self.mouse_pos + (10.0, 10.0));
+                    ctx.stroke(
+                        rect.inflate(0.5, 0.5),
+                        data.config.get_color_unchecked(LapceTheme::LAPCE_BORDER),
+                        1.0,
+                    );
+                    ctx.fill(
+                        rect,
+                        &data
+                            .config
+                            .get_color_unchecked(LapceTheme::EDITOR_BACKGROUND)
+                            .clone()
+                            .with_alpha(0.8),
+                    );
+                    ctx.draw_text(
+                        &text_layout,
+                        Point::new(rect.x0 + 10.0, rect.y0 + 5.0),
+                    );
+                }
             }
         }
     }
@@ -682,6 +753,31 @@ impl LapceTab {
                     Target::Window(data.window_id),
                 ));
             }
+            Event::Command(cmd) if cmd.is(LAPCE_OPEN_FOLDER_IN_NEW_WINDOW) => {
+                ctx.set_handled();
+                let file = cmd.get_unchecked(LAPCE_OPEN_FOLDER_IN_NEW_WINDOW);
+                let workspace = LapceWorkspace {
+                    kind: LapceWorkspaceType::Local,
+                    path: Some(file.path.clone()),
+                    last_open: std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs(),
+                };
+                ctx.submit_command(Command::new(
+                    LAPCE_UI_COMMAND,
+                    LapceUICommand::OpenInNewWindow {
+                        window_id: data.window_id,
+                        workspace,
+                    },
+                    Target::Global,
+                ));
+            }
+            Event::Command(cmd) if cmd.is(LAPCE_ADD_FOLDER_TO_WORKSPACE) => {
+                ctx.set_handled();
+                let file = cmd.get_unchecked(LAPCE_ADD_FOLDER_TO_WORKSPACE);
+                Arc::make_mut(&mut data.file_explorer).add_root(file.path.clone());
+            }
             Event::Command(cmd) if cmd.is(LAPCE_OPEN_FILE) => {
                 ctx.set_handled();
                 let file = cmd.get_unchecked(LAPCE_OPEN_FILE);
@@ -780,8 +876,11 @@ impl LapceTab {
                     LapceUICommand::UpdateSearch(pattern) => {
                         if pattern.is_empty() {
                             Arc::make_mut(&mut data.find).unset();
-                            Arc::make_mut(&mut data.search).matches =
-                                Arc::new(HashMap::new());
+                            let search = Arc::make_mut(&mut data.search);
+                            data.proxy.cancel_search(search.query_id);
+                            search.matches = Arc::new(HashMap::new());
+                            Arc::make_mut(&mut data.file_explorer)
+                                .set_search_matches(HashMap::new());
                         } else {
                             let find = Arc::make_mut(&mut data.find);
                             find.set_find(pattern, false, false, false);
@@ -801,25 +900,99 @@ impl LapceTab {
                                     ));
                                 }
                             }
-                            let pattern = pattern.to_string();
-                            let event_sink = ctx.get_external_handle();
-                            let tab_id = data.id;
-                            data.proxy.global_search(
-                                pattern.clone(),
-                                Box::new(move |result| {
-                                    if let Ok(matches) = result {
-                                        let _ = event_sink.submit_command(
-                                            LAPCE_UI_COMMAND,
-                                            LapceUICommand::GlobalSearchResult(
-                                                pattern,
-                                                Arc::new(matches),
-                                            ),
-                                            Target::Widget(tab_id),
-                                        );
-                                    }
-                                }),
-                            )
+                            let search = Arc::make_mut(&mut data.search);
+                            if let Some(scope) = search.scope.clone() {
+                                // The matches of the folder are streamed in as
+                                // they're found, replacing the previous search
+                                data.proxy.cancel_search(search.query_id);
+                                search.query_id += 1;
+                                search.matches = Arc::new(HashMap::new());
+                                Arc::make_mut(&mut data.file_explorer)
+                                    .set_search_matches(HashMap::new());
+                                data.proxy.start_search(
+                                    search.query_id,
+                                    pattern,
+                                    &scope,
+                                );
+                            } else {
+                                let pattern = pattern.to_string();
+                                let event_sink = ctx.get_external_handle();
+                                let tab_id = data.id;
+                                data.proxy.global_search(
+                                    pattern.clone(),
+                                    Box::new(move |result| {
+                                        if let Ok(matches) = result {
+                                            let _ = event_sink.submit_command(
+                                                LAPCE_UI_COMMAND,
+                                                LapceUICommand::GlobalSearchResult(
+                                                    pattern,
+                                                    Arc::new(matches),
+                                                ),
+                                                Target::Widget(tab_id),
+                                            );
+                                        }
+                                    }),
+                                )
+                            }
+                        }
+                    }
+                    LapceUICommand::FindInFolder { path } => {
+                        Arc::make_mut(&mut data.search).scope = Some(path.clone());
+                        data.show_panel(ctx, PanelKind::Search);
+                        let pattern = data
+                            .main_split
+                            .local_docs
+                            .get(&LocalBufferKind::Search)
+                            .unwrap()
+                            .buffer()
+                            .text()
+                            .to_string();
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::UpdateSearch(pattern),
+                            Target::Widget(data.id),
+                        ));
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ClearSearchScope => {
+                        let search = Arc::make_mut(&mut data.search);
+                        data.proxy.cancel_search(search.query_id);
+                        search.scope = None;
+                        let pattern = data
+                            .main_split
+                            .local_docs
+                            .get(&LocalBufferKind::Search)
+                            .unwrap()
+                            .buffer()
+                            .text()
+                            .to_string();
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::UpdateSearch(pattern),
+                            Target::Widget(data.id),
+                        ));
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::SearchResult {
+                        query_id,
+                        path,
+                        line,
+                        column,
+                        end_column,
+                        text,
+                    } => {
+                        if *query_id == data.search.query_id
+                            && data.search.scope.is_some()
+                        {
+                            let search = Arc::make_mut(&mut data.search);
+                            Arc::make_mut(&mut search.matches)
+                                .entry(path.clone())
+                                .or_default()
+                                .push((*line, (*column, *end_column), text.clone()));
+                            Arc::make_mut(&mut data.file_explorer)
+                                .add_search_match(path);
                         }
+                        ctx.set_handled();
                     }
                     LapceUICommand::GlobalSearchResult(pattern, matches) => {
                         let doc = data
@@ -827,9 +1000,20 @@ impl LapceTab {
                             .local_docs
                             .get(&LocalBufferKind::Search)
                             .unwrap();
-                        if &doc.buffer().text().slice_to_cow(..) == pattern {
+                        // A late result of the whole workspace is dropped once
+                        // the search is limited to a folder
+                        if &doc.buffer().text().slice_to_cow(..) == pattern
+                            && data.search.scope.is_none()
+                        {
                             Arc::make_mut(&mut data.search).matches =
                                 matches.clone();
+                            Arc::make_mut(&mut data.file_explorer)
+                                .set_search_matches(
+                                    matches
+                                        .iter()
+                                        .map(|(path, m)| (path.clone(), m.len()))
+                                        .collect(),
+                                );
                         }
                     }
                     LapceUICommand::LoadBufferHead {
@@ -859,15 +1043,99 @@ impl LapceTab {
                         ctx.set_handled();
                     }
                     LapceUICommand::HomeDir(path) => {
+                        data.home_dir = Some(path.clone());
                         Arc::make_mut(&mut data.picker).init_home(path);
                         data.set_picker_pwd(path.clone());
                         ctx.set_handled();
                     }
-                    LapceUICommand::WorkspaceFileChange => {
-                        data.handle_workspace_file_change(ctx);
+                    LapceUICommand::WorkspaceFileChange { created } => {
+                        data.handle_workspace_file_change(ctx, created);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::WorkspaceRootChanged { old, new } => {
+                        let close = (
+                            "Close Folder".to_string(),
+                            data.id,
+                            LapceCommand {
+                                kind: CommandKind::Workbench(
+                                    LapceWorkbenchCommand::CloseFolder,
+                                ),
+                                data: None,
+                            },
+                        );
+                        let content = match new {
+                            Some(new) => AlertContentData {
+                                title: "The workspace folder was renamed"
+                                    .to_string(),
+                                msg: format!(
+                                    "{} is now {}.",
+                                    old.display(),
+                                    new.display()
+                                ),
+                                buttons: vec![
+                                    (
+                                        "Reopen at New Location".to_string(),
+                                        data.id,
+                                        LapceCommand {
+                                            kind: CommandKind::Workbench(
+                                                LapceWorkbenchCommand::ReopenWorkspaceAt,
+                                            ),
+                                            data: serde_json::to_value(new).ok(),
+                                        },
+                                    ),
+                                    close,
+                                ],
+                            },
+                            None => AlertContentData {
+                                title: "The workspace folder is gone".to_string(),
+                                msg: format!(
+                                    "{} was moved or deleted.",
+                                    old.display()
+                                ),
+                                buttons: vec![close],
+                            },
+                        };
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::ShowAlert(content),
+                            Target::Widget(data.id),
+                        ));
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::TerminalCwdChanged { term_id, cwd } => {
+                        let terminal_panel = Arc::make_mut(&mut data.terminal);
+                        if let Some(terminal) =
+                            terminal_panel.terminals.get_mut(term_id)
+                        {
+                            Arc::make_mut(terminal).cwd = Some(cwd.clone());
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::TerminalExited { term_id, code } => {
+                        let close = match data.config.terminal.close_on_exit.as_str()
+                        {
+                            "never" => false,
+                            "on-success" => *code == Some(0),
+                            _ => true,
+                        };
+                        if close {
+                            ctx.submit_command(Command::new(
+                                LAPCE_UI_COMMAND,
+                                LapceUICommand::CloseTerminal(*term_id),
+                                Target::Widget(data.id),
+                            ));
+                        } else {
+                            let terminal_panel = Arc::make_mut(&mut data.terminal);
+                            if let Some(terminal) =
+                                terminal_panel.terminals.get_mut(term_id)
+                            {
+                                Arc::make_mut(terminal).exited = true;
+                            }
+                        }
                         ctx.set_handled();
                     }
                     LapceUICommand::CloseTerminal(id) => {
+                        let _ = data.term_tx.send((*id, TermEvent::CloseTerminal));
                         let terminal_panel = Arc::make_mut(&mut data.terminal);
                         if let Some(terminal) = terminal_panel.terminals.get_mut(id)
                         {
@@ -883,8 +1151,20 @@ impl LapceTab {
                         }
                         ctx.set_handled();
                     }
+                    LapceUICommand::RegisterExplorerMenuItem(item) => {
+                        Arc::make_mut(&mut data.file_explorer)
+                            .add_plugin_menu_item(item.to_owned());
+                        ctx.set_handled();
+                    }
                     LapceUICommand::UpdateInstalledPlugins(plugins) => {
                         data.installed_plugins = Arc::new(plugins.to_owned());
+                        // The installs that are done come with the installed
+                        // plugins, while the failed ones were reported before
+                        Arc::make_mut(&mut data.plugin_installs).retain(
+                            |_, status| {
+                                matches!(status, PluginInstallStatus::Failed(_))
+                            },
+                        );
                     }
                     LapceUICommand::UpdateInstalledPluginDescriptions(plugins) => {
                         data.installed_plugins_desc = Arc::new(plugins.to_owned());
@@ -934,6 +1214,44 @@ impl LapceTab {
                             }
                         }
                     }
+                    LapceUICommand::UpdatePluginInstall { plugin_id, status } => {
+                        let plugin_installs =
+                            Arc::make_mut(&mut data.plugin_installs);
+                        match status {
+                            Some(status) => {
+                                plugin_installs
+                                    .insert(plugin_id.clone(), status.clone());
+                            }
+                            None => {
+                                plugin_installs.remove(plugin_id);
+                            }
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::PluginActivationFailed { plugin_id, error } => {
+                        Arc::make_mut(&mut data.failed_plugins)
+                            .insert(plugin_id.to_owned(), error.to_owned());
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::UpdateLanguageServerStatus {
+                        language,
+                        state,
+                    } => {
+                        data.language_servers.insert(language.clone(), *state);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::RestartLanguageServer { language } => {
+                        data.proxy.restart_language_server(language);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::UpdatePluginState { plugin_id, state } => {
+                        // The error stays around for as long as it's failed
+                        if *state != PluginState::Failed {
+                            Arc::make_mut(&mut data.failed_plugins)
+                                .remove(plugin_id);
+                        }
+                        ctx.set_handled();
+                    }
                     LapceUICommand::DisablePlugin(plugin) => {
                         data.proxy.disable_plugin(plugin);
                     }
@@ -943,6 +1261,15 @@ impl LapceTab {
                     LapceUICommand::RemovePlugin(plugin) => {
                         data.proxy.remove_plugin(plugin);
                     }
+                    LapceUICommand::UpdateChangedFiles(files) => {
+                        let source_control = Arc::make_mut(&mut data.source_control);
+                        source_control.changed_files = files.to_owned();
+                        sort_changed_files(
+                            &mut source_control.changed_files,
+                            data.config.ui.source_control_order(),
+                        );
+                        ctx.set_handled();
+                    }
                     LapceUICommand::UpdateDiffInfo(diff) => {
                         let source_control = Arc::make_mut(&mut data.source_control);
                         source_control.branch = diff.head.to_string();
@@ -977,6 +1304,9 @@ impl LapceTab {
                                             title: begin.title.clone(),
                                             message: begin.message.clone(),
                                             percentage: begin.percentage,
+                                            cancellable: begin
+                                                .cancellable
+                                                .unwrap_or(false),
                                         });
                                     }
                                     lsp_types::WorkDoneProgress::Report(report) => {
@@ -984,6 +1314,11 @@ impl LapceTab {
                                             if p.token == params.token {
                                                 p.message = report.message.clone();
                                                 p.percentage = report.percentage;
+                                                if let Some(cancellable) =
+                                                    report.cancellable
+                                                {
+                                                    p.cancellable = cancellable;
+                                                }
                                             }
                                         }
                                     }
@@ -1008,6 +1343,16 @@ impl LapceTab {
                             }
                         }
                     }
+                    LapceUICommand::ApplyWorkspaceEdit(edit) => {
+                        let paths = apply_workspace_edit(
+                            ctx,
+                            &mut data.main_split,
+                            edit,
+                            &data.config,
+                        );
+                        data.file_explorer.refresh_parents(&paths);
+                        ctx.set_handled();
+                    }
                     LapceUICommand::PublishDiagnostics(diagnostics) => {
                         let path = path_from_url(&diagnostics.uri);
                         let diagnostics = diagnostics
@@ -1098,9 +1443,10 @@ impl LapceTab {
                         ctx.set_handled();
                     }
                     LapceUICommand::BufferSave(path, rev, exit_widget_id) => {
-                        let doc = data.main_split.open_docs.get_mut(path).unwrap();
+                        // The buffer was already marked as saved by `BufferSaved`,
+                        // which the proxy sends before it answers
+                        let doc = data.main_split.open_docs.get(path).unwrap();
                         if doc.rev() == *rev {
-                            Arc::make_mut(doc).buffer_mut().set_pristine();
                             if let Some(widget_id) = exit_widget_id {
                                 ctx.submit_command(Command::new(
                                     LAPCE_COMMAND,
@@ -1116,6 +1462,40 @@ impl LapceTab {
                         }
                         ctx.set_handled();
                     }
+                    LapceUICommand::BufferSaved {
+                        path,
+                        rev,
+                        success,
+                        error,
+                    } => {
+                        if *success {
+                            if let Some(doc) =
+                                data.main_split.open_docs.get_mut(path)
+                            {
+                                if doc.rev() == *rev {
+                                    Arc::make_mut(doc).buffer_mut().set_pristine();
+                                }
+                            }
+                        } else {
+                            let name = path
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or_default();
+                            ctx.submit_command(Command::new(
+                                LAPCE_UI_COMMAND,
+                                LapceUICommand::ShowAlert(AlertContentData {
+                                    title: format!("Failed to save {name}"),
+                                    msg: format!(
+                                        "{} Your changes have not been saved.",
+                                        error.as_deref().unwrap_or_default()
+                                    ),
+                                    buttons: Vec::new(),
+                                }),
+                                Target::Widget(data.id),
+                            ));
+                        }
+                        ctx.set_handled();
+                    }
                     LapceUICommand::LoadBufferAndGoToPosition {
                         path,
                         content,
@@ -1188,6 +1568,11 @@ impl LapceTab {
                         KeyPressData::update_file(keymap, keys);
                     }
                     LapceUICommand::OpenFile(path) => {
+                        let limit = data.config.ui.explorer_recent_files_limit();
+                        if limit > 0 {
+                            Arc::make_mut(&mut data.file_explorer)
+                                .add_recent(path, limit);
+                        }
                         data.main_split.jump_to_location(
                             ctx,
                             None,
@@ -1201,6 +1586,223 @@ impl LapceTab {
                         );
                         ctx.set_handled();
                     }
+                    LapceUICommand::OpenFileAt { path, line, column } => {
+                        data.main_split.jump_to_location(
+                            ctx,
+                            None,
+                            EditorLocation {
+                                path: path.clone(),
+                                position: Some(LineCol {
+                                    line: line.saturating_sub(1),
+                                    column: column.saturating_sub(1),
+                                }),
+                                scroll_offset: None,
+                                history: None,
+                            },
+                            &data.config,
+                        );
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ExplorerPastePath { text } => {
+                        ctx.set_handled();
+                        let workspace = match data.workspace.path.as_ref() {
+                            Some(workspace) => workspace,
+                            None => return,
+                        };
+                        let path = match pasted_path(
+                            text,
+                            workspace,
+                            data.home_dir.as_deref(),
+                        ) {
+                            Some(path) => path,
+                            None => return,
+                        };
+                        let alert = |title: String| AlertContentData {
+                            title,
+                            msg: "".to_string(),
+                            buttons: Vec::new(),
+                        };
+                        if !data
+                            .file_explorer
+                            .roots()
+                            .any(|root| path.starts_with(&root.path_buf))
+                        {
+                            ctx.submit_command(Command::new(
+                                LAPCE_UI_COMMAND,
+                                LapceUICommand::ShowAlert(alert(format!(
+                                    "{} is outside the workspace",
+                                    path.display()
+                                ))),
+                                Target::Widget(data.id),
+                            ));
+                            return;
+                        }
+
+                        // The listing of the parent tells whether the path exists
+                        let parent = match path.parent() {
+                            Some(parent) => parent.to_path_buf(),
+                            None => return,
+                        };
+                        let missing =
+                            alert(format!("{} doesn't exist", path.display()));
+                        let event_sink = ctx.get_external_handle();
+                        let tab_id = data.id;
+                        data.proxy.read_dir(
+                            &parent,
+                            false,
+                            None,
+                            0,
+                            move |result| {
+                                let exists = result.map_or(false, |resp| {
+                                    resp.items.contains_key(&path)
+                                });
+                                let cmd = if exists {
+                                    LapceUICommand::ExplorerRevealPath { path }
+                                } else {
+                                    LapceUICommand::ShowAlert(missing)
+                                };
+                                let _ = event_sink.submit_command(
+                                    LAPCE_UI_COMMAND,
+                                    cmd,
+                                    Target::Widget(tab_id),
+                                );
+                            },
+                        );
+                    }
+                    LapceUICommand::ExplorerRevealPath { path } => {
+                        let file_explorer = Arc::make_mut(&mut data.file_explorer);
+                        file_explorer.active_selected = Some(path.clone());
+                        file_explorer.reveal_path(path);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ExplorerSelectPath { path } => {
+                        Arc::make_mut(&mut data.file_explorer).select_path(path);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ExplorerTogglePath { path } => {
+                        Arc::make_mut(&mut data.file_explorer).toggle_path(path);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ExplorerRefresh { path } => {
+                        data.file_explorer.refresh_dir(path);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::CopyFileContents { path } => {
+                        let name = path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        let size = data
+                            .file_explorer
+                            .get_file_node(path)
+                            .map_or(0, |node| node.size);
+                        if size > MAX_COPY_CONTENTS_SIZE {
+                            ctx.submit_command(Command::new(
+                                LAPCE_UI_COMMAND,
+                                LapceUICommand::ShowAlert(AlertContentData {
+                                    title: format!(
+                                        "Couldn't copy the contents of {name}"
+                                    ),
+                                    msg: format!(
+                                        "The file is bigger than {} MB, open it \
+                                         to copy from it instead.",
+                                        MAX_COPY_CONTENTS_SIZE / (1024 * 1024)
+                                    ),
+                                    buttons: Vec::new(),
+                                }),
+                                Target::Widget(data.id),
+                            ));
+                        } else {
+                            let event_sink = ctx.get_external_handle();
+                            let tab_id = data.id;
+                            let copied = path.clone();
+                            data.proxy.read_file(path, move |result| {
+                                // Files that aren't UTF-8 fail to be read as text
+                                let cmd = match result {
+                                    Ok(resp) => LapceUICommand::CopyFileContentsRead {
+                                        path: copied,
+                                        content: resp.content,
+                                    },
+                                    Err(_) => {
+                                        LapceUICommand::ShowAlert(AlertContentData {
+                                            title: format!(
+                                                "Couldn't copy the contents of {name}"
+                                            ),
+                                            msg: "The file couldn't be read as text."
+                                                .to_string(),
+                                            buttons: Vec::new(),
+                                        })
+                                    }
+                                };
+                                let _ = event_sink.submit_command(
+                                    LAPCE_UI_COMMAND,
+                                    cmd,
+                                    Target::Widget(tab_id),
+                                );
+                            });
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::CopyFileContentsRead { path, content } => {
+                        // Binary files can still be valid UTF-8, but they have
+                        // NUL bytes where text doesn't
+                        if content.contains('\0') {
+                            let name = path
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or_default();
+                            ctx.submit_command(Command::new(
+                                LAPCE_UI_COMMAND,
+                                LapceUICommand::ShowAlert(AlertContentData {
+                                    title: format!(
+                                        "Couldn't copy the contents of {name}"
+                                    ),
+                                    msg: "The file is binary.".to_string(),
+                                    buttons: Vec::new(),
+                                }),
+                                Target::Widget(data.id),
+                            ));
+                        } else {
+                            Application::global().clipboard().put_string(content);
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::SelectForCompare { path } => {
+                        Arc::make_mut(&mut data.file_explorer).compare_selected =
+                            Some(path.clone());
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::CompareWithSelected { path } => {
+                        if let Some(selected) =
+                            data.file_explorer.compare_selected.as_ref()
+                        {
+                            let event_sink = ctx.get_external_handle();
+                            let tab_id = data.id;
+                            let path = path.clone();
+                            data.proxy.read_file(selected, move |result| {
+                                if let Ok(resp) = result {
+                                    let _ = event_sink.submit_command(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::OpenCompareDiff {
+                                            path,
+                                            content: Rope::from(resp.content),
+                                        },
+                                        Target::Widget(tab_id),
+                                    );
+                                }
+                            });
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::OpenCompareDiff { path, content } => {
+                        data.main_split.open_compare_diff(
+                            ctx,
+                            path,
+                            content.clone(),
+                            &data.config,
+                        );
+                        ctx.set_handled();
+                    }
                     LapceUICommand::GoToLocationNew(editor_view_id, location) => {
                         data.main_split.go_to_location(
                             ctx,
@@ -1360,13 +1962,14 @@ impl LapceTab {
                         ctx.set_handled();
                     }
                     LapceUICommand::OpenFileChanged { path, content } => {
-                        let doc = data.main_split.open_docs.get_mut(path).unwrap();
-                        let doc = Arc::make_mut(doc);
-                        doc.handle_file_changed(content.to_owned());
+                        data.handle_file_changed(ctx, path, content.to_owned());
                     }
                     LapceUICommand::ReloadBuffer { path, rev, content } => {
                         let doc = data.main_split.open_docs.get_mut(path).unwrap();
-                        if doc.rev() + 1 == *rev {
+                        if doc.rev() + 1 == *rev && !doc.buffer().is_pristine() {
+                            // Unsaved changes aren't replaced without asking
+                            data.handle_file_changed(ctx, path, content.to_owned());
+                        } else if doc.rev() + 1 == *rev {
                             let doc = Arc::make_mut(doc);
                             doc.reload(content.to_owned(), true);
 
@@ -1403,7 +2006,37 @@ impl LapceTab {
                         }
                         ctx.set_handled();
                     }
-                    LapceUICommand::UpdateSemanticStyles(_id, path, rev, styles) => {
+                    LapceUICommand::ReloadBufferDelta { path, rev, changes } => {
+                        // Edits for an outdated revision don't apply
+                        if let Some(doc) = data
+                            .main_split
+                            .open_docs
+                            .get_mut(path)
+                            .filter(|doc| doc.rev() == *rev)
+                        {
+                            if doc.buffer().is_pristine() {
+                                let doc = Arc::make_mut(doc);
+                                if let Some(delta) = doc.reload_edits(changes) {
+                                    for (_, editor) in
+                                        data.main_split.editors.iter_mut()
+                                    {
+                                        if &editor.content == doc.content() {
+                                            Arc::make_mut(editor)
+                                                .cursor
+                                                .apply_delta(&delta);
+                                        }
+                                    }
+                                }
+                            } else if let Some(content) =
+                                doc.content_with_edits(changes)
+                            {
+                                // Unsaved changes aren't replaced without asking
+                                data.handle_file_changed(ctx, path, content);
+                            }
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::UpdateSemanticStyles(_id, path, rev, styles) => {
                         let doc = data.main_split.open_docs.get_mut(path).unwrap();
                         if doc.rev() == *rev {
                             let doc = Arc::make_mut(doc);
@@ -1504,6 +2137,75 @@ impl LapceTab {
                         }
                         doc.trigger_syntax_change(None);
                     }
+                    LapceUICommand::ReopenWithEncoding(encoding) => {
+                        ctx.set_handled();
+                        let editor =
+                            if let Some(editor) = data.main_split.active_editor() {
+                                editor
+                            } else {
+                                return;
+                            };
+                        let doc = data.main_split.content_doc(&editor.content);
+                        let path = match doc.content() {
+                            BufferContent::File(path) => path.clone(),
+                            _ => return,
+                        };
+
+                        let event_sink = ctx.get_external_handle();
+                        let tab_id = data.id;
+                        data.proxy.reopen_with_encoding(
+                            doc.id(),
+                            encoding,
+                            move |result| {
+                                if let Ok(resp) = result {
+                                    let _ = event_sink.submit_command(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::UpdateFileFormat {
+                                            path: path.clone(),
+                                            encoding: resp.encoding,
+                                            line_ending: resp.line_ending,
+                                        },
+                                        Target::Widget(tab_id),
+                                    );
+                                    // Unsaved changes are kept if the user wants
+                                    let _ = event_sink.submit_command(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::OpenFileChanged {
+                                            path,
+                                            content: Rope::from(resp.content),
+                                        },
+                                        Target::Widget(tab_id),
+                                    );
+                                }
+                            },
+                        );
+                    }
+                    LapceUICommand::UpdateFileFormat {
+                        path,
+                        encoding,
+                        line_ending,
+                    } => {
+                        if let Some(doc) = data.main_split.open_docs.get_mut(path) {
+                            let doc = Arc::make_mut(doc);
+                            doc.set_encoding(encoding.clone());
+                            doc.set_line_ending(*line_ending);
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ConvertLineEnding(line_ending) => {
+                        ctx.set_handled();
+                        let path = match data
+                            .main_split
+                            .active_editor()
+                            .map(|editor| &editor.content)
+                        {
+                            Some(BufferContent::File(path)) => path.clone(),
+                            _ => return,
+                        };
+                        if let Some(doc) = data.main_split.open_docs.get_mut(&path) {
+                            Arc::make_mut(doc).convert_line_ending(*line_ending);
+                        }
+                    }
                     LapceUICommand::UpdateHistoryChanges {
                         path,
                         rev,
@@ -1541,22 +2243,204 @@ impl LapceTab {
                             .set_item_children(path, items.clone());
                         ctx.set_handled();
                     }
-                    LapceUICommand::UpdateExplorerItems(path, items, expand) => {
+                    LapceUICommand::UpdateExplorerItems {
+                        path,
+                        items,
+                        expand,
+                        more,
+                        generation,
+                    } => {
                         let file_explorer = Arc::make_mut(&mut data.file_explorer);
                         file_explorer.update_children(
                             path,
                             items.to_owned(),
                             *expand,
+                            *more,
+                            *generation,
+                        );
+                        if data.config.ui.explorer_show_sizes() {
+                            file_explorer.request_dir_sizes(path);
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::AppendExplorerItems {
+                        path,
+                        items,
+                        more,
+                        generation,
+                    } => {
+                        let file_explorer = Arc::make_mut(&mut data.file_explorer);
+                        file_explorer.append_children(
+                            path,
+                            items.to_owned(),
+                            *more,
+                            *generation,
+                        );
+                        if data.config.ui.explorer_show_sizes() {
+                            file_explorer.request_dir_sizes(path);
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ToggleExplorerFlatDir { path } => {
+                        let file_explorer = Arc::make_mut(&mut data.file_explorer);
+                        file_explorer.toggle_flat(path);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ExpandExplorerDir {
+                        path,
+                        levels,
+                        continued,
+                    } => {
+                        Arc::make_mut(&mut data.file_explorer)
+                            .expand_levels(path, *levels, *continued);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::RestoreExplorerDirs { path, open_dirs } => {
+                        Arc::make_mut(&mut data.file_explorer)
+                            .restore_open_dirs(path, open_dirs.clone());
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ExpandExplorerDirAll { path } => {
+                        let event_sink = ctx.get_external_handle();
+                        let tab_id = data.id;
+                        let local_path = path.clone();
+                        // Reading the tree at once tells whether it is too
+                        // large, as the proxy stops at a limit of entries
+                        data.proxy.read_dir_tree(
+                            path,
+                            usize::MAX,
+                            &[],
+                            move |result| {
+                                let path = local_path;
+                                let command = match result {
+                                    Ok(resp) if resp.truncated => {
+                                        LapceUICommand::ShowAlert(
+                                            expand_all_levels_alert(tab_id, &path),
+                                        )
+                                    }
+                                    _ => LapceUICommand::ExpandExplorerDir {
+                                        path,
+                                        levels: None,
+                                        continued: false,
+                                    },
+                                };
+                                let _ = event_sink.submit_command(
+                                    LAPCE_UI_COMMAND,
+                                    command,
+                                    Target::Widget(tab_id),
+                                );
+                            },
                         );
                         ctx.set_handled();
                     }
-                    LapceUICommand::CreateFileOpen { path } => {
+                    LapceUICommand::PinExplorerPath { path } => {
+                        let file_explorer = Arc::make_mut(&mut data.file_explorer);
+                        file_explorer.pin(path);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::UnpinExplorerPath { path } => {
+                        let file_explorer = Arc::make_mut(&mut data.file_explorer);
+                        file_explorer.unpin(path);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::FileOperationError {
+                        operation,
+                        path,
+                        kind,
+                        message,
+                    } => {
+                        // The explorer is out of date when the file is already
+                        // there or gone, so it shows how things are now
+                        if matches!(
+                            kind,
+                            FileErrorKind::AlreadyExists | FileErrorKind::NotFound
+                        ) {
+                            data.file_explorer.refresh_parents(&[path.clone()]);
+                        }
+                        let name = path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        // The message of the OS only helps when it's not
+                        // something the user can fix by themselves
+                        let msg = if kind.is_recoverable() {
+                            kind.hint().to_string()
+                        } else {
+                            format!("{message}. {}", kind.hint()).trim().to_string()
+                        };
+                        ctx.submit_command(Command::new(
+                            LAPCE_UI_COMMAND,
+                            LapceUICommand::ShowAlert(AlertContentData {
+                                title: format!(
+                                    "Couldn't {} {name}: {}",
+                                    operation.verb(),
+                                    kind.description()
+                                ),
+                                msg,
+                                buttons: Vec::new(),
+                            }),
+                            Target::Widget(data.id),
+                        ));
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ClearExplorerRecent => {
+                        Arc::make_mut(&mut data.file_explorer).recent.clear();
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::SetExplorerDisplayRoot { path } => {
+                        let file_explorer = Arc::make_mut(&mut data.file_explorer);
+                        file_explorer.set_display_root(path.clone());
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::UpdateExplorerFlatFiles { path, files } => {
+                        let file_explorer = Arc::make_mut(&mut data.file_explorer);
+                        file_explorer.update_flat_files(path, files.to_owned());
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::UpdateExplorerDirSize { path, size } => {
+                        Arc::make_mut(&mut data.file_explorer)
+                            .set_dir_size(path, *size);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::UpdateExplorerMetadata(metadata) => {
+                        Arc::make_mut(&mut data.file_explorer)
+                            .set_metadata(metadata);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::UpdateExplorerFilter(pattern) => {
+                        let file_explorer = Arc::make_mut(&mut data.file_explorer);
+                        file_explorer.set_filter(pattern);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::CreateFileOpen {
+                        path,
+                        template,
+                        content,
+                    } => {
+                        let content = match (content, template) {
+                            (Some(content), _) => Some(content.clone()),
+                            (None, Some(template)) => {
+                                match render_template(template, path) {
+                                    Ok(content) => Some(content),
+                                    Err(err) => {
+                                        log::warn!(
+                                            "Failed to read template {:?}: {:?}",
+                                            template,
+                                            err,
+                                        );
+                                        None
+                                    }
+                                }
+                            }
+                            (None, None) => None,
+                        };
                         let path_c = path.clone();
                         let event_sink = ctx.get_external_handle();
                         let tab_id = data.id;
                         let explorer = data.file_explorer.clone();
                         data.proxy.create_file(
                             path,
+                            content,
                             Box::new(move |res| {
                                 match res {
                                     Ok(_) => {
@@ -1567,7 +2451,6 @@ impl LapceTab {
                                         );
                                     }
                                     Err(err) => {
-                                        // TODO: Inform the user through a corner-notif
                                         log::warn!(
                                             "Failed to create file: {:?}",
                                             err,
@@ -1580,55 +2463,476 @@ impl LapceTab {
                         ctx.set_handled();
                     }
                     LapceUICommand::CreateDirectory { path } => {
+                        // The directory that is listed already, beneath which
+                        // the whole chain of new directories is shown
+                        let listed = path
+                            .ancestors()
+                            .skip(1)
+                            .find(|dir| {
+                                data.file_explorer.get_file_node(dir).is_some()
+                            })
+                            .map(Path::to_path_buf);
+                        let created = path.clone();
+                        let event_sink = ctx.get_external_handle();
                         let explorer = data.file_explorer.clone();
                         data.proxy.create_directory(
                             path,
-                            Box::new(move |res| {
-                                if let Err(err) = res {
-                                    // TODO: Inform the user through a corner-notif
+                            Box::new(move |res| match (res, listed) {
+                                (Ok(_), Some(listed)) => {
+                                    explorer.read_dir_revealing(&listed, &created);
+                                    let _ = event_sink.submit_command(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::ActiveFileChanged {
+                                            path: Some(created),
+                                        },
+                                        Target::Widget(explorer.widget_id),
+                                    );
+                                }
+                                (Ok(_), None) => explorer.reload(),
+                                (Err(err), _) => {
                                     log::warn!(
                                         "Failed to create directory: {:?}",
                                         err
                                     );
+                                    explorer.reload();
                                 }
-                                explorer.reload();
                             }),
                         );
                         ctx.set_handled();
                     }
+                    LapceUICommand::PasteImageAsNewFile { dir } => {
+                        let image = Application::global()
+                            .clipboard()
+                            .get_format(CLIPBOARD_PNG_FORMAT);
+                        if let Some(image) = image {
+                            let path = data
+                                .file_explorer
+                                .unused_file_path(dir, "image", "png");
+                            let explorer = data.file_explorer.clone();
+                            data.proxy.create_binary_file(
+                                &path,
+                                image,
+                                Box::new(move |res| {
+                                    if let Err(err) = res {
+                                        // TODO: Inform the user through a corner-notif
+                                        log::warn!(
+                                            "Failed to paste image: {:?}",
+                                            err
+                                        );
+                                    }
+                                    explorer.reload();
+                                }),
+                            );
+                        }
+                        ctx.set_handled();
+                    }
                     LapceUICommand::RenamePath { from, to } => {
                         let explorer = data.file_explorer.clone();
-                        data.proxy.rename_path(
-                            from,
-                            to,
+                        let event_sink = ctx.get_external_handle();
+                        let tab_id = data.id;
+                        let (local_from, local_to) = (from.clone(), to.clone());
+                        FileExplorerData::rename_path(
+                            data.proxy.clone(),
+                            from.clone(),
+                            to.clone(),
                             Box::new(move |res| {
-                                if let Err(err) = res {
-                                    // TODO: inform the user through a corner-notif
-                                    log::warn!("Failed to rename path: {:?}", err);
+                                match res {
+                                    Ok(_) => {
+                                        let _ = event_sink.submit_command(
+                                            LAPCE_UI_COMMAND,
+                                            LapceUICommand::PathRenamed {
+                                                from: local_from,
+                                                to: local_to,
+                                            },
+                                            Target::Widget(tab_id),
+                                        );
+                                    }
+                                    Err(err) => {
+                                        log::warn!(
+                                            "Failed to rename path: {:?}",
+                                            err
+                                        );
+                                    }
                                 }
                                 explorer.reload();
                             }),
                         );
                     }
+                    LapceUICommand::PathRenamed { from, to } => {
+                        data.main_split.rename_path(from, to);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::CancelWorkDoneProgress(token) => {
+                        // Only the operations started by us can be cancelled, which
+                        // use string tokens
+                        if let lsp_types::NumberOrString::String(token) = token {
+                            data.proxy.cancel_work_done_progress(token);
+                        }
+                        ctx.set_handled();
+                    }
                     LapceUICommand::TrashPath { path } => {
                         let explorer = data.file_explorer.clone();
+                        let trashed_path = path.clone();
                         data.proxy.trash_path(
                             path,
-                            Box::new(move |res| {
-                                if let Err(err) = res {
+                            Some(new_progress_token()),
+                            move |res| {
+                                match res {
                                     // TODO: inform the user through a corner-notif
-                                    log::warn!("Failed to trash path: {:?}", err);
+                                    Ok(TrashPathResponse {
+                                        location: TrashLocation::Workspace,
+                                    }) => {
+                                        log::info!(
+                                            "No OS trash, moved {:?} to the workspace trash",
+                                            trashed_path
+                                        );
+                                    }
+                                    Ok(_) => {}
+                                    Err(err) => {
+                                        log::warn!(
+                                            "Failed to trash path: {:?}",
+                                            err
+                                        );
+                                    }
                                 }
                                 explorer.reload();
+                            },
+                        );
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::TrashPaths { paths } => {
+                        // Each path is trashed on its own, and once all of them
+                        // are done the parents are read again and the failures
+                        // are reported together
+                        let all_paths = Arc::new(paths.clone());
+                        let pending =
+                            Arc::new(Mutex::new((paths.len(), Vec::new())));
+                        for path in paths {
+                            let all_paths = all_paths.clone();
+                            let pending = pending.clone();
+                            let explorer = data.file_explorer.clone();
+                            let event_sink = ctx.get_external_handle();
+                            let tab_id = data.id;
+                            let trashed_path = path.clone();
+                            data.proxy.trash_path(
+                                path,
+                                Some(new_progress_token()),
+                                move |res| {
+                                    let (left, failed) = &mut *pending.lock();
+                                    if let Err(err) = res {
+                                        log::warn!(
+                                            "Failed to trash {:?}: {:?}",
+                                            trashed_path,
+                                            err
+                                        );
+                                        failed.push(trashed_path);
+                                    }
+                                    *left -= 1;
+                                    if *left > 0 {
+                                        return;
+                                    }
+
+                                    explorer.refresh_parents(&all_paths);
+                                    if failed.is_empty() {
+                                        return;
+                                    }
+                                    let title = format!(
+                                        "Failed to move {} of {} items to the trash",
+                                        failed.len(),
+                                        all_paths.len()
+                                    );
+                                    let names = path_names_summary(
+                                        failed,
+                                        MAX_FAILED_NAMES_SHOWN,
+                                    );
+                                    let _ = event_sink.submit_command(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::ShowAlert(
+                                            AlertContentData {
+                                                title,
+                                                msg: format!(
+                                                    "{names} couldn't be moved."
+                                                ),
+                                                buttons: Vec::new(),
+                                            },
+                                        ),
+                                        Target::Widget(tab_id),
+                                    );
+                                },
+                            );
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::DeletePaths { paths } => {
+                        // Like trashing, the parents are read again and the
+                        // failures are reported together once all are done
+                        let all_paths = Arc::new(paths.clone());
+                        let pending =
+                            Arc::new(Mutex::new((paths.len(), Vec::new())));
+                        for path in paths {
+                            let all_paths = all_paths.clone();
+                            let pending = pending.clone();
+                            let explorer = data.file_explorer.clone();
+                            let event_sink = ctx.get_external_handle();
+                            let tab_id = data.id;
+                            let deleted_path = path.clone();
+                            data.proxy.delete_path(
+                                path,
+                                Box::new(move |res| {
+                                    let (left, failed) = &mut *pending.lock();
+                                    if let Err(err) = res {
+                                        log::warn!(
+                                            "Failed to delete {:?}: {:?}",
+                                            deleted_path,
+                                            err
+                                        );
+                                        failed.push(deleted_path);
+                                    }
+                                    *left -= 1;
+                                    if *left > 0 {
+                                        return;
+                                    }
+
+                                    explorer.refresh_parents(&all_paths);
+                                    if failed.is_empty() {
+                                        return;
+                                    }
+                                    let title = format!(
+                                        "Failed to delete {} of {} items",
+                                        failed.len(),
+                                        all_paths.len()
+                                    );
+                                    let names = path_names_summary(
+                                        failed,
+                                        MAX_FAILED_NAMES_SHOWN,
+                                    );
+                                    let _ = event_sink.submit_command(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::ShowAlert(
+                                            AlertContentData {
+                                                title,
+                                                msg: format!(
+                                                    "{names} couldn't be deleted."
+                                                ),
+                                                buttons: Vec::new(),
+                                            },
+                                        ),
+                                        Target::Widget(tab_id),
+                                    );
+                                }),
+                            );
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ExplorerSetOpen { path, open } => {
+                        Arc::make_mut(&mut data.file_explorer).set_open(path, *open);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::RestoreFromTrash { id } => {
+                        let explorer = data.file_explorer.clone();
+                        data.proxy.restore_from_trash(id, move |res| {
+                            if let Err(err) = res {
+                                log::warn!(
+                                    "Failed to restore from trash: {:?}",
+                                    err
+                                );
+                            }
+                            explorer.reload();
+                        });
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::GitStage { paths } => {
+                        // The new status arrives as `DiffInfo` once it's done
+                        data.proxy.git_stage(
+                            paths,
+                            Box::new(|res| {
+                                if let Err(err) = res {
+                                    log::warn!("Failed to stage files: {:?}", err);
+                                }
+                            }),
+                        );
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::GitUnstage { paths } => {
+                        data.proxy.git_unstage(
+                            paths,
+                            Box::new(|res| {
+                                if let Err(err) = res {
+                                    log::warn!("Failed to unstage files: {:?}", err);
+                                }
                             }),
                         );
                         ctx.set_handled();
                     }
+                    LapceUICommand::SetGitIgnored { path, ignored } => {
+                        let event_sink = ctx.get_external_handle();
+                        let tab_id = data.id;
+                        let ignored_path = path.clone();
+                        let ignored = *ignored;
+                        data.proxy.set_git_ignored(
+                            path,
+                            ignored,
+                            Box::new(move |res| {
+                                let path = ignored_path;
+                                let name = path
+                                    .file_name()
+                                    .map(|name| name.to_string_lossy().to_string())
+                                    .unwrap_or_default();
+                                // The directory is read again so that the
+                                // dimming of the ignored files is up to date
+                                let cmd = match (res, path.parent()) {
+                                    (Ok(_), Some(parent)) => {
+                                        LapceUICommand::ExplorerRefresh {
+                                            path: parent.to_path_buf(),
+                                        }
+                                    }
+                                    (Ok(_), None) => return,
+                                    (Err(err), _) => {
+                                        LapceUICommand::ShowAlert(AlertContentData {
+                                            title: if ignored {
+                                                format!("Couldn't add {name} to .gitignore")
+                                            } else {
+                                                format!(
+                                                    "Couldn't remove {name} from .gitignore"
+                                                )
+                                            },
+                                            msg: err["message"]
+                                                .as_str()
+                                                .unwrap_or_default()
+                                                .to_string(),
+                                            buttons: Vec::new(),
+                                        })
+                                    }
+                                };
+                                let _ = event_sink.submit_command(
+                                    LAPCE_UI_COMMAND,
+                                    cmd,
+                                    Target::Widget(tab_id),
+                                );
+                            }),
+                        );
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::RevealInFileManager { path } => {
+                        data.proxy.reveal_in_file_manager(path);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::OpenInSystemTerminal { path } => {
+                        data.proxy.open_in_system_terminal(path);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::OpenWorkspaceSettings => {
+                        if let Some(workspace) = data.workspace.path.as_ref() {
+                            let path =
+                                workspace.join(".lapce").join("settings.toml");
+                            let event_sink = ctx.get_external_handle();
+                            let tab_id = data.id;
+                            let settings = path.clone();
+                            data.proxy.ensure_file(
+                                &settings,
+                                Box::new(move |res| {
+                                    if let Err(err) = res {
+                                        let _ = event_sink.submit_command(
+                                            LAPCE_UI_COMMAND,
+                                            LapceUICommand::ShowAlert(
+                                                AlertContentData {
+                                                    title: "Couldn't create the workspace settings file".to_string(),
+                                                    msg: err["message"]
+                                                        .as_str()
+                                                        .unwrap_or_default()
+                                                        .to_string(),
+                                                    buttons: Vec::new(),
+                                                },
+                                            ),
+                                            Target::Widget(tab_id),
+                                        );
+                                        return;
+                                    }
+                                    let _ = event_sink.submit_command(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::OpenFile(path.clone()),
+                                        Target::Widget(tab_id),
+                                    );
+                                    let _ = event_sink.submit_command(
+                                        LAPCE_UI_COMMAND,
+                                        LapceUICommand::ExplorerRevealPath { path },
+                                        Target::Widget(tab_id),
+                                    );
+                                }),
+                            );
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::OpenWithDefaultApplication { path } => {
+                        data.proxy.open_with_default_application(path);
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::DuplicatePath { path } => {
+                        let event_sink = ctx.get_external_handle();
+                        let tab_id = data.id;
+                        let proxy = data.proxy.clone();
+                        data.proxy.duplicate_path(
+                            path,
+                            Some(new_progress_token()),
+                            move |res| match res {
+                                Ok(resp) => {
+                                    let parent = match resp.path.parent() {
+                                        Some(parent) => parent.to_path_buf(),
+                                        None => return,
+                                    };
+                                    let local_event_sink = event_sink.clone();
+                                    // Start renaming once the copy is in the tree
+                                    FileExplorerData::read_dir_cb(
+                                        &parent,
+                                        true,
+                                        tab_id,
+                                        &proxy,
+                                        event_sink,
+                                        Some(move || {
+                                            let _ = local_event_sink.submit_command(
+                                            LAPCE_UI_COMMAND,
+                                            LapceUICommand::ExplorerStartRenamePath {
+                                                path: resp.path,
+                                            },
+                                            Target::Widget(tab_id),
+                                        );
+                                        }),
+                                    );
+                                }
+                                Err(err) => {
+                                    log::warn!(
+                                        "Failed to duplicate path: {:?}",
+                                        err
+                                    );
+                                }
+                            },
+                        );
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ExplorerStartRenamePath { path } => {
+                        let file_explorer = Arc::make_mut(&mut data.file_explorer);
+                        if let Some((list_index, indent_level)) =
+                            file_explorer.get_node_index(path)
+                        {
+                            file_explorer.start_renaming(
+                                ctx,
+                                &mut data.main_split,
+                                list_index,
+                                indent_level,
+                                path.file_name()
+                                    .map(|x| x.to_string_lossy().to_string())
+                                    .unwrap_or_else(String::new),
+                            );
+                        }
+                        ctx.set_handled();
+                    }
                     LapceUICommand::ExplorerNew {
                         list_index,
                         indent_level,
                         is_dir,
                         base_path,
+                        template,
+                        content,
                     } => {
                         let file_explorer = Arc::make_mut(&mut data.file_explorer);
                         file_explorer.start_naming(
@@ -1638,6 +2942,8 @@ impl LapceTab {
                             *indent_level,
                             *is_dir,
                             base_path.clone(),
+                            template.clone(),
+                            content.clone(),
                         );
                         ctx.set_handled();
                     }
@@ -1656,6 +2962,33 @@ impl LapceTab {
                         );
                         ctx.set_handled();
                     }
+                    LapceUICommand::ExplorerOpenPaths { paths } => {
+                        let file_explorer = Arc::make_mut(&mut data.file_explorer);
+                        for path in paths {
+                            let is_dir = file_explorer
+                                .get_file_node(path)
+                                .map_or(false, |node| node.is_dir);
+                            if is_dir {
+                                file_explorer.expand_dir(path);
+                            } else if data.config.ui.explorer_opens_externally(path)
+                            {
+                                data.proxy.open_with_default_application(path);
+                            } else {
+                                ctx.submit_command(Command::new(
+                                    LAPCE_UI_COMMAND,
+                                    LapceUICommand::OpenFile(path.clone()),
+                                    Target::Widget(data.id),
+                                ));
+                            }
+                        }
+                        ctx.set_handled();
+                    }
+                    LapceUICommand::ExplorerReadDirFailed { path } => {
+                        Arc::make_mut(&mut data.file_explorer)
+                            .reading_dirs
+                            .remove(path);
+                        ctx.set_handled();
+                    }
                     LapceUICommand::ExplorerEndNaming { apply_naming } => {
                         let file_explorer = Arc::make_mut(&mut data.file_explorer);
                         if *apply_naming {
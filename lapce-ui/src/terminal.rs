@@ -7,15 +7,16 @@ use alacritty_terminal::{
 };
 use druid::{
     piet::{Text, TextAttribute, TextLayout, TextLayoutBuilder},
-    BoxConstraints, Command, Data, Env, Event, EventCtx, FontWeight, LayoutCtx,
-    LifeCycle, LifeCycleCtx, MouseEvent, PaintCtx, Point, Rect, RenderContext, Size,
-    Target, UpdateCtx, Widget, WidgetExt, WidgetId, WidgetPod,
+    BoxConstraints, Command, Data, Env, Event, EventCtx, FontWeight, KbKey,
+    LayoutCtx, LifeCycle, LifeCycleCtx, MouseEvent, PaintCtx, Point, Rect,
+    RenderContext, Size, Target, UpdateCtx, Widget, WidgetExt, WidgetId, WidgetPod,
 };
 use lapce_core::mode::Mode;
 use lapce_data::{
     command::{LapceUICommand, LAPCE_UI_COMMAND},
     config::LapceTheme,
     data::{FocusArea, LapceTabData},
+    explorer::display_path,
     panel::PanelKind,
     terminal::{LapceTerminalData, LapceTerminalViewData},
 };
@@ -417,9 +418,16 @@ impl Widget<LapceTabData> for LapceTerminalHeader {
             );
 
             let term = data.terminal.terminals.get(&self.term_id).unwrap();
+            // Shells that don't set a title may still report their directory
+            let title = match term.cwd.as_ref() {
+                Some(cwd) if term.title.is_empty() => {
+                    display_path(cwd, data.abbreviated_home())
+                }
+                _ => term.title.clone(),
+            };
             let text_layout = ctx
                 .text()
-                .new_text_layout(term.title.clone())
+                .new_text_layout(title)
                 .font(
                     data.config.ui.font_family(),
                     data.config.ui.font_size() as f64,
@@ -525,6 +533,17 @@ impl Widget<LapceTabData> for LapceTerminal {
                     .wheel_scroll(wheel_event.wheel_delta.y);
                 ctx.request_paint();
             }
+            Event::KeyDown(key_event) if term_data.terminal.exited => {
+                // There's nothing left to type into, so Enter closes it
+                if key_event.key == KbKey::Enter {
+                    ctx.submit_command(Command::new(
+                        LAPCE_UI_COMMAND,
+                        LapceUICommand::CloseTerminal(self.term_id),
+                        Target::Widget(data.id),
+                    ));
+                }
+                ctx.set_handled();
+            }
             Event::KeyDown(key_event) => {
                 let mut keypress = data.keypress.clone();
                 if !Arc::make_mut(&mut keypress).key_down(
@@ -288,10 +288,27 @@ impl Widget<LapceWindowData> for LapceWindow {
                                 .unwrap_or_default(),
                         );
                         for (_, tab) in data.tabs.iter_mut() {
-                            tab.config = Arc::new(
+                            let config = Arc::new(
                                 Config::load(&tab.workspace.clone())
                                     .unwrap_or_default(),
                             );
+                            // Read the listed directories again so that the
+                            // newly excluded paths go away and the others return
+                            if config.ui.explorer_exclude()
+                                != tab.config.ui.explorer_exclude()
+                            {
+                                tab.proxy
+                                    .set_file_exclude(config.ui.explorer_exclude());
+                                tab.file_explorer.reload();
+                            }
+                            tab.proxy.set_diagnostics_debounce(
+                                config.editor.diagnostics_debounce,
+                            );
+                            tab.config = config;
+                            Arc::make_mut(&mut tab.file_explorer)
+                                .set_compact_folders(
+                                    tab.config.ui.explorer_compact_folders(),
+                                );
                         }
                         Arc::make_mut(&mut data.keypress)
                             .update_keymaps(&data.config);